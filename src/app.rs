@@ -1,19 +1,31 @@
 use crate::commands::{
-    About, CloseWindow, Copy, Cut, Find, FindNext, FindPrevious, FontSizeDecrease,
-    FontSizeIncrease, FontSizeReset, NewFile, OpenFile, Paste, Quit, Redo, SaveFile, SaveFileAs,
-    SelectAll, Undo,
+    About, ClearRecent, CloseWindow, Copy, CopyAsPlainText, CopyAsRichText, Cut, ExportDocx,
+    ExportEpub, ExportHtml, ExportPdf, Find, FindNext, FindPrevious, FontSizeDecrease,
+    FontSizeIncrease, FontSizeReset, GoToHeading, GoToLine, HideAllMarkers, NavigateBack,
+    NavigateForward, NewFile, NextPane, OpenFile, OpenFolder, OpenRecent, Paste, PreviousPane,
+    QuickOpen, Quit, Redo, RevealActiveFile, RevealMarkersAtCursor, SaveFile, SaveFileAs,
+    SelectAll, ShowAllMarkers, SplitDown, SplitRight, TogglePreview, ToggleSidebar, ToggleVimMode,
+    Undo,
 };
+use crate::keymap;
 use crate::services::assets::AsterAssetSource;
-use crate::services::fs::{read_to_string, write_atomic};
+use crate::services::fs::{DiskState, read_to_string};
+use crate::services::recovery::RecoveryJournal;
+use crate::services::session::{
+    SavedBounds, SavedWindowState, Session, WindowSession, load_session, save_session,
+};
+use crate::services::settings;
+use crate::ui::preview::PreviewView;
 use crate::ui::root::RootView;
 use camino::Utf8PathBuf;
 use gpui::{
-    App, AppContext, Application, Bounds, KeyBinding, Menu, MenuItem, OsAction, Pixels,
-    SystemMenuType, TitlebarOptions, Window, WindowBounds, WindowOptions,
+    App, AppContext, Application, Bounds, Menu, MenuItem, OsAction, Pixels, SystemMenuType,
+    TitlebarOptions, Window, WindowBounds, WindowHandle, WindowOptions, point, px, size,
 };
 use gpui_component::notification::NotificationList;
 use rfd::{MessageButtons, MessageDialog, MessageDialogResult, MessageLevel};
 use std::collections::VecDeque;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use url::Url;
@@ -35,77 +47,9 @@ pub fn run() {
 
         cx.activate(true);
 
-        cx.bind_keys([
-            KeyBinding::new("cmd-n", NewFile, None),
-            KeyBinding::new("cmd-o", OpenFile, None),
-            KeyBinding::new("cmd-s", SaveFile, None),
-            KeyBinding::new("shift-cmd-s", SaveFileAs, None),
-            KeyBinding::new("cmd-w", CloseWindow, None),
-            KeyBinding::new("cmd-q", Quit, None),
-            KeyBinding::new("cmd-z", Undo, None),
-            KeyBinding::new("shift-cmd-z", Redo, None),
-            KeyBinding::new("cmd-y", Redo, None),
-            KeyBinding::new("cmd-x", Cut, None),
-            KeyBinding::new("cmd-c", Copy, None),
-            KeyBinding::new("cmd-v", Paste, None),
-            KeyBinding::new("cmd-a", SelectAll, None),
-            KeyBinding::new("cmd-f", Find, None),
-            KeyBinding::new("cmd-g", FindNext, None),
-            KeyBinding::new("shift-cmd-g", FindPrevious, None),
-            KeyBinding::new("cmd-=", FontSizeIncrease, None),
-            KeyBinding::new("cmd--", FontSizeDecrease, None),
-            KeyBinding::new("cmd-0", FontSizeReset, None),
-        ]);
-
-        cx.set_menus(vec![
-            Menu {
-                name: "Aster".into(),
-                items: vec![
-                    MenuItem::action("About Aster", About),
-                    MenuItem::separator(),
-                    MenuItem::os_submenu("Services", SystemMenuType::Services),
-                    MenuItem::separator(),
-                    MenuItem::action("Quit Aster", Quit),
-                ],
-            },
-            Menu {
-                name: "File".into(),
-                items: vec![
-                    MenuItem::action("New", NewFile),
-                    MenuItem::action("Open…", OpenFile),
-                    MenuItem::separator(),
-                    MenuItem::action("Save", SaveFile),
-                    MenuItem::action("Save As…", SaveFileAs),
-                    MenuItem::separator(),
-                    MenuItem::action("Close Window", CloseWindow),
-                ],
-            },
-            Menu {
-                name: "Edit".into(),
-                items: vec![
-                    MenuItem::os_action("Undo", Undo, OsAction::Undo),
-                    MenuItem::os_action("Redo", Redo, OsAction::Redo),
-                    MenuItem::separator(),
-                    MenuItem::os_action("Cut", Cut, OsAction::Cut),
-                    MenuItem::os_action("Copy", Copy, OsAction::Copy),
-                    MenuItem::os_action("Paste", Paste, OsAction::Paste),
-                    MenuItem::separator(),
-                    MenuItem::action("Find…", Find),
-                    MenuItem::action("Find Next", FindNext),
-                    MenuItem::action("Find Previous", FindPrevious),
-                    MenuItem::separator(),
-                    MenuItem::os_action("Select All", SelectAll, OsAction::SelectAll),
-                ],
-            },
-            Menu {
-                name: "View".into(),
-                items: vec![
-                    MenuItem::action("Increase Font Size", FontSizeIncrease),
-                    MenuItem::action("Decrease Font Size", FontSizeDecrease),
-                    MenuItem::action("Reset Font Size", FontSizeReset),
-                ],
-            },
-        ]);
+        cx.bind_keys(keymap::default_bindings());
+
+        set_app_menus(cx);
 
         cx.on_action(|_: &Quit, cx| {
             let windows = cx.window_stack().unwrap_or_else(|| cx.windows());
@@ -123,6 +67,19 @@ pub fn run() {
                 }
             }
 
+            // Bottom-most first, so the front window is reopened last and on top.
+            let sessions = windows
+                .iter()
+                .rev()
+                .filter_map(|window| window.downcast::<RootView>())
+                .filter_map(|handle| {
+                    handle
+                        .update(cx, |root, window, cx| root.session(window, cx))
+                        .ok()
+                })
+                .collect();
+            remember_session(sessions);
+
             // Close windows ourselves (bypasses `on_window_should_close`) and then quit.
             for window in windows {
                 let _ = window.update(cx, |_, window, _| window.remove_window());
@@ -130,6 +87,31 @@ pub fn run() {
 
             cx.quit();
         });
+        cx.on_action(|action: &OpenRecent, cx| {
+            let path = action.0.clone();
+            if !path.exists() {
+                settings::update_recent(|recent| recent.remove(&path));
+                set_app_menus(cx);
+                MessageDialog::new()
+                    .set_level(MessageLevel::Warning)
+                    .set_title("Can't open recent item")
+                    .set_description(format!(
+                        "{} no longer exists and was removed from the list.",
+                        path.display()
+                    ))
+                    .set_buttons(MessageButtons::Ok)
+                    .show();
+                return;
+            }
+            if let Ok(path) = Utf8PathBuf::try_from(path) {
+                open_path_in_active_window_or_new(cx, path);
+            }
+        });
+        cx.on_action(|_: &ClearRecent, cx| {
+            settings::update_recent(|recent| recent.clear_unpinned());
+            set_app_menus(cx);
+            cx.refresh_windows();
+        });
         cx.on_action(|_: &About, _cx| {
             MessageDialog::new()
                 .set_level(MessageLevel::Info)
@@ -142,7 +124,21 @@ pub fn run() {
                 .show();
         });
 
-        let _ = open_window(cx, None);
+        let session = settings::get_restore_session()
+            .then(load_session)
+            .flatten()
+            .filter(|session| !session.windows.is_empty());
+        let windows: Vec<WindowHandle<RootView>> = match session {
+            Some(session) => session
+                .windows
+                .into_iter()
+                .filter_map(|saved| open_session_window(cx, saved).ok())
+                .collect(),
+            None => open_window(cx, None).into_iter().collect(),
+        };
+        if let Some(window) = windows.last() {
+            offer_recovered_documents(cx, *window);
+        }
 
         let args: Vec<String> = std::env::args().skip(1).collect();
         for arg in args {
@@ -184,10 +180,163 @@ pub fn run() {
     });
 }
 
-fn open_window(cx: &mut App, initial_path: Option<Utf8PathBuf>) -> anyhow::Result<()> {
-    cx.open_window(
+/// Installs the menu bar. Called again whenever the recent list changes, as
+/// File → Open Recent lists it.
+pub fn set_app_menus(cx: &mut App) {
+    let recent = settings::get_recent();
+    let mut recent_items: Vec<MenuItem> = recent
+        .entries()
+        .map(|entry| MenuItem::action(recent_label(&entry.path), OpenRecent(entry.path.clone())))
+        .collect();
+    if !recent.is_empty() {
+        recent_items.push(MenuItem::separator());
+    }
+    recent_items.push(MenuItem::action("Clear Recently Opened", ClearRecent));
+    cx.set_menus(vec![
+        Menu {
+            name: "Aster".into(),
+            items: vec![
+                MenuItem::action("About Aster", About),
+                MenuItem::separator(),
+                MenuItem::os_submenu("Services", SystemMenuType::Services),
+                MenuItem::separator(),
+                MenuItem::action("Quit Aster", Quit),
+            ],
+        },
+        Menu {
+            name: "File".into(),
+            items: vec![
+                MenuItem::action("New", NewFile),
+                MenuItem::action("Open…", OpenFile),
+                MenuItem::action("Open Folder…", OpenFolder),
+                MenuItem::submenu(Menu {
+                    name: "Open Recent".into(),
+                    items: recent_items,
+                }),
+                MenuItem::separator(),
+                MenuItem::action("Save", SaveFile),
+                MenuItem::action("Save As…", SaveFileAs),
+                MenuItem::separator(),
+                MenuItem::submenu(Menu {
+                    name: "Export".into(),
+                    items: vec![
+                        MenuItem::action("HTML…", ExportHtml),
+                        MenuItem::action("PDF…", ExportPdf),
+                        MenuItem::action("EPUB…", ExportEpub),
+                        MenuItem::action("DOCX…", ExportDocx),
+                    ],
+                }),
+                MenuItem::separator(),
+                MenuItem::action("Close Window", CloseWindow),
+            ],
+        },
+        Menu {
+            name: "Edit".into(),
+            items: vec![
+                MenuItem::os_action("Undo", Undo, OsAction::Undo),
+                MenuItem::os_action("Redo", Redo, OsAction::Redo),
+                MenuItem::separator(),
+                MenuItem::os_action("Cut", Cut, OsAction::Cut),
+                MenuItem::os_action("Copy", Copy, OsAction::Copy),
+                MenuItem::action("Copy as Rich Text", CopyAsRichText),
+                MenuItem::action("Copy as Plain Text", CopyAsPlainText),
+                MenuItem::os_action("Paste", Paste, OsAction::Paste),
+                MenuItem::separator(),
+                MenuItem::action("Find…", Find),
+                MenuItem::action("Find Next", FindNext),
+                MenuItem::action("Find Previous", FindPrevious),
+                MenuItem::separator(),
+                MenuItem::os_action("Select All", SelectAll, OsAction::SelectAll),
+            ],
+        },
+        Menu {
+            name: "Go".into(),
+            items: vec![
+                MenuItem::action("Back", NavigateBack),
+                MenuItem::action("Forward", NavigateForward),
+                MenuItem::separator(),
+                MenuItem::action("Go to Line…", GoToLine),
+                MenuItem::action("Go to Heading…", GoToHeading),
+                MenuItem::action("Go to File…", QuickOpen),
+            ],
+        },
+        Menu {
+            name: "View".into(),
+            items: vec![
+                MenuItem::action("Increase Font Size", FontSizeIncrease),
+                MenuItem::action("Decrease Font Size", FontSizeDecrease),
+                MenuItem::action("Reset Font Size", FontSizeReset),
+                MenuItem::separator(),
+                MenuItem::action("Toggle Preview", TogglePreview),
+                MenuItem::action("Toggle Sidebar", ToggleSidebar),
+                MenuItem::action("Reveal Active File", RevealActiveFile),
+                MenuItem::separator(),
+                MenuItem::action("Split Right", SplitRight),
+                MenuItem::action("Split Down", SplitDown),
+                MenuItem::action("Focus Next Pane", NextPane),
+                MenuItem::action("Focus Previous Pane", PreviousPane),
+                MenuItem::separator(),
+                MenuItem::action("Reveal Markers at Cursor", RevealMarkersAtCursor),
+                MenuItem::action("Show All Markers", ShowAllMarkers),
+                MenuItem::action("Hide All Markers", HideAllMarkers),
+                MenuItem::separator(),
+                MenuItem::action("Toggle Vim Mode", ToggleVimMode),
+            ],
+        },
+    ]);
+}
+
+/// `path` for a menu, with the home directory shortened to `~`.
+fn recent_label(path: &Path) -> String {
+    let home = directories::BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf());
+    match home.as_ref().and_then(|home| path.strip_prefix(home).ok()) {
+        Some(rest) => format!("~/{}", rest.display()),
+        None => path.display().to_string(),
+    }
+}
+
+fn open_window(
+    cx: &mut App,
+    initial_path: Option<Utf8PathBuf>,
+) -> anyhow::Result<WindowHandle<RootView>> {
+    open_window_with_bounds(
+        cx,
+        initial_path,
+        WindowBounds::Maximized(Bounds::<Pixels>::default()),
+    )
+}
+
+/// Opens a window where a saved one was and reopens its files.
+fn open_session_window(
+    cx: &mut App,
+    saved: WindowSession,
+) -> anyhow::Result<WindowHandle<RootView>> {
+    let SavedBounds {
+        x,
+        y,
+        width,
+        height,
+        state,
+    } = saved.bounds;
+    let bounds = Bounds::new(point(px(x), px(y)), size(px(width), px(height)));
+    let window_bounds = match state {
+        SavedWindowState::Windowed => WindowBounds::Windowed(bounds),
+        SavedWindowState::Maximized => WindowBounds::Maximized(bounds),
+        SavedWindowState::Fullscreen => WindowBounds::Fullscreen(bounds),
+    };
+    let window = open_window_with_bounds(cx, None, window_bounds)?;
+    window.update(cx, |root, _, cx| root.restore_session(saved, cx))?;
+    Ok(window)
+}
+
+fn open_window_with_bounds(
+    cx: &mut App,
+    initial_path: Option<Utf8PathBuf>,
+    window_bounds: WindowBounds,
+) -> anyhow::Result<WindowHandle<RootView>> {
+    let window = cx.open_window(
         WindowOptions {
-            window_bounds: Some(WindowBounds::Maximized(Bounds::<Pixels>::default())),
+            window_bounds: Some(window_bounds),
             titlebar: Some(TitlebarOptions {
                 title: None,
                 appears_transparent: true,
@@ -197,7 +346,57 @@ fn open_window(cx: &mut App, initial_path: Option<Utf8PathBuf>) -> anyhow::Resul
         },
         |window, cx| build_root_view(window, cx, initial_path.clone()),
     )?;
-    Ok(())
+    Ok(window)
+}
+
+/// Offers to reopen documents whose unsaved changes were journaled by a run
+/// of Aster that didn't exit normally.
+fn offer_recovered_documents(cx: &mut App, window: WindowHandle<RootView>) {
+    let journal = RecoveryJournal::default();
+    journal.remove_stale_locks();
+    let entries = journal.entries();
+    if entries.is_empty() {
+        return;
+    }
+    let names: Vec<&str> = entries
+        .iter()
+        .map(|(_, document)| {
+            document
+                .path
+                .as_ref()
+                .and_then(|path| path.file_name())
+                .and_then(|name| name.to_str())
+                .unwrap_or("untitled.md")
+        })
+        .collect();
+    let choice = MessageDialog::new()
+        .set_level(MessageLevel::Warning)
+        .set_title("Restore unsaved documents?")
+        .set_description(format!(
+            "Aster didn't exit normally last time. These documents had unsaved changes:\n\n{}",
+            names.join("\n")
+        ))
+        .set_buttons(MessageButtons::OkCancelCustom(
+            "Restore".to_string(),
+            "Discard".to_string(),
+        ))
+        .show();
+    let restore = match choice {
+        MessageDialogResult::Ok | MessageDialogResult::Yes => true,
+        MessageDialogResult::Custom(label) => label == "Restore",
+        _ => false,
+    };
+
+    let keys: Vec<String> = entries.iter().map(|(key, _)| key.clone()).collect();
+    if restore {
+        let documents = entries.into_iter().map(|(_, document)| document).collect();
+        let _ = window.update(cx, |root, _, cx| root.restore_documents(documents, cx));
+    }
+    // Restored documents are journaled again under keys of this run.
+    let ticket = journal.ticket();
+    for key in keys {
+        journal.remove(&key, ticket);
+    }
 }
 
 fn build_root_view(
@@ -210,106 +409,69 @@ fn build_root_view(
     let notifications = cx.new(|cx| NotificationList::new(window, cx));
     let editor_view = cx.new(|_| RootView::build_editor(document.clone(), inline_markdown.clone()));
     let file_explorer_view = cx.new(|_| RootView::build_file_explorer(document.clone()));
+    let preview_view = cx.new(|_| PreviewView::new());
 
-    if let Some(path) = initial_path.as_ref() {
-        if let Ok(text) = read_to_string(path) {
-            let _ = document.update(cx, |d, cx| {
-                d.path = Some(path.clone());
-                d.set_text(&text);
-                d.save_snapshot();
-                cx.notify();
-            });
-        }
+    if let Some(path) = initial_path.as_ref().filter(|path| path.is_dir()) {
+        file_explorer_view.update(cx, |explorer, cx| explorer.open_folder(path.clone(), cx));
+    } else if let Some(path) = initial_path.as_ref()
+        && let Ok(text) = read_to_string(path)
+    {
+        let _ = document.update(cx, |d, cx| {
+            d.path = Some(path.clone());
+            d.set_text(&text);
+            d.save_snapshot();
+            d.disk_state = DiskState::new(path, text.as_bytes());
+            cx.notify();
+        });
     }
 
-    install_should_close_prompt(window, cx, document.clone());
-    cx.new(|_| {
+    let root = cx.new(|cx| {
         RootView::new(
             document,
             inline_markdown,
             editor_view,
             file_explorer_view,
+            preview_view,
             notifications,
+            window,
+            cx,
         )
-    })
+    });
+    install_should_close_prompt(window, cx, root.downgrade());
+    root
 }
 
+/// Offers to save each tab with unsaved changes before the window closes.
 fn install_should_close_prompt(
     window: &mut Window,
     cx: &mut App,
-    document: gpui::Entity<crate::model::document::DocumentState>,
+    root: gpui::WeakEntity<RootView>,
 ) {
-    window.on_window_should_close(cx, {
-        move |_, cx| {
-            let is_dirty = document.read_with(cx, |d, _| d.dirty);
-            if !is_dirty {
-                return true;
-            }
-
-            let choice = MessageDialog::new()
-                .set_level(MessageLevel::Warning)
-                .set_title("Unsaved changes")
-                .set_description("Save changes before closing?")
-                .set_buttons(MessageButtons::YesNoCancelCustom(
-                    "Save".to_string(),
-                    "Don't Save".to_string(),
-                    "Cancel".to_string(),
-                ))
-                .show();
-
-            let mut save = || {
-                let current_path = document.read_with(cx, |d, _| d.path.clone());
-                // Only save if we have an existing path - avoid blocking file dialog
-                let Some(path) = current_path else {
-                    // No path - need to use Save As, which requires async dialog
-                    // Cancel the close and notify user to save first
-                    MessageDialog::new()
-                        .set_level(MessageLevel::Info)
-                        .set_title("Save required")
-                        .set_description(
-                            "Please use Save As (Cmd+Shift+S) to save this file first.",
-                        )
-                        .set_buttons(MessageButtons::Ok)
-                        .show();
-                    return false;
-                };
-
-                let contents = document.read_with(cx, |d, _| d.text());
-                match write_atomic(&path, &contents) {
-                    Ok(()) => {
-                        let _ = document.update(cx, |d, cx| {
-                            d.path = Some(path.clone());
-                            d.save_snapshot();
-                            cx.notify();
-                        });
-                        true
-                    }
-                    Err(err) => {
-                        MessageDialog::new()
-                            .set_level(MessageLevel::Error)
-                            .set_title("Save failed")
-                            .set_description(format!("Failed to save {}: {}", path, err))
-                            .set_buttons(MessageButtons::Ok)
-                            .show();
-                        false
-                    }
-                }
-            };
-
-            match choice {
-                MessageDialogResult::Ok | MessageDialogResult::Yes => save(),
-                MessageDialogResult::No => true,
-                MessageDialogResult::Custom(label) => match label.as_str() {
-                    "Save" => save(),
-                    "Don't Save" => true,
-                    _ => false,
-                },
-                _ => false,
-            }
+    window.on_window_should_close(cx, move |window, cx| {
+        let can_close = root
+            .update(cx, |root, cx| root.confirm_before_close(window, cx))
+            .unwrap_or(true);
+        // Closing the last window ends the session as quitting does.
+        if can_close
+            && cx.windows().len() == 1
+            && let Ok(session) = root.update(cx, |root, cx| root.session(window, cx))
+        {
+            remember_session(vec![session]);
         }
+        can_close
     });
 }
 
+/// Saves the windows to reopen on the next launch, unless that is turned off.
+fn remember_session(windows: Vec<WindowSession>) {
+    if !settings::get_restore_session() {
+        return;
+    }
+    if let Err(err) = save_session(&Session { windows }) {
+        tracing::warn!("can't save session: {err}");
+    }
+}
+
 fn parse_open_target(raw: &str) -> Option<Utf8PathBuf> {
     if let Ok(url) = Url::parse(raw) {
         if url.scheme() == "file" {
@@ -0,0 +1,378 @@
+use crate::services::export::pdf::{PageSize, PdfOptions};
+use crate::services::export::{ExportOptions, export_file};
+use crate::services::format::format_markdown;
+use crate::services::fs::write_atomic;
+use crate::services::lint::lint_markdown;
+use crate::services::stats::{DocumentStats, document_stats};
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::Serialize;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+usage: Aster [<file.md>...]
+       Aster export <input.md> -o <output.html|.pdf|.epub|.docx> [--css <stylesheet>]
+             [--page-size a4|letter|legal] [--margin <mm>]
+       Aster lint <file.md>...
+       Aster format [--check] <file.md>...
+       Aster stats [--json] <file.md>...
+
+A first argument naming an existing file is opened rather than run as a
+command; write ./<name> to open a folder named like a command.
+
+Exit status is 1 when a command fails or finds problems (lint issues, or
+unformatted files with --check) and 2 for invalid arguments.";
+
+/// Headless invocations that run without opening a window.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// Render `input` to `output`, picking the format from the output extension.
+    Export {
+        input: Utf8PathBuf,
+        output: Utf8PathBuf,
+        css: Option<Utf8PathBuf>,
+        pdf: PdfOptions,
+    },
+    /// Report Markdown problems in `files`.
+    Lint {
+        files: Vec<Utf8PathBuf>,
+    },
+    /// Rewrite `files` in place, or with `check` only list those that would change.
+    Format {
+        files: Vec<Utf8PathBuf>,
+        check: bool,
+    },
+    /// Print word and structure counts for `files`.
+    Stats {
+        files: Vec<Utf8PathBuf>,
+        json: bool,
+    },
+    Help,
+}
+
+/// Parses command-line arguments (without the program name). Returns `None`
+/// when Aster should start normally, treating the arguments as files to open.
+pub fn parse(args: &[String]) -> Option<Result<Command, String>> {
+    parse_in(args, Utf8Path::new("."))
+}
+
+/// Like [`parse`], resolving relative paths against `dir`.
+fn parse_in(args: &[String], dir: &Utf8Path) -> Option<Result<Command, String>> {
+    let (first, rest) = args.split_first()?;
+    // A file that happens to be named like a command is opened.
+    if dir.join(first).is_file() {
+        return None;
+    }
+    Some(match first.as_str() {
+        "export" => parse_export(rest),
+        "lint" => parse_files(rest, None).map(|(files, _)| Command::Lint { files }),
+        "format" => parse_files(rest, Some("--check"))
+            .map(|(files, check)| Command::Format { files, check }),
+        "stats" => {
+            parse_files(rest, Some("--json")).map(|(files, json)| Command::Stats { files, json })
+        }
+        "help" | "--help" | "-h" => Ok(Command::Help),
+        // The original `--export <output> <input>` form.
+        _ if args.iter().any(|arg| arg == "--export") => parse_export(args),
+        _ => return None,
+    })
+}
+
+fn parse_export(args: &[String]) -> Result<Command, String> {
+    let mut output = None;
+    let mut input = None;
+    let mut css = None;
+    let mut pdf = PdfOptions::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" | "--export" => {
+                output = Some(args.next().ok_or(format!("{arg} needs an output path"))?)
+            }
+            "--css" => css = Some(args.next().ok_or("--css needs a stylesheet path")?),
+            "--page-size" => {
+                let name = args.next().ok_or("--page-size needs a size")?;
+                pdf.page_size =
+                    PageSize::from_name(name).ok_or(format!("unknown page size {name}"))?;
+            }
+            "--margin" => {
+                let margin = args.next().ok_or("--margin needs a size in millimetres")?;
+                pdf.margin_mm = margin
+                    .parse()
+                    .ok()
+                    .filter(|mm: &f32| *mm >= 0.)
+                    .ok_or(format!("invalid margin {margin}"))?;
+            }
+            flag if flag.starts_with("--") => return Err(format!("unknown option {flag}")),
+            path if input.is_none() => input = Some(path),
+            path => return Err(format!("unexpected argument {path}")),
+        }
+    }
+    Ok(Command::Export {
+        input: input.ok_or("missing input file")?.into(),
+        output: output.ok_or("missing output path")?.into(),
+        css: css.map(Utf8PathBuf::from),
+        pdf,
+    })
+}
+
+/// Parses a list of input files plus an optional boolean `flag`.
+fn parse_files(args: &[String], flag: Option<&str>) -> Result<(Vec<Utf8PathBuf>, bool), String> {
+    let mut files = Vec::new();
+    let mut flag_set = false;
+    for arg in args {
+        match arg.as_str() {
+            arg if Some(arg) == flag => flag_set = true,
+            arg if arg.starts_with('-') => return Err(format!("unknown option {arg}")),
+            path => files.push(Utf8PathBuf::from(path)),
+        }
+    }
+    if files.is_empty() {
+        return Err("missing input file".into());
+    }
+    Ok((files, flag_set))
+}
+
+/// Runs a headless command, reporting errors on stderr.
+pub fn run(command: Command) -> ExitCode {
+    match command {
+        Command::Export {
+            input,
+            output,
+            css,
+            pdf,
+        } => {
+            let options = ExportOptions {
+                source_path: None,
+                css_path: css,
+                pdf,
+            };
+            match export_file(&input, &output, options) {
+                Ok(warnings) => {
+                    for warning in warnings {
+                        eprintln!("Aster: warning: {output}: {warning}");
+                    }
+                    ExitCode::SUCCESS
+                }
+                Err(err) => {
+                    eprintln!("Aster: failed to export {input} to {output}: {err}");
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Command::Lint { files } => for_each_file(&files, |path, source| {
+            let issues = lint_markdown(source, Some(path));
+            for issue in &issues {
+                println!(
+                    "{path}:{}:{}: {}: {}",
+                    issue.line, issue.column, issue.rule, issue.message
+                );
+            }
+            issues.is_empty()
+        }),
+        Command::Format { files, check } => for_each_file(&files, |path, source| {
+            let formatted = format_markdown(source);
+            if formatted == source {
+                return true;
+            }
+            if check {
+                println!("{path}: not formatted");
+                return false;
+            }
+            match write_atomic(&path.to_path_buf(), formatted) {
+                Ok(()) => {
+                    println!("formatted {path}");
+                    true
+                }
+                Err(err) => {
+                    eprintln!("Aster: failed to write {path}: {err}");
+                    false
+                }
+            }
+        }),
+        Command::Stats { files, json } => {
+            let mut all = Vec::new();
+            let code = for_each_file(&files, |path, source| {
+                all.push(FileStats {
+                    path: path.as_str().to_string(),
+                    stats: document_stats(source),
+                });
+                true
+            });
+            if json {
+                println!("{}", serde_json::to_string_pretty(&all).unwrap_or_default());
+            } else {
+                for FileStats { path, stats } in &all {
+                    println!(
+                        "{path}: {} words, {} characters, {} lines, {} headings, {} links, \
+                         {} images, {} code blocks, {} tables, ~{} min read",
+                        stats.words,
+                        stats.characters,
+                        stats.lines,
+                        stats.headings,
+                        stats.links,
+                        stats.images,
+                        stats.code_blocks,
+                        stats.tables,
+                        stats.reading_minutes
+                    );
+                }
+            }
+            code
+        }
+        Command::Help => {
+            println!("{USAGE}");
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct FileStats {
+    path: String,
+    #[serde(flatten)]
+    stats: DocumentStats,
+}
+
+/// Reads each of `files` and passes it to `check`, which returns whether the
+/// file passed. Fails if any file could not be read or did not pass.
+fn for_each_file(
+    files: &[Utf8PathBuf],
+    mut check: impl FnMut(&Utf8Path, &str) -> bool,
+) -> ExitCode {
+    let mut passed = true;
+    for path in files {
+        match std::fs::read_to_string(path) {
+            Ok(source) => passed &= check(path, &source),
+            Err(err) => {
+                eprintln!("Aster: failed to read {path}: {err}");
+                passed = false;
+            }
+        }
+    }
+    if passed {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Handles headless arguments. Returns the exit code if Aster should exit
+/// without starting the UI.
+pub fn run_from_args(args: &[String]) -> Option<ExitCode> {
+    match parse(args)? {
+        Ok(command) => Some(run(command)),
+        Err(err) => {
+            eprintln!("Aster: {err}\n{USAGE}");
+            Some(ExitCode::from(2))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_export_arguments_in_any_order() {
+        assert_eq!(parse(&args(&["notes.md"])), None);
+        assert_eq!(
+            parse(&args(&["--export", "out.html", "in.md"])),
+            Some(Ok(Command::Export {
+                input: "in.md".into(),
+                output: "out.html".into(),
+                css: None,
+                pdf: PdfOptions::default(),
+            }))
+        );
+        assert_eq!(
+            parse(&args(&["in.md", "--css", "a.css", "--export", "out.html"])),
+            Some(Ok(Command::Export {
+                input: "in.md".into(),
+                output: "out.html".into(),
+                css: Some("a.css".into()),
+                pdf: PdfOptions::default(),
+            }))
+        );
+        assert_eq!(
+            parse(&args(&[
+                "--export",
+                "o.pdf",
+                "i.md",
+                "--page-size",
+                "Letter",
+                "--margin",
+                "12.5"
+            ])),
+            Some(Ok(Command::Export {
+                input: "i.md".into(),
+                output: "o.pdf".into(),
+                css: None,
+                pdf: PdfOptions {
+                    page_size: PageSize::Letter,
+                    margin_mm: 12.5,
+                },
+            }))
+        );
+        assert!(matches!(
+            parse(&args(&["--export", "o.pdf", "i.md", "--page-size", "a9"])),
+            Some(Err(_))
+        ));
+        assert!(matches!(
+            parse(&args(&["--export", "out.html"])),
+            Some(Err(_))
+        ));
+    }
+
+    #[test]
+    fn parses_batch_subcommands() {
+        assert_eq!(
+            parse(&args(&["export", "in.md", "-o", "out.docx"])),
+            Some(Ok(Command::Export {
+                input: "in.md".into(),
+                output: "out.docx".into(),
+                css: None,
+                pdf: PdfOptions::default(),
+            }))
+        );
+        assert_eq!(
+            parse(&args(&["format", "--check", "a.md", "b.md"])),
+            Some(Ok(Command::Format {
+                files: vec!["a.md".into(), "b.md".into()],
+                check: true,
+            }))
+        );
+        assert_eq!(
+            parse(&args(&["stats", "a.md"])),
+            Some(Ok(Command::Stats {
+                files: vec!["a.md".into()],
+                json: false,
+            }))
+        );
+        assert!(matches!(parse(&args(&["lint"])), Some(Err(_))));
+        assert!(matches!(
+            parse(&args(&["lint", "--fix", "a.md"])),
+            Some(Err(_))
+        ));
+    }
+
+    #[test]
+    fn opens_files_named_like_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = Utf8Path::from_path(dir.path()).unwrap();
+        std::fs::write(dir.join("stats"), "# Stats").unwrap();
+        std::fs::create_dir(dir.join("export")).unwrap();
+
+        assert_eq!(parse_in(&args(&["stats"]), dir), None);
+        assert_eq!(parse_in(&args(&["stats", "a.md"]), dir), None);
+        // A folder named like a command doesn't shadow it; ./export opens it.
+        assert!(matches!(
+            parse_in(&args(&["export", "in.md", "-o", "out.pdf"]), dir),
+            Some(Ok(Command::Export { .. }))
+        ));
+        assert_eq!(parse_in(&args(&["./export"]), dir), None);
+    }
+}
@@ -1,26 +1,64 @@
-use gpui::actions;
+use gpui::{Action, actions};
+use std::path::PathBuf;
 
 actions!(
     aster,
     [
         About,
+        ClearRecent,
+        CloseTab,
         CloseWindow,
         Copy,
+        CopyAsPlainText,
+        CopyAsRichText,
         Cut,
+        ExportDocx,
+        ExportEpub,
+        ExportHtml,
+        ExportPdf,
         Find,
         FindNext,
         FindPrevious,
         FontSizeIncrease,
         FontSizeDecrease,
         FontSizeReset,
+        GoToHeading,
+        GoToLine,
+        HideAllMarkers,
+        NavigateBack,
+        NavigateForward,
         NewFile,
+        NextPane,
+        NextTab,
         OpenFile,
+        OpenFolder,
         Paste,
+        PreviousPane,
+        PreviousTab,
+        QuickOpen,
         Quit,
         Redo,
+        RevealActiveFile,
+        RevealMarkersAtCursor,
         SaveFile,
         SaveFileAs,
         SelectAll,
+        ShowAllMarkers,
+        SplitDown,
+        SplitRight,
+        TogglePreview,
+        ToggleSidebar,
+        ToggleVimMode,
         Undo,
     ]
 );
+
+/// Switches to the tab at a 0-based position (`Cmd+1` … `Cmd+9`).
+#[derive(Clone, PartialEq, Action)]
+#[action(namespace = aster, no_json)]
+pub struct ActivateTab(pub usize);
+
+/// Opens a recently used file or folder (File → Open Recent).
+#[derive(Clone, PartialEq, Action)]
+#[action(namespace = aster, no_json)]
+pub struct OpenRecent(pub PathBuf);
@@ -5,6 +5,10 @@ use thiserror::Error;
 pub enum AppError {
     #[error("io: {0}")]
     Io(#[from] io::Error),
+    #[error("export: {0}")]
+    Export(String),
+    #[error("clipboard: {0}")]
+    Clipboard(String),
 }
 
 pub type AppResult<T> = Result<T, AppError>;
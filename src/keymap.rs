@@ -0,0 +1,140 @@
+use crate::commands::{
+    ActivateTab, CloseTab, CloseWindow, Copy, CopyAsPlainText, CopyAsRichText, Cut, Find, FindNext,
+    FindPrevious, FontSizeDecrease, FontSizeIncrease, FontSizeReset, GoToHeading, GoToLine,
+    NavigateBack, NavigateForward, NewFile, NextPane, NextTab, OpenFile, OpenFolder, Paste,
+    PreviousPane, PreviousTab, QuickOpen, Quit, Redo, SaveFile, SaveFileAs, SelectAll, SplitDown,
+    SplitRight, TogglePreview, ToggleSidebar, Undo,
+};
+use gpui::KeyBinding;
+
+/// Desktop platform family used to pick the default keymap.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum KeymapPlatform {
+    MacOs,
+    Linux,
+    Windows,
+}
+
+impl KeymapPlatform {
+    /// Platform the binary was compiled for.
+    pub fn current() -> Self {
+        if cfg!(target_os = "macos") {
+            Self::MacOs
+        } else if cfg!(target_os = "windows") {
+            Self::Windows
+        } else {
+            Self::Linux
+        }
+    }
+
+    /// Keystroke prefix of the primary shortcut modifier (`cmd` on macOS, `ctrl` elsewhere).
+    pub fn primary_modifier(self) -> &'static str {
+        match self {
+            Self::MacOs => "cmd",
+            Self::Linux | Self::Windows => "ctrl",
+        }
+    }
+}
+
+/// Default key bindings for the platform Aster is running on.
+pub fn default_bindings() -> Vec<KeyBinding> {
+    bindings_for(KeymapPlatform::current())
+}
+
+/// Builds the default keymap for `platform`.
+///
+/// Shortcuts are shared across platforms and only the primary modifier
+/// changes, plus a few platform conventions (`F3` find on Linux/Windows).
+pub fn bindings_for(platform: KeymapPlatform) -> Vec<KeyBinding> {
+    let m = platform.primary_modifier();
+    let mut bindings = vec![
+        KeyBinding::new(&format!("{m}-n"), NewFile, None),
+        KeyBinding::new(&format!("{m}-o"), OpenFile, None),
+        KeyBinding::new(&format!("alt-{m}-o"), OpenFolder, None),
+        KeyBinding::new(&format!("{m}-s"), SaveFile, None),
+        KeyBinding::new(&format!("shift-{m}-s"), SaveFileAs, None),
+        KeyBinding::new(&format!("{m}-w"), CloseTab, None),
+        KeyBinding::new(&format!("shift-{m}-w"), CloseWindow, None),
+        KeyBinding::new(&format!("{m}-q"), Quit, None),
+        KeyBinding::new(&format!("{m}-z"), Undo, None),
+        KeyBinding::new(&format!("shift-{m}-z"), Redo, None),
+        KeyBinding::new(&format!("{m}-y"), Redo, None),
+        KeyBinding::new(&format!("{m}-x"), Cut, None),
+        KeyBinding::new(&format!("{m}-c"), Copy, None),
+        KeyBinding::new(&format!("shift-{m}-c"), CopyAsRichText, None),
+        KeyBinding::new(&format!("alt-shift-{m}-c"), CopyAsPlainText, None),
+        KeyBinding::new(&format!("{m}-v"), Paste, None),
+        KeyBinding::new(&format!("{m}-a"), SelectAll, None),
+        KeyBinding::new(&format!("{m}-f"), Find, None),
+        KeyBinding::new(&format!("{m}-g"), FindNext, None),
+        KeyBinding::new(&format!("shift-{m}-g"), FindPrevious, None),
+        KeyBinding::new(&format!("{m}-="), FontSizeIncrease, None),
+        KeyBinding::new(&format!("{m}-0"), FontSizeReset, None),
+        KeyBinding::new(&format!("{m}-l"), GoToLine, None),
+        KeyBinding::new(&format!("shift-{m}-o"), GoToHeading, None),
+        KeyBinding::new(&format!("{m}-p"), QuickOpen, None),
+        KeyBinding::new(&format!("shift-{m}-v"), TogglePreview, None),
+        KeyBinding::new(&format!("{m}-b"), ToggleSidebar, None),
+        KeyBinding::new(&format!("{m}-\\"), SplitRight, None),
+        KeyBinding::new(&format!("alt-{m}-\\"), SplitDown, None),
+        KeyBinding::new(&format!("alt-{m}-]"), NextPane, None),
+        KeyBinding::new(&format!("alt-{m}-["), PreviousPane, None),
+        KeyBinding::new("ctrl-tab", NextTab, None),
+        KeyBinding::new("ctrl-shift-tab", PreviousTab, None),
+    ];
+    bindings
+        .extend((1..=9).map(|n| KeyBinding::new(&format!("{m}-{n}"), ActivateTab(n - 1), None)));
+
+    if platform == KeymapPlatform::MacOs {
+        bindings.extend([
+            KeyBinding::new("cmd--", FontSizeDecrease, None),
+            KeyBinding::new("ctrl--", NavigateBack, None),
+            KeyBinding::new("ctrl-shift--", NavigateForward, None),
+        ]);
+    } else {
+        // Back keeps `Ctrl+-` everywhere, so shrinking the font takes `Ctrl+Alt+-` here.
+        bindings.extend([
+            KeyBinding::new("f3", FindNext, None),
+            KeyBinding::new("shift-f3", FindPrevious, None),
+            KeyBinding::new("ctrl-alt--", FontSizeDecrease, None),
+            KeyBinding::new("ctrl--", NavigateBack, None),
+            KeyBinding::new("ctrl-shift--", NavigateForward, None),
+        ]);
+    }
+
+    bindings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mac_bindings_use_command_modifier() {
+        for binding in bindings_for(KeymapPlatform::MacOs) {
+            let keystroke = binding.keystrokes()[0].inner();
+            if matches!(keystroke.key.as_str(), "-" | "tab") && keystroke.modifiers.control {
+                // Back/Forward follow the macOS editor convention of Ctrl+-, and
+                // tab cycling the Ctrl+Tab convention.
+                continue;
+            }
+            assert!(keystroke.modifiers.platform);
+            assert!(!keystroke.modifiers.control);
+        }
+    }
+
+    #[test]
+    fn linux_and_windows_bindings_use_control_modifier() {
+        for platform in [KeymapPlatform::Linux, KeymapPlatform::Windows] {
+            for binding in bindings_for(platform) {
+                let keystroke = binding.keystrokes()[0].inner();
+                assert!(!keystroke.modifiers.platform);
+                if matches!(keystroke.key.as_str(), "f3") {
+                    // Find next/previous also live on the bare function key.
+                    continue;
+                }
+                assert!(keystroke.modifiers.control, "{}", keystroke.key);
+            }
+        }
+    }
+}
@@ -1,12 +1,21 @@
 mod app;
+mod cli;
 mod commands;
 mod error;
+mod keymap;
 mod logging;
 mod model;
 mod services;
 mod ui;
 
-fn main() {
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
     logging::init_tracing();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = cli::run_from_args(&args) {
+        return code;
+    }
     app::run();
+    ExitCode::SUCCESS
 }
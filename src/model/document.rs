@@ -1,10 +1,13 @@
 use camino::Utf8PathBuf;
 use ropey::Rope;
+use std::collections::VecDeque;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 use std::ops::Range;
 
+use crate::model::jump_list::{JumpEntry, JumpList, is_far_jump};
 use crate::model::undo::{EditOperation, UndoHistory};
+use crate::services::fs::DiskState;
 
 #[derive(Clone, Debug)]
 pub struct EditDelta {
@@ -16,6 +19,121 @@ pub struct EditDelta {
     pub new_end_byte: usize,
 }
 
+impl EditDelta {
+    /// Smallest delta turning `old` into `new`, from their common prefix and suffix.
+    pub fn between(old: &str, new: &str) -> Self {
+        let mut prefix = old
+            .bytes()
+            .zip(new.bytes())
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !old.is_char_boundary(prefix) {
+            prefix -= 1;
+        }
+        let max_suffix = old.len().min(new.len()) - prefix;
+        let mut suffix = old
+            .bytes()
+            .rev()
+            .zip(new.bytes().rev())
+            .take(max_suffix)
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !old.is_char_boundary(old.len() - suffix) {
+            suffix -= 1;
+        }
+
+        let old_end_byte = old.len() - suffix;
+        let new_end_byte = new.len() - suffix;
+        let start_char = old[..prefix].chars().count();
+        Self {
+            start_char,
+            old_end_char: start_char + old[prefix..old_end_byte].chars().count(),
+            new_end_char: start_char + new[prefix..new_end_byte].chars().count(),
+            start_byte: prefix,
+            old_end_byte,
+            new_end_byte,
+        }
+    }
+
+    /// Where character `idx` ends up after this edit; positions inside the
+    /// replaced text snap to where the edit happened.
+    pub fn map_char(&self, idx: usize) -> usize {
+        if idx >= self.old_end_char {
+            idx - self.old_end_char + self.new_end_char
+        } else if idx > self.start_char {
+            self.start_char.min(self.new_end_char)
+        } else {
+            idx
+        }
+    }
+
+    /// Composes this edit with `next` (applied after it) into one covering edit.
+    pub fn then(&self, next: &EditDelta) -> Self {
+        // A position after `self` mapped back to before it.
+        let back = |pos: usize, start: usize, old_end: usize, new_end: usize| {
+            if pos >= new_end {
+                pos - new_end + old_end
+            } else if pos > start {
+                old_end
+            } else {
+                pos
+            }
+        };
+        // A position before `next` mapped forward to after it.
+        let forward = |pos: usize, start: usize, old_end: usize, new_end: usize| {
+            if pos >= old_end {
+                pos - old_end + new_end
+            } else if pos > start {
+                new_end
+            } else {
+                pos
+            }
+        };
+
+        Self {
+            start_char: self.start_char.min(next.start_char),
+            old_end_char: self.old_end_char.max(back(
+                next.old_end_char,
+                self.start_char,
+                self.old_end_char,
+                self.new_end_char,
+            )),
+            new_end_char: next.new_end_char.max(forward(
+                self.new_end_char,
+                next.start_char,
+                next.old_end_char,
+                next.new_end_char,
+            )),
+            start_byte: self.start_byte.min(next.start_byte),
+            old_end_byte: self.old_end_byte.max(back(
+                next.old_end_byte,
+                self.start_byte,
+                self.old_end_byte,
+                self.new_end_byte,
+            )),
+            new_end_byte: next.new_end_byte.max(forward(
+                self.new_end_byte,
+                next.start_byte,
+                next.old_end_byte,
+                next.new_end_byte,
+            )),
+        }
+    }
+}
+
+/// Cursor and selection of an editor pane that isn't the one editing the
+/// document right now, with the revision they refer to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CursorState {
+    pub revision: u64,
+    pub cursor: usize,
+    pub selection: Option<Range<usize>>,
+    pub selection_anchor: Option<usize>,
+}
+
+/// Number of recent edit deltas kept for incremental consumers.
+const EDIT_LOG_LIMIT: usize = 256;
+
 #[derive(Clone)]
 pub struct DocumentState {
     pub path: Option<Utf8PathBuf>,
@@ -34,6 +152,12 @@ pub struct DocumentState {
     pending_edit: Option<PendingEdit>,
     /// Most recent edit delta, updated on each mutation
     pub last_edit: Option<EditDelta>,
+    /// Deltas of recent revisions as (revision produced, delta), oldest first
+    edit_log: VecDeque<(u64, EditDelta)>,
+    /// Back/forward navigation history
+    pub jumps: JumpList,
+    /// The file at `path` as last loaded or saved, to detect changes by others.
+    pub disk_state: Option<DiskState>,
 }
 
 /// Temporary state captured before an edit for undo history
@@ -59,6 +183,9 @@ impl DocumentState {
             undo_history: UndoHistory::default(),
             pending_edit: None,
             last_edit: None,
+            edit_log: VecDeque::new(),
+            jumps: JumpList::default(),
+            disk_state: None,
         }
     }
 
@@ -71,7 +198,7 @@ impl DocumentState {
         self.cursor = self.rope.len_chars();
         self.clear_selection();
         self.bump_revision();
-        self.last_edit = Some(EditDelta {
+        self.record_edit(EditDelta {
             start_char: 0,
             old_end_char: old_chars,
             new_end_char: new_chars,
@@ -79,6 +206,7 @@ impl DocumentState {
             old_end_byte: old_bytes,
             new_end_byte: new_bytes,
         });
+        self.jumps.clamp_to(self.path.as_deref(), new_chars);
         // Don't compute hash here - save_snapshot will handle dirty state
         // Don't compute word count here - it will be computed lazily
         self.word_count_cache = None;
@@ -97,6 +225,27 @@ impl DocumentState {
         self.clear_selection();
     }
 
+    /// Current position as a navigation history entry.
+    pub fn jump_position(&self) -> JumpEntry {
+        JumpEntry {
+            path: self.path.clone(),
+            char_idx: self.cursor,
+        }
+    }
+
+    /// Record the current position in the jump list if `target` is far away.
+    pub fn record_jump(&mut self, target: usize) {
+        if is_far_jump(&self.rope, self.cursor, target) {
+            self.jumps.record(self.jump_position());
+        }
+    }
+
+    /// Move the cursor to `idx`, remembering where it came from for Back.
+    pub fn jump_to(&mut self, idx: usize) {
+        self.record_jump(idx);
+        self.set_cursor(idx);
+    }
+
     pub fn set_selection(&mut self, start: usize, end: usize) {
         let (start, end) = if start <= end {
             (start, end)
@@ -112,6 +261,43 @@ impl DocumentState {
         self.selection_anchor = Some(start.min(self.len_chars()));
     }
 
+    pub fn cursor_state(&self) -> CursorState {
+        CursorState {
+            revision: self.revision,
+            cursor: self.cursor,
+            selection: self.selection.clone(),
+            selection_anchor: self.selection_anchor,
+        }
+    }
+
+    /// `state` moved past the edits made since it was saved. Positions are
+    /// clamped when the edit log no longer reaches back that far.
+    pub fn rebase_cursor_state(&self, state: &CursorState) -> CursorState {
+        let delta = (state.revision != self.revision)
+            .then(|| self.edit_since(state.revision))
+            .flatten();
+        let len = self.len_chars();
+        let map = |idx: usize| delta.as_ref().map_or(idx, |d| d.map_char(idx)).min(len);
+        CursorState {
+            revision: self.revision,
+            cursor: map(state.cursor),
+            selection: state
+                .selection
+                .clone()
+                .map(|range| map(range.start)..map(range.end))
+                .filter(|range| !range.is_empty()),
+            selection_anchor: state.selection_anchor.map(map),
+        }
+    }
+
+    /// Makes `state` (e.g. of a pane becoming active) the document's cursor.
+    pub fn restore_cursor_state(&mut self, state: &CursorState) {
+        let state = self.rebase_cursor_state(state);
+        self.cursor = state.cursor;
+        self.selection = state.selection;
+        self.selection_anchor = state.selection_anchor;
+    }
+
     pub fn clear_selection(&mut self) {
         self.selection = None;
         self.selection_anchor = None;
@@ -145,14 +331,16 @@ impl DocumentState {
         let new_end_byte = start_byte.saturating_add(text.len());
         self.bump_revision();
         self.dirty = true;
-        self.last_edit = Some(EditDelta {
+        let delta = EditDelta {
             start_char: clamped,
             old_end_char: clamped,
             new_end_char,
             start_byte,
             old_end_byte: start_byte,
             new_end_byte,
-        });
+        };
+        self.jumps.apply_edit(self.path.as_deref(), &delta);
+        self.record_edit(delta);
         self.clear_selection();
         self.word_count_cache = None; // Invalidate cache
     }
@@ -168,14 +356,16 @@ impl DocumentState {
         self.rope.remove(range);
         self.bump_revision();
         self.dirty = true;
-        self.last_edit = Some(EditDelta {
+        let delta = EditDelta {
             start_char,
             old_end_char,
             new_end_char: start_char,
             start_byte,
             old_end_byte,
             new_end_byte: start_byte,
-        });
+        };
+        self.jumps.apply_edit(self.path.as_deref(), &delta);
+        self.record_edit(delta);
         self.cursor = self.cursor.min(self.rope.len_chars());
         self.clear_selection();
         self.word_count_cache = None; // Invalidate cache
@@ -217,9 +407,43 @@ impl DocumentState {
         self.dirty = false;
     }
 
+    /// Hash of `text`, which matches `last_saved_hash` if `text` is what was
+    /// last loaded or saved.
+    pub fn text_hash(text: &str) -> u64 {
+        Self::hash_rope(&Rope::from_str(text))
+    }
+
+    /// Replaces the text with `text` from disk as one undoable edit. The cursor
+    /// and selection stay on the same text where it is unchanged.
+    pub fn reload(&mut self, text: &str) {
+        let old_text = self.text();
+        if old_text != text {
+            self.begin_edit();
+            let delta = EditDelta::between(&old_text, text);
+            self.rope = Rope::from_str(text);
+            self.cursor = delta.map_char(self.cursor);
+            self.selection = self
+                .selection
+                .clone()
+                .map(|range| delta.map_char(range.start)..delta.map_char(range.end))
+                .filter(|range| !range.is_empty());
+            self.selection_anchor = self.selection_anchor.map(|idx| delta.map_char(idx));
+            self.bump_revision();
+            self.jumps.apply_edit(self.path.as_deref(), &delta);
+            self.record_edit(delta);
+            self.word_count_cache = None;
+            self.commit_edit();
+        }
+        self.save_snapshot();
+    }
+
     fn current_hash(&self) -> u64 {
+        Self::hash_rope(&self.rope)
+    }
+
+    fn hash_rope(rope: &Rope) -> u64 {
         let mut h = DefaultHasher::new();
-        self.rope.hash(&mut h);
+        rope.hash(&mut h);
         h.finish()
     }
 
@@ -227,6 +451,26 @@ impl DocumentState {
         self.revision = self.revision.wrapping_add(1);
     }
 
+    /// Remember the delta that produced the current revision.
+    fn record_edit(&mut self, delta: EditDelta) {
+        self.edit_log.push_back((self.revision, delta.clone()));
+        if self.edit_log.len() > EDIT_LOG_LIMIT {
+            self.edit_log.pop_front();
+        }
+        self.last_edit = Some(delta);
+    }
+
+    /// Combined delta of every edit after `revision`, if the log still reaches back that far.
+    pub fn edit_since(&self, revision: u64) -> Option<EditDelta> {
+        let first = self
+            .edit_log
+            .iter()
+            .position(|(rev, _)| *rev == revision.wrapping_add(1))?;
+        let mut deltas = self.edit_log.iter().skip(first).map(|(_, delta)| delta);
+        let first = deltas.next()?.clone();
+        Some(deltas.fold(first, |merged, delta| merged.then(delta)))
+    }
+
     /// Get word count, computing it if not cached
     pub fn get_word_count(&mut self) -> usize {
         if let Some(count) = self.word_count_cache {
@@ -257,9 +501,14 @@ impl DocumentState {
     /// Commit the pending edit to history - call after making changes
     pub fn commit_edit(&mut self) {
         if let Some(pending) = self.pending_edit.take() {
+            let new_text = self.text();
+            // Skip no-op edits (e.g. entering and leaving Vim insert mode without typing)
+            if new_text == pending.old_text {
+                return;
+            }
             let op = EditOperation {
                 old_text: pending.old_text,
-                new_text: self.text(),
+                new_text,
                 old_cursor: pending.old_cursor,
                 new_cursor: self.cursor,
                 old_selection: pending.old_selection,
@@ -272,11 +521,14 @@ impl DocumentState {
     /// Undo the last edit operation
     pub fn undo(&mut self) -> bool {
         if let Some(op) = self.undo_history.undo() {
+            let delta = EditDelta::between(&self.text(), &op.old_text);
             self.rope = Rope::from_str(&op.old_text);
             self.cursor = op.old_cursor.min(self.rope.len_chars());
             self.selection = op.old_selection;
             self.selection_anchor = self.selection.as_ref().map(|r| r.start);
             self.bump_revision();
+            self.jumps.apply_edit(self.path.as_deref(), &delta);
+            self.record_edit(delta);
             self.word_count_cache = None;
             // Update dirty state: dirty if current content differs from saved
             self.dirty = self.current_hash() != self.last_saved_hash;
@@ -289,11 +541,14 @@ impl DocumentState {
     /// Redo the last undone operation
     pub fn redo(&mut self) -> bool {
         if let Some(op) = self.undo_history.redo() {
+            let delta = EditDelta::between(&self.text(), &op.new_text);
             self.rope = Rope::from_str(&op.new_text);
             self.cursor = op.new_cursor.min(self.rope.len_chars());
             self.selection = op.new_selection;
             self.selection_anchor = self.selection.as_ref().map(|r| r.start);
             self.bump_revision();
+            self.jumps.apply_edit(self.path.as_deref(), &delta);
+            self.record_edit(delta);
             self.word_count_cache = None;
             // Update dirty state: dirty if current content differs from saved
             self.dirty = self.current_hash() != self.last_saved_hash;
@@ -0,0 +1,189 @@
+use crate::services::fs::DirEntry;
+use camino::{Utf8Path, Utf8PathBuf};
+use std::collections::{HashMap, HashSet};
+
+/// A visible line of the workspace tree.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TreeRow {
+    pub path: Utf8PathBuf,
+    pub name: String,
+    pub is_dir: bool,
+    pub expanded: bool,
+    /// Nesting below the workspace root (0 for its direct children).
+    pub depth: usize,
+}
+
+/// Markdown files under a workspace folder. Directories are listed by the
+/// caller, off the UI thread, the first time they are shown; the listing is
+/// kept when a folder is collapsed.
+pub struct FileTree {
+    root: Utf8PathBuf,
+    expanded: HashSet<Utf8PathBuf>,
+    children: HashMap<Utf8PathBuf, Vec<DirEntry>>,
+    /// Directories handed out by `take_unlisted` whose listing hasn't arrived.
+    listing: HashSet<Utf8PathBuf>,
+}
+
+impl FileTree {
+    pub fn new(root: Utf8PathBuf) -> Self {
+        Self {
+            root,
+            expanded: HashSet::new(),
+            children: HashMap::new(),
+            listing: HashSet::new(),
+        }
+    }
+
+    pub fn root(&self) -> &Utf8Path {
+        &self.root
+    }
+
+    pub fn contains(&self, path: &Utf8Path) -> bool {
+        path.starts_with(&self.root) && path != self.root
+    }
+
+    /// Expands or collapses `dir`.
+    pub fn toggle(&mut self, dir: &Utf8Path) {
+        if !self.expanded.remove(dir) {
+            self.expanded.insert(dir.to_path_buf());
+        }
+    }
+
+    /// Expands every folder between the root and `path`. Returns false if
+    /// `path` is outside the workspace.
+    pub fn reveal(&mut self, path: &Utf8Path) -> bool {
+        if !self.contains(path) {
+            return false;
+        }
+        for dir in path.ancestors().skip(1) {
+            if dir == self.root {
+                break;
+            }
+            self.expanded.insert(dir.to_path_buf());
+        }
+        true
+    }
+
+    /// Shown directories that still need listing, marked as being listed so
+    /// they are only handed out once.
+    pub fn take_unlisted(&mut self) -> Vec<Utf8PathBuf> {
+        let mut unlisted = Vec::new();
+        self.collect_unlisted(&self.root, &mut unlisted);
+        self.listing.extend(unlisted.iter().cloned());
+        unlisted
+    }
+
+    fn collect_unlisted(&self, dir: &Utf8Path, unlisted: &mut Vec<Utf8PathBuf>) {
+        let Some(entries) = self.children.get(dir) else {
+            if !self.listing.contains(dir) {
+                unlisted.push(dir.to_path_buf());
+            }
+            return;
+        };
+        for entry in entries {
+            if entry.is_dir && self.expanded.contains(&entry.path) {
+                self.collect_unlisted(&entry.path, unlisted);
+            }
+        }
+    }
+
+    /// Stores the listing of `dir`. Listings from outside the tree, e.g. of a
+    /// previously opened folder, are ignored.
+    pub fn set_children(&mut self, dir: Utf8PathBuf, entries: Vec<DirEntry>) {
+        if dir == self.root || self.contains(&dir) {
+            self.listing.remove(&dir);
+            self.children.insert(dir, entries);
+        }
+    }
+
+    /// The rows currently visible, in display order. Folders that haven't
+    /// been listed yet show no children.
+    pub fn rows(&self) -> Vec<TreeRow> {
+        let mut rows = Vec::new();
+        self.push_rows(&self.root, 0, &mut rows);
+        rows
+    }
+
+    fn push_rows(&self, dir: &Utf8Path, depth: usize, rows: &mut Vec<TreeRow>) {
+        let Some(entries) = self.children.get(dir) else {
+            return;
+        };
+        for entry in entries {
+            let expanded = entry.is_dir && self.expanded.contains(&entry.path);
+            rows.push(TreeRow {
+                name: entry.path.file_name().unwrap_or_default().to_string(),
+                path: entry.path.clone(),
+                is_dir: entry.is_dir,
+                expanded,
+                depth,
+            });
+            if expanded {
+                self.push_rows(&entry.path, depth + 1, rows);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::fs::list_markdown_dir;
+
+    /// Lists everything `take_unlisted` asks for, as the explorer does in
+    /// the background, and returns the visible rows.
+    fn names(tree: &mut FileTree) -> Vec<(usize, String)> {
+        loop {
+            let unlisted = tree.take_unlisted();
+            if unlisted.is_empty() {
+                break;
+            }
+            for dir in unlisted {
+                let entries = list_markdown_dir(&dir);
+                tree.set_children(dir, entries);
+            }
+        }
+        tree.rows()
+            .into_iter()
+            .map(|row| (row.depth, row.name))
+            .collect()
+    }
+
+    #[test]
+    fn reveal_expands_ancestors_of_nested_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = Utf8Path::from_path(dir.path()).unwrap().to_path_buf();
+        std::fs::create_dir_all(root.join("docs/guide")).unwrap();
+        std::fs::write(root.join("docs/guide/setup.md"), "").unwrap();
+        std::fs::write(root.join("readme.md"), "").unwrap();
+
+        let mut tree = FileTree::new(root.clone());
+        assert!(tree.rows().is_empty());
+        assert_eq!(tree.take_unlisted(), vec![root.clone()]);
+        assert!(tree.take_unlisted().is_empty());
+        tree.set_children(root.clone(), list_markdown_dir(&root));
+        assert_eq!(
+            names(&mut tree),
+            vec![(0, "docs".into()), (0, "readme.md".into())]
+        );
+
+        assert!(tree.reveal(&root.join("docs/guide/setup.md")));
+        assert_eq!(
+            names(&mut tree),
+            vec![
+                (0, "docs".into()),
+                (1, "guide".into()),
+                (2, "setup.md".into()),
+                (0, "readme.md".into()),
+            ]
+        );
+
+        tree.toggle(&root.join("docs"));
+        assert_eq!(names(&mut tree).len(), 2);
+        assert!(!tree.reveal(Utf8Path::new("/elsewhere/file.md")));
+
+        // Reopening a folder reuses its listing.
+        tree.toggle(&root.join("docs"));
+        assert!(tree.take_unlisted().is_empty());
+        assert_eq!(tree.rows().len(), 4);
+    }
+}
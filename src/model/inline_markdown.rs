@@ -1,10 +1,22 @@
-use crate::services::syntax::SyntaxSpan;
+use crate::services::code_highlight::{CodeToken, FencedCode};
+use crate::services::inline_markdown::InlineParseResult;
+use crate::services::syntax::{BlockSpans, SyntaxSpan};
+use std::collections::HashMap;
 use std::sync::Arc;
 
 /// Incremental inline-markdown presentation state consumed by the editor.
 #[derive(Clone)]
 pub struct InlineMarkdownState {
     pub spans: Arc<Vec<SyntaxSpan>>,
+    /// Per-block spans for `source_revision`, the base for incremental re-parsing.
+    pub blocks: Arc<Vec<BlockSpans>>,
+    /// Fenced code blocks for `source_revision`.
+    pub code_blocks: Arc<Vec<FencedCode>>,
+    /// Code highlights keyed by [`FencedCode::hash`], filled in by background jobs.
+    code_highlights: HashMap<u64, Arc<Vec<CodeToken>>>,
+    /// Highlights of the block previously at the same position, shown while an
+    /// edited block is re-highlighted so colors do not flicker while typing.
+    stale_highlights: HashMap<u64, Arc<Vec<CodeToken>>>,
     pub source_revision: u64,
     pub parse_millis: f32,
     pub dropped_updates: u64,
@@ -14,9 +26,72 @@ impl InlineMarkdownState {
     pub fn new() -> Self {
         Self {
             spans: Arc::new(Vec::new()),
+            blocks: Arc::new(Vec::new()),
+            code_blocks: Arc::new(Vec::new()),
+            code_highlights: HashMap::new(),
+            stale_highlights: HashMap::new(),
             source_revision: 0,
             parse_millis: 0.0,
             dropped_updates: 0,
         }
     }
+
+    /// Store a parse of `revision` unless a newer one has already landed.
+    /// Returns whether the state changed.
+    pub fn apply_parse(&mut self, revision: u64, parsed: InlineParseResult) -> bool {
+        if revision < self.source_revision {
+            self.dropped_updates = self.dropped_updates.saturating_add(1);
+            return false;
+        }
+
+        let mut stale = HashMap::new();
+        for (ix, block) in parsed.code_blocks.iter().enumerate() {
+            if self.code_highlights.contains_key(&block.hash) {
+                continue;
+            }
+            let previous = self
+                .code_blocks
+                .get(ix)
+                .filter(|previous| previous.language == block.language)
+                .and_then(|previous| self.highlights_for(previous));
+            if let Some(tokens) = previous {
+                stale.insert(block.hash, tokens.clone());
+            }
+        }
+        self.code_highlights
+            .retain(|hash, _| parsed.code_blocks.iter().any(|block| block.hash == *hash));
+        self.stale_highlights = stale;
+
+        self.spans = Arc::new(parsed.spans);
+        self.blocks = Arc::new(parsed.blocks);
+        self.code_blocks = Arc::new(parsed.code_blocks);
+        self.source_revision = revision;
+        self.parse_millis = parsed.parse_millis;
+        true
+    }
+
+    /// Tokens to draw for `block`: its own highlights, or stale ones while pending.
+    pub fn highlights_for(&self, block: &FencedCode) -> Option<&Arc<Vec<CodeToken>>> {
+        self.code_highlights
+            .get(&block.hash)
+            .or_else(|| self.stale_highlights.get(&block.hash))
+    }
+
+    /// Code blocks with a language whose highlights have not been computed yet.
+    pub fn missing_highlights(&self) -> impl Iterator<Item = &FencedCode> {
+        self.code_blocks.iter().filter(|block| {
+            !block.language.is_empty() && !self.code_highlights.contains_key(&block.hash)
+        })
+    }
+
+    /// Store highlights computed for the block content with `hash`.
+    /// Returns whether a current block uses them.
+    pub fn store_highlights(&mut self, hash: u64, tokens: Vec<CodeToken>) -> bool {
+        if !self.code_blocks.iter().any(|block| block.hash == hash) {
+            return false;
+        }
+        self.stale_highlights.remove(&hash);
+        self.code_highlights.insert(hash, Arc::new(tokens));
+        true
+    }
 }
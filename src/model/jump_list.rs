@@ -0,0 +1,174 @@
+use camino::{Utf8Path, Utf8PathBuf};
+use ropey::Rope;
+
+use crate::model::document::EditDelta;
+
+/// Cursor moves spanning at least this many lines are recorded as jumps.
+pub const JUMP_LINE_THRESHOLD: usize = 10;
+
+/// Maximum number of entries kept on each side of the jump list.
+const MAX_JUMPS: usize = 100;
+
+/// A position the user can navigate back (or forward) to.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct JumpEntry {
+    pub path: Option<Utf8PathBuf>,
+    pub char_idx: usize,
+}
+
+/// Back/forward navigation history across cursor jumps and files.
+#[derive(Clone, Default)]
+pub struct JumpList {
+    /// Positions to go back to (most recent at end)
+    back: Vec<JumpEntry>,
+    /// Positions to go forward to (most recent at end)
+    forward: Vec<JumpEntry>,
+}
+
+impl JumpList {
+    /// Record `from` as the position being jumped away from.
+    /// Clears the forward history, like a browser.
+    pub fn record(&mut self, from: JumpEntry) {
+        if self.back.last() != Some(&from) {
+            self.back.push(from);
+        }
+        self.forward.clear();
+        if self.back.len() > MAX_JUMPS {
+            self.back.remove(0);
+        }
+    }
+
+    /// Step back from `current`, returning the position to move to.
+    pub fn go_back(&mut self, current: JumpEntry) -> Option<JumpEntry> {
+        let target = self.back.pop()?;
+        self.forward.push(current);
+        Some(target)
+    }
+
+    /// Step forward from `current`, returning the position to move to.
+    pub fn go_forward(&mut self, current: JumpEntry) -> Option<JumpEntry> {
+        let target = self.forward.pop()?;
+        self.back.push(current);
+        Some(target)
+    }
+
+    /// Drop an entry that could not be restored (e.g. its file was deleted).
+    pub fn discard(&mut self, entry: &JumpEntry) {
+        self.back.retain(|e| e != entry);
+        self.forward.retain(|e| e != entry);
+    }
+
+    /// Shift entries in `path` so they keep pointing at the same text after an edit.
+    pub fn apply_edit(&mut self, path: Option<&Utf8Path>, delta: &EditDelta) {
+        for entry in self.entries_in_mut(path) {
+            entry.char_idx = delta.map_char(entry.char_idx);
+        }
+    }
+
+    /// Clamp entries in `path` after the whole text was replaced (undo, reload).
+    pub fn clamp_to(&mut self, path: Option<&Utf8Path>, len_chars: usize) {
+        for entry in self.entries_in_mut(path) {
+            entry.char_idx = entry.char_idx.min(len_chars);
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn can_go_back(&self) -> bool {
+        !self.back.is_empty()
+    }
+
+    #[allow(dead_code)]
+    pub fn can_go_forward(&self) -> bool {
+        !self.forward.is_empty()
+    }
+
+    fn entries_in_mut<'a>(
+        &'a mut self,
+        path: Option<&'a Utf8Path>,
+    ) -> impl Iterator<Item = &'a mut JumpEntry> + 'a {
+        self.back
+            .iter_mut()
+            .chain(self.forward.iter_mut())
+            .filter(move |entry| entry.path.as_deref() == path)
+    }
+}
+
+/// Whether moving the cursor from `from` to `to` is far enough to record a jump.
+pub fn is_far_jump(rope: &Rope, from: usize, to: usize) -> bool {
+    let len = rope.len_chars();
+    let from_line = rope.char_to_line(from.min(len));
+    let to_line = rope.char_to_line(to.min(len));
+    from_line.abs_diff(to_line) >= JUMP_LINE_THRESHOLD
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(path: &str, char_idx: usize) -> JumpEntry {
+        JumpEntry {
+            path: Some(Utf8PathBuf::from(path)),
+            char_idx,
+        }
+    }
+
+    #[test]
+    fn back_and_forward_walk_the_history() {
+        let mut jumps = JumpList::default();
+        jumps.record(at("a.md", 10));
+        jumps.record(at("a.md", 200));
+
+        assert_eq!(jumps.go_back(at("b.md", 5)), Some(at("a.md", 200)));
+        assert_eq!(jumps.go_back(at("a.md", 200)), Some(at("a.md", 10)));
+        assert_eq!(jumps.go_back(at("a.md", 10)), None);
+        assert_eq!(jumps.go_forward(at("a.md", 10)), Some(at("a.md", 200)));
+        assert_eq!(jumps.go_forward(at("a.md", 200)), Some(at("b.md", 5)));
+        assert!(!jumps.can_go_forward());
+
+        jumps.go_back(at("b.md", 5));
+        jumps.record(at("a.md", 200));
+        assert!(!jumps.can_go_forward());
+    }
+
+    #[test]
+    fn entries_follow_edits_in_their_file() {
+        let mut jumps = JumpList::default();
+        jumps.record(at("a.md", 5));
+        jumps.record(at("a.md", 50));
+        jumps.record(at("b.md", 50));
+
+        // Insert 10 chars at 20 in a.md.
+        let insert = EditDelta {
+            start_char: 20,
+            old_end_char: 20,
+            new_end_char: 30,
+            start_byte: 20,
+            old_end_byte: 20,
+            new_end_byte: 30,
+        };
+        jumps.apply_edit(Some(Utf8Path::new("a.md")), &insert);
+        // Delete 40..70 in a.md, swallowing the entry at 60.
+        let delete = EditDelta {
+            start_char: 40,
+            old_end_char: 70,
+            new_end_char: 40,
+            start_byte: 40,
+            old_end_byte: 70,
+            new_end_byte: 40,
+        };
+        jumps.apply_edit(Some(Utf8Path::new("a.md")), &delete);
+
+        let current = at("a.md", 0);
+        assert_eq!(jumps.go_back(current.clone()), Some(at("b.md", 50)));
+        assert_eq!(jumps.go_back(current.clone()), Some(at("a.md", 40)));
+        assert_eq!(jumps.go_back(current), Some(at("a.md", 5)));
+    }
+
+    #[test]
+    fn only_distant_moves_count_as_jumps() {
+        let rope = Rope::from_str(&"line\n".repeat(40));
+        assert!(!is_far_jump(&rope, 0, 5 * 5));
+        assert!(is_far_jump(&rope, 0, 5 * JUMP_LINE_THRESHOLD));
+        assert!(is_far_jump(&rope, 5 * 30, 0));
+    }
+}
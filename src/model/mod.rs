@@ -1,3 +1,8 @@
 pub mod document;
+pub mod file_tree;
 pub mod inline_markdown;
+pub mod jump_list;
+pub mod pane_layout;
+pub mod recent;
+pub mod tabs;
 pub mod undo;
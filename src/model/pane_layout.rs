@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+
+/// How a split arranges its children.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum SplitAxis {
+    /// Side by side.
+    Horizontal,
+    /// Stacked top to bottom.
+    Vertical,
+}
+
+/// Arrangement of editor panes, identified by id, in the editor area.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum PaneLayout {
+    Pane(usize),
+    Split {
+        axis: SplitAxis,
+        children: Vec<PaneLayout>,
+    },
+}
+
+impl PaneLayout {
+    /// Puts pane `new` next to pane `target` along `axis`. Splitting along the
+    /// axis of the enclosing split adds a sibling instead of nesting.
+    pub fn split(&mut self, target: usize, new: usize, axis: SplitAxis) -> bool {
+        match self {
+            PaneLayout::Pane(id) if *id == target => {
+                *self = PaneLayout::Split {
+                    axis,
+                    children: vec![PaneLayout::Pane(target), PaneLayout::Pane(new)],
+                };
+                true
+            }
+            PaneLayout::Pane(_) => false,
+            PaneLayout::Split {
+                axis: split_axis,
+                children,
+            } => {
+                if *split_axis == axis
+                    && let Some(ix) = children
+                        .iter()
+                        .position(|child| *child == PaneLayout::Pane(target))
+                {
+                    children.insert(ix + 1, PaneLayout::Pane(new));
+                    return true;
+                }
+                children
+                    .iter_mut()
+                    .any(|child| child.split(target, new, axis))
+            }
+        }
+    }
+
+    /// Removes pane `id`; a split left with one child is replaced by it. The
+    /// last pane can't be removed.
+    pub fn remove(&mut self, id: usize) -> bool {
+        let PaneLayout::Split { children, .. } = self else {
+            return false;
+        };
+        let removed = if let Some(ix) = children
+            .iter()
+            .position(|child| *child == PaneLayout::Pane(id))
+        {
+            children.remove(ix);
+            true
+        } else {
+            children.iter_mut().any(|child| child.remove(id))
+        };
+        if children.len() == 1 {
+            *self = children.remove(0);
+        }
+        removed
+    }
+
+    /// Pane ids in reading order.
+    pub fn pane_ids(&self) -> Vec<usize> {
+        match self {
+            PaneLayout::Pane(id) => vec![*id],
+            PaneLayout::Split { children, .. } => {
+                children.iter().flat_map(PaneLayout::pane_ids).collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_nest_across_axes_and_collapse_on_remove() {
+        let mut layout = PaneLayout::Pane(0);
+        assert!(layout.split(0, 1, SplitAxis::Horizontal));
+        assert!(layout.split(0, 2, SplitAxis::Horizontal));
+        assert!(layout.split(1, 3, SplitAxis::Vertical));
+        assert!(!layout.split(9, 4, SplitAxis::Vertical));
+        assert_eq!(layout.pane_ids(), vec![0, 2, 1, 3]);
+        assert_eq!(
+            layout,
+            PaneLayout::Split {
+                axis: SplitAxis::Horizontal,
+                children: vec![
+                    PaneLayout::Pane(0),
+                    PaneLayout::Pane(2),
+                    PaneLayout::Split {
+                        axis: SplitAxis::Vertical,
+                        children: vec![PaneLayout::Pane(1), PaneLayout::Pane(3)],
+                    },
+                ],
+            }
+        );
+
+        assert!(layout.remove(3));
+        assert!(layout.remove(0));
+        assert_eq!(layout.pane_ids(), vec![2, 1]);
+        assert!(layout.remove(2));
+        assert_eq!(layout, PaneLayout::Pane(1));
+        assert!(!layout.remove(1));
+    }
+}
@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Maximum number of unpinned entries kept; pinned ones don't count.
+const MAX_RECENT: usize = 20;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecentKind {
+    File,
+    Folder,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RecentEntry {
+    pub path: PathBuf,
+    pub kind: RecentKind,
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+impl RecentEntry {
+    /// Whether the file or folder is still there.
+    pub fn exists(&self) -> bool {
+        match self.kind {
+            RecentKind::File => self.path.is_file(),
+            RecentKind::Folder => self.path.is_dir(),
+        }
+    }
+}
+
+/// Recently opened files and folders, most recent first, as listed by
+/// File → Open Recent and the welcome screen.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RecentList {
+    entries: Vec<RecentEntry>,
+}
+
+impl RecentList {
+    /// Moves `path` to the front, keeping it pinned if it was. The oldest
+    /// unpinned entries fall off the end.
+    pub fn add(&mut self, path: PathBuf, kind: RecentKind) {
+        let pinned = self
+            .entries
+            .iter()
+            .any(|entry| entry.path == path && entry.pinned);
+        self.entries.retain(|entry| entry.path != path);
+        self.entries.insert(0, RecentEntry { path, kind, pinned });
+        let mut unpinned = 0;
+        self.entries.retain(|entry| {
+            unpinned += usize::from(!entry.pinned);
+            entry.pinned || unpinned <= MAX_RECENT
+        });
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.entries.retain(|entry| entry.path != path);
+    }
+
+    pub fn set_pinned(&mut self, path: &Path, pinned: bool) {
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.path == path) {
+            entry.pinned = pinned;
+        }
+    }
+
+    /// Drops entries whose file or folder no longer exists.
+    pub fn remove_missing(&mut self) {
+        self.entries.retain(RecentEntry::exists);
+    }
+
+    /// Drops every entry that isn't pinned.
+    pub fn clear_unpinned(&mut self) {
+        self.entries.retain(|entry| entry.pinned);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Pinned entries first, then the rest; each most recent first.
+    pub fn entries(&self) -> impl Iterator<Item = &RecentEntry> {
+        let pinned = self.entries.iter().filter(|entry| entry.pinned);
+        pinned.chain(self.entries.iter().filter(|entry| !entry.pinned))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(list: &RecentList) -> Vec<&str> {
+        list.entries()
+            .map(|entry| entry.path.to_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn keeps_pinned_entries_first_and_past_the_limit() {
+        let mut list = RecentList::default();
+        list.add(PathBuf::from("a.md"), RecentKind::File);
+        list.add(PathBuf::from("notes"), RecentKind::Folder);
+        list.add(PathBuf::from("b.md"), RecentKind::File);
+        list.set_pinned(Path::new("a.md"), true);
+        assert_eq!(paths(&list), vec!["a.md", "b.md", "notes"]);
+
+        // Reopening moves an entry to the front without unpinning it.
+        list.add(PathBuf::from("a.md"), RecentKind::File);
+        list.add(PathBuf::from("notes"), RecentKind::Folder);
+        assert_eq!(paths(&list), vec!["a.md", "notes", "b.md"]);
+        assert!(list.entries().next().unwrap().pinned);
+
+        for n in 0..MAX_RECENT {
+            list.add(PathBuf::from(format!("{n}.md")), RecentKind::File);
+        }
+        assert_eq!(list.entries().count(), MAX_RECENT + 1);
+        assert_eq!(paths(&list)[..2], ["a.md", "19.md"]);
+
+        list.clear_unpinned();
+        assert_eq!(paths(&list), vec!["a.md"]);
+        list.remove(Path::new("a.md"));
+        assert!(list.is_empty());
+    }
+}
@@ -0,0 +1,139 @@
+/// Ordered open items with one active entry, as shown in a tab bar.
+pub struct TabList<T> {
+    items: Vec<T>,
+    active: usize,
+}
+
+impl<T> TabList<T> {
+    pub fn new(first: T) -> Self {
+        Self {
+            items: vec![first],
+            active: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.items.iter()
+    }
+
+    pub fn get(&self, ix: usize) -> Option<&T> {
+        self.items.get(ix)
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    pub fn active(&self) -> &T {
+        &self.items[self.active]
+    }
+
+    pub fn position(&self, predicate: impl FnMut(&T) -> bool) -> Option<usize> {
+        self.items.iter().position(predicate)
+    }
+
+    /// Adds `item` after the active tab and activates it.
+    pub fn open(&mut self, item: T) {
+        self.active = (self.active + 1).min(self.items.len());
+        self.items.insert(self.active, item);
+    }
+
+    /// Activates tab `ix`. Returns false if it does not exist.
+    pub fn activate(&mut self, ix: usize) -> bool {
+        if ix >= self.items.len() {
+            return false;
+        }
+        self.active = ix;
+        true
+    }
+
+    /// Activates the tab `offset` places away, wrapping around.
+    pub fn activate_relative(&mut self, offset: isize) {
+        let len = self.items.len() as isize;
+        self.active = (self.active as isize + offset).rem_euclid(len) as usize;
+    }
+
+    /// Removes tab `ix`; the tab to its right (or else left) becomes active if
+    /// it was. The last tab can't be removed.
+    pub fn close(&mut self, ix: usize) -> Option<T> {
+        if self.items.len() <= 1 || ix >= self.items.len() {
+            return None;
+        }
+        let item = self.items.remove(ix);
+        if ix < self.active || self.active == self.items.len() {
+            self.active -= 1;
+        }
+        Some(item)
+    }
+
+    /// Moves tab `from` to position `to`, keeping the same tab active.
+    pub fn move_tab(&mut self, from: usize, to: usize) {
+        if from >= self.items.len() || to >= self.items.len() || from == to {
+            return;
+        }
+        let item = self.items.remove(from);
+        self.items.insert(to, item);
+        if self.active == from {
+            self.active = to;
+        } else if from < self.active && self.active <= to {
+            self.active -= 1;
+        } else if to <= self.active && self.active < from {
+            self.active += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tabs(names: &[&'static str], active: usize) -> TabList<&'static str> {
+        let mut list = TabList::new(names[0]);
+        for name in &names[1..] {
+            list.open(name);
+        }
+        list.activate(active);
+        list
+    }
+
+    fn order(list: &TabList<&'static str>) -> (Vec<&'static str>, &'static str) {
+        (list.iter().copied().collect(), list.active())
+    }
+
+    #[test]
+    fn open_and_close_keep_a_sensible_active_tab() {
+        let mut list = tabs(&["a", "b", "c"], 0);
+        list.open("d");
+        assert_eq!(order(&list), (vec!["a", "d", "b", "c"], "d"));
+
+        assert_eq!(list.close(1), Some("d"));
+        assert_eq!(order(&list), (vec!["a", "b", "c"], "b"));
+        list.activate(2);
+        list.close(2);
+        assert_eq!(order(&list), (vec!["a", "b"], "b"));
+        list.close(0);
+        assert_eq!(order(&list), (vec!["b"], "b"));
+        assert_eq!(list.close(0), None);
+
+        let mut list = tabs(&["a", "b", "c"], 0);
+        list.activate_relative(-1);
+        assert_eq!(list.active(), &"c");
+        list.activate_relative(1);
+        assert_eq!(list.active(), &"a");
+    }
+
+    #[test]
+    fn moving_tabs_follows_the_active_one() {
+        let mut list = tabs(&["a", "b", "c", "d"], 1);
+        list.move_tab(0, 3);
+        assert_eq!(order(&list), (vec!["b", "c", "d", "a"], "b"));
+        list.move_tab(3, 0);
+        assert_eq!(order(&list), (vec!["a", "b", "c", "d"], "b"));
+        list.move_tab(1, 2);
+        assert_eq!(order(&list), (vec!["a", "c", "b", "d"], "b"));
+    }
+}
@@ -0,0 +1,29 @@
+//! Clipboard flavors GPUI cannot write, such as HTML.
+
+use crate::error::{AppError, AppResult};
+use arboard::Clipboard;
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+
+/// Kept alive for the whole session: on X11 the clipboard owner must stay
+/// around to serve paste requests.
+static CLIPBOARD: Lazy<Mutex<Option<Clipboard>>> = Lazy::new(|| Mutex::new(None));
+
+/// Puts `html` on the system clipboard, with `alt_text` as the plain-text
+/// flavor for targets that do not accept HTML.
+pub fn write_html(html: &str, alt_text: &str) -> AppResult<()> {
+    let mut guard = CLIPBOARD
+        .lock()
+        .map_err(|_| AppError::Clipboard("clipboard lock poisoned".into()))?;
+    let clipboard = match &mut *guard {
+        Some(clipboard) => clipboard,
+        slot @ None => slot.insert(Clipboard::new().map_err(clipboard_error)?),
+    };
+    clipboard
+        .set_html(html, Some(alt_text))
+        .map_err(clipboard_error)
+}
+
+fn clipboard_error(err: arboard::Error) -> AppError {
+    AppError::Clipboard(err.to_string())
+}
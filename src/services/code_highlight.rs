@@ -0,0 +1,234 @@
+use crate::services::syntax::{SyntaxKind, SyntaxSpan};
+use once_cell::sync::Lazy;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use syntect::parsing::{ParseState, Scope, ScopeStack, SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+
+static SYNTAXES: Lazy<SyntaxSet> = Lazy::new(SyntaxSet::load_defaults_newlines);
+
+/// Scope prefixes mapped to token kinds. The innermost scope with a match wins,
+/// and earlier entries win over later ones for the same scope.
+static SCOPE_KINDS: Lazy<Vec<(Scope, CodeTokenKind)>> = Lazy::new(|| {
+    [
+        ("comment", CodeTokenKind::Comment),
+        ("string", CodeTokenKind::String),
+        ("constant.numeric", CodeTokenKind::Number),
+        ("constant", CodeTokenKind::Constant),
+        ("keyword.operator", CodeTokenKind::Operator),
+        ("keyword", CodeTokenKind::Keyword),
+        ("storage", CodeTokenKind::Keyword),
+        ("variable.language", CodeTokenKind::Keyword),
+        ("entity.name.function", CodeTokenKind::Function),
+        ("support.function", CodeTokenKind::Function),
+        ("variable.function", CodeTokenKind::Function),
+        ("entity.name.tag", CodeTokenKind::Tag),
+        ("entity.other.attribute-name", CodeTokenKind::Attribute),
+        ("entity.name", CodeTokenKind::Type),
+        ("support.type", CodeTokenKind::Type),
+        ("support.class", CodeTokenKind::Type),
+    ]
+    .into_iter()
+    .filter_map(|(prefix, kind)| Scope::new(prefix).ok().map(|scope| (scope, kind)))
+    .collect()
+});
+
+/// Theme-independent category of a highlighted code token.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CodeTokenKind {
+    Keyword,
+    String,
+    Comment,
+    Number,
+    Constant,
+    Function,
+    Type,
+    Tag,
+    Attribute,
+    Operator,
+}
+
+/// A highlighted token; `range` is relative to the start of the code body.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CodeToken {
+    pub range: Range<usize>,
+    pub kind: CodeTokenKind,
+}
+
+/// Body of a fenced code block found in the document.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FencedCode {
+    pub body: Range<usize>,
+    /// First word of the info string, e.g. `rust` for ```` ```rust ````.
+    pub language: String,
+    /// Hash of the language and body text, the highlight cache key.
+    pub hash: u64,
+}
+
+/// Finds fenced code blocks (with a non-empty body) from parsed syntax spans.
+pub fn fenced_code_blocks(source: &str, spans: &[SyntaxSpan]) -> Vec<FencedCode> {
+    let mut blocks = Vec::new();
+    let mut previous: Option<&SyntaxSpan> = None;
+
+    for span in spans
+        .iter()
+        .filter(|span| matches!(span.kind, SyntaxKind::CodeFence | SyntaxKind::CodeBlock))
+    {
+        // A fenced body starts right after its opening fence line; an indented
+        // block after a closing fence starts at least one indented line later.
+        if let Some(fence) = previous.filter(|fence| fence.kind == SyntaxKind::CodeFence)
+            && span.kind == SyntaxKind::CodeBlock
+            && Some(span.range.start) == line_after(source, fence.range.end)
+            && let (Some(info), Some(body)) = (
+                source.get(fence.range.clone()),
+                source.get(span.range.clone()),
+            )
+        {
+            let language = info
+                .trim_start_matches(['`', '~'])
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string();
+            blocks.push(FencedCode {
+                body: span.range.clone(),
+                hash: code_hash(&language, body),
+                language,
+            });
+        }
+        previous = Some(span);
+    }
+    blocks
+}
+
+/// Start of the line following the line ending at `end`, for `\n` and
+/// `\r\n` documents alike.
+fn line_after(source: &str, end: usize) -> Option<usize> {
+    let rest = source.get(end..)?;
+    let ending = ["\r\n", "\n"]
+        .into_iter()
+        .find(|ending| rest.starts_with(ending))?;
+    Some(end + ending.len())
+}
+
+/// Cache key for the highlights of `code` in `language`.
+pub fn code_hash(language: &str, code: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    language.hash(&mut hasher);
+    code.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Highlights `code` as `language`. Unknown languages yield no tokens.
+///
+/// Runs syntect's parser, which is too slow for the UI thread on large blocks;
+/// callers run it in the background and cache the result by [`FencedCode::hash`].
+pub fn highlight_code(language: &str, code: &str) -> Vec<CodeToken> {
+    let Some(syntax) = find_syntax(language) else {
+        return Vec::new();
+    };
+
+    let mut state = ParseState::new(syntax);
+    let mut stack = ScopeStack::new();
+    let mut tokens: Vec<CodeToken> = Vec::new();
+    let mut push = |range: Range<usize>, stack: &ScopeStack| {
+        let Some(kind) = token_kind(stack) else {
+            return;
+        };
+        if range.is_empty() {
+            return;
+        }
+        match tokens.last_mut() {
+            Some(last) if last.kind == kind && last.range.end == range.start => {
+                last.range.end = range.end;
+            }
+            _ => tokens.push(CodeToken { range, kind }),
+        }
+    };
+
+    let mut offset = 0;
+    for line in LinesWithEndings::from(code) {
+        let Ok(ops) = state.parse_line(line, &SYNTAXES) else {
+            break;
+        };
+        let mut last = 0;
+        for (pos, op) in ops {
+            push(offset + last..offset + pos, &stack);
+            last = pos;
+            if stack.apply(&op).is_err() {
+                return tokens;
+            }
+        }
+        push(offset + last..offset + line.len(), &stack);
+        offset += line.len();
+    }
+    tokens
+}
+
+fn find_syntax(language: &str) -> Option<&'static SyntaxReference> {
+    let language = language.to_ascii_lowercase();
+    let token = match language.as_str() {
+        "" => return None,
+        "shell" | "zsh" | "console" => "bash",
+        "golang" => "go",
+        "c++" => "cpp",
+        "ts" | "typescript" | "jsx" | "tsx" => "js",
+        other => other,
+    };
+    SYNTAXES.find_syntax_by_token(token)
+}
+
+fn token_kind(stack: &ScopeStack) -> Option<CodeTokenKind> {
+    stack.as_slice().iter().rev().find_map(|scope| {
+        SCOPE_KINDS
+            .iter()
+            .find(|(prefix, _)| prefix.is_prefix_of(*scope))
+            .map(|(_, kind)| *kind)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::syntax::markdown_spans;
+
+    #[test]
+    fn finds_fenced_blocks_with_their_language() {
+        let source =
+            "Text\n\n```rust title\nfn main() {}\n```\n\n    indented\n\n~~~\nplain\n~~~\n";
+        let blocks = fenced_code_blocks(source, &markdown_spans(source));
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].language, "rust");
+        assert_eq!(&source[blocks[0].body.clone()], "fn main() {}");
+        assert_eq!(blocks[1].language, "");
+        assert_eq!(&source[blocks[1].body.clone()], "plain");
+        assert_ne!(blocks[0].hash, blocks[1].hash);
+
+        let crlf = source.replace('\n', "\r\n");
+        let blocks = fenced_code_blocks(&crlf, &markdown_spans(&crlf));
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].language, "rust");
+        assert_eq!(&crlf[blocks[0].body.clone()], "fn main() {}");
+    }
+
+    #[test]
+    fn highlights_known_languages_by_scope() {
+        let code = "// note\nfn main() { let x = \"hi\"; }\n";
+        let tokens = highlight_code("rust", code);
+        let kind_of = |text: &str| {
+            let start = code.find(text).unwrap();
+            tokens
+                .iter()
+                .find(|token| token.range.contains(&start))
+                .map(|token| token.kind)
+        };
+        assert_eq!(kind_of("// note"), Some(CodeTokenKind::Comment));
+        assert_eq!(kind_of("fn"), Some(CodeTokenKind::Keyword));
+        assert_eq!(kind_of("\"hi\""), Some(CodeTokenKind::String));
+        assert!(tokens.iter().all(|token| token.range.end <= code.len()));
+
+        assert!(highlight_code("no-such-language", code).is_empty());
+        assert!(highlight_code("", code).is_empty());
+    }
+}
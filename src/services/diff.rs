@@ -0,0 +1,146 @@
+//! Line diffs between two versions of a document, e.g. a buffer and the file
+//! on disk, in unified diff format.
+
+use std::fmt::Write as _;
+
+/// Unchanged lines shown around each change.
+const CONTEXT: usize = 3;
+/// Above this many line pairs the changed middle is shown as one replacement
+/// instead of being matched line by line.
+const MAX_TABLE_CELLS: usize = 4_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Unified diff turning `old` into `new`; just the two header lines if they
+/// have the same lines.
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let lines = diff_lines(&old, &new);
+    let mut out = format!("--- {old_name}\n+++ {new_name}\n");
+
+    let mut ix = 0;
+    while let Some(change) = lines[ix..]
+        .iter()
+        .position(|line| !matches!(line, Line::Same(_)))
+    {
+        let start = (ix + change).saturating_sub(CONTEXT);
+        // Extend the hunk while the next change is close enough to share context.
+        let mut end = ix + change;
+        while let Some(next) = lines[end + 1..]
+            .iter()
+            .take(2 * CONTEXT + 1)
+            .position(|line| !matches!(line, Line::Same(_)))
+        {
+            end += 1 + next;
+        }
+        let end = (end + 1 + CONTEXT).min(lines.len());
+
+        let before = &lines[..start];
+        let hunk = &lines[start..end];
+        let old_before = before
+            .iter()
+            .filter(|l| !matches!(l, Line::Added(_)))
+            .count();
+        let new_before = before
+            .iter()
+            .filter(|l| !matches!(l, Line::Removed(_)))
+            .count();
+        let old_len = hunk.iter().filter(|l| !matches!(l, Line::Added(_))).count();
+        let new_len = hunk
+            .iter()
+            .filter(|l| !matches!(l, Line::Removed(_)))
+            .count();
+        // An empty side is numbered after the line it follows.
+        let first = |before: usize, len: usize| if len == 0 { before } else { before + 1 };
+        let _ = writeln!(
+            out,
+            "@@ -{},{old_len} +{},{new_len} @@",
+            first(old_before, old_len),
+            first(new_before, new_len)
+        );
+        for line in hunk {
+            let _ = match line {
+                Line::Same(text) => writeln!(out, " {text}"),
+                Line::Removed(text) => writeln!(out, "-{text}"),
+                Line::Added(text) => writeln!(out, "+{text}"),
+            };
+        }
+        ix = end;
+    }
+    out
+}
+
+/// Every line of `old` and `new` in order, matched by a longest common
+/// subsequence of the part between their common prefix and suffix.
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Line<'a>> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+
+    let mut lines: Vec<Line> = old[..prefix].iter().map(|l| Line::Same(l)).collect();
+    if a.len().saturating_mul(b.len()) > MAX_TABLE_CELLS {
+        lines.extend(a.iter().map(|l| Line::Removed(l)));
+        lines.extend(b.iter().map(|l| Line::Added(l)));
+    } else {
+        // lcs[i * width + j]: length of the longest common subsequence of a[i..] and b[j..].
+        let width = b.len() + 1;
+        let mut lcs = vec![0u32; (a.len() + 1) * width];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lcs[i * width + j] = if a[i] == b[j] {
+                    lcs[(i + 1) * width + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                lines.push(Line::Same(a[i]));
+                i += 1;
+                j += 1;
+            } else if i < a.len()
+                && (j == b.len() || lcs[(i + 1) * width + j] >= lcs[i * width + j + 1])
+            {
+                lines.push(Line::Removed(a[i]));
+                i += 1;
+            } else {
+                lines.push(Line::Added(b[j]));
+                j += 1;
+            }
+        }
+    }
+    lines.extend(old[old.len() - suffix..].iter().map(|l| Line::Same(l)));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_nearby_changes_into_hunks() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\nn\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\nn\no\n";
+        assert_eq!(
+            unified_diff(old, new, "disk", "buffer"),
+            "--- disk\n+++ buffer\n\
+             @@ -1,5 +1,5 @@\n a\n-b\n+B\n c\n d\n e\n\
+             @@ -12,3 +12,4 @@\n l\n m\n n\n+o\n"
+        );
+        assert_eq!(unified_diff("x\r\n", "x\n", "a", "b"), "--- a\n+++ b\n");
+    }
+}
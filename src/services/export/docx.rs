@@ -0,0 +1,734 @@
+use crate::services::code_highlight::{CodeTokenKind, highlight_code};
+use crate::services::export::html::{escape, image_mime, slug};
+use crate::services::export::zip::ZipWriter;
+use crate::services::export::{document_title, utc_timestamp};
+use crate::services::images::resolve_local_image;
+use crate::services::markdown_ast::{Block, BlockKind, Inline, ListItem, parse_markdown};
+use camino::Utf8Path;
+use pulldown_cmark::Alignment;
+use std::fmt::Write as _;
+use std::ops::Range;
+use std::path::PathBuf;
+
+const NAMESPACES: &str = "xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\" \
+     xmlns:r=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships\" \
+     xmlns:wp=\"http://schemas.openxmlformats.org/drawingml/2006/wordprocessingDrawing\" \
+     xmlns:a=\"http://schemas.openxmlformats.org/drawingml/2006/main\" \
+     xmlns:pic=\"http://schemas.openxmlformats.org/drawingml/2006/picture\"";
+const RELATIONSHIP_TYPES: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+
+const ROOT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+  <Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="word/document.xml"/>
+  <Relationship Id="rId2" Type="http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties" Target="docProps/core.xml"/>
+</Relationships>
+"#;
+
+const STYLES: &str = include_str!("docx_styles.xml");
+
+/// Indent per list or quote level, in twentieths of a point.
+const INDENT: u32 = 720;
+/// Widest image, in EMU (6 inches).
+const MAX_IMAGE_WIDTH: u64 = 6 * 914_400;
+/// EMU per pixel at 96 DPI.
+const EMU_PER_PIXEL: u64 = 9525;
+/// Text width of an A4 page with one-inch margins, in twentieths of a point.
+const TEXT_WIDTH: u32 = 11_906 - 2 * 1440;
+
+/// Renders `markdown` as a Word document using the built-in heading, list,
+/// quote and code styles. Local images are embedded.
+pub fn render_docx(markdown: &str, source_path: Option<&Utf8Path>) -> Vec<u8> {
+    let document = parse_markdown(markdown);
+    let title = document_title(&document, source_path);
+
+    let mut writer = DocxWriter::new(source_path);
+    writer.blocks(&document.blocks);
+
+    let mut zip = ZipWriter::new();
+    zip.add_deflated("[Content_Types].xml", writer.content_types().as_bytes());
+    zip.add_deflated("_rels/.rels", ROOT_RELS.as_bytes());
+    zip.add_deflated(
+        "word/document.xml",
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <w:document {NAMESPACES}><w:body>{}\
+             <w:sectPr><w:pgSz w:w=\"11906\" w:h=\"16838\"/>\
+             <w:pgMar w:top=\"1440\" w:right=\"1440\" w:bottom=\"1440\" w:left=\"1440\" \
+             w:header=\"708\" w:footer=\"708\" w:gutter=\"0\"/></w:sectPr>\
+             </w:body></w:document>\n",
+            writer.body
+        )
+        .as_bytes(),
+    );
+    zip.add_deflated("word/styles.xml", STYLES.as_bytes());
+    zip.add_deflated("word/numbering.xml", writer.numbering().as_bytes());
+    zip.add_deflated(
+        "word/_rels/document.xml.rels",
+        writer.relationships().as_bytes(),
+    );
+    for image in &writer.images {
+        if let Ok(bytes) = std::fs::read(&image.path) {
+            zip.add_deflated(&format!("word/{}", image.target), &bytes);
+        }
+    }
+    let now = utc_timestamp();
+    zip.add_deflated(
+        "docProps/core.xml",
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <cp:coreProperties xmlns:cp=\"http://schemas.openxmlformats.org/package/2006/metadata/core-properties\" \
+             xmlns:dc=\"http://purl.org/dc/elements/1.1/\" xmlns:dcterms=\"http://purl.org/dc/terms/\" \
+             xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">\
+             <dc:title>{}</dc:title>\
+             <dcterms:created xsi:type=\"dcterms:W3CDTF\">{now}</dcterms:created>\
+             <dcterms:modified xsi:type=\"dcterms:W3CDTF\">{now}</dcterms:modified>\
+             </cp:coreProperties>\n",
+            escape(&title)
+        )
+        .as_bytes(),
+    );
+    zip.finish()
+}
+
+/// Character formatting of a run.
+#[derive(Clone, Copy, Default)]
+struct RunStyle {
+    bold: bool,
+    italic: bool,
+    strike: bool,
+    code: bool,
+    link: bool,
+    superscript: bool,
+    color: Option<u32>,
+}
+
+/// A list numbering instance: which definition it uses and where it starts.
+struct Numbering {
+    ordered: bool,
+    level: u32,
+    start: u64,
+}
+
+struct Relationship {
+    kind: &'static str,
+    target: String,
+    external: bool,
+}
+
+struct EmbeddedImage {
+    path: PathBuf,
+    /// Path inside `word/`.
+    target: String,
+    relationship: usize,
+    /// Size in EMU.
+    size: (u64, u64),
+}
+
+/// Serialises the Markdown AST as WordprocessingML.
+struct DocxWriter<'a> {
+    body: String,
+    source_path: Option<&'a Utf8Path>,
+    relationships: Vec<Relationship>,
+    images: Vec<EmbeddedImage>,
+    numberings: Vec<Numbering>,
+    /// Nesting of the block being written.
+    list_depth: u32,
+    quote_depth: u32,
+    in_footnote: bool,
+    /// Numbering for the next paragraph, as `(numId, level)`.
+    pending_number: Option<(usize, u32)>,
+    /// Runs to start the next paragraph with (task boxes, footnote labels).
+    pending_runs: String,
+    bookmarks: u32,
+}
+
+impl<'a> DocxWriter<'a> {
+    fn new(source_path: Option<&'a Utf8Path>) -> Self {
+        Self {
+            body: String::new(),
+            source_path,
+            // rId1..rId2 are the styles and numbering parts.
+            relationships: vec![
+                Relationship {
+                    kind: "styles",
+                    target: "styles.xml".into(),
+                    external: false,
+                },
+                Relationship {
+                    kind: "numbering",
+                    target: "numbering.xml".into(),
+                    external: false,
+                },
+            ],
+            images: Vec::new(),
+            numberings: Vec::new(),
+            list_depth: 0,
+            quote_depth: 0,
+            in_footnote: false,
+            pending_number: None,
+            pending_runs: String::new(),
+            bookmarks: 0,
+        }
+    }
+
+    fn relationship(&mut self, kind: &'static str, target: String, external: bool) -> usize {
+        self.relationships.push(Relationship {
+            kind,
+            target,
+            external,
+        });
+        self.relationships.len()
+    }
+
+    fn blocks(&mut self, blocks: &[Block]) {
+        for block in blocks {
+            self.block(block);
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        match &block.kind {
+            BlockKind::Paragraph(inlines) => {
+                let style = self.body_style();
+                self.open_paragraph(style);
+                self.inlines(inlines, RunStyle::default());
+                self.body.push_str("</w:p>");
+            }
+            BlockKind::Heading { level, content } => {
+                self.open_paragraph(&format!("Heading{level}"));
+                self.bookmarks += 1;
+                let _ = write!(
+                    self.body,
+                    "<w:bookmarkStart w:id=\"{id}\" w:name=\"{}\"/><w:bookmarkEnd w:id=\"{id}\"/>",
+                    escape(&bookmark_name(&Inline::plain_text(content))),
+                    id = self.bookmarks
+                );
+                self.inlines(content, RunStyle::default());
+                self.body.push_str("</w:p>");
+            }
+            BlockKind::BlockQuote(children) => {
+                self.quote_depth += 1;
+                self.blocks(children);
+                self.quote_depth -= 1;
+            }
+            BlockKind::List { start, items } => self.list(*start, items),
+            BlockKind::CodeBlock { language, code } => {
+                let code = code.strip_suffix('\n').unwrap_or(code);
+                let tokens: Vec<(Range<usize>, u32)> = language
+                    .as_deref()
+                    .map(|language| highlight_code(language, code))
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|token| (token.range, token_color(token.kind)))
+                    .collect();
+                self.code_lines(code, &tokens);
+            }
+            BlockKind::Table {
+                alignments,
+                header,
+                rows,
+            } => self.table(alignments, header, rows),
+            BlockKind::Rule => self.body.push_str(
+                "<w:p><w:pPr><w:pBdr><w:bottom w:val=\"single\" w:sz=\"6\" w:space=\"1\" \
+                 w:color=\"D0D7DE\"/></w:pBdr></w:pPr></w:p>",
+            ),
+            BlockKind::Html(html) => self.code_lines(html.trim_end(), &[]),
+            BlockKind::FootnoteDefinition { label, blocks } => {
+                self.in_footnote = true;
+                self.pending_runs = run(
+                    &format!("[{label}] "),
+                    RunStyle {
+                        superscript: true,
+                        ..RunStyle::default()
+                    },
+                );
+                self.blocks(blocks);
+                self.flush_pending_runs();
+                self.in_footnote = false;
+            }
+        }
+    }
+
+    /// Paragraph style for body text at the current nesting.
+    fn body_style(&self) -> &'static str {
+        if self.in_footnote {
+            "FootnoteText"
+        } else if self.quote_depth > 0 {
+            "Quote"
+        } else if self.list_depth > 0 {
+            "ListParagraph"
+        } else {
+            "Normal"
+        }
+    }
+
+    /// Starts a paragraph in `style`, numbered or indented to the current
+    /// nesting.
+    fn open_paragraph(&mut self, style: &str) {
+        let _ = write!(self.body, "<w:p><w:pPr><w:pStyle w:val=\"{style}\"/>");
+        let nesting = self.list_depth + self.quote_depth;
+        if let Some((num, level)) = self.pending_number.take() {
+            let _ = write!(
+                self.body,
+                "<w:numPr><w:ilvl w:val=\"{level}\"/><w:numId w:val=\"{num}\"/></w:numPr>"
+            );
+            if self.quote_depth > 0 {
+                let _ = write!(
+                    self.body,
+                    "<w:ind w:left=\"{}\" w:hanging=\"360\"/>",
+                    nesting * INDENT
+                );
+            }
+        } else if nesting > 0 {
+            let _ = write!(self.body, "<w:ind w:left=\"{}\"/>", nesting * INDENT);
+        }
+        self.body.push_str("</w:pPr>");
+        let runs = std::mem::take(&mut self.pending_runs);
+        self.body.push_str(&runs);
+    }
+
+    /// Writes pending runs that no paragraph picked up.
+    fn flush_pending_runs(&mut self) {
+        if !self.pending_runs.is_empty() {
+            let style = self.body_style();
+            self.open_paragraph(style);
+            self.body.push_str("</w:p>");
+        }
+        self.pending_number = None;
+    }
+
+    fn list(&mut self, start: Option<u64>, items: &[ListItem]) {
+        self.numberings.push(Numbering {
+            ordered: start.is_some(),
+            level: self.list_depth,
+            start: start.unwrap_or(1),
+        });
+        let num = self.numberings.len();
+        let level = self.list_depth;
+        self.list_depth += 1;
+        for item in items {
+            match item.checked {
+                // Task items show a box instead of a bullet.
+                Some(checked) => {
+                    self.pending_runs = run(if checked { "☒ " } else { "☐ " }, RunStyle::default());
+                }
+                None => self.pending_number = Some((num, level)),
+            }
+            self.blocks(&item.blocks);
+            self.flush_pending_runs();
+        }
+        self.list_depth -= 1;
+    }
+
+    /// One Code paragraph per line, coloring the parts covered by `tokens`.
+    fn code_lines(&mut self, code: &str, tokens: &[(Range<usize>, u32)]) {
+        let mut line_start = 0;
+        for line in code.split('\n') {
+            let line_end = line_start + line.len();
+            self.open_paragraph("Code");
+            let mut pos = line_start;
+            for (range, color) in tokens {
+                let range = range.start.max(pos)..range.end.min(line_end);
+                if range.start >= range.end
+                    || !code.is_char_boundary(range.start)
+                    || !code.is_char_boundary(range.end)
+                {
+                    continue;
+                }
+                self.body
+                    .push_str(&run(&code[pos..range.start], RunStyle::default()));
+                self.body.push_str(&run(
+                    &code[range.clone()],
+                    RunStyle {
+                        color: Some(*color),
+                        ..RunStyle::default()
+                    },
+                ));
+                pos = range.end;
+            }
+            self.body
+                .push_str(&run(&code[pos..line_end], RunStyle::default()));
+            self.body.push_str("</w:p>");
+            line_start = line_end + 1;
+        }
+    }
+
+    fn table(
+        &mut self,
+        alignments: &[Alignment],
+        header: &[Vec<Inline>],
+        rows: &[Vec<Vec<Inline>>],
+    ) {
+        let columns = header.len().max(1) as u32;
+        let width =
+            TEXT_WIDTH.saturating_sub((self.list_depth + self.quote_depth) * INDENT) / columns;
+        self.body.push_str(
+            "<w:tbl><w:tblPr><w:tblStyle w:val=\"Table\"/><w:tblW w:w=\"0\" w:type=\"auto\"/></w:tblPr><w:tblGrid>",
+        );
+        for _ in 0..columns {
+            let _ = write!(self.body, "<w:gridCol w:w=\"{width}\"/>");
+        }
+        self.body.push_str("</w:tblGrid>");
+        self.table_row(header, alignments, true);
+        for row in rows {
+            self.table_row(row, alignments, false);
+        }
+        // Word needs a paragraph between consecutive tables.
+        self.body.push_str("</w:tbl><w:p/>");
+    }
+
+    fn table_row(&mut self, cells: &[Vec<Inline>], alignments: &[Alignment], header: bool) {
+        self.body.push_str("<w:tr>");
+        if header {
+            self.body.push_str("<w:trPr><w:tblHeader/></w:trPr>");
+        }
+        for (ix, cell) in cells.iter().enumerate() {
+            self.body.push_str("<w:tc>");
+            if header {
+                self.body.push_str(
+                    "<w:tcPr><w:shd w:val=\"clear\" w:color=\"auto\" w:fill=\"F6F8FA\"/></w:tcPr>",
+                );
+            }
+            let justification = match alignments.get(ix) {
+                Some(Alignment::Center) => "<w:jc w:val=\"center\"/>",
+                Some(Alignment::Right) => "<w:jc w:val=\"right\"/>",
+                _ => "",
+            };
+            let _ = write!(
+                self.body,
+                "<w:p><w:pPr><w:pStyle w:val=\"TableText\"/>{justification}</w:pPr>"
+            );
+            self.inlines(
+                cell,
+                RunStyle {
+                    bold: header,
+                    ..RunStyle::default()
+                },
+            );
+            self.body.push_str("</w:p></w:tc>");
+        }
+        self.body.push_str("</w:tr>");
+    }
+
+    fn inlines(&mut self, inlines: &[Inline], style: RunStyle) {
+        for inline in inlines {
+            self.inline(inline, style);
+        }
+    }
+
+    fn inline(&mut self, inline: &Inline, style: RunStyle) {
+        match inline {
+            Inline::Text(text) | Inline::Html(text) => self.body.push_str(&run(text, style)),
+            Inline::Code(code) => self.body.push_str(&run(
+                code,
+                RunStyle {
+                    code: true,
+                    ..style
+                },
+            )),
+            Inline::Emphasis(children) => self.inlines(
+                children,
+                RunStyle {
+                    italic: true,
+                    ..style
+                },
+            ),
+            Inline::Strong(children) => self.inlines(
+                children,
+                RunStyle {
+                    bold: true,
+                    ..style
+                },
+            ),
+            Inline::Strikethrough(children) => self.inlines(
+                children,
+                RunStyle {
+                    strike: true,
+                    ..style
+                },
+            ),
+            Inline::Link { url, content, .. } => {
+                match url.strip_prefix('#') {
+                    Some(fragment) => {
+                        let _ = write!(
+                            self.body,
+                            "<w:hyperlink w:anchor=\"{}\">",
+                            escape(&bookmark_name(fragment))
+                        );
+                    }
+                    None => {
+                        let id = self.relationship("hyperlink", url.clone(), true);
+                        let _ = write!(self.body, "<w:hyperlink r:id=\"rId{id}\">");
+                    }
+                }
+                self.inlines(
+                    content,
+                    RunStyle {
+                        link: true,
+                        ..style
+                    },
+                );
+                self.body.push_str("</w:hyperlink>");
+            }
+            Inline::Image { url, alt, .. } => match self.embed_image(url) {
+                Some(ix) => self.drawing(ix, alt),
+                None => self.body.push_str(&run(alt, style)),
+            },
+            Inline::FootnoteReference(label) => self.body.push_str(&run(
+                &format!("[{label}]"),
+                RunStyle {
+                    superscript: true,
+                    ..style
+                },
+            )),
+            Inline::SoftBreak => self.body.push_str(&run(" ", style)),
+            Inline::HardBreak => self.body.push_str("<w:r><w:br/></w:r>"),
+        }
+    }
+
+    /// Index into `images` of the local image at `url`, adding it if new.
+    /// SVG and unreadable images are left out.
+    fn embed_image(&mut self, url: &str) -> Option<usize> {
+        let path = resolve_local_image(self.source_path, url)?;
+        if let Some(ix) = self.images.iter().position(|image| image.path == path) {
+            return Some(ix);
+        }
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        if image_mime(&path).is_none() || extension == "svg" {
+            return None;
+        }
+        let (width, height) = image::image_dimensions(&path).ok()?;
+        let (mut cx, mut cy) = (
+            u64::from(width) * EMU_PER_PIXEL,
+            u64::from(height) * EMU_PER_PIXEL,
+        );
+        if cx > MAX_IMAGE_WIDTH {
+            cy = cy * MAX_IMAGE_WIDTH / cx;
+            cx = MAX_IMAGE_WIDTH;
+        }
+        let target = format!("media/image{}.{extension}", self.images.len() + 1);
+        let relationship = self.relationship("image", target.clone(), false);
+        self.images.push(EmbeddedImage {
+            path,
+            target,
+            relationship,
+            size: (cx, cy),
+        });
+        Some(self.images.len() - 1)
+    }
+
+    fn drawing(&mut self, ix: usize, alt: &str) {
+        let image = &self.images[ix];
+        let (cx, cy) = image.size;
+        let id = ix + 1;
+        let _ = write!(
+            self.body,
+            "<w:r><w:drawing><wp:inline distT=\"0\" distB=\"0\" distL=\"0\" distR=\"0\">\
+             <wp:extent cx=\"{cx}\" cy=\"{cy}\"/><wp:docPr id=\"{id}\" name=\"Picture {id}\" descr=\"{alt}\"/>\
+             <a:graphic><a:graphicData uri=\"http://schemas.openxmlformats.org/drawingml/2006/picture\">\
+             <pic:pic><pic:nvPicPr><pic:cNvPr id=\"{id}\" name=\"Picture {id}\"/><pic:cNvPicPr/></pic:nvPicPr>\
+             <pic:blipFill><a:blip r:embed=\"rId{rel}\"/><a:stretch><a:fillRect/></a:stretch></pic:blipFill>\
+             <pic:spPr><a:xfrm><a:off x=\"0\" y=\"0\"/><a:ext cx=\"{cx}\" cy=\"{cy}\"/></a:xfrm>\
+             <a:prstGeom prst=\"rect\"><a:avLst/></a:prstGeom></pic:spPr></pic:pic>\
+             </a:graphicData></a:graphic></wp:inline></w:drawing></w:r>",
+            alt = escape(alt),
+            rel = image.relationship
+        );
+    }
+
+    fn content_types(&self) -> String {
+        let mut defaults = String::new();
+        let mut seen = Vec::new();
+        for image in &self.images {
+            let extension = image.target.rsplit('.').next().unwrap_or_default();
+            if seen.contains(&extension) {
+                continue;
+            }
+            seen.push(extension);
+            let mime = image_mime(&image.path).unwrap_or("application/octet-stream");
+            let _ = write!(
+                defaults,
+                "<Default Extension=\"{extension}\" ContentType=\"{mime}\"/>"
+            );
+        }
+        let part = "application/vnd.openxmlformats-officedocument.wordprocessingml";
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\
+             <Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\
+             <Default Extension=\"xml\" ContentType=\"application/xml\"/>{defaults}\
+             <Override PartName=\"/word/document.xml\" ContentType=\"{part}.document.main+xml\"/>\
+             <Override PartName=\"/word/styles.xml\" ContentType=\"{part}.styles+xml\"/>\
+             <Override PartName=\"/word/numbering.xml\" ContentType=\"{part}.numbering+xml\"/>\
+             <Override PartName=\"/docProps/core.xml\" \
+             ContentType=\"application/vnd.openxmlformats-package.core-properties+xml\"/>\
+             </Types>\n"
+        )
+    }
+
+    fn relationships(&self) -> String {
+        let mut out = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">",
+        );
+        for (ix, relationship) in self.relationships.iter().enumerate() {
+            let _ = write!(
+                out,
+                "<Relationship Id=\"rId{}\" Type=\"{RELATIONSHIP_TYPES}/{}\" Target=\"{}\"{}/>",
+                ix + 1,
+                relationship.kind,
+                escape(&relationship.target),
+                if relationship.external {
+                    " TargetMode=\"External\""
+                } else {
+                    ""
+                }
+            );
+        }
+        out.push_str("</Relationships>\n");
+        out
+    }
+
+    /// Bullet and decimal list definitions, plus one instance per list so
+    /// each ordered list restarts at its own number.
+    fn numbering(&self) -> String {
+        let mut out = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<w:numbering {NAMESPACES}>"
+        );
+        for (id, ordered) in [(0, false), (1, true)] {
+            let _ = write!(
+                out,
+                "<w:abstractNum w:abstractNumId=\"{id}\"><w:multiLevelType w:val=\"hybridMultilevel\"/>"
+            );
+            for level in 0..9u32 {
+                let (format, text) = if ordered {
+                    ("decimal", format!("%{}.", level + 1))
+                } else {
+                    ("bullet", ["•", "◦", "▪"][level as usize % 3].to_string())
+                };
+                let _ = write!(
+                    out,
+                    "<w:lvl w:ilvl=\"{level}\"><w:start w:val=\"1\"/><w:numFmt w:val=\"{format}\"/>\
+                     <w:lvlText w:val=\"{text}\"/><w:lvlJc w:val=\"left\"/>\
+                     <w:pPr><w:ind w:left=\"{}\" w:hanging=\"360\"/></w:pPr></w:lvl>",
+                    (level + 1) * INDENT
+                );
+            }
+            out.push_str("</w:abstractNum>");
+        }
+        for (ix, numbering) in self.numberings.iter().enumerate() {
+            let _ = write!(
+                out,
+                "<w:num w:numId=\"{}\"><w:abstractNumId w:val=\"{}\"/>",
+                ix + 1,
+                u8::from(numbering.ordered)
+            );
+            if numbering.ordered {
+                let _ = write!(
+                    out,
+                    "<w:lvlOverride w:ilvl=\"{}\"><w:startOverride w:val=\"{}\"/></w:lvlOverride>",
+                    numbering.level, numbering.start
+                );
+            }
+            out.push_str("</w:num>");
+        }
+        out.push_str("</w:numbering>\n");
+        out
+    }
+}
+
+/// A text run in `style`; empty text writes nothing.
+fn run(text: &str, style: RunStyle) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+    let mut out = String::new();
+    if style.link {
+        out.push_str("<w:rStyle w:val=\"Hyperlink\"/>");
+    } else if style.code {
+        out.push_str("<w:rStyle w:val=\"InlineCode\"/>");
+    }
+    if style.bold {
+        out.push_str("<w:b/>");
+    }
+    if style.italic {
+        out.push_str("<w:i/>");
+    }
+    if style.strike {
+        out.push_str("<w:strike/>");
+    }
+    if let Some(color) = style.color {
+        let _ = write!(out, "<w:color w:val=\"{color:06X}\"/>");
+    }
+    if style.superscript {
+        out.push_str("<w:vertAlign w:val=\"superscript\"/>");
+    }
+    let properties = if out.is_empty() {
+        String::new()
+    } else {
+        format!("<w:rPr>{out}</w:rPr>")
+    };
+    format!(
+        "<w:r>{properties}<w:t xml:space=\"preserve\">{}</w:t></w:r>",
+        escape(text)
+    )
+}
+
+/// Word bookmark names: at most 40 characters, starting with a letter.
+fn bookmark_name(title: &str) -> String {
+    format!("h-{}", slug(title)).chars().take(40).collect()
+}
+
+fn token_color(kind: CodeTokenKind) -> u32 {
+    match kind {
+        CodeTokenKind::Keyword => 0xa626a4,
+        CodeTokenKind::String => 0x50a14f,
+        CodeTokenKind::Comment => 0xa0a1a7,
+        CodeTokenKind::Number | CodeTokenKind::Constant | CodeTokenKind::Attribute => 0x986801,
+        CodeTokenKind::Function => 0x4078f2,
+        CodeTokenKind::Type => 0xc18401,
+        CodeTokenKind::Tag => 0xe45649,
+        CodeTokenKind::Operator => 0x0184bc,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_blocks_to_word_styles() {
+        let mut writer = DocxWriter::new(None);
+        let document = parse_markdown(
+            "# Title\n\n3. three\n4. four\n   - nested\n\n> quoted **bold**\n\n\
+             ```rust\nfn main() {}\n```\n\n- [x] done\n\n[site](https://example.com)\n",
+        );
+        writer.blocks(&document.blocks);
+        let body = &writer.body;
+
+        assert!(body.contains("<w:pStyle w:val=\"Heading1\"/>"));
+        assert!(body.contains("<w:numPr><w:ilvl w:val=\"0\"/><w:numId w:val=\"1\"/></w:numPr>"));
+        assert!(body.contains("<w:numPr><w:ilvl w:val=\"1\"/><w:numId w:val=\"2\"/></w:numPr>"));
+        assert!(body.contains("<w:pStyle w:val=\"Quote\"/>"));
+        assert!(body.contains("<w:b/></w:rPr><w:t xml:space=\"preserve\">bold</w:t>"));
+        assert!(
+            body.contains(
+                "<w:color w:val=\"A626A4\"/></w:rPr><w:t xml:space=\"preserve\">fn</w:t>"
+            )
+        );
+        assert!(body.contains("<w:t xml:space=\"preserve\">☒ </w:t>"));
+        assert!(body.contains("<w:hyperlink r:id=\"rId3\">"));
+
+        let numbering = writer.numbering();
+        assert!(numbering.contains(
+            "<w:num w:numId=\"1\"><w:abstractNumId w:val=\"1\"/><w:lvlOverride w:ilvl=\"0\">\
+             <w:startOverride w:val=\"3\"/></w:lvlOverride></w:num>"
+        ));
+        assert!(
+            writer
+                .relationships()
+                .contains("Target=\"https://example.com\" TargetMode=\"External\"")
+        );
+    }
+}
@@ -0,0 +1,235 @@
+use crate::services::export::html::{DEFAULT_CSS, HeadingAnchor, HtmlWriter, escape};
+use crate::services::export::zip::ZipWriter;
+use crate::services::export::{document_title, utc_timestamp};
+use crate::services::markdown_ast::{Block, BlockKind, parse_markdown};
+use camino::Utf8Path;
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::Write as _;
+use std::hash::{Hash, Hasher};
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+/// Renders `markdown` as an EPUB 3 book: one chapter per H1/H2 section, a
+/// navigation document built from the headings, and local images packaged.
+pub fn render_epub(markdown: &str, source_path: Option<&Utf8Path>, css: Option<&str>) -> Vec<u8> {
+    let document = parse_markdown(markdown);
+    let title = document_title(&document, source_path);
+    let chapters = split_chapters(&document.blocks);
+
+    // Footnote references may point into another chapter.
+    let mut footnote_files = HashMap::new();
+    for (ix, chapter) in chapters.iter().enumerate() {
+        for block in *chapter {
+            if let BlockKind::FootnoteDefinition { label, .. } = &block.kind {
+                footnote_files.insert(label.clone(), chapter_file(ix));
+            }
+        }
+    }
+
+    let mut zip = ZipWriter::new();
+    zip.add_stored("mimetype", b"application/epub+zip");
+    zip.add_deflated("META-INF/container.xml", CONTAINER_XML.as_bytes());
+
+    let mut toc: Vec<(String, HeadingAnchor)> = Vec::new();
+    let mut images = Vec::new();
+    for (ix, chapter) in chapters.iter().enumerate() {
+        let mut writer = HtmlWriter::xhtml(source_path);
+        writer.footnote_files = footnote_files.clone();
+        writer.packaged_images = Some(images);
+        writer.blocks(chapter);
+
+        let file = chapter_file(ix);
+        let chapter_title = writer
+            .headings
+            .first()
+            .map_or_else(|| title.clone(), |heading| heading.title.clone());
+        zip.add_deflated(
+            &format!("OEBPS/{file}"),
+            xhtml_page(&chapter_title, &writer.out).as_bytes(),
+        );
+        toc.extend(
+            writer
+                .headings
+                .into_iter()
+                .map(|heading| (file.clone(), heading)),
+        );
+        images = writer.packaged_images.unwrap_or_default();
+    }
+
+    for image in &images {
+        // Unreadable images were filtered when packaged; skip any that vanished since.
+        if let Ok(bytes) = std::fs::read(&image.path) {
+            zip.add_deflated(&format!("OEBPS/{}", image.href), &bytes);
+        }
+    }
+    zip.add_deflated("OEBPS/style.css", css.unwrap_or(DEFAULT_CSS).as_bytes());
+    zip.add_deflated("OEBPS/nav.xhtml", nav_document(&title, &toc).as_bytes());
+
+    let mut manifest = String::from(
+        "    <item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n\
+         \x20   <item id=\"css\" href=\"style.css\" media-type=\"text/css\"/>\n",
+    );
+    let mut spine = String::new();
+    for ix in 0..chapters.len() {
+        let _ = writeln!(
+            manifest,
+            "    <item id=\"chapter-{n}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>",
+            chapter_file(ix),
+            n = ix + 1
+        );
+        let _ = writeln!(spine, "    <itemref idref=\"chapter-{}\"/>", ix + 1);
+    }
+    for (ix, image) in images.iter().enumerate() {
+        let _ = writeln!(
+            manifest,
+            "    <item id=\"image-{}\" href=\"{}\" media-type=\"{}\"/>",
+            ix + 1,
+            image.href,
+            image.mime
+        );
+    }
+
+    let mut hasher = DefaultHasher::new();
+    markdown.hash(&mut hasher);
+    let package = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"book-id\">\n\
+         \x20 <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n\
+         \x20   <dc:identifier id=\"book-id\">urn:aster:{:016x}</dc:identifier>\n\
+         \x20   <dc:title>{}</dc:title>\n\
+         \x20   <dc:language>en</dc:language>\n\
+         \x20   <meta property=\"dcterms:modified\">{}</meta>\n\
+         \x20 </metadata>\n\
+         \x20 <manifest>\n{manifest}  </manifest>\n\
+         \x20 <spine>\n{spine}  </spine>\n\
+         </package>\n",
+        hasher.finish(),
+        escape(&title),
+        utc_timestamp()
+    );
+    zip.add_deflated("OEBPS/content.opf", package.as_bytes());
+    zip.finish()
+}
+
+/// Splits top-level blocks into chapters, each starting at an H1 or H2.
+fn split_chapters(blocks: &[Block]) -> Vec<&[Block]> {
+    let mut chapters = Vec::new();
+    let mut start = 0;
+    for (ix, block) in blocks.iter().enumerate() {
+        if matches!(block.kind, BlockKind::Heading { level: 1 | 2, .. }) && ix > start {
+            chapters.push(&blocks[start..ix]);
+            start = ix;
+        }
+    }
+    chapters.push(&blocks[start..]);
+    chapters
+}
+
+fn chapter_file(ix: usize) -> String {
+    format!("chapter-{}.xhtml", ix + 1)
+}
+
+fn xhtml_page(title: &str, body: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" \
+         xml:lang=\"en\" lang=\"en\">\n<head>\n<meta charset=\"utf-8\" />\n<title>{}</title>\n\
+         <link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\" />\n</head>\n\
+         <body>\n{body}</body>\n</html>\n",
+        escape(title)
+    )
+}
+
+/// Navigation document with the headings as a nested list.
+fn nav_document(title: &str, toc: &[(String, HeadingAnchor)]) -> String {
+    let mut list = String::new();
+    if toc.is_empty() {
+        let _ = write!(
+            list,
+            "<ol><li><a href=\"{}\">{}</a></li></ol>",
+            chapter_file(0),
+            escape(title)
+        );
+    } else {
+        // Open list levels, innermost last; each holds an unclosed <li>.
+        let mut levels: Vec<u8> = Vec::new();
+        for (file, heading) in toc {
+            while levels.last().is_some_and(|level| *level > heading.level) {
+                levels.pop();
+                list.push_str("</li></ol>");
+            }
+            match levels.last() {
+                Some(level) if *level == heading.level => list.push_str("</li>"),
+                _ => {
+                    list.push_str("<ol>");
+                    levels.push(heading.level);
+                }
+            }
+            let _ = write!(
+                list,
+                "<li><a href=\"{file}#{}\">{}</a>",
+                heading.id,
+                escape(&heading.title)
+            );
+        }
+        for _ in levels {
+            list.push_str("</li></ol>");
+        }
+    }
+    xhtml_page(
+        title,
+        &format!(
+            "<nav epub:type=\"toc\" id=\"toc\">\n<h1>{}</h1>\n{list}\n</nav>\n",
+            escape(title)
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_chapters_at_top_level_headings() {
+        let document =
+            parse_markdown("Intro\n\n# One\n\ntext\n\n### Deep\n\n## Two\n\n> # Quoted\n");
+        let chapters = split_chapters(&document.blocks);
+        let lengths: Vec<usize> = chapters.iter().map(|chapter| chapter.len()).collect();
+        assert_eq!(lengths, vec![1, 3, 2]);
+    }
+
+    #[test]
+    fn nav_nests_headings_by_level() {
+        let heading = |level, title: &str| {
+            (
+                "chapter-1.xhtml".to_string(),
+                HeadingAnchor {
+                    level,
+                    title: title.to_string(),
+                    id: title.to_lowercase(),
+                },
+            )
+        };
+        let nav = nav_document(
+            "Book",
+            &[
+                heading(1, "A"),
+                heading(2, "B"),
+                heading(3, "C"),
+                heading(1, "D"),
+            ],
+        );
+        assert!(nav.contains(
+            "<ol><li><a href=\"chapter-1.xhtml#a\">A</a><ol><li><a href=\"chapter-1.xhtml#b\">B</a>\
+             <ol><li><a href=\"chapter-1.xhtml#c\">C</a></li></ol></li></ol></li>\
+             <li><a href=\"chapter-1.xhtml#d\">D</a></li></ol>"
+        ));
+    }
+}
@@ -0,0 +1,475 @@
+use crate::services::code_highlight::{CodeTokenKind, highlight_code};
+use crate::services::export::document_title;
+use crate::services::images::resolve_local_image;
+use crate::services::markdown_ast::{Block, BlockKind, Inline, parse_markdown};
+use base64::Engine as _;
+use camino::Utf8Path;
+use pulldown_cmark::Alignment;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+/// Built-in stylesheet used when no custom CSS is configured.
+pub const DEFAULT_CSS: &str = include_str!("export.css");
+
+/// Renders `markdown` as a self-contained HTML page. Local images are embedded
+/// as data URIs; `css` replaces the built-in stylesheet.
+pub fn render_html(markdown: &str, source_path: Option<&Utf8Path>, css: Option<&str>) -> String {
+    let document = parse_markdown(markdown);
+    let title = document_title(&document, source_path);
+
+    let mut writer = HtmlWriter::new(source_path);
+    writer.blocks(&document.blocks);
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<style>\n{}</style>\n</head>\n<body>\n<article>\n{}</article>\n</body>\n</html>\n",
+        escape(&title),
+        css.unwrap_or(DEFAULT_CSS),
+        writer.out
+    )
+}
+
+/// Renders `markdown` as an HTML fragment for the clipboard, without a page
+/// wrapper or stylesheet. Local images are embedded as data URIs.
+pub fn render_html_fragment(markdown: &str, source_path: Option<&Utf8Path>) -> String {
+    let document = parse_markdown(markdown);
+    let mut writer = HtmlWriter::new(source_path);
+    writer.blocks(&document.blocks);
+    // Without a charset, some targets read the fragment as Latin-1.
+    format!("<meta charset=\"utf-8\">{}", writer.out)
+}
+
+/// A heading written by [`HtmlWriter`], with its anchor id.
+pub(super) struct HeadingAnchor {
+    pub level: u8,
+    pub title: String,
+    pub id: String,
+}
+
+/// A local image referenced by an XHTML document, to be packaged with it.
+pub(super) struct PackagedImage {
+    pub path: PathBuf,
+    /// Path of the image relative to the document.
+    pub href: String,
+    pub mime: &'static str,
+}
+
+/// Serialises the Markdown AST as HTML, or as XHTML for EPUB chapters.
+pub(super) struct HtmlWriter<'a> {
+    pub out: String,
+    source_path: Option<&'a Utf8Path>,
+    /// Write XHTML: self-closed void elements and raw HTML escaped as text.
+    xhtml: bool,
+    /// Local images to package alongside the output, or `None` to embed
+    /// them as data URIs.
+    pub packaged_images: Option<Vec<PackagedImage>>,
+    /// File holding each footnote definition, for references across files.
+    pub footnote_files: HashMap<String, String>,
+    /// Headings written so far.
+    pub headings: Vec<HeadingAnchor>,
+    heading_ids: HashMap<String, usize>,
+}
+
+impl<'a> HtmlWriter<'a> {
+    pub fn new(source_path: Option<&'a Utf8Path>) -> Self {
+        Self {
+            out: String::new(),
+            source_path,
+            xhtml: false,
+            packaged_images: None,
+            footnote_files: HashMap::new(),
+            headings: Vec::new(),
+            heading_ids: HashMap::new(),
+        }
+    }
+
+    /// A writer for XHTML documents that reference packaged images.
+    pub fn xhtml(source_path: Option<&'a Utf8Path>) -> Self {
+        Self {
+            xhtml: true,
+            packaged_images: Some(Vec::new()),
+            ..Self::new(source_path)
+        }
+    }
+
+    /// Closing of void elements such as `<hr>`.
+    fn void_end(&self) -> &'static str {
+        if self.xhtml { " />" } else { ">" }
+    }
+
+    /// Unique anchor id for a heading titled `title`.
+    fn heading_id(&mut self, title: &str) -> String {
+        let mut id = slug(title);
+        if self.xhtml && !id.starts_with(|c: char| c.is_alphabetic()) {
+            // XML ids must start with a letter.
+            id.insert_str(0, "h-");
+        }
+        let seen = self.heading_ids.entry(id.clone()).or_default();
+        *seen += 1;
+        if *seen > 1 {
+            id = format!("{id}-{}", *seen - 1);
+        }
+        id
+    }
+
+    fn raw_html(&mut self, html: &str) {
+        if self.xhtml {
+            self.out.push_str(&escape(html));
+        } else {
+            self.out.push_str(html);
+        }
+    }
+
+    pub fn blocks(&mut self, blocks: &[Block]) {
+        for block in blocks {
+            self.block(block);
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        match &block.kind {
+            BlockKind::Paragraph(inlines) => {
+                self.out.push_str("<p>");
+                self.inlines(inlines);
+                self.out.push_str("</p>\n");
+            }
+            BlockKind::Heading { level, content } => {
+                let title = Inline::plain_text(content);
+                let id = self.heading_id(&title);
+                let _ = write!(self.out, "<h{level} id=\"{id}\">");
+                self.headings.push(HeadingAnchor {
+                    level: *level,
+                    title,
+                    id,
+                });
+                self.inlines(content);
+                let _ = writeln!(self.out, "</h{level}>");
+            }
+            BlockKind::BlockQuote(children) => {
+                self.out.push_str("<blockquote>\n");
+                self.blocks(children);
+                self.out.push_str("</blockquote>\n");
+            }
+            BlockKind::List { start, items } => {
+                let is_task_list = items.iter().any(|item| item.checked.is_some());
+                match start {
+                    Some(1) => self.out.push_str("<ol>\n"),
+                    Some(start) => {
+                        let _ = writeln!(self.out, "<ol start=\"{start}\">");
+                    }
+                    None if is_task_list => self.out.push_str("<ul class=\"task-list\">\n"),
+                    None => self.out.push_str("<ul>\n"),
+                }
+                for item in items {
+                    self.out.push_str("<li>");
+                    if let Some(checked) = item.checked {
+                        let attributes = match (self.xhtml, checked) {
+                            (false, false) => " disabled",
+                            (false, true) => " disabled checked",
+                            (true, false) => " disabled=\"disabled\"",
+                            (true, true) => " disabled=\"disabled\" checked=\"checked\"",
+                        };
+                        let _ = write!(
+                            self.out,
+                            "<input type=\"checkbox\"{attributes}{} ",
+                            self.void_end()
+                        );
+                    }
+                    // Tight items hold a single paragraph: keep it inline.
+                    match item.blocks.as_slice() {
+                        [
+                            Block {
+                                kind: BlockKind::Paragraph(inlines),
+                                ..
+                            },
+                        ] => self.inlines(inlines),
+                        blocks => {
+                            self.out.push('\n');
+                            self.blocks(blocks);
+                        }
+                    }
+                    self.out.push_str("</li>\n");
+                }
+                self.out.push_str(if start.is_some() {
+                    "</ol>\n"
+                } else {
+                    "</ul>\n"
+                });
+            }
+            BlockKind::CodeBlock { language, code } => self.code_block(language.as_deref(), code),
+            BlockKind::Table {
+                alignments,
+                header,
+                rows,
+            } => {
+                self.out.push_str("<table>\n<thead>\n");
+                self.table_row(header, alignments, "th");
+                self.out.push_str("</thead>\n<tbody>\n");
+                for row in rows {
+                    self.table_row(row, alignments, "td");
+                }
+                self.out.push_str("</tbody>\n</table>\n");
+            }
+            BlockKind::Rule => {
+                let _ = writeln!(self.out, "<hr{}", self.void_end());
+            }
+            BlockKind::Html(html) => self.raw_html(html),
+            BlockKind::FootnoteDefinition { label, blocks } => {
+                let id = slug(label);
+                let _ = writeln!(
+                    self.out,
+                    "<div class=\"footnote-definition\" id=\"fn-{id}\">\
+                     <sup class=\"footnote-label\">{}</sup>",
+                    escape(label)
+                );
+                self.blocks(blocks);
+                self.out.push_str("</div>\n");
+            }
+        }
+    }
+
+    fn code_block(&mut self, language: Option<&str>, code: &str) {
+        let Some(language) = language else {
+            let _ = writeln!(self.out, "<pre><code>{}</code></pre>", escape(code));
+            return;
+        };
+        let _ = write!(
+            self.out,
+            "<pre><code class=\"language-{}\">",
+            escape(language)
+        );
+        let mut pos = 0;
+        for token in highlight_code(language, code) {
+            if token.range.start < pos || !code.is_char_boundary(token.range.end) {
+                continue;
+            }
+            self.out.push_str(&escape(&code[pos..token.range.start]));
+            let _ = write!(
+                self.out,
+                "<span class=\"tok-{}\">{}</span>",
+                token_class(token.kind),
+                escape(&code[token.range.clone()])
+            );
+            pos = token.range.end;
+        }
+        self.out.push_str(&escape(&code[pos..]));
+        self.out.push_str("</code></pre>\n");
+    }
+
+    fn table_row(&mut self, cells: &[Vec<Inline>], alignments: &[Alignment], tag: &str) {
+        self.out.push_str("<tr>");
+        for (ix, cell) in cells.iter().enumerate() {
+            match alignments.get(ix) {
+                Some(Alignment::Left) => {
+                    let _ = write!(self.out, "<{tag} style=\"text-align: left\">");
+                }
+                Some(Alignment::Center) => {
+                    let _ = write!(self.out, "<{tag} style=\"text-align: center\">");
+                }
+                Some(Alignment::Right) => {
+                    let _ = write!(self.out, "<{tag} style=\"text-align: right\">");
+                }
+                _ => {
+                    let _ = write!(self.out, "<{tag}>");
+                }
+            }
+            self.inlines(cell);
+            let _ = write!(self.out, "</{tag}>");
+        }
+        self.out.push_str("</tr>\n");
+    }
+
+    fn inlines(&mut self, inlines: &[Inline]) {
+        for inline in inlines {
+            self.inline(inline);
+        }
+    }
+
+    fn inline(&mut self, inline: &Inline) {
+        match inline {
+            Inline::Text(text) => self.out.push_str(&escape(text)),
+            Inline::Code(code) => {
+                let _ = write!(self.out, "<code>{}</code>", escape(code));
+            }
+            Inline::Emphasis(children) => self.wrap("em", children),
+            Inline::Strong(children) => self.wrap("strong", children),
+            Inline::Strikethrough(children) => self.wrap("del", children),
+            Inline::Link {
+                url,
+                title,
+                content,
+            } => {
+                let _ = write!(self.out, "<a href=\"{}\"", escape(url));
+                if !title.is_empty() {
+                    let _ = write!(self.out, " title=\"{}\"", escape(title));
+                }
+                self.out.push('>');
+                self.inlines(content);
+                self.out.push_str("</a>");
+            }
+            Inline::Image { url, title, alt } => {
+                let src = self.image_source(url);
+                let _ = write!(
+                    self.out,
+                    "<img src=\"{}\" alt=\"{}\"",
+                    escape(&src),
+                    escape(alt)
+                );
+                if !title.is_empty() {
+                    let _ = write!(self.out, " title=\"{}\"", escape(title));
+                }
+                self.out.push_str(self.void_end());
+            }
+            Inline::FootnoteReference(label) => {
+                let file = self.footnote_files.get(label).map_or("", String::as_str);
+                let _ = write!(
+                    self.out,
+                    "<sup class=\"footnote-reference\"><a href=\"{file}#fn-{}\">{}</a></sup>",
+                    slug(label),
+                    escape(label)
+                );
+            }
+            Inline::SoftBreak => self.out.push('\n'),
+            Inline::HardBreak => {
+                let _ = writeln!(self.out, "<br{}", self.void_end());
+            }
+            Inline::Html(html) => self.raw_html(html),
+        }
+    }
+
+    fn wrap(&mut self, tag: &str, children: &[Inline]) {
+        let _ = write!(self.out, "<{tag}>");
+        self.inlines(children);
+        let _ = write!(self.out, "</{tag}>");
+    }
+
+    /// Where a local image is found in the output (a data URI or a packaged
+    /// file); remote and unreadable images keep their original URL.
+    fn image_source(&mut self, url: &str) -> String {
+        let Some(path) = resolve_local_image(self.source_path, url).filter(|path| path.is_file())
+        else {
+            return url.to_string();
+        };
+        let Some(mime) = image_mime(&path) else {
+            return url.to_string();
+        };
+        match &mut self.packaged_images {
+            Some(images) => {
+                if let Some(image) = images.iter().find(|image| image.path == path) {
+                    return image.href.clone();
+                }
+                let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("img");
+                let href = format!("images/image-{}.{}", images.len() + 1, extension);
+                images.push(PackagedImage {
+                    path,
+                    href: href.clone(),
+                    mime,
+                });
+                href
+            }
+            None => std::fs::read(&path)
+                .map(|bytes| {
+                    format!(
+                        "data:{mime};base64,{}",
+                        base64::engine::general_purpose::STANDARD.encode(bytes)
+                    )
+                })
+                .unwrap_or_else(|_| url.to_string()),
+        }
+    }
+}
+
+pub(super) fn image_mime(path: &std::path::Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "bmp" => "image/bmp",
+        _ => return None,
+    })
+}
+
+fn token_class(kind: CodeTokenKind) -> &'static str {
+    match kind {
+        CodeTokenKind::Keyword => "keyword",
+        CodeTokenKind::String => "string",
+        CodeTokenKind::Comment => "comment",
+        CodeTokenKind::Number => "number",
+        CodeTokenKind::Constant => "constant",
+        CodeTokenKind::Function => "function",
+        CodeTokenKind::Type => "type",
+        CodeTokenKind::Tag => "tag",
+        CodeTokenKind::Attribute => "attribute",
+        CodeTokenKind::Operator => "operator",
+    }
+}
+
+/// GitHub-style anchor id: lowercase, spaces to dashes, punctuation dropped.
+pub fn slug(text: &str) -> String {
+    text.trim()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c.to_ascii_lowercase()),
+            _ => None,
+        })
+        .collect()
+}
+
+pub(super) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_gfm_blocks() {
+        let html = render_html(
+            "# A & B\n\n- [x] done\n- [ ] ~~todo~~\n\n| a | b |\n|:-|-:|\n| 1 | 2 |\n\n\
+             Note[^n].\n\n[^n]: The note.\n",
+            None,
+            Some("body {}\n"),
+        );
+        assert!(html.contains("<title>A &amp; B</title>"));
+        assert!(html.contains("<style>\nbody {}\n</style>"));
+        assert!(html.contains("<h1 id=\"a--b\">A &amp; B</h1>"));
+        assert!(html.contains("<li><input type=\"checkbox\" disabled checked> done</li>"));
+        assert!(html.contains("<del>todo</del>"));
+        assert!(html.contains("<th style=\"text-align: left\">a</th>"));
+        assert!(html.contains("<td style=\"text-align: right\">2</td>"));
+        assert!(html.contains("<a href=\"#fn-n\">n</a>"));
+        assert!(html.contains("id=\"fn-n\""));
+    }
+
+    #[test]
+    fn embeds_local_images_as_data_uris() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("dot.png"), b"png").unwrap();
+        let source = Utf8Path::from_path(dir.path()).unwrap().join("note.md");
+
+        let html = render_html(
+            "![dot](dot.png) ![web](https://example.com/a.png)",
+            Some(&source),
+            None,
+        );
+        assert!(html.contains("<img src=\"data:image/png;base64,cG5n\" alt=\"dot\">"));
+        assert!(html.contains("<img src=\"https://example.com/a.png\" alt=\"web\">"));
+    }
+}
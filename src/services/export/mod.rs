@@ -0,0 +1,157 @@
+pub mod docx;
+pub mod epub;
+pub mod html;
+pub mod pdf;
+mod pdf_font;
+mod pdf_writer;
+mod zip;
+
+use crate::error::{AppError, AppResult};
+use crate::services::fs::write_atomic;
+use crate::services::markdown_ast::{BlockKind, Inline, MarkdownDocument};
+use camino::{Utf8Path, Utf8PathBuf};
+use pdf::PdfOptions;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Document formats Aster can export to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExportFormat {
+    Html,
+    Pdf,
+    Epub,
+    Docx,
+}
+
+impl ExportFormat {
+    /// Picks the format from an output path's extension.
+    pub fn from_path(path: &Utf8Path) -> Option<Self> {
+        match path.extension()?.to_ascii_lowercase().as_str() {
+            "html" | "htm" => Some(Self::Html),
+            "pdf" => Some(Self::Pdf),
+            "epub" => Some(Self::Epub),
+            "docx" => Some(Self::Docx),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Html => "html",
+            Self::Pdf => "pdf",
+            Self::Epub => "epub",
+            Self::Docx => "docx",
+        }
+    }
+}
+
+/// Options shared by the exporters.
+#[derive(Clone, Debug, Default)]
+pub struct ExportOptions {
+    /// Path of the source document, for resolving relative image paths.
+    pub source_path: Option<Utf8PathBuf>,
+    /// Stylesheet replacing the built-in HTML theme (HTML and EPUB).
+    pub css_path: Option<Utf8PathBuf>,
+    /// Page setup for PDF exports.
+    pub pdf: PdfOptions,
+}
+
+/// Renders `markdown` as `format` and writes it to `output`. Returns warnings
+/// about content that could not be exported faithfully.
+pub fn export_to_path(
+    markdown: &str,
+    format: ExportFormat,
+    output: &Utf8PathBuf,
+    options: &ExportOptions,
+) -> AppResult<Vec<String>> {
+    let mut warnings = Vec::new();
+    match format {
+        ExportFormat::Html => {
+            let css = read_css(options)?;
+            let html = html::render_html(markdown, options.source_path.as_deref(), css.as_deref());
+            write_atomic(output, &html)?;
+        }
+        ExportFormat::Pdf => {
+            let (pdf, missing) =
+                pdf::render_pdf(markdown, options.source_path.as_deref(), &options.pdf);
+            if !missing.is_empty() {
+                warnings.push(format!(
+                    "no installed font has {}; written as \"?\"",
+                    missing.iter().collect::<String>()
+                ));
+            }
+            write_atomic(output, &pdf)?;
+        }
+        ExportFormat::Epub => {
+            let css = read_css(options)?;
+            let epub = epub::render_epub(markdown, options.source_path.as_deref(), css.as_deref());
+            write_atomic(output, &epub)?;
+        }
+        ExportFormat::Docx => {
+            let docx = docx::render_docx(markdown, options.source_path.as_deref());
+            write_atomic(output, &docx)?;
+        }
+    }
+    Ok(warnings)
+}
+
+fn read_css(options: &ExportOptions) -> AppResult<Option<String>> {
+    match &options.css_path {
+        Some(path) => Ok(Some(std::fs::read_to_string(path)?)),
+        None => Ok(None),
+    }
+}
+
+/// Title of an exported document: its first level-1 heading, else the file name.
+fn document_title(document: &MarkdownDocument, source_path: Option<&Utf8Path>) -> String {
+    document
+        .blocks
+        .iter()
+        .find_map(|block| match &block.kind {
+            BlockKind::Heading { level: 1, content } => Some(Inline::plain_text(content)),
+            _ => None,
+        })
+        .or_else(|| {
+            source_path
+                .and_then(Utf8Path::file_stem)
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| "Untitled".to_string())
+}
+
+/// Current time as an ISO 8601 UTC timestamp (`2024-05-01T09:30:00Z`).
+fn utc_timestamp() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let (days, secs) = ((secs / 86_400) as i64, secs % 86_400);
+    // Civil-from-days conversion (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Exports the Markdown file at `input` to `output`, picking the format from
+/// the output extension. Used by the headless `--export` command line.
+pub fn export_file(
+    input: &Utf8PathBuf,
+    output: &Utf8PathBuf,
+    mut options: ExportOptions,
+) -> AppResult<Vec<String>> {
+    let format = ExportFormat::from_path(output)
+        .ok_or_else(|| AppError::Export(format!("unsupported export format: {output}")))?;
+    let markdown = std::fs::read_to_string(input)?;
+    options.source_path.get_or_insert_with(|| input.clone());
+    export_to_path(&markdown, format, output, &options)
+}
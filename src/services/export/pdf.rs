@@ -0,0 +1,794 @@
+use crate::services::code_highlight::{CodeTokenKind, highlight_code};
+use crate::services::export::document_title;
+use crate::services::export::pdf_writer::{Color, Font, PageContent, PdfImage, PdfWriter};
+use crate::services::images::resolve_local_image;
+use crate::services::markdown_ast::{Block, BlockKind, Inline, ListItem, parse_markdown};
+use camino::Utf8Path;
+use pulldown_cmark::Alignment;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::PathBuf;
+
+const POINTS_PER_MM: f32 = 72. / 25.4;
+const BODY_SIZE: f32 = 11.;
+const CODE_SIZE: f32 = 9.;
+const LINE_SPACING: f32 = 1.4;
+const LIST_INDENT: f32 = 18.;
+const QUOTE_INDENT: f32 = 14.;
+const CELL_PADDING: f32 = 4.;
+
+const TEXT: Color = Color::hex(0x243446);
+const MUTED: Color = Color::hex(0x7c8a99);
+const ACCENT: Color = Color::hex(0x2d7fd2);
+const BORDER: Color = Color::hex(0xd8dde3);
+const CODE_BG: Color = Color::hex(0xf7f6f2);
+const HEADER_BG: Color = Color::hex(0xf2f3f7);
+const INLINE_CODE: Color = Color::hex(0x1f6f8b);
+
+/// Paper size of exported PDFs.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PageSize {
+    #[default]
+    A4,
+    Letter,
+    Legal,
+}
+
+impl PageSize {
+    /// Parses a size name as accepted on the command line.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "a4" => Some(Self::A4),
+            "letter" => Some(Self::Letter),
+            "legal" => Some(Self::Legal),
+            _ => None,
+        }
+    }
+
+    /// Width and height in points.
+    pub fn dimensions(self) -> (f32, f32) {
+        match self {
+            Self::A4 => (595.28, 841.89),
+            Self::Letter => (612., 792.),
+            Self::Legal => (612., 1008.),
+        }
+    }
+}
+
+/// Page setup for PDF exports.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PdfOptions {
+    pub page_size: PageSize,
+    /// Margin on every side, in millimetres.
+    pub margin_mm: f32,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            page_size: PageSize::A4,
+            margin_mm: 20.,
+        }
+    }
+}
+
+/// Lays out `markdown` on pages and returns the PDF file contents, along with
+/// the characters no available font could draw; those are written as `?`.
+pub fn render_pdf(
+    markdown: &str,
+    source_path: Option<&Utf8Path>,
+    options: &PdfOptions,
+) -> (Vec<u8>, Vec<char>) {
+    let document = parse_markdown(markdown);
+    let title = document_title(&document, source_path);
+
+    let (width, height) = options.page_size.dimensions();
+    // Keep at least a third of the page for content, whatever the margin.
+    let margin = (options.margin_mm.max(0.) * POINTS_PER_MM).min(width.min(height) / 3.);
+    let writer = PdfWriter::new(width, height, title);
+    let page = writer.new_page();
+    let mut layout = Layout {
+        writer,
+        page,
+        page_height: height,
+        margin,
+        y: margin,
+        left: margin,
+        right: width - margin,
+        quote_bars: Vec::new(),
+        pending_marker: None,
+        source_path,
+        images: HashMap::new(),
+    };
+    layout.blocks(&document.blocks);
+    layout.finish()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct TextStyle {
+    font: Font,
+    size: f32,
+    color: Color,
+}
+
+impl TextStyle {
+    fn body() -> Self {
+        Self {
+            font: Font::Regular,
+            size: BODY_SIZE,
+            color: TEXT,
+        }
+    }
+
+    fn with_font(self, font: Font) -> Self {
+        Self { font, ..self }
+    }
+
+    fn bold(self) -> Self {
+        let font = match self.font {
+            Font::Italic | Font::BoldItalic => Font::BoldItalic,
+            Font::Mono => Font::Mono,
+            Font::Regular | Font::Bold => Font::Bold,
+        };
+        self.with_font(font)
+    }
+
+    fn italic(self) -> Self {
+        let font = match self.font {
+            Font::Bold | Font::BoldItalic => Font::BoldItalic,
+            Font::Mono => Font::Mono,
+            Font::Regular | Font::Italic => Font::Italic,
+        };
+        self.with_font(font)
+    }
+
+    fn width(&self, text: &str) -> f32 {
+        self.font.text_width(text, self.size)
+    }
+}
+
+/// Flattened inline content: styled text, forced breaks and images.
+enum Piece {
+    Text(String, TextStyle),
+    Break,
+    Image { url: String, alt: String },
+}
+
+/// A wrapped line of styled text segments.
+#[derive(Default)]
+struct Line {
+    segments: Vec<(String, TextStyle)>,
+    width: f32,
+    size: f32,
+}
+
+impl Line {
+    fn height(&self, fallback: f32) -> f32 {
+        self.size.max(fallback) * LINE_SPACING
+    }
+
+    fn push(&mut self, text: &str, style: TextStyle) {
+        self.width += style.width(text);
+        self.size = self.size.max(style.size);
+        match self.segments.last_mut() {
+            Some((last, last_style)) if *last_style == style => last.push_str(text),
+            _ => self.segments.push((text.to_string(), style)),
+        }
+    }
+
+    fn trim_end(&mut self) {
+        if let Some((text, style)) = self.segments.last_mut() {
+            let trimmed = text.trim_end().len();
+            self.width -= style.width(&text[trimmed..]);
+            text.truncate(trimmed);
+        }
+    }
+}
+
+fn flatten(inlines: &[Inline], style: TextStyle, out: &mut Vec<Piece>) {
+    for inline in inlines {
+        match inline {
+            Inline::Text(text) => out.push(Piece::Text(text.clone(), style)),
+            Inline::Code(code) => out.push(Piece::Text(
+                code.clone(),
+                TextStyle {
+                    font: Font::Mono,
+                    size: style.size * 0.9,
+                    color: INLINE_CODE,
+                },
+            )),
+            Inline::Emphasis(children) => flatten(children, style.italic(), out),
+            Inline::Strong(children) => flatten(children, style.bold(), out),
+            // Base-14 fonts have no strikethrough; mute the text instead.
+            Inline::Strikethrough(children) => flatten(
+                children,
+                TextStyle {
+                    color: MUTED,
+                    ..style
+                },
+                out,
+            ),
+            Inline::Link { content, .. } => flatten(
+                content,
+                TextStyle {
+                    color: ACCENT,
+                    ..style
+                },
+                out,
+            ),
+            Inline::Image { url, alt, .. } => out.push(Piece::Image {
+                url: url.clone(),
+                alt: alt.clone(),
+            }),
+            Inline::FootnoteReference(label) => out.push(Piece::Text(
+                format!("[{label}]"),
+                TextStyle {
+                    color: ACCENT,
+                    size: style.size * 0.8,
+                    ..style
+                },
+            )),
+            Inline::SoftBreak => out.push(Piece::Text(" ".into(), style)),
+            Inline::HardBreak => out.push(Piece::Break),
+            Inline::Html(html) => out.push(Piece::Text(
+                html.clone(),
+                TextStyle {
+                    color: MUTED,
+                    ..style
+                },
+            )),
+        }
+    }
+}
+
+/// Greedily wraps text pieces to `width`. Images must be split out first.
+fn wrap(pieces: &[Piece], width: f32) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut line = Line::default();
+    for piece in pieces {
+        let (text, style) = match piece {
+            Piece::Text(text, style) => (text, *style),
+            Piece::Break => {
+                line.trim_end();
+                lines.push(std::mem::take(&mut line));
+                continue;
+            }
+            Piece::Image { .. } => continue,
+        };
+        for word in text.split_inclusive(' ') {
+            let word = if line.segments.is_empty() {
+                word.trim_start()
+            } else {
+                word
+            };
+            if word.is_empty() {
+                continue;
+            }
+            let fits = line.width + style.width(word.trim_end()) <= width;
+            if !fits && !line.segments.is_empty() {
+                line.trim_end();
+                lines.push(std::mem::take(&mut line));
+                let word = word.trim_start();
+                push_word(&mut line, &mut lines, word, style, width);
+            } else {
+                push_word(&mut line, &mut lines, word, style, width);
+            }
+        }
+    }
+    line.trim_end();
+    if !line.segments.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Adds `word` to `line`, breaking it by characters if it is wider than a line.
+fn push_word(line: &mut Line, lines: &mut Vec<Line>, word: &str, style: TextStyle, width: f32) {
+    if line.width + style.width(word.trim_end()) <= width {
+        line.push(word, style);
+        return;
+    }
+    for c in word.chars() {
+        let mut buf = [0; 4];
+        let c = c.encode_utf8(&mut buf);
+        if line.width + style.width(c) > width && !line.segments.is_empty() {
+            lines.push(std::mem::take(line));
+        }
+        line.push(c, style);
+    }
+}
+
+struct Layout<'a> {
+    writer: PdfWriter,
+    page: PageContent,
+    page_height: f32,
+    margin: f32,
+    /// Top of the next block on the current page.
+    y: f32,
+    left: f32,
+    right: f32,
+    /// X positions of the bars of enclosing block quotes.
+    quote_bars: Vec<f32>,
+    /// List or footnote marker to draw beside the next line.
+    pending_marker: Option<(String, TextStyle, f32)>,
+    source_path: Option<&'a Utf8Path>,
+    /// Embedded images by path, `None` if the image could not be decoded.
+    images: HashMap<PathBuf, Option<(usize, u32, u32)>>,
+}
+
+impl Layout<'_> {
+    fn finish(mut self) -> (Vec<u8>, Vec<char>) {
+        let page = std::mem::replace(&mut self.page, self.writer.new_page());
+        self.writer.push_page(page);
+
+        let total = self.writer.page_count();
+        let (width, height) = (self.right + self.margin, self.page_height);
+        let footer = TextStyle {
+            size: 8.,
+            color: MUTED,
+            ..TextStyle::body()
+        };
+        let baseline = height - self.margin / 2.;
+        for (ix, page) in self.writer.pages_mut().enumerate() {
+            let label = format!("{} / {total}", ix + 1);
+            let x = (width - footer.width(&label)) / 2.;
+            page.text(x, baseline, footer.font, footer.size, footer.color, &label);
+        }
+        let missing = self.writer.missing_chars();
+        (self.writer.finish(), missing)
+    }
+
+    fn content_bottom(&self) -> f32 {
+        self.page_height - self.margin
+    }
+
+    /// Starts a new page unless `height` still fits on the current one.
+    fn ensure_space(&mut self, height: f32) {
+        if self.y + height > self.content_bottom() && self.y > self.margin {
+            let page = std::mem::replace(&mut self.page, self.writer.new_page());
+            self.writer.push_page(page);
+            self.y = self.margin;
+        }
+    }
+
+    /// Draws quote bars and any pending list marker beside a line.
+    fn decorate(&mut self, height: f32, baseline: f32) {
+        for x in &self.quote_bars {
+            self.page.fill_rect(*x, self.y, 2., height, BORDER);
+        }
+        if let Some((marker, style, indent)) = self.pending_marker.take() {
+            let x = self.left - indent;
+            self.page
+                .text(x, baseline, style.font, style.size, style.color, &marker);
+        }
+    }
+
+    fn gap(&mut self, amount: f32) {
+        self.y += amount;
+    }
+
+    fn blocks(&mut self, blocks: &[Block]) {
+        for (ix, block) in blocks.iter().enumerate() {
+            if ix > 0 {
+                self.gap(BODY_SIZE * 0.6);
+            }
+            self.block(block);
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        match &block.kind {
+            BlockKind::Paragraph(inlines) => self.paragraph(inlines, TextStyle::body()),
+            BlockKind::Heading { level, content } => {
+                let size = match level {
+                    1 => 22.,
+                    2 => 18.,
+                    3 => 15.,
+                    4 => 13.,
+                    _ => BODY_SIZE,
+                };
+                self.gap(size * 0.4);
+                // Keep the heading with at least one following line.
+                self.ensure_space(size * LINE_SPACING + BODY_SIZE * LINE_SPACING * 2.);
+                let style = TextStyle {
+                    font: Font::Bold,
+                    size,
+                    color: TEXT,
+                };
+                self.paragraph(content, style);
+                if *level <= 2 {
+                    self.gap(2.);
+                    self.page
+                        .line(self.left, self.y, self.right, self.y, 0.5, BORDER);
+                    self.gap(2.);
+                }
+            }
+            BlockKind::BlockQuote(children) => {
+                self.quote_bars.push(self.left);
+                self.left += QUOTE_INDENT;
+                self.blocks(children);
+                self.left -= QUOTE_INDENT;
+                self.quote_bars.pop();
+            }
+            BlockKind::List { start, items } => self.list(*start, items),
+            BlockKind::CodeBlock { language, code } => self.code_block(language.as_deref(), code),
+            BlockKind::Table {
+                alignments,
+                header,
+                rows,
+            } => self.table(alignments, header, rows),
+            BlockKind::Rule => {
+                self.ensure_space(BODY_SIZE);
+                self.gap(BODY_SIZE / 2.);
+                self.page
+                    .line(self.left, self.y, self.right, self.y, 0.75, BORDER);
+                self.gap(BODY_SIZE / 2.);
+            }
+            BlockKind::Html(html) => self.code_lines(html.trim_end(), &[], MUTED),
+            BlockKind::FootnoteDefinition { label, blocks } => {
+                let style = TextStyle {
+                    size: BODY_SIZE * 0.85,
+                    color: ACCENT,
+                    ..TextStyle::body()
+                };
+                self.pending_marker = Some((format!("[{label}]"), style, LIST_INDENT + 6.));
+                self.left += LIST_INDENT + 6.;
+                self.blocks(blocks);
+                self.left -= LIST_INDENT + 6.;
+                self.pending_marker = None;
+            }
+        }
+    }
+
+    fn paragraph(&mut self, inlines: &[Inline], style: TextStyle) {
+        let mut pieces = Vec::new();
+        flatten(inlines, style, &mut pieces);
+
+        // Images break the paragraph and are laid out as their own blocks.
+        let mut text = Vec::new();
+        for piece in pieces {
+            match piece {
+                Piece::Image { url, alt } => {
+                    self.text_lines(&text, style.size);
+                    text.clear();
+                    self.image(&url, &alt);
+                }
+                piece => text.push(piece),
+            }
+        }
+        self.text_lines(&text, style.size);
+    }
+
+    /// Wraps and draws text pieces at the current position.
+    fn text_lines(&mut self, pieces: &[Piece], size: f32) {
+        let has_text = pieces.iter().any(|piece| match piece {
+            Piece::Text(text, _) => !text.trim().is_empty(),
+            _ => false,
+        });
+        if !has_text {
+            return;
+        }
+        let width = self.right - self.left;
+        for line in wrap(pieces, width) {
+            let height = line.height(size);
+            self.ensure_space(height);
+            let baseline = self.y + height * 0.75;
+            self.decorate(height, baseline);
+            let mut x = self.left;
+            for (text, style) in &line.segments {
+                self.page
+                    .text(x, baseline, style.font, style.size, style.color, text);
+                x += style.width(text);
+            }
+            self.y += height;
+        }
+    }
+
+    fn list(&mut self, start: Option<u64>, items: &[ListItem]) {
+        for (ix, item) in items.iter().enumerate() {
+            if ix > 0 {
+                self.gap(BODY_SIZE * 0.2);
+            }
+            let marker = match (item.checked, start) {
+                (Some(true), _) => "[x]".to_string(),
+                (Some(false), _) => "[ ]".to_string(),
+                (None, Some(start)) => format!("{}.", start + ix as u64),
+                (None, None) => "•".to_string(),
+            };
+            let style = TextStyle {
+                color: MUTED,
+                ..TextStyle::body()
+            };
+            self.left += LIST_INDENT;
+            self.pending_marker = Some((marker, style, LIST_INDENT - 4.));
+            self.blocks(&item.blocks);
+            self.pending_marker = None;
+            self.left -= LIST_INDENT;
+        }
+    }
+
+    fn code_block(&mut self, language: Option<&str>, code: &str) {
+        let code = code.strip_suffix('\n').unwrap_or(code);
+        let tokens: Vec<(Range<usize>, Color)> = language
+            .map(|language| highlight_code(language, code))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|token| (token.range, token_color(token.kind)))
+            .collect();
+        self.code_lines(code, &tokens, TEXT);
+    }
+
+    /// Monospace lines on a shaded background, hard-wrapped at the right edge.
+    fn code_lines(&mut self, code: &str, tokens: &[(Range<usize>, Color)], color: Color) {
+        let height = CODE_SIZE * LINE_SPACING;
+        let padding = 4.;
+        let width = self.right - self.left;
+        let char_width = Font::Mono.text_width("m", CODE_SIZE);
+        let max_chars = (((width - padding * 2.) / char_width) as usize).max(1);
+
+        self.ensure_space(height + padding);
+        self.page
+            .fill_rect(self.left, self.y, width, padding, CODE_BG);
+        self.y += padding;
+
+        let mut line_start = 0;
+        for line in code.split('\n') {
+            let boundaries: Vec<usize> = line
+                .char_indices()
+                .map(|(ix, _)| line_start + ix)
+                .chain(std::iter::once(line_start + line.len()))
+                .collect();
+            let last = boundaries.len() - 1;
+            for chunk in (0..last.max(1)).step_by(max_chars) {
+                let range = boundaries[chunk.min(last)]..boundaries[(chunk + max_chars).min(last)];
+                self.ensure_space(height);
+                self.page
+                    .fill_rect(self.left, self.y, width, height, CODE_BG);
+                let baseline = self.y + height * 0.75;
+                self.decorate(height, baseline);
+                self.code_chunk(code, range, tokens, color, self.left + padding, baseline);
+                self.y += height;
+            }
+            line_start += line.len() + 1;
+        }
+
+        self.page
+            .fill_rect(self.left, self.y, width, padding, CODE_BG);
+        self.y += padding;
+    }
+
+    /// Draws `code[range]`, coloring the parts covered by `tokens`.
+    fn code_chunk(
+        &mut self,
+        code: &str,
+        range: Range<usize>,
+        tokens: &[(Range<usize>, Color)],
+        color: Color,
+        mut x: f32,
+        baseline: f32,
+    ) {
+        // Plain runs between tokens, then the tokens themselves, in order.
+        let mut runs = Vec::new();
+        let mut pos = range.start;
+        for (token, token_color) in tokens {
+            if token.end <= pos || token.start >= range.end {
+                continue;
+            }
+            if token.start > pos {
+                runs.push((pos..token.start, color));
+            }
+            let end = token.end.min(range.end);
+            runs.push((token.start.max(pos)..end, *token_color));
+            pos = end;
+        }
+        runs.push((pos..range.end, color));
+
+        for (run, color) in runs {
+            let Some(text) = code.get(run).filter(|text| !text.is_empty()) else {
+                continue;
+            };
+            self.page
+                .text(x, baseline, Font::Mono, CODE_SIZE, color, text);
+            x += Font::Mono.text_width(text, CODE_SIZE);
+        }
+    }
+
+    fn table(
+        &mut self,
+        alignments: &[Alignment],
+        header: &[Vec<Inline>],
+        rows: &[Vec<Vec<Inline>>],
+    ) {
+        let columns = header.len().max(1);
+        let column_width = (self.right - self.left) / columns as f32;
+        self.table_row(header, alignments, column_width, true);
+        for row in rows {
+            self.table_row(row, alignments, column_width, false);
+        }
+    }
+
+    fn table_row(
+        &mut self,
+        cells: &[Vec<Inline>],
+        alignments: &[Alignment],
+        column_width: f32,
+        is_header: bool,
+    ) {
+        let style = if is_header {
+            TextStyle::body().bold()
+        } else {
+            TextStyle::body()
+        };
+        let cell_lines: Vec<Vec<Line>> = cells
+            .iter()
+            .map(|cell| {
+                let mut pieces = Vec::new();
+                flatten(cell, style, &mut pieces);
+                wrap(&pieces, column_width - CELL_PADDING * 2.)
+            })
+            .collect();
+        let row_height = cell_lines
+            .iter()
+            .map(|lines| {
+                lines
+                    .iter()
+                    .map(|line| line.height(style.size))
+                    .sum::<f32>()
+            })
+            .fold(style.size * LINE_SPACING, f32::max)
+            + CELL_PADDING * 2.;
+
+        self.ensure_space(row_height);
+        let row_width = column_width * cells.len().max(1) as f32;
+        if is_header {
+            self.page
+                .fill_rect(self.left, self.y, row_width, row_height, HEADER_BG);
+        }
+        self.decorate(row_height, self.y + CELL_PADDING + style.size);
+        for (ix, lines) in cell_lines.iter().enumerate() {
+            let cell_x = self.left + column_width * ix as f32;
+            self.page
+                .stroke_rect(cell_x, self.y, column_width, row_height, BORDER);
+            let inner = column_width - CELL_PADDING * 2.;
+            let mut y = self.y + CELL_PADDING;
+            for line in lines {
+                let height = line.height(style.size);
+                let baseline = y + height * 0.75;
+                let mut x = cell_x
+                    + CELL_PADDING
+                    + match alignments.get(ix) {
+                        Some(Alignment::Center) => (inner - line.width) / 2.,
+                        Some(Alignment::Right) => inner - line.width,
+                        _ => 0.,
+                    };
+                for (text, style) in &line.segments {
+                    self.page
+                        .text(x, baseline, style.font, style.size, style.color, text);
+                    x += style.width(text);
+                }
+                y += height;
+            }
+        }
+        self.y += row_height;
+    }
+
+    fn image(&mut self, url: &str, alt: &str) {
+        let loaded = resolve_local_image(self.source_path, url).and_then(|path| {
+            if !self.images.contains_key(&path) {
+                let image = load_image(&path).map(|image| {
+                    let (width, height) = (image.width, image.height);
+                    (self.writer.add_image(image), width, height)
+                });
+                self.images.insert(path.clone(), image);
+            }
+            self.images[&path]
+        });
+        let Some((id, pixel_width, pixel_height)) = loaded else {
+            let style = TextStyle {
+                color: MUTED,
+                ..TextStyle::body().italic()
+            };
+            let pieces = [Piece::Text(format!("[Image: {alt}]"), style)];
+            self.text_lines(&pieces, style.size);
+            return;
+        };
+
+        // Pixels at 96 dpi, shrunk to fit the column and the page.
+        let mut width = pixel_width as f32 * 0.75;
+        let mut height = pixel_height as f32 * 0.75;
+        let max_width = self.right - self.left;
+        let max_height = self.content_bottom() - self.margin;
+        let scale = (max_width / width).min(max_height / height).min(1.);
+        width *= scale;
+        height *= scale;
+
+        self.ensure_space(height);
+        self.decorate(height, self.y + BODY_SIZE);
+        self.page.image(id, self.left, self.y, width, height);
+        self.y += height;
+    }
+}
+
+/// Decodes an image and flattens any transparency onto white.
+fn load_image(path: &std::path::Path) -> Option<PdfImage> {
+    let image = image::open(path).ok()?.to_rgba8();
+    let (width, height) = image.dimensions();
+    let rgb = image
+        .pixels()
+        .flat_map(|pixel| {
+            let [r, g, b, a] = pixel.0;
+            let blend = |c: u8| ((c as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8;
+            [blend(r), blend(g), blend(b)]
+        })
+        .collect();
+    Some(PdfImage { width, height, rgb })
+}
+
+fn token_color(kind: CodeTokenKind) -> Color {
+    match kind {
+        CodeTokenKind::Keyword => Color::hex(0xa626a4),
+        CodeTokenKind::String => Color::hex(0x50a14f),
+        CodeTokenKind::Comment => Color::hex(0xa0a1a7),
+        CodeTokenKind::Number | CodeTokenKind::Constant | CodeTokenKind::Attribute => {
+            Color::hex(0x986801)
+        }
+        CodeTokenKind::Function => Color::hex(0x4078f2),
+        CodeTokenKind::Type => Color::hex(0xc18401),
+        CodeTokenKind::Tag => Color::hex(0xe45649),
+        CodeTokenKind::Operator => Color::hex(0x0184bc),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_words_to_the_line_width() {
+        let style = TextStyle::body();
+        let pieces = [
+            Piece::Text("alpha beta ".into(), style),
+            Piece::Text("gamma".into(), style.bold()),
+            Piece::Break,
+            Piece::Text("delta".into(), style),
+        ];
+        let width = style.width("alpha beta") + 1.;
+        let lines: Vec<Vec<String>> = wrap(&pieces, width)
+            .into_iter()
+            .map(|line| line.segments.into_iter().map(|(text, _)| text).collect())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                vec!["alpha beta".to_string()],
+                vec!["gamma".to_string()],
+                vec!["delta".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn long_documents_flow_onto_more_pages() {
+        let options = PdfOptions::default();
+        let (short, _) = render_pdf("# Title\n\nHello", None, &options);
+        let long_source = "Paragraph text that repeats.\n\n".repeat(200)
+            + "| a | b |\n|---|--:|\n| 1 | 2 |\n\n```rust\nfn main() {}\n```\n";
+        let (long, _) = render_pdf(&long_source, None, &options);
+
+        let page_count = |pdf: &[u8]| {
+            let text = String::from_utf8_lossy(pdf);
+            text.matches("/Type /Page ").count()
+        };
+        assert_eq!(page_count(&short), 1);
+        assert!(page_count(&long) > 2);
+
+        let letter = PdfOptions {
+            page_size: PageSize::Letter,
+            margin_mm: 10.,
+        };
+        let text = String::from_utf8_lossy(&render_pdf("x", None, &letter).0).into_owned();
+        assert!(text.contains("/MediaBox [0 0 612.00 792.00]"));
+    }
+}
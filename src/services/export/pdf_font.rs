@@ -0,0 +1,368 @@
+//! TrueType fallback font for PDF text the base-14 fonts can't encode. It is
+//! embedded as a CID font with a ToUnicode map, so the text stays copyable,
+//! and subset to the glyphs the document draws.
+
+use once_cell::sync::Lazy;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use ttf_parser::{Face, GlyphId, name_id};
+
+/// Fonts tried in order; the first one with TrueType outlines is used.
+const CANDIDATES: &[&str] = &[
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu-sans-fonts/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/noto/NotoSans-Regular.ttf",
+    "/usr/share/fonts/noto/NotoSans-Regular.ttf",
+    "/System/Library/Fonts/Supplemental/Arial Unicode.ttf",
+    "/Library/Fonts/Arial Unicode.ttf",
+    "C:\\Windows\\Fonts\\arialuni.ttf",
+    "C:\\Windows\\Fonts\\segoeui.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+];
+
+static UNICODE_FONT: Lazy<Option<UnicodeFont>> =
+    Lazy::new(|| CANDIDATES.iter().find_map(|path| UnicodeFont::load(path)));
+
+/// The fallback font, if one is installed.
+pub fn unicode_font() -> Option<&'static UnicodeFont> {
+    UNICODE_FONT.as_ref()
+}
+
+pub struct UnicodeFont {
+    data: &'static [u8],
+    face: Face<'static>,
+    /// PostScript name, restricted to characters valid in a PDF name.
+    name: String,
+}
+
+impl UnicodeFont {
+    fn load(path: &str) -> Option<Self> {
+        // Loaded once per process and kept for its lifetime.
+        let data: &'static [u8] = std::fs::read(path).ok()?.leak();
+        let face = Face::parse(data, 0).ok()?;
+        face.tables().glyf?;
+        let name = face
+            .names()
+            .into_iter()
+            .filter(|name| name.name_id == name_id::POST_SCRIPT_NAME)
+            .find_map(|name| name.to_string())
+            .map(|name| name.replace(|c: char| !c.is_ascii_alphanumeric() && c != '-', ""))
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| "AsterFallback".to_string());
+        Some(Self { data, face, name })
+    }
+
+    /// The font cut down to `glyphs`, with glyph ids unchanged. Falls back
+    /// to the whole font if its tables can't be read.
+    pub fn subset(&self, glyphs: &BTreeMap<u16, char>) -> FontSubset {
+        // The six-letter tag marks the font as a subset and tells subsets of
+        // the same font apart.
+        let mut hash: u32 = 2166136261;
+        for glyph in glyphs.keys() {
+            hash = (hash ^ u32::from(*glyph)).wrapping_mul(16777619);
+        }
+        let tag: String = (0..6)
+            .map(|ix| char::from(b'A' + ((hash >> (ix * 5)) % 26) as u8))
+            .collect();
+        match subset_truetype(self.data, glyphs.keys().copied()) {
+            Some(data) => FontSubset {
+                name: format!("{tag}+{}", self.name),
+                data,
+            },
+            None => FontSubset {
+                name: self.name.clone(),
+                data: self.data.to_vec(),
+            },
+        }
+    }
+
+    /// Glyph id of `c`, or `None` if the font doesn't cover it.
+    pub fn glyph(&self, c: char) -> Option<u16> {
+        self.face
+            .glyph_index(c)
+            .map(|glyph| glyph.0)
+            .filter(|glyph| *glyph != 0)
+    }
+
+    /// Advance width of `glyph` in 1/1000 em.
+    pub fn width(&self, glyph: u16) -> u32 {
+        let advance = self.face.glyph_hor_advance(GlyphId(glyph)).unwrap_or(0);
+        self.scale(i32::from(advance)) as u32
+    }
+
+    fn scale(&self, units: i32) -> i32 {
+        units * 1000 / i32::from(self.face.units_per_em().max(1))
+    }
+
+    /// `/W` array entries giving the width of each glyph in `glyphs`.
+    pub fn widths(&self, glyphs: &BTreeMap<u16, char>) -> String {
+        glyphs
+            .keys()
+            .map(|glyph| format!("{glyph} [{}]", self.width(*glyph)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Font descriptor dictionary pointing at the embedded font file.
+    pub fn descriptor(&self, subset: &FontSubset, font_file: usize) -> String {
+        let bbox = self.face.global_bounding_box();
+        format!(
+            "<< /Type /FontDescriptor /FontName /{} /Flags 32 /FontBBox [{} {} {} {}] \
+             /ItalicAngle 0 /Ascent {} /Descent {} /CapHeight {} /StemV 80 \
+             /FontFile2 {font_file} 0 R >>",
+            subset.name,
+            self.scale(bbox.x_min.into()),
+            self.scale(bbox.y_min.into()),
+            self.scale(bbox.x_max.into()),
+            self.scale(bbox.y_max.into()),
+            self.scale(self.face.ascender().into()),
+            self.scale(self.face.descender().into()),
+            self.scale(
+                self.face
+                    .capital_height()
+                    .unwrap_or(self.face.ascender())
+                    .into()
+            ),
+        )
+    }
+}
+
+/// The embedded font file and the name it goes by.
+pub struct FontSubset {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// Tables a PDF viewer needs to draw glyphs of an embedded TrueType font.
+const SUBSET_TABLES: [&[u8; 4]; 9] = [
+    b"cvt ", b"fpgm", b"glyf", b"head", b"hhea", b"hmtx", b"loca", b"maxp", b"prep",
+];
+
+fn read_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// Rewrites the TrueType font in `data` so only `glyphs`, the glyphs their
+/// composites are built from and `.notdef` keep outlines and metrics. Glyph
+/// ids stay the same, so `/CIDToGIDMap /Identity` still holds.
+fn subset_truetype(data: &[u8], glyphs: impl IntoIterator<Item = u16>) -> Option<Vec<u8>> {
+    let num_tables = usize::from(read_u16(data, 4)?);
+    let mut tables = BTreeMap::new();
+    for ix in 0..num_tables {
+        let record = 12 + ix * 16;
+        let tag: [u8; 4] = data.get(record..record + 4)?.try_into().ok()?;
+        let offset = read_u32(data, record + 8)? as usize;
+        let length = read_u32(data, record + 12)? as usize;
+        tables.insert(tag, data.get(offset..offset + length)?);
+    }
+    let head = tables.get(b"head")?;
+    let glyf = tables.get(b"glyf")?;
+    let loca = tables.get(b"loca")?;
+    let hmtx = tables.get(b"hmtx")?;
+    let num_glyphs = usize::from(read_u16(tables.get(b"maxp")?, 4)?);
+    let num_metrics = usize::from(read_u16(tables.get(b"hhea")?, 34)?);
+    let long_loca = read_u16(head, 50)? != 0;
+    let location = |glyph: usize| -> Option<usize> {
+        if long_loca {
+            read_u32(loca, glyph * 4).map(|offset| offset as usize)
+        } else {
+            read_u16(loca, glyph * 2).map(|offset| usize::from(offset) * 2)
+        }
+    };
+    let outline =
+        |glyph: usize| -> Option<&[u8]> { glyf.get(location(glyph)?..location(glyph + 1)?) };
+
+    let mut keep = BTreeSet::new();
+    let mut pending: Vec<usize> = glyphs.into_iter().map(usize::from).collect();
+    pending.push(0);
+    while let Some(glyph) = pending.pop() {
+        if glyph < num_glyphs && keep.insert(glyph) {
+            pending.extend(components(outline(glyph)?));
+        }
+    }
+
+    let mut new_glyf = Vec::new();
+    let mut new_loca = Vec::with_capacity((num_glyphs + 1) * 4);
+    for glyph in 0..num_glyphs {
+        new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+        if keep.contains(&glyph) {
+            new_glyf.extend_from_slice(outline(glyph)?);
+            new_glyf.resize(new_glyf.len().next_multiple_of(4), 0);
+        }
+    }
+    new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+    // Metrics of dropped glyphs are zeroed, which compresses to nearly nothing.
+    let mut new_hmtx = vec![0; hmtx.len()];
+    for &glyph in &keep {
+        let (at, len) = if glyph < num_metrics {
+            (glyph * 4, 4)
+        } else {
+            (num_metrics * 4 + (glyph - num_metrics) * 2, 2)
+        };
+        new_hmtx
+            .get_mut(at..at + len)?
+            .copy_from_slice(hmtx.get(at..at + len)?);
+    }
+    let mut new_head = head.to_vec();
+    new_head.get_mut(8..12)?.fill(0);
+    new_head
+        .get_mut(50..52)?
+        .copy_from_slice(&1u16.to_be_bytes());
+
+    let mut out_tables: Vec<(&[u8; 4], Vec<u8>)> = Vec::new();
+    for tag in SUBSET_TABLES {
+        let table = match tag {
+            b"glyf" => std::mem::take(&mut new_glyf),
+            b"loca" => std::mem::take(&mut new_loca),
+            b"hmtx" => std::mem::take(&mut new_hmtx),
+            b"head" => std::mem::take(&mut new_head),
+            _ => match tables.get(tag) {
+                Some(table) => table.to_vec(),
+                None => continue,
+            },
+        };
+        out_tables.push((tag, table));
+    }
+    Some(write_font(&out_tables))
+}
+
+/// Glyph ids a composite glyph is built from; none for a simple glyph.
+fn components(outline: &[u8]) -> Vec<usize> {
+    const ARGS_ARE_WORDS: u16 = 0x0001;
+    const HAS_SCALE: u16 = 0x0008;
+    const MORE_COMPONENTS: u16 = 0x0020;
+    const HAS_XY_SCALE: u16 = 0x0040;
+    const HAS_TWO_BY_TWO: u16 = 0x0080;
+
+    let mut glyphs = Vec::new();
+    if read_u16(outline, 0).is_none_or(|contours| contours as i16 >= 0) {
+        return glyphs;
+    }
+    let mut at = 10;
+    while let (Some(flags), Some(glyph)) = (read_u16(outline, at), read_u16(outline, at + 2)) {
+        glyphs.push(usize::from(glyph));
+        at += 4 + if flags & ARGS_ARE_WORDS != 0 { 4 } else { 2 };
+        if flags & HAS_SCALE != 0 {
+            at += 2;
+        } else if flags & HAS_XY_SCALE != 0 {
+            at += 4;
+        } else if flags & HAS_TWO_BY_TWO != 0 {
+            at += 8;
+        }
+        if flags & MORE_COMPONENTS == 0 {
+            break;
+        }
+    }
+    glyphs
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+/// Assembles a TrueType font file from tables sorted by tag.
+fn write_font(tables: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+    let count = tables.len() as u16;
+    let entry_selector = 15 - count.leading_zeros() as u16;
+    let search_range = (1 << entry_selector) * 16;
+    let mut out = Vec::new();
+    out.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+    for value in [
+        count,
+        search_range,
+        entry_selector,
+        count * 16 - search_range,
+    ] {
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+    let mut offset = 12 + tables.len() * 16;
+    let mut head_at = None;
+    for (tag, table) in tables {
+        if *tag == b"head" {
+            head_at = Some(offset);
+        }
+        out.extend_from_slice(*tag);
+        out.extend_from_slice(&checksum(table).to_be_bytes());
+        out.extend_from_slice(&(offset as u32).to_be_bytes());
+        out.extend_from_slice(&(table.len() as u32).to_be_bytes());
+        offset += table.len().next_multiple_of(4);
+    }
+    for (_, table) in tables {
+        out.extend_from_slice(table);
+        out.resize(out.len().next_multiple_of(4), 0);
+    }
+    if let Some(at) = head_at {
+        let adjustment = 0xB1B0_AFBAu32.wrapping_sub(checksum(&out));
+        out[at + 8..at + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+    out
+}
+
+/// ToUnicode CMap mapping 2-byte glyph ids back to the characters they show.
+pub fn to_unicode_cmap(glyphs: &BTreeMap<u16, char>) -> String {
+    let mut out = String::from(
+        "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+         /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+         /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+         1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+    );
+    let entries: Vec<_> = glyphs.iter().collect();
+    // A bfchar section holds at most 100 entries.
+    for chunk in entries.chunks(100) {
+        let _ = writeln!(out, "{} beginbfchar", chunk.len());
+        for (glyph, c) in chunk {
+            let mut units = [0; 2];
+            let utf16: String = c
+                .encode_utf16(&mut units)
+                .iter()
+                .map(|unit| format!("{unit:04X}"))
+                .collect();
+            let _ = writeln!(out, "<{glyph:04X}> <{utf16}>");
+        }
+        out.push_str("endbfchar\n");
+    }
+    out.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cmap_maps_glyphs_to_utf16() {
+        let glyphs = BTreeMap::from([(3, 'Ж'), (0x1234, '😀')]);
+        let cmap = to_unicode_cmap(&glyphs);
+        assert!(cmap.contains("2 beginbfchar\n<0003> <0416>\n<1234> <D83DDE00>\nendbfchar"));
+    }
+
+    #[test]
+    fn subsets_keep_glyph_ids_of_used_glyphs() {
+        let Some(font) = unicode_font() else {
+            return;
+        };
+        let glyph = font.glyph('Ж').unwrap();
+        let subset = font.subset(&BTreeMap::from([(glyph, 'Ж')]));
+        assert!(subset.name.ends_with(&format!("+{}", font.name)));
+        assert!(subset.data.len() < font.data.len() / 4);
+
+        let face = Face::parse(&subset.data, 0).unwrap();
+        assert!(face.glyph_bounding_box(GlyphId(glyph)).is_some());
+        assert_eq!(
+            face.glyph_hor_advance(GlyphId(glyph)),
+            font.face.glyph_hor_advance(GlyphId(glyph))
+        );
+        let dropped = GlyphId(font.glyph('Я').unwrap());
+        assert!(face.glyph_bounding_box(dropped).is_none());
+    }
+}
@@ -0,0 +1,638 @@
+//! Minimal PDF 1.4 writer: standard Type1 fonts, filled/stroked shapes and
+//! RGB images, enough for the PDF exporter's layout. Characters outside
+//! WinAnsiEncoding are drawn with an embedded system font when one is found.
+
+use super::pdf_font::{to_unicode_cmap, unicode_font};
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::io::Write as _;
+
+/// Resource name of the embedded fallback font.
+const UNICODE_FONT_RESOURCE: &str = "FU";
+
+/// The base-14 fonts the exporter uses. They need no embedding.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Font {
+    Regular,
+    Bold,
+    Italic,
+    BoldItalic,
+    Mono,
+}
+
+impl Font {
+    const ALL: [Font; 5] = [
+        Font::Regular,
+        Font::Bold,
+        Font::Italic,
+        Font::BoldItalic,
+        Font::Mono,
+    ];
+
+    fn resource_name(self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+            Font::Italic => "F3",
+            Font::BoldItalic => "F4",
+            Font::Mono => "F5",
+        }
+    }
+
+    fn base_font(self) -> &'static str {
+        match self {
+            Font::Regular => "Helvetica",
+            Font::Bold => "Helvetica-Bold",
+            Font::Italic => "Helvetica-Oblique",
+            Font::BoldItalic => "Helvetica-BoldOblique",
+            Font::Mono => "Courier",
+        }
+    }
+
+    /// Advance width of `text` at `size` points.
+    pub fn text_width(self, text: &str, size: f32) -> f32 {
+        let units: u32 = text.chars().map(|c| self.char_width(c)).sum();
+        units as f32 * size / 1000.
+    }
+
+    /// Advance width in 1/1000 em, from the fonts' AFM metrics, or the
+    /// fallback font's for characters WinAnsiEncoding lacks.
+    fn char_width(self, c: char) -> u32 {
+        if let Glyph::Unicode(glyph) = encode(c)
+            && let Some(font) = unicode_font()
+        {
+            return font.width(glyph);
+        }
+        let table = match self {
+            Font::Mono => return 600,
+            Font::Regular | Font::Italic => &HELVETICA_WIDTHS,
+            Font::Bold | Font::BoldItalic => &HELVETICA_BOLD_WIDTHS,
+        };
+        match c {
+            ' '..='~' => table[c as usize - 32] as u32,
+            '•' => 350,
+            '–' => 556,
+            '—' => 1000,
+            '…' => 1000,
+            _ => 556,
+        }
+    }
+}
+
+#[rustfmt::skip]
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+#[rustfmt::skip]
+const HELVETICA_BOLD_WIDTHS: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611,
+    975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556,
+    333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611,
+    611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+];
+
+/// How a character is drawn.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Glyph {
+    /// With the base-14 fonts, as this WinAnsiEncoding byte.
+    Ansi(u8),
+    /// With the embedded fallback font, as this glyph id.
+    Unicode(u16),
+    /// Not at all: no available font has it, so `?` is drawn instead.
+    Missing,
+}
+
+fn encode(c: char) -> Glyph {
+    if let Some(byte) = win_ansi(c) {
+        Glyph::Ansi(byte)
+    } else if let Some(glyph) = unicode_font().and_then(|font| font.glyph(c)) {
+        Glyph::Unicode(glyph)
+    } else {
+        Glyph::Missing
+    }
+}
+
+/// Maps `c` to its WinAnsiEncoding byte, if the encoding has it.
+fn win_ansi(c: char) -> Option<u8> {
+    let byte = match c {
+        ' '..='~' | '\u{a0}'..='\u{ff}' => c as u8,
+        '€' => 0x80,
+        '‚' => 0x82,
+        'ƒ' => 0x83,
+        '„' => 0x84,
+        '…' => 0x85,
+        '†' => 0x86,
+        '‡' => 0x87,
+        'ˆ' => 0x88,
+        '‰' => 0x89,
+        'Š' => 0x8a,
+        '‹' => 0x8b,
+        'Œ' => 0x8c,
+        'Ž' => 0x8e,
+        '‘' => 0x91,
+        '’' => 0x92,
+        '“' => 0x93,
+        '”' => 0x94,
+        '•' => 0x95,
+        '–' => 0x96,
+        '—' => 0x97,
+        '˜' => 0x98,
+        '™' => 0x99,
+        'š' => 0x9a,
+        '›' => 0x9b,
+        'œ' => 0x9c,
+        'ž' => 0x9e,
+        'Ÿ' => 0x9f,
+        _ => return None,
+    };
+    Some(byte)
+}
+
+/// `text` as a PDF string literal in WinAnsiEncoding, with `?` for
+/// characters the encoding lacks.
+fn string_literal(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('(');
+    for byte in text.chars().map(|c| win_ansi(c).unwrap_or(b'?')) {
+        match byte {
+            b'(' | b')' | b'\\' => {
+                out.push('\\');
+                out.push(byte as char);
+            }
+            0x20..=0x7e => out.push(byte as char),
+            _ => {
+                let _ = write!(out, "\\{byte:03o}");
+            }
+        }
+    }
+    out.push(')');
+    out
+}
+
+/// `text` as a PDF text string: a literal when it is plain Latin-1, else
+/// UTF-16BE with a byte order mark.
+fn text_string(text: &str) -> String {
+    if text
+        .chars()
+        .all(|c| matches!(c, ' '..='~' | '\u{a0}'..='\u{ff}'))
+    {
+        return string_literal(text);
+    }
+    let mut out = String::from("<FEFF");
+    for unit in text.encode_utf16() {
+        let _ = write!(out, "{unit:04X}");
+    }
+    out.push('>');
+    out
+}
+
+/// RGB color with components in `0.0..=1.0`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Color(pub f32, pub f32, pub f32);
+
+impl Color {
+    pub const fn hex(rgb: u32) -> Self {
+        Self(
+            ((rgb >> 16) & 0xff) as f32 / 255.,
+            ((rgb >> 8) & 0xff) as f32 / 255.,
+            (rgb & 0xff) as f32 / 255.,
+        )
+    }
+}
+
+/// 8-bit RGB image data.
+pub struct PdfImage {
+    pub width: u32,
+    pub height: u32,
+    pub rgb: Vec<u8>,
+}
+
+/// Drawing operations of one page. Coordinates are in points from the
+/// top-left corner; they are flipped to PDF's bottom-left origin here.
+pub struct PageContent {
+    height: f32,
+    ops: String,
+    /// Fallback font glyphs drawn on this page, with the character each shows.
+    glyphs: BTreeMap<u16, char>,
+    /// Characters drawn as `?` because no font has them.
+    missing: BTreeSet<char>,
+}
+
+impl PageContent {
+    fn new(height: f32) -> Self {
+        Self {
+            height,
+            ops: String::new(),
+            glyphs: BTreeMap::new(),
+            missing: BTreeSet::new(),
+        }
+    }
+
+    /// Draws `text` with its baseline at `baseline`. Runs of characters the
+    /// base-14 fonts can't encode switch to the fallback font.
+    pub fn text(&mut self, x: f32, baseline: f32, font: Font, size: f32, color: Color, text: &str) {
+        let Color(r, g, b) = color;
+        let _ = write!(
+            self.ops,
+            "BT {r:.3} {g:.3} {b:.3} rg {x:.2} {:.2} Td",
+            self.height - baseline
+        );
+        let mut ansi = String::new();
+        let mut unicode = String::new();
+        for c in text.chars() {
+            match encode(c) {
+                Glyph::Unicode(glyph) => {
+                    self.flush_ansi(font, size, &mut ansi);
+                    self.glyphs.insert(glyph, c);
+                    let _ = write!(unicode, "{glyph:04X}");
+                }
+                glyph => {
+                    if glyph == Glyph::Missing {
+                        self.missing.insert(c);
+                    }
+                    self.flush_unicode(size, &mut unicode);
+                    ansi.push(c);
+                }
+            }
+        }
+        self.flush_ansi(font, size, &mut ansi);
+        self.flush_unicode(size, &mut unicode);
+        self.ops.push_str(" ET\n");
+    }
+
+    fn flush_ansi(&mut self, font: Font, size: f32, run: &mut String) {
+        if !run.is_empty() {
+            let _ = write!(
+                self.ops,
+                " /{} {size:.2} Tf {} Tj",
+                font.resource_name(),
+                string_literal(run)
+            );
+            run.clear();
+        }
+    }
+
+    fn flush_unicode(&mut self, size: f32, run: &mut String) {
+        if !run.is_empty() {
+            let _ = write!(
+                self.ops,
+                " /{UNICODE_FONT_RESOURCE} {size:.2} Tf <{run}> Tj"
+            );
+            run.clear();
+        }
+    }
+
+    pub fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
+        let Color(r, g, b) = color;
+        let _ = writeln!(
+            self.ops,
+            "{r:.3} {g:.3} {b:.3} rg {x:.2} {:.2} {width:.2} {height:.2} re f",
+            self.height - y - height
+        );
+    }
+
+    pub fn stroke_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
+        let Color(r, g, b) = color;
+        let _ = writeln!(
+            self.ops,
+            "{r:.3} {g:.3} {b:.3} RG 0.5 w {x:.2} {:.2} {width:.2} {height:.2} re S",
+            self.height - y - height
+        );
+    }
+
+    pub fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, width: f32, color: Color) {
+        let Color(r, g, b) = color;
+        let _ = writeln!(
+            self.ops,
+            "{r:.3} {g:.3} {b:.3} RG {width:.2} w {x1:.2} {:.2} m {x2:.2} {:.2} l S",
+            self.height - y1,
+            self.height - y2
+        );
+    }
+
+    /// Draws image `id` (from [`PdfWriter::add_image`]) into the given box.
+    pub fn image(&mut self, id: usize, x: f32, y: f32, width: f32, height: f32) {
+        let _ = writeln!(
+            self.ops,
+            "q {width:.2} 0 0 {height:.2} {x:.2} {:.2} cm /Im{id} Do Q",
+            self.height - y - height
+        );
+    }
+}
+
+/// Accumulates pages and images and serialises them into a PDF file.
+pub struct PdfWriter {
+    width: f32,
+    height: f32,
+    title: String,
+    pages: Vec<PageContent>,
+    images: Vec<PdfImage>,
+}
+
+impl PdfWriter {
+    pub fn new(width: f32, height: f32, title: String) -> Self {
+        Self {
+            width,
+            height,
+            title,
+            pages: Vec::new(),
+            images: Vec::new(),
+        }
+    }
+
+    pub fn new_page(&self) -> PageContent {
+        PageContent::new(self.height)
+    }
+
+    pub fn push_page(&mut self, page: PageContent) {
+        self.pages.push(page);
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn pages_mut(&mut self) -> impl Iterator<Item = &mut PageContent> {
+        self.pages.iter_mut()
+    }
+
+    /// Characters drawn as `?` so far because no available font has them.
+    pub fn missing_chars(&self) -> Vec<char> {
+        let missing: BTreeSet<char> = self
+            .pages
+            .iter()
+            .flat_map(|page| page.missing.iter().copied())
+            .collect();
+        missing.into_iter().collect()
+    }
+
+    /// Registers an image and returns its id for [`PageContent::image`].
+    pub fn add_image(&mut self, image: PdfImage) -> usize {
+        self.images.push(image);
+        self.images.len() - 1
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        if self.pages.is_empty() {
+            self.pages.push(PageContent::new(self.height));
+        }
+
+        // Object ids: catalog, page tree, info, resources, fonts, images, a
+        // page object and its content stream per page, then the fallback font.
+        const CATALOG: usize = 1;
+        const PAGES: usize = 2;
+        const INFO: usize = 3;
+        const RESOURCES: usize = 4;
+        let font_base = 5;
+        let image_base = font_base + Font::ALL.len();
+        let page_base = image_base + self.images.len();
+        let unicode_base = page_base + self.pages.len() * 2;
+        let glyphs: BTreeMap<u16, char> = self
+            .pages
+            .iter()
+            .flat_map(|page| page.glyphs.iter().map(|(glyph, c)| (*glyph, *c)))
+            .collect();
+        let unicode_font = unicode_font().filter(|_| !glyphs.is_empty());
+
+        let mut out = PdfBuffer::default();
+        out.bytes
+            .extend_from_slice(b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n");
+
+        out.object(CATALOG, &format!("<< /Type /Catalog /Pages {PAGES} 0 R >>"));
+        let kids: Vec<String> = (0..self.pages.len())
+            .map(|ix| format!("{} 0 R", page_base + ix * 2))
+            .collect();
+        out.object(
+            PAGES,
+            &format!(
+                "<< /Type /Pages /Kids [{}] /Count {} /MediaBox [0 0 {:.2} {:.2}] >>",
+                kids.join(" "),
+                self.pages.len(),
+                self.width,
+                self.height
+            ),
+        );
+        out.object(
+            INFO,
+            &format!(
+                "<< /Title {} /Producer (Aster) >>",
+                text_string(&self.title)
+            ),
+        );
+
+        let mut fonts: String = Font::ALL
+            .iter()
+            .enumerate()
+            .map(|(ix, font)| format!("/{} {} 0 R ", font.resource_name(), font_base + ix))
+            .collect();
+        if unicode_font.is_some() {
+            let _ = write!(fonts, "/{UNICODE_FONT_RESOURCE} {unicode_base} 0 R ");
+        }
+        let images: String = (0..self.images.len())
+            .map(|ix| format!("/Im{ix} {} 0 R ", image_base + ix))
+            .collect();
+        out.object(
+            RESOURCES,
+            &format!("<< /Font << {fonts}>> /XObject << {images}>> >>"),
+        );
+        for (ix, font) in Font::ALL.iter().enumerate() {
+            out.object(
+                font_base + ix,
+                &format!(
+                    "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+                    font.base_font()
+                ),
+            );
+        }
+        for (ix, image) in self.images.iter().enumerate() {
+            out.stream(
+                image_base + ix,
+                &format!(
+                    "/Type /XObject /Subtype /Image /Width {} /Height {} \
+                     /ColorSpace /DeviceRGB /BitsPerComponent 8",
+                    image.width, image.height
+                ),
+                &image.rgb,
+            );
+        }
+        for (ix, page) in self.pages.iter().enumerate() {
+            let id = page_base + ix * 2;
+            out.object(
+                id,
+                &format!(
+                    "<< /Type /Page /Parent {PAGES} 0 R /Resources {RESOURCES} 0 R \
+                     /Contents {} 0 R >>",
+                    id + 1
+                ),
+            );
+            out.stream(id + 1, "", page.ops.as_bytes());
+        }
+        if let Some(font) = unicode_font {
+            let subset = font.subset(&glyphs);
+            // Type0 font, its CID font, descriptor, font file and ToUnicode map.
+            let [cid_font, descriptor, font_file, cmap] =
+                [1, 2, 3, 4].map(|offset| unicode_base + offset);
+            out.object(
+                unicode_base,
+                &format!(
+                    "<< /Type /Font /Subtype /Type0 /BaseFont /{} /Encoding /Identity-H \
+                     /DescendantFonts [{cid_font} 0 R] /ToUnicode {cmap} 0 R >>",
+                    subset.name
+                ),
+            );
+            out.object(
+                cid_font,
+                &format!(
+                    "<< /Type /Font /Subtype /CIDFontType2 /BaseFont /{} \
+                     /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> \
+                     /FontDescriptor {descriptor} 0 R /CIDToGIDMap /Identity /W [{}] >>",
+                    subset.name,
+                    font.widths(&glyphs)
+                ),
+            );
+            out.object(descriptor, &font.descriptor(&subset, font_file));
+            out.stream(
+                font_file,
+                &format!("/Length1 {}", subset.data.len()),
+                &subset.data,
+            );
+            out.stream(cmap, "", to_unicode_cmap(&glyphs).as_bytes());
+        }
+
+        out.finish(CATALOG, INFO)
+    }
+}
+
+#[derive(Default)]
+struct PdfBuffer {
+    bytes: Vec<u8>,
+    /// Object ids with the byte offset they start at.
+    offsets: Vec<(usize, usize)>,
+}
+
+impl PdfBuffer {
+    fn object(&mut self, id: usize, body: &str) {
+        self.offsets.push((id, self.bytes.len()));
+        let _ = write!(self.bytes, "{id} 0 obj\n{body}\nendobj\n");
+    }
+
+    /// Writes a Flate-compressed stream object with extra dictionary entries.
+    fn stream(&mut self, id: usize, dict: &str, data: &[u8]) {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        let _ = encoder.write_all(data);
+        let compressed = encoder.finish().unwrap_or_default();
+
+        self.offsets.push((id, self.bytes.len()));
+        let _ = write!(
+            self.bytes,
+            "{id} 0 obj\n<< {dict} /Filter /FlateDecode /Length {} >>\nstream\n",
+            compressed.len()
+        );
+        self.bytes.extend_from_slice(&compressed);
+        self.bytes.extend_from_slice(b"\nendstream\nendobj\n");
+    }
+
+    fn finish(mut self, root: usize, info: usize) -> Vec<u8> {
+        self.offsets.sort_unstable();
+        let xref = self.bytes.len();
+        let size = self.offsets.len() + 1;
+        let _ = write!(self.bytes, "xref\n0 {size}\n0000000000 65535 f \n");
+        for (_, offset) in &self.offsets {
+            let _ = writeln!(self.bytes, "{offset:010} 00000 n ");
+        }
+        let _ = write!(
+            self.bytes,
+            "trailer\n<< /Size {size} /Root {root} 0 R /Info {info} 0 R >>\nstartxref\n{xref}\n%%EOF\n"
+        );
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_a_well_formed_file() {
+        let mut writer = PdfWriter::new(200., 100., "T (1)".into());
+        let mut page = writer.new_page();
+        page.text(10., 20., Font::Bold, 12., Color::hex(0), "Café (x)");
+        writer.push_page(page);
+        let pdf = writer.finish();
+
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.starts_with("%PDF-1.4"));
+        assert!(text.contains("/Title (T \\(1\\))"));
+        assert!(text.trim_end().ends_with("%%EOF"));
+
+        // Every xref entry points at the start of its object.
+        let xref = text.rfind("xref\n").unwrap();
+        let entries = text[xref..]
+            .lines()
+            .skip(3)
+            .take_while(|l| l.ends_with(" n "));
+        for (ix, entry) in entries.enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj", ix + 1).as_bytes()));
+        }
+        assert_eq!(string_literal("Café (x)"), "(Caf\\351 \\(x\\))");
+    }
+
+    #[test]
+    fn keeps_non_latin_text() {
+        let mut writer = PdfWriter::new(200., 100., "Привет".into());
+        let mut page = writer.new_page();
+        page.text(
+            10.,
+            20.,
+            Font::Regular,
+            12.,
+            Color::hex(0),
+            "Hi Жук\u{10fffd}",
+        );
+        let ops = page.ops.clone();
+        writer.push_page(page);
+        let missing = writer.missing_chars();
+        assert!(missing.contains(&'\u{10fffd}'));
+        let pdf = writer.finish();
+
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.contains("/Title <FEFF041F04400438043204350442>"));
+        let Some(font) = unicode_font() else {
+            // No fallback font installed: the text is reported as missing.
+            return;
+        };
+        assert_eq!(missing, vec!['\u{10fffd}']);
+        let glyph = font.glyph('Ж').unwrap();
+        assert!(ops.contains(&format!("/F1 12.00 Tf (Hi ) Tj /FU 12.00 Tf <{glyph:04X}")));
+        assert!(ops.ends_with("/F1 12.00 Tf (?) Tj ET\n"));
+        assert!(text.contains("/Subtype /CIDFontType2"));
+        assert!(text.contains("/FontFile2"));
+        assert!(text.contains("/ToUnicode"));
+    }
+
+    #[test]
+    fn embeds_only_the_glyphs_used() {
+        if unicode_font().is_none() {
+            return;
+        }
+        let mut writer = PdfWriter::new(200., 100., "Заметки".into());
+        let mut page = writer.new_page();
+        page.text(10., 20., Font::Regular, 12., Color::hex(0), "Привет, мир");
+        writer.push_page(page);
+        let pdf = writer.finish();
+
+        // The whole fallback font runs to hundreds of kilobytes.
+        assert!(pdf.len() < 16 * 1024, "{} bytes", pdf.len());
+    }
+}
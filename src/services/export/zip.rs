@@ -0,0 +1,176 @@
+//! Minimal ZIP archive writer for the EPUB and DOCX containers.
+
+use flate2::Compression;
+use flate2::Crc;
+use flate2::write::DeflateEncoder;
+use std::io::Write as _;
+
+/// MS-DOS timestamp of 1980-01-01 00:00, so identical input gives identical archives.
+const DOS_TIME: u16 = 0;
+const DOS_DATE: u16 = (1 << 5) | 1;
+/// General purpose flag: file names are UTF-8.
+const UTF8_NAMES: u16 = 1 << 11;
+
+struct Entry {
+    name: String,
+    method: u16,
+    crc: u32,
+    compressed_size: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Builds a ZIP archive in memory.
+#[derive(Default)]
+pub struct ZipWriter {
+    bytes: Vec<u8>,
+    entries: Vec<Entry>,
+}
+
+impl ZipWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an uncompressed entry (required for the EPUB `mimetype` file).
+    pub fn add_stored(&mut self, name: &str, data: &[u8]) {
+        self.add_entry(name, 0, data, data);
+    }
+
+    /// Adds a Deflate-compressed entry.
+    pub fn add_deflated(&mut self, name: &str, data: &[u8]) {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        let _ = encoder.write_all(data);
+        let compressed = encoder.finish().unwrap_or_default();
+        self.add_entry(name, 8, data, &compressed);
+    }
+
+    fn add_entry(&mut self, name: &str, method: u16, data: &[u8], stored: &[u8]) {
+        let mut crc = Crc::new();
+        crc.update(data);
+        let entry = Entry {
+            name: name.to_string(),
+            method,
+            crc: crc.sum(),
+            compressed_size: stored.len() as u32,
+            size: data.len() as u32,
+            offset: self.bytes.len() as u32,
+        };
+
+        self.put_u32(0x0403_4b50);
+        self.put_u16(20);
+        self.put_entry_fields(&entry);
+        self.put_u16(0);
+        self.bytes.extend_from_slice(entry.name.as_bytes());
+        self.bytes.extend_from_slice(stored);
+        self.entries.push(entry);
+    }
+
+    /// Writes the central directory and returns the archive.
+    pub fn finish(mut self) -> Vec<u8> {
+        let directory_offset = self.bytes.len() as u32;
+        let entries = std::mem::take(&mut self.entries);
+        for entry in &entries {
+            self.put_u32(0x0201_4b50);
+            self.put_u16(20);
+            self.put_u16(20);
+            self.put_entry_fields(entry);
+            // Extra field, comment, disk number, internal and external attributes.
+            self.put_u16(0);
+            self.put_u16(0);
+            self.put_u16(0);
+            self.put_u16(0);
+            self.put_u32(0);
+            self.put_u32(entry.offset);
+            self.bytes.extend_from_slice(entry.name.as_bytes());
+        }
+        let directory_size = self.bytes.len() as u32 - directory_offset;
+
+        self.put_u32(0x0605_4b50);
+        self.put_u16(0);
+        self.put_u16(0);
+        self.put_u16(entries.len() as u16);
+        self.put_u16(entries.len() as u16);
+        self.put_u32(directory_size);
+        self.put_u32(directory_offset);
+        self.put_u16(0);
+        self.bytes
+    }
+
+    /// Fields shared by local and central headers, from the flags up to the
+    /// file name length.
+    fn put_entry_fields(&mut self, entry: &Entry) {
+        self.put_u16(UTF8_NAMES);
+        self.put_u16(entry.method);
+        self.put_u16(DOS_TIME);
+        self.put_u16(DOS_DATE);
+        self.put_u32(entry.crc);
+        self.put_u32(entry.compressed_size);
+        self.put_u32(entry.size);
+        self.put_u16(entry.name.len() as u16);
+    }
+
+    fn put_u16(&mut self, value: u16) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn put_u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::DeflateDecoder;
+    use std::io::Read as _;
+
+    fn u16_at(bytes: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes([bytes[at], bytes[at + 1]])
+    }
+
+    fn u32_at(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn entries_can_be_read_back_through_the_central_directory() {
+        let mut zip = ZipWriter::new();
+        zip.add_stored("mimetype", b"application/epub+zip");
+        let text = "héllo ".repeat(50);
+        zip.add_deflated("dir/ünï.txt", text.as_bytes());
+        let bytes = zip.finish();
+
+        let end = bytes.len() - 22;
+        assert_eq!(u32_at(&bytes, end), 0x0605_4b50);
+        assert_eq!(u16_at(&bytes, end + 10), 2);
+        let mut at = u32_at(&bytes, end + 16) as usize;
+
+        let mut names = Vec::new();
+        for _ in 0..2 {
+            assert_eq!(u32_at(&bytes, at), 0x0201_4b50);
+            let method = u16_at(&bytes, at + 10);
+            let compressed = u32_at(&bytes, at + 20) as usize;
+            let name_len = u16_at(&bytes, at + 28) as usize;
+            let offset = u32_at(&bytes, at + 42) as usize;
+            let name = String::from_utf8(bytes[at + 46..at + 46 + name_len].to_vec()).unwrap();
+
+            let data_start = offset + 30 + name_len;
+            let data = &bytes[data_start..data_start + compressed];
+            let contents = if method == 8 {
+                let mut out = String::new();
+                DeflateDecoder::new(data).read_to_string(&mut out).unwrap();
+                out
+            } else {
+                String::from_utf8(data.to_vec()).unwrap()
+            };
+            names.push((name, contents));
+            at += 46 + name_len;
+        }
+
+        assert_eq!(names[0], ("mimetype".into(), "application/epub+zip".into()));
+        assert_eq!(names[1], ("dir/ünï.txt".into(), text));
+        // The EPUB mimetype entry must start right after the first local header.
+        assert_eq!(&bytes[30..38], b"mimetype");
+    }
+}
@@ -0,0 +1,250 @@
+use pulldown_cmark::{Alignment, Event, Options, Parser, Tag, TagEnd};
+use std::ops::Range;
+
+/// Normalises the layout of Markdown without changing how it renders:
+/// ATX headings get a single space and no closing hashes, top-level tables
+/// are aligned, trailing whitespace (other than hard breaks) and repeated
+/// blank lines are removed, and the file ends with exactly one newline.
+/// Code and HTML blocks are left untouched, and `\r\n` files stay `\r\n`.
+pub fn format_markdown(source: &str) -> String {
+    let structured = rewrite_blocks(source);
+    normalize_whitespace(&structured, line_ending(source))
+}
+
+/// The line ending used by `source`, judged by its first line.
+fn line_ending(source: &str) -> &'static str {
+    match source.find('\n') {
+        Some(ix) if source[..ix].ends_with('\r') => "\r\n",
+        _ => "\n",
+    }
+}
+
+fn options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+}
+
+/// Rewrites headings and tables that start at the beginning of a line.
+fn rewrite_blocks(source: &str) -> String {
+    let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+    let mut table: Option<Table> = None;
+
+    for (event, range) in Parser::new_ext(source, options()).into_offset_iter() {
+        let at_line_start = range.start == 0 || source[..range.start].ends_with('\n');
+        match event {
+            Event::Start(Tag::Heading { level, .. })
+                if at_line_start && source[range.clone()].starts_with('#') =>
+            {
+                let line = source[range.clone()].trim_end_matches(['\n', '\r']);
+                edits.push((
+                    range.start..range.start + line.len(),
+                    format!("{} {}", "#".repeat(level as usize), atx_content(line))
+                        .trim_end()
+                        .to_string(),
+                ));
+            }
+            Event::Start(Tag::Table(alignments)) if at_line_start => {
+                table = Some(Table {
+                    range,
+                    alignments,
+                    rows: Vec::new(),
+                });
+            }
+            Event::Start(Tag::TableHead | Tag::TableRow) => {
+                if let Some(table) = &mut table {
+                    table.rows.push(Vec::new());
+                }
+            }
+            Event::Start(Tag::TableCell) => {
+                if let Some(row) = table.as_mut().and_then(|table| table.rows.last_mut()) {
+                    row.push(source[range].trim().to_string());
+                }
+            }
+            Event::End(TagEnd::Table) => {
+                if let Some(table) = table.take() {
+                    let end = table.range.start
+                        + source[table.range.clone()]
+                            .trim_end_matches(['\n', '\r'])
+                            .len();
+                    edits.push((table.range.start..end, table.render()));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut out = String::with_capacity(source.len());
+    let mut pos = 0;
+    for (range, replacement) in edits {
+        out.push_str(&source[pos..range.start]);
+        out.push_str(&replacement);
+        pos = range.end;
+    }
+    out.push_str(&source[pos..]);
+    out
+}
+
+/// Text of an ATX heading line without the opening and closing hashes.
+fn atx_content(line: &str) -> &str {
+    let content = line.trim_start_matches('#').trim();
+    let without_closing = content.trim_end_matches('#');
+    if without_closing.is_empty() {
+        ""
+    } else if without_closing.ends_with([' ', '\t']) {
+        without_closing.trim_end()
+    } else {
+        // Hashes glued to the text are part of it, e.g. `# C#`.
+        content
+    }
+}
+
+struct Table {
+    range: Range<usize>,
+    alignments: Vec<Alignment>,
+    /// Header first, then body rows; cells as trimmed source text.
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    fn render(&self) -> String {
+        let columns = self.alignments.len();
+        let mut widths = vec![3; columns];
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let mut lines = Vec::with_capacity(self.rows.len() + 1);
+        for (ix, row) in self.rows.iter().enumerate() {
+            let cells = (0..columns).map(|column| {
+                let cell = row.get(column).map_or("", String::as_str);
+                let padding = widths[column] - cell.chars().count();
+                match self.alignments[column] {
+                    Alignment::Right => format!("{}{cell}", " ".repeat(padding)),
+                    Alignment::Center => format!(
+                        "{}{cell}{}",
+                        " ".repeat(padding / 2),
+                        " ".repeat(padding - padding / 2)
+                    ),
+                    Alignment::Left | Alignment::None => format!("{cell}{}", " ".repeat(padding)),
+                }
+            });
+            lines.push(format!("| {} |", cells.collect::<Vec<_>>().join(" | ")));
+            if ix == 0 {
+                let delimiters = self
+                    .alignments
+                    .iter()
+                    .zip(&widths)
+                    .map(|(alignment, width)| match alignment {
+                        Alignment::Left => format!(":{}", "-".repeat(width - 1)),
+                        Alignment::Right => format!("{}:", "-".repeat(width - 1)),
+                        Alignment::Center => format!(":{}:", "-".repeat(width - 2)),
+                        Alignment::None => "-".repeat(*width),
+                    });
+                lines.push(format!(
+                    "| {} |",
+                    delimiters.collect::<Vec<_>>().join(" | ")
+                ));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+/// Strips trailing whitespace and repeated blank lines outside code and HTML
+/// blocks, and ends the text with a single newline. Lines are terminated with
+/// `newline`, except in code and HTML blocks, which keep their own endings.
+fn normalize_whitespace(source: &str, newline: &str) -> String {
+    let verbatim: Vec<Range<usize>> = Parser::new_ext(source, options())
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::Start(Tag::CodeBlock(_) | Tag::HtmlBlock) => Some(range),
+            _ => None,
+        })
+        .collect();
+
+    let lines: Vec<&str> = source.lines().collect();
+    let mut out = String::with_capacity(source.len());
+    let mut line_start = 0;
+    let mut previous_blank = true;
+    for (ix, line) in lines.iter().enumerate() {
+        let start = line_start;
+        let ending = if source[start + line.len()..].starts_with("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        };
+        line_start += line.len() + ending.len();
+        if verbatim
+            .iter()
+            .any(|range| range.start < start + line.len() && start < range.end)
+        {
+            out.push_str(line);
+            out.push_str(ending);
+            previous_blank = false;
+            continue;
+        }
+
+        let trimmed = line.trim_end_matches([' ', '\t']);
+        if trimmed.is_empty() {
+            if !previous_blank {
+                out.push_str(newline);
+            }
+            previous_blank = true;
+            continue;
+        }
+        out.push_str(trimmed);
+        // Keep two trailing spaces as a hard break when the paragraph goes on.
+        let next_is_text = lines
+            .get(ix + 1)
+            .is_some_and(|next| !next.trim().is_empty());
+        if line.ends_with("  ") && next_is_text && !trimmed.starts_with('#') {
+            out.push_str("  ");
+        }
+        out.push_str(newline);
+        previous_blank = false;
+    }
+
+    let trimmed = out.trim_end_matches(['\r', '\n']);
+    if trimmed.is_empty() {
+        String::new()
+    } else {
+        format!("{trimmed}{newline}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_headings_tables_and_whitespace() {
+        let source = "#   Title ##\n\n\n\nSome text  \nmore text   \n\n## C#\n\n\
+                      | a | long header |\n|:-|:-:|\n| wide cell | x |\n\n\
+                      ```\nkeep   \n\n\n  spacing\n```\n\n\n";
+        let formatted = format_markdown(source);
+        assert_eq!(
+            formatted,
+            "# Title\n\nSome text  \nmore text\n\n## C#\n\n\
+             | a         | long header |\n| :-------- | :---------: |\n| wide cell |      x      |\n\n\
+             ```\nkeep   \n\n\n  spacing\n```\n"
+        );
+        assert_eq!(format_markdown(&formatted), formatted);
+    }
+
+    #[test]
+    fn keeps_crlf_line_endings() {
+        let source = "#  Title\r\n\r\n\r\n| a | b |\r\n|-|-|\r\n| 1 | 2 |\r\n\r\n\
+                      ```\r\ncode   \r\n```\r\ntext  \r\n\r\n";
+        let formatted = format_markdown(source);
+        assert_eq!(
+            formatted,
+            "# Title\r\n\r\n| a   | b   |\r\n| --- | --- |\r\n| 1   | 2   |\r\n\r\n\
+             ```\r\ncode   \r\n```\r\ntext\r\n"
+        );
+        assert_eq!(format_markdown(&formatted), formatted);
+    }
+}
@@ -1,12 +1,15 @@
 use crate::error::{AppError, AppResult};
-use camino::Utf8PathBuf;
+use camino::{Utf8Path, Utf8PathBuf};
 use futures::channel::oneshot;
 use gpui::App;
 use rfd::AsyncFileDialog;
+use std::collections::hash_map::DefaultHasher;
 use std::fs;
 use std::future::Future;
+use std::hash::{Hash, Hasher};
 use std::io::Write;
 use std::path::PathBuf;
+use std::time::SystemTime;
 use tempfile::NamedTempFile;
 
 /// Opens a native file picker and returns a single selected markdown file.
@@ -33,6 +36,70 @@ pub fn pick_open_markdown_path_async() -> impl Future<Output = Option<Utf8PathBu
     }
 }
 
+/// Opens a native folder picker for choosing a workspace root.
+pub fn pick_folder_async() -> impl Future<Output = Option<Utf8PathBuf>> + Send {
+    let home_dir = directories::UserDirs::new().map(|d| d.home_dir().to_path_buf());
+    let mut dialog = AsyncFileDialog::new().set_title("Open Folder");
+
+    if let Some(dir) = home_dir {
+        dialog = dialog.set_directory(dir);
+    }
+
+    async move {
+        dialog
+            .pick_folder()
+            .await
+            .and_then(|handle| Utf8PathBuf::try_from(handle.path().to_path_buf()).ok())
+    }
+}
+
+/// A subdirectory or Markdown file listed by [`list_markdown_dir`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DirEntry {
+    pub path: Utf8PathBuf,
+    pub is_dir: bool,
+}
+
+/// Lists the subdirectories and Markdown files directly inside `dir`,
+/// directories first. Hidden entries and anything matched by `.gitignore`
+/// (or `.ignore`) files are skipped; unreadable entries are left out.
+pub fn list_markdown_dir(dir: &Utf8Path) -> Vec<DirEntry> {
+    let mut entries: Vec<DirEntry> = ignore::WalkBuilder::new(dir)
+        .max_depth(Some(1))
+        .require_git(false)
+        .build()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.depth() == 1)
+        .filter_map(|entry| {
+            let is_dir = entry.file_type()?.is_dir();
+            let path = Utf8PathBuf::try_from(entry.into_path()).ok()?;
+            (is_dir || is_markdown_path(&path)).then_some(DirEntry { path, is_dir })
+        })
+        .collect();
+    entries.sort_by_cached_key(|entry| {
+        (
+            !entry.is_dir,
+            entry.path.file_name().unwrap_or_default().to_lowercase(),
+        )
+    });
+    entries
+}
+
+/// Every Markdown file under `root`, at any depth, sorted by path. Skips the
+/// same entries as [`list_markdown_dir`].
+pub fn walk_markdown_files(root: &Utf8Path) -> Vec<Utf8PathBuf> {
+    let mut files: Vec<Utf8PathBuf> = ignore::WalkBuilder::new(root)
+        .require_git(false)
+        .build()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_some_and(|kind| kind.is_file()))
+        .filter_map(|entry| Utf8PathBuf::try_from(entry.into_path()).ok())
+        .filter(is_markdown_path)
+        .collect();
+    files.sort();
+    files
+}
+
 /// Returns true if the path has a supported markdown extension.
 pub fn is_markdown_path(path: &Utf8PathBuf) -> bool {
     path.extension().is_some_and(|ext| {
@@ -66,11 +133,68 @@ pub fn pick_save_path_async(
     cx.prompt_for_new_path(&directory, suggested_name)
 }
 
+/// Asks for an export destination next to `source`, named after it with
+/// `extension` (or `untitled.<extension>` in the home directory).
+pub fn pick_export_path_async(
+    cx: &App,
+    source: Option<&Utf8PathBuf>,
+    extension: &str,
+) -> oneshot::Receiver<Result<Option<PathBuf>>> {
+    let directory = source
+        .and_then(|path| path.parent())
+        .map(|dir| dir.as_std_path().to_path_buf())
+        .or_else(|| directories::UserDirs::new().map(|d| d.home_dir().to_path_buf()))
+        .unwrap_or_else(|| PathBuf::from("."));
+    let stem = source
+        .and_then(|path| path.file_stem())
+        .unwrap_or("untitled");
+    cx.prompt_for_new_path(&directory, Some(&format!("{stem}.{extension}")))
+}
+
 pub fn read_to_string(path: &Utf8PathBuf) -> AppResult<String> {
     Ok(fs::read_to_string(path)?)
 }
 
-pub fn write_atomic(path: &Utf8PathBuf, contents: &str) -> AppResult<()> {
+/// A file as it was when a document last loaded or saved it, to tell whether
+/// someone else has changed it since.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiskState {
+    pub modified: Option<SystemTime>,
+    pub len: u64,
+    pub hash: u64,
+}
+
+impl DiskState {
+    /// State of `path` just after `contents` were read from or written to it.
+    pub fn new(path: &Utf8Path, contents: &[u8]) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            hash: hash_bytes(contents),
+        })
+    }
+
+    /// Whether `path` now holds something else. A file that is gone or only
+    /// touched doesn't count, since saving over it loses nothing.
+    pub fn changed_since(&self, path: &Utf8Path) -> bool {
+        let Ok(metadata) = fs::metadata(path) else {
+            return false;
+        };
+        if metadata.len() == self.len && metadata.modified().ok() == self.modified {
+            return false;
+        }
+        fs::read(path).is_ok_and(|contents| hash_bytes(&contents) != self.hash)
+    }
+}
+
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+pub fn write_atomic(path: &Utf8PathBuf, contents: impl AsRef<[u8]>) -> AppResult<()> {
     let mut tmp = NamedTempFile::new_in(
         path.parent()
             .and_then(|p| Utf8PathBuf::try_from(p.to_path_buf()).ok())
@@ -79,7 +203,7 @@ pub fn write_atomic(path: &Utf8PathBuf, contents: &str) -> AppResult<()> {
                     .unwrap_or_else(|_| Utf8PathBuf::from("tmp"))
             }),
     )?;
-    tmp.write_all(contents.as_bytes())?;
+    tmp.write_all(contents.as_ref())?;
     tmp.flush()?;
     tmp.persist(path).map_err(|e| AppError::Io(e.error))?;
     Ok(())
@@ -90,8 +214,8 @@ pub use anyhow::Result;
 
 #[cfg(test)]
 mod tests {
-    use super::is_markdown_path;
-    use camino::Utf8PathBuf;
+    use super::{DiskState, is_markdown_path, list_markdown_dir, walk_markdown_files};
+    use camino::{Utf8Path, Utf8PathBuf};
 
     #[test]
     fn detects_supported_markdown_extensions() {
@@ -106,4 +230,73 @@ mod tests {
         assert!(!is_markdown_path(&Utf8PathBuf::from("/tmp/doc.txt")));
         assert!(!is_markdown_path(&Utf8PathBuf::from("/tmp/doc")));
     }
+
+    #[test]
+    fn lists_markdown_entries_respecting_gitignore() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = Utf8Path::from_path(dir.path()).unwrap();
+        for sub in ["notes", "build", ".hidden"] {
+            std::fs::create_dir(root.join(sub)).unwrap();
+        }
+        for file in [
+            "b.md",
+            "A.markdown",
+            "image.png",
+            "draft.md",
+            "build/out.md",
+        ] {
+            std::fs::write(root.join(file), "").unwrap();
+        }
+        std::fs::write(root.join(".gitignore"), "build/\ndraft.md\n").unwrap();
+
+        let entries = list_markdown_dir(root);
+        let names: Vec<&str> = entries
+            .iter()
+            .map(|entry| entry.path.file_name().unwrap())
+            .collect();
+        assert_eq!(names, vec!["notes", "A.markdown", "b.md"]);
+    }
+
+    #[test]
+    fn walks_nested_markdown_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = Utf8Path::from_path(dir.path()).unwrap();
+        std::fs::create_dir_all(root.join("notes/deep")).unwrap();
+        std::fs::create_dir(root.join(".git")).unwrap();
+        for file in [
+            "a.md",
+            "notes/b.md",
+            "notes/deep/c.markdown",
+            "notes/x.txt",
+            ".git/d.md",
+        ] {
+            std::fs::write(root.join(file), "").unwrap();
+        }
+
+        let files = walk_markdown_files(root);
+        let relative: Vec<&str> = files
+            .iter()
+            .map(|path| path.strip_prefix(root).unwrap().as_str())
+            .collect();
+        assert_eq!(
+            relative,
+            vec!["a.md", "notes/b.md", "notes/deep/c.markdown"]
+        );
+    }
+
+    #[test]
+    fn detects_files_changed_since_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = Utf8Path::from_path(dir.path()).unwrap().join("note.md");
+        std::fs::write(&path, "# Mine").unwrap();
+        let state = DiskState::new(&path, b"# Mine").unwrap();
+        assert!(!state.changed_since(&path));
+
+        std::fs::write(&path, "# Mine").unwrap();
+        assert!(!state.changed_since(&path));
+        std::fs::write(&path, "# Theirs").unwrap();
+        assert!(state.changed_since(&path));
+        std::fs::remove_file(&path).unwrap();
+        assert!(!state.changed_since(&path));
+    }
 }
@@ -0,0 +1,87 @@
+/// Scores `candidate` against `query` as a case-insensitive subsequence match.
+///
+/// Returns `None` when the query characters do not all appear in order.
+/// Higher scores are better: consecutive runs and matches at word starts are
+/// rewarded, while late first matches and long candidates are penalised
+/// slightly so tighter matches rank first.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+
+    let chars: Vec<char> = candidate.chars().collect();
+    let mut score = 0i64;
+    let mut query_ix = 0usize;
+    let mut previous_match: Option<usize> = None;
+    let mut first_match: Option<usize> = None;
+
+    for (ix, ch) in chars.iter().enumerate() {
+        if query_ix == query.len() {
+            break;
+        }
+        if ch.to_lowercase().next() != Some(query[query_ix]) {
+            continue;
+        }
+
+        score += 1;
+        if previous_match.is_some_and(|prev| prev + 1 == ix) {
+            score += 8;
+        }
+        if is_word_start(&chars, ix) {
+            score += 10;
+        }
+        first_match.get_or_insert(ix);
+        previous_match = Some(ix);
+        query_ix += 1;
+    }
+
+    if query_ix < query.len() {
+        return None;
+    }
+
+    score -= first_match.unwrap_or(0) as i64;
+    score -= (chars.len() / 16) as i64;
+    Some(score)
+}
+
+fn is_word_start(chars: &[char], ix: usize) -> bool {
+    if ix == 0 {
+        return true;
+    }
+    let previous = chars[ix - 1];
+    !previous.is_alphanumeric() || (previous.is_lowercase() && chars[ix].is_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fuzzy_score;
+
+    #[test]
+    fn requires_all_query_chars_in_order() {
+        assert!(fuzzy_score("abc", "a big cat").is_some());
+        assert!(fuzzy_score("cab", "a big cat").is_none());
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+    }
+
+    #[test]
+    fn matching_is_case_insensitive() {
+        assert!(fuzzy_score("READ", "readme.md").is_some());
+        assert!(fuzzy_score("inst", "Installation").is_some());
+    }
+
+    #[test]
+    fn prefers_contiguous_and_word_start_matches() {
+        let contiguous = fuzzy_score("setup", "Setup guide").unwrap();
+        let scattered = fuzzy_score("setup", "Some extra tips unordered please").unwrap();
+        assert!(contiguous > scattered);
+
+        let word_start = fuzzy_score("rn", "release notes").unwrap();
+        let inner = fuzzy_score("rn", "urn").unwrap();
+        assert!(word_start > inner);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::services::syntax::{SyntaxKind, SyntaxSpan};
+use camino::Utf8Path;
+use std::ops::Range;
+use std::path::PathBuf;
+use url::Url;
+
+/// An inline image (`![alt](destination)`) found in the document.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ImageRef {
+    /// Source range of the whole image syntax.
+    pub range: Range<usize>,
+    /// End of the source line containing the image (its `\n`, or the end of text).
+    pub line_end: usize,
+    /// Destination without angle brackets or title.
+    pub destination: String,
+}
+
+/// Finds inline images from parsed syntax spans. Reference-style images are skipped.
+pub fn image_refs(source: &str, spans: &[SyntaxSpan]) -> Vec<ImageRef> {
+    let text = |span: &SyntaxSpan| source.get(span.range.clone()).unwrap_or_default();
+    let mut images = Vec::new();
+
+    // Spans are sorted by start; find the one of `kind` starting at `pos`.
+    let span_at = |pos: usize, kind: SyntaxKind| {
+        let first = spans.partition_point(|span| span.range.start < pos);
+        spans[first..]
+            .iter()
+            .take_while(|span| span.range.start == pos)
+            .find(|span| span.kind == kind)
+    };
+
+    for open in spans {
+        if open.kind != SyntaxKind::LinkTextDelimiter || text(open) != "![" {
+            continue;
+        }
+        // `![alt](destination "title")`: the alt text (if any) ends at the `]`.
+        let close_bracket = span_at(open.range.end, SyntaxKind::LinkText)
+            .map_or(open.range.end, |alt| alt.range.end);
+        let Some(url) = span_at(close_bracket + 1, SyntaxKind::LinkUrlDelimiter)
+            .filter(|paren| text(paren) == "(")
+            .and_then(|paren| span_at(paren.range.end, SyntaxKind::LinkUrl))
+        else {
+            continue;
+        };
+        let Some(close) = span_at(url.range.end, SyntaxKind::LinkUrlDelimiter) else {
+            continue;
+        };
+        let destination = parse_destination(text(url));
+        if destination.is_empty() {
+            continue;
+        }
+
+        let range = open.range.start..close.range.end;
+        let line_end = source[range.end..]
+            .find('\n')
+            .map_or(source.len(), |ix| range.end + ix);
+        images.push(ImageRef {
+            range,
+            line_end,
+            destination,
+        });
+    }
+    images
+}
+
+/// Resolves a local image destination against the document's path.
+/// Returns `None` for remote URLs and relative paths of untitled documents.
+pub fn resolve_local_image(document: Option<&Utf8Path>, destination: &str) -> Option<PathBuf> {
+    if let Ok(url) = Url::parse(destination) {
+        // Single-letter "schemes" are Windows drive letters.
+        if url.scheme().len() > 1 {
+            return match url.scheme() {
+                "file" => url.to_file_path().ok(),
+                _ => None,
+            };
+        }
+    }
+
+    let path = PathBuf::from(destination);
+    if path.is_absolute() {
+        return Some(path);
+    }
+    let dir = document?.parent()?;
+    // Resolve through a file URL so percent-escapes like `%20` are decoded.
+    Url::from_directory_path(dir.as_std_path())
+        .ok()
+        .and_then(|base| base.join(destination).ok())
+        .and_then(|url| url.to_file_path().ok())
+        .or_else(|| Some(dir.as_std_path().join(path)))
+}
+
+fn parse_destination(raw: &str) -> String {
+    let raw = raw.trim();
+    match raw.strip_prefix('<') {
+        Some(rest) => rest.split('>').next().unwrap_or_default().to_string(),
+        None => raw
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::syntax::markdown_spans;
+
+    #[test]
+    fn finds_inline_images_and_their_lines() {
+        let source = "Intro ![a *b*](img/one.png \"Title\") end\n\n[link](x.md) ![ref][r]\n\
+            ![](<two words.png>)";
+        let images = image_refs(source, &markdown_spans(source));
+        assert_eq!(images.len(), 2);
+
+        assert_eq!(
+            &source[images[0].range.clone()],
+            "![a *b*](img/one.png \"Title\")"
+        );
+        assert_eq!(images[0].destination, "img/one.png");
+        assert_eq!(images[0].line_end, source.find('\n').unwrap());
+
+        assert_eq!(images[1].destination, "two words.png");
+        assert_eq!(images[1].line_end, source.len());
+    }
+
+    #[test]
+    fn resolves_only_local_destinations() {
+        let dir = std::env::temp_dir();
+        let document = Utf8Path::from_path(&dir).unwrap().join("note.md");
+
+        assert_eq!(
+            resolve_local_image(Some(&document), "img/a%20b.png"),
+            Some(dir.join("img").join("a b.png"))
+        );
+        assert_eq!(
+            resolve_local_image(Some(&document), "../up.png"),
+            Some(dir.parent().unwrap().join("up.png"))
+        );
+        assert_eq!(resolve_local_image(None, "https://example.com/a.png"), None);
+        assert_eq!(resolve_local_image(None, "relative.png"), None);
+    }
+}
@@ -1,34 +1,285 @@
 use crate::model::document::EditDelta;
-use crate::services::syntax::SyntaxSpan;
-use crate::services::syntax::markdown_spans;
+use crate::services::code_highlight::{FencedCode, fenced_code_blocks};
+use crate::services::syntax::{BlockSpans, SyntaxSpan, flatten_blocks, markdown_blocks};
+use std::collections::HashSet;
 use std::time::Instant;
 
 #[derive(Clone, Debug)]
 pub struct InlineParseResult {
     pub spans: Vec<SyntaxSpan>,
+    pub blocks: Vec<BlockSpans>,
+    pub code_blocks: Vec<FencedCode>,
     pub parse_millis: f32,
 }
 
 /// Computes markdown syntax spans for inline editor presentation.
 ///
-/// This runs on a background thread and returns immutable span snapshots keyed
-/// by document revision in the caller.
-pub fn compute_inline_spans(source: &str, _last_edit: Option<&EditDelta>) -> InlineParseResult {
+/// With the blocks of the previously parsed revision and the edit made since,
+/// only the blocks touched by the edit are re-parsed; otherwise (or when the
+/// edit changes document-wide state such as reference definitions) the whole
+/// document is parsed. May run on a background thread.
+pub fn compute_inline_spans(
+    source: &str,
+    previous: Option<&[BlockSpans]>,
+    edit: Option<&EditDelta>,
+) -> InlineParseResult {
     let started = Instant::now();
-    let _delta_bounds = _last_edit.map(|d| {
-        (
-            d.start_char,
-            d.old_end_char,
-            d.new_end_char,
-            d.start_byte,
-            d.old_end_byte,
-            d.new_end_byte,
-        )
-    });
-    let spans = markdown_spans(source);
+    let blocks = previous
+        .zip(edit)
+        .and_then(|(previous, edit)| reparse_blocks(source, previous, edit))
+        .unwrap_or_else(|| markdown_blocks(source, 0, &HashSet::new()));
+    finish(source, blocks, started)
+}
+
+/// Incremental-only variant of [`compute_inline_spans`]: returns `None` when a
+/// full parse would be needed, so callers can move that work off the UI thread.
+pub fn compute_inline_spans_incremental(
+    source: &str,
+    previous: &[BlockSpans],
+    edit: &EditDelta,
+) -> Option<InlineParseResult> {
+    let started = Instant::now();
+    let blocks = reparse_blocks(source, previous, edit)?;
+    Some(finish(source, blocks, started))
+}
+
+fn finish(source: &str, blocks: Vec<BlockSpans>, started: Instant) -> InlineParseResult {
+    let spans = flatten_blocks(&blocks);
+    let code_blocks = fenced_code_blocks(source, &spans);
     let parse_millis = started.elapsed().as_secs_f32() * 1000.0;
     InlineParseResult {
         spans,
+        blocks,
+        code_blocks,
         parse_millis,
     }
 }
+
+/// Re-parses the blocks around `edit` and reuses the rest of `previous`.
+///
+/// Parsing restarts at the block before the first touched one (an edit can
+/// change how the preceding block ends, e.g. a setext underline) and stops at
+/// the first old block after the edit that the new parse also starts a block
+/// at. From such a boundary on, block parsing is identical to before, so the
+/// old blocks are shifted instead of parsed. Returns `None` if the edit does
+/// not fit `source` or changes the set of link reference definitions.
+fn reparse_blocks(
+    source: &str,
+    previous: &[BlockSpans],
+    edit: &EditDelta,
+) -> Option<Vec<BlockSpans>> {
+    if edit.start_byte > edit.old_end_byte
+        || edit.start_byte > edit.new_end_byte
+        || edit.new_end_byte > source.len()
+        || !source.is_char_boundary(edit.new_end_byte)
+    {
+        return None;
+    }
+    let moved = |pos: usize| pos - edit.old_end_byte + edit.new_end_byte;
+
+    let touched = previous.partition_point(|block| block.range.end < edit.start_byte);
+    let first = touched.saturating_sub(1);
+    let region_start = if touched == 0 {
+        0
+    } else {
+        line_start(source, previous[first].range.start)
+    };
+
+    // Old blocks starting on a line after the edit can anchor the resync.
+    let mut anchor = previous
+        .iter()
+        .enumerate()
+        .skip(touched)
+        .find(|(_, block)| {
+            block.range.start > edit.old_end_byte
+                && source
+                    .get(edit.new_end_byte..moved(block.range.start))
+                    .is_some_and(|between| between.contains('\n'))
+        })
+        .map_or(previous.len(), |(ix, _)| ix);
+    let mut step = 1;
+
+    loop {
+        let (window_end, resync) = match previous.get(anchor) {
+            Some(block) => (moved(block.range.end), Some(moved(block.range.start))),
+            None => (source.len(), None),
+        };
+        if window_end > source.len() || !source.is_char_boundary(window_end) {
+            return None;
+        }
+
+        let external_labels: HashSet<String> = previous[..first]
+            .iter()
+            .chain(&previous[anchor..])
+            .flat_map(|block| block.definitions.iter().cloned())
+            .collect();
+        let parsed = markdown_blocks(
+            &source[region_start..window_end],
+            region_start,
+            &external_labels,
+        );
+        let keep = match resync {
+            Some(resync) => parsed.iter().position(|block| block.range.start == resync),
+            None => Some(parsed.len()),
+        };
+
+        if let Some(keep) = keep {
+            if definition_labels(&previous[first..anchor]) != definition_labels(&parsed[..keep]) {
+                return None;
+            }
+            let mut blocks = Vec::with_capacity(previous.len() + keep);
+            blocks.extend_from_slice(&previous[..first]);
+            blocks.extend(parsed.into_iter().take(keep));
+            blocks.extend(
+                previous[anchor..]
+                    .iter()
+                    .map(|block| block.shifted(edit.old_end_byte, edit.new_end_byte)),
+            );
+            return Some(blocks);
+        }
+
+        // No shared boundary yet (e.g. a newly opened code fence): widen the window.
+        anchor = (anchor + step).min(previous.len());
+        step *= 2;
+    }
+}
+
+fn line_start(source: &str, pos: usize) -> usize {
+    source[..pos].rfind('\n').map_or(0, |ix| ix + 1)
+}
+
+fn definition_labels(blocks: &[BlockSpans]) -> HashSet<&str> {
+    blocks
+        .iter()
+        .flat_map(|block| block.definitions.iter().map(String::as_str))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::document::DocumentState;
+
+    const DOCUMENT: &str = "# Title\n\nIntro with *emphasis* and a [link][ref].\n\n\
+        - item one\n- [x] done\n\n> quote\n> more\n\n```rust\nfn main() {}\n```\n\n\
+        Setext\n------\n\n    indented code\n\n[ref]: https://example.com\n\nLast `code` line.\n";
+
+    const SNIPPETS: &[&str] = &[
+        "```\n",
+        "# ",
+        "*",
+        "**",
+        "\n",
+        "\n\n",
+        "[ref]: /x\n",
+        "> ",
+        "- ",
+        "===\n",
+        "word",
+        "`",
+        "[ref]",
+        "    ",
+        "~~",
+        "é",
+    ];
+
+    /// Tiny deterministic generator so the edit sequence is reproducible.
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self, bound: usize) -> usize {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((self.0 >> 33) as usize) % bound.max(1)
+        }
+    }
+
+    fn apply_random_edit(doc: &mut DocumentState, rng: &mut Lcg) {
+        let len = doc.len_chars();
+        let at = rng.next(len + 1);
+        if rng.next(3) == 0 && len > 0 {
+            let end = (at + 1 + rng.next(12)).min(len);
+            doc.delete_range(at.min(end)..end);
+        } else {
+            doc.insert(at, SNIPPETS[rng.next(SNIPPETS.len())]);
+        }
+    }
+
+    #[test]
+    fn incremental_parse_matches_full_parse_after_every_edit() {
+        for seed in 0..20 {
+            let mut rng = Lcg(seed);
+            let mut doc = DocumentState::new_empty();
+            doc.set_text(DOCUMENT);
+            let mut revision = doc.revision;
+            let mut blocks = markdown_blocks(DOCUMENT, 0, &HashSet::new());
+
+            for _ in 0..60 {
+                // Sometimes batch several edits between parses, as the debounce does.
+                for _ in 0..1 + rng.next(3) {
+                    apply_random_edit(&mut doc, &mut rng);
+                }
+                let text = doc.text();
+                let edit = doc.edit_since(revision).expect("edit log covers revision");
+                let incremental = compute_inline_spans(&text, Some(&blocks), Some(&edit));
+                let full = markdown_blocks(&text, 0, &HashSet::new());
+                assert_eq!(incremental.blocks, full, "seed {seed}, text {text:?}");
+
+                blocks = incremental.blocks;
+                revision = doc.revision;
+            }
+        }
+    }
+
+    #[test]
+    fn undo_and_redo_deltas_keep_incremental_parse_exact() {
+        let mut doc = DocumentState::new_empty();
+        doc.set_text(DOCUMENT);
+        doc.clear_undo_history();
+        let mut revision = doc.revision;
+        let mut blocks = markdown_blocks(DOCUMENT, 0, &HashSet::new());
+
+        doc.begin_edit();
+        doc.insert(9, "```\n");
+        doc.commit_edit();
+        doc.begin_edit();
+        doc.insert(0, "Para\n");
+        doc.commit_edit();
+
+        for step in 0..4 {
+            match step {
+                0 | 1 => assert!(doc.undo()),
+                _ => assert!(doc.redo()),
+            }
+            let text = doc.text();
+            let edit = doc.edit_since(revision).unwrap();
+            let incremental = compute_inline_spans(&text, Some(&blocks), Some(&edit));
+            assert_eq!(
+                incremental.blocks,
+                markdown_blocks(&text, 0, &HashSet::new())
+            );
+            blocks = incremental.blocks;
+            revision = doc.revision;
+        }
+    }
+
+    #[test]
+    fn local_edit_reuses_distant_blocks() {
+        let source = "# One\n\npara *a*\n\npara *b*\n\n# Two\n";
+        let blocks = markdown_blocks(source, 0, &HashSet::new());
+
+        // Insert "x" inside the last paragraph's emphasis.
+        let edited = "# One\n\npara *a*\n\npara *bx*\n\n# Two\n";
+        let edit = EditDelta::between(source, edited);
+        let reparsed = reparse_blocks(edited, &blocks, &edit).unwrap();
+        assert_eq!(reparsed, markdown_blocks(edited, 0, &HashSet::new()));
+        assert_eq!(reparsed[0], blocks[0]);
+
+        // Defining a new reference label forces a full parse.
+        let with_definition = format!("{edited}\n[b]: /url\n");
+        let edit = EditDelta::between(edited, &with_definition);
+        assert!(reparse_blocks(&with_definition, &reparsed, &edit).is_none());
+    }
+}
@@ -0,0 +1,209 @@
+use crate::services::export::html::slug;
+use crate::services::images::resolve_local_image;
+use camino::Utf8Path;
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// A problem found by [`lint_markdown`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LintIssue {
+    /// 1-based line and column (in characters) of the problem.
+    pub line: usize,
+    pub column: usize,
+    /// Short rule name, e.g. `heading-increment`.
+    pub rule: &'static str,
+    pub message: String,
+}
+
+/// Checks `source` for common Markdown mistakes. Local link and image
+/// targets are resolved against `source_path` when it is known.
+pub fn lint_markdown(source: &str, source_path: Option<&Utf8Path>) -> Vec<LintIssue> {
+    let positions = Positions::new(source);
+    let mut issues = Vec::new();
+    let mut issue = |offset: usize, rule: &'static str, message: String| {
+        let (line, column) = positions.line_column(offset);
+        issues.push(LintIssue {
+            line,
+            column,
+            rule,
+            message,
+        });
+    };
+
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS;
+    let mut previous_level = None;
+    // Start offset and text so far of the open heading.
+    let mut heading: Option<(usize, String)> = None;
+    let mut titles = HashSet::new();
+    let mut anchors = HashSet::new();
+    let mut fragment_links = Vec::new();
+    let mut verbatim: Vec<Range<usize>> = Vec::new();
+
+    for (event, range) in Parser::new_ext(source, options).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                let level = level as u8;
+                if let Some(previous) = previous_level.filter(|previous| level > previous + 1) {
+                    issue(
+                        range.start,
+                        "heading-increment",
+                        format!("heading level jumps from {previous} to {level}"),
+                    );
+                }
+                previous_level = Some(level);
+                heading = Some((range.start, String::new()));
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, title)) = &mut heading {
+                    title.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                let Some((start, title)) = heading.take() else {
+                    continue;
+                };
+                let title = title.trim().to_string();
+                let base = slug(&title);
+                let mut anchor = base.clone();
+                let mut n = 0;
+                while !anchors.insert(anchor.clone()) {
+                    n += 1;
+                    anchor = format!("{base}-{n}");
+                }
+                if !titles.insert(title.to_lowercase()) {
+                    issue(
+                        start,
+                        "duplicate-heading",
+                        format!("heading \"{title}\" is used more than once"),
+                    );
+                }
+            }
+            Event::Start(Tag::Link { dest_url, .. }) => {
+                if dest_url.is_empty() {
+                    issue(range.start, "empty-link", "link has no destination".into());
+                } else if let Some(fragment) = dest_url.strip_prefix('#') {
+                    fragment_links.push((range.start, fragment.to_string()));
+                } else if let Some(path) = resolve_local_image(source_path, &dest_url)
+                    && !path.exists()
+                {
+                    issue(
+                        range.start,
+                        "missing-file",
+                        format!("link target {dest_url} does not exist"),
+                    );
+                }
+            }
+            Event::Start(Tag::Image { dest_url, .. }) => {
+                if let Some(path) = resolve_local_image(source_path, &dest_url)
+                    && !path.is_file()
+                {
+                    issue(
+                        range.start,
+                        "missing-image",
+                        format!("image {dest_url} does not exist"),
+                    );
+                }
+            }
+            Event::Start(Tag::CodeBlock(_) | Tag::HtmlBlock) => verbatim.push(range),
+            _ => {}
+        }
+    }
+
+    for (offset, fragment) in fragment_links {
+        if !anchors.contains(&fragment.to_lowercase()) {
+            issue(
+                offset,
+                "broken-anchor",
+                format!("no heading matches #{fragment}"),
+            );
+        }
+    }
+
+    let mut line_start = 0;
+    for line in source.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        let trimmed = content.trim_end_matches([' ', '\t']);
+        let trailing = &content[trimmed.len()..];
+        // Two spaces at the end of a line are a hard line break.
+        let in_verbatim = verbatim.iter().any(|range| range.contains(&line_start));
+        let hard_break = trailing == "  " && !trimmed.is_empty();
+        if !trailing.is_empty() && !hard_break && !in_verbatim {
+            issue(
+                line_start + trimmed.len(),
+                "trailing-whitespace",
+                "line ends with whitespace".into(),
+            );
+        }
+        line_start += line.len();
+    }
+
+    issues.sort_by_key(|issue| (issue.line, issue.column));
+    issues
+}
+
+/// Maps byte offsets to 1-based line and column numbers.
+struct Positions<'a> {
+    source: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> Positions<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            line_starts: std::iter::once(0)
+                .chain(source.match_indices('\n').map(|(ix, _)| ix + 1))
+                .collect(),
+        }
+    }
+
+    fn line_column(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|start| *start <= offset) - 1;
+        let column = self.source[self.line_starts[line]..offset].chars().count();
+        (line + 1, column + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_rules_with_positions() {
+        let source = "# Intro\n\n### Skipped\n\nSee [x](#intro), [y](#nope) and [z]().  \n\
+                      tail \n\n```\ncode   \n```\n\n# Intro\n";
+        let issues: Vec<(usize, usize, &str)> = lint_markdown(source, None)
+            .into_iter()
+            .map(|issue| (issue.line, issue.column, issue.rule))
+            .collect();
+        assert_eq!(
+            issues,
+            vec![
+                (3, 1, "heading-increment"),
+                (5, 18, "broken-anchor"),
+                (5, 33, "empty-link"),
+                (6, 5, "trailing-whitespace"),
+                (12, 1, "duplicate-heading"),
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_local_targets_against_the_document_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("here.png"), b"png").unwrap();
+        let source_path = Utf8Path::from_path(dir.path()).unwrap().join("doc.md");
+
+        let issues = lint_markdown(
+            "![a](here.png) ![b](gone.png) [c](other.md) [d](https://example.com)\n",
+            Some(&source_path),
+        );
+        let rules: Vec<&str> = issues.iter().map(|issue| issue.rule).collect();
+        assert_eq!(rules, vec!["missing-image", "missing-file"]);
+        assert_eq!(issues[0].message, "image gone.png does not exist");
+    }
+}
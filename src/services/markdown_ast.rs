@@ -0,0 +1,480 @@
+use pulldown_cmark::{Alignment, CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use std::iter::Peekable;
+use std::ops::Range;
+
+/// Markdown document tree shared by the preview and the exporters.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MarkdownDocument {
+    pub blocks: Vec<Block>,
+}
+
+/// A block together with the source line (0-based) it starts on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Block {
+    pub line: usize,
+    pub kind: BlockKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BlockKind {
+    Paragraph(Vec<Inline>),
+    Heading {
+        level: u8,
+        content: Vec<Inline>,
+    },
+    BlockQuote(Vec<Block>),
+    List {
+        /// Number of the first item for ordered lists.
+        start: Option<u64>,
+        items: Vec<ListItem>,
+    },
+    CodeBlock {
+        /// First word of a fenced block's info string.
+        language: Option<String>,
+        code: String,
+    },
+    Table {
+        alignments: Vec<Alignment>,
+        header: Vec<Vec<Inline>>,
+        rows: Vec<Vec<Vec<Inline>>>,
+    },
+    Rule,
+    Html(String),
+    FootnoteDefinition {
+        label: String,
+        blocks: Vec<Block>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListItem {
+    /// `Some` for task list items, with their checked state.
+    pub checked: Option<bool>,
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Inline {
+    Text(String),
+    Code(String),
+    Emphasis(Vec<Inline>),
+    Strong(Vec<Inline>),
+    Strikethrough(Vec<Inline>),
+    Link {
+        url: String,
+        title: String,
+        content: Vec<Inline>,
+    },
+    Image {
+        url: String,
+        title: String,
+        alt: String,
+    },
+    FootnoteReference(String),
+    SoftBreak,
+    HardBreak,
+    Html(String),
+}
+
+impl Inline {
+    /// Text content of `inlines` without any formatting.
+    pub fn plain_text(inlines: &[Inline]) -> String {
+        let mut text = String::new();
+        for inline in inlines {
+            match inline {
+                Inline::Text(s) | Inline::Code(s) | Inline::Html(s) => text.push_str(s),
+                Inline::Emphasis(children)
+                | Inline::Strong(children)
+                | Inline::Strikethrough(children)
+                | Inline::Link {
+                    content: children, ..
+                } => text.push_str(&Inline::plain_text(children)),
+                Inline::Image { alt, .. } => text.push_str(alt),
+                Inline::FootnoteReference(label) => {
+                    text.push_str(&format!("[{label}]"));
+                }
+                Inline::SoftBreak => text.push(' '),
+                Inline::HardBreak => text.push('\n'),
+            }
+        }
+        text
+    }
+}
+
+/// Parses `source` (CommonMark with GFM tables, footnotes, strikethrough and
+/// task lists) into a [`MarkdownDocument`].
+pub fn parse_markdown(source: &str) -> MarkdownDocument {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS;
+    let mut builder = TreeBuilder {
+        events: Parser::new_ext(source, options)
+            .into_offset_iter()
+            .peekable(),
+        line_starts: std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(ix, _)| ix + 1))
+            .collect(),
+        task_markers: Vec::new(),
+    };
+    MarkdownDocument {
+        blocks: builder.blocks(),
+    }
+}
+
+struct TreeBuilder<'a, I: Iterator<Item = (Event<'a>, Range<usize>)>> {
+    events: Peekable<I>,
+    line_starts: Vec<usize>,
+    /// Task marker of each open list item, innermost last.
+    task_markers: Vec<Option<bool>>,
+}
+
+impl<'a, I: Iterator<Item = (Event<'a>, Range<usize>)>> TreeBuilder<'a, I> {
+    fn line_of(&self, offset: usize) -> usize {
+        self.line_starts.partition_point(|start| *start <= offset) - 1
+    }
+
+    /// Blocks up to (and consuming) the end of the enclosing container.
+    fn blocks(&mut self) -> Vec<Block> {
+        let mut blocks = Vec::new();
+        while let Some((event, range)) = self.events.peek() {
+            let (is_end, is_inline) = (matches!(event, Event::End(_)), is_inline_event(event));
+            let start = range.start;
+            let line = self.line_of(start);
+            if is_end {
+                self.events.next();
+                break;
+            }
+            if is_inline {
+                // Tight list items hold their text without a paragraph.
+                let content = self.inline_run();
+                blocks.push(Block {
+                    line,
+                    kind: BlockKind::Paragraph(content),
+                });
+                continue;
+            }
+            let Some((event, _)) = self.events.next() else {
+                break;
+            };
+            if let Some(kind) = self.block(event) {
+                blocks.push(Block { line, kind });
+            }
+        }
+        blocks
+    }
+
+    fn block(&mut self, event: Event<'a>) -> Option<BlockKind> {
+        let tag = match event {
+            Event::Start(tag) => tag,
+            Event::Rule => return Some(BlockKind::Rule),
+            Event::Html(html) => return Some(BlockKind::Html(html.to_string())),
+            _ => return None,
+        };
+        Some(match tag {
+            Tag::Paragraph => BlockKind::Paragraph(self.inlines()),
+            Tag::Heading { level, .. } => BlockKind::Heading {
+                level: heading_level(level),
+                content: self.inlines(),
+            },
+            Tag::BlockQuote(_) => BlockKind::BlockQuote(self.blocks()),
+            Tag::CodeBlock(kind) => {
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => {
+                        info.split_whitespace().next().map(str::to_string)
+                    }
+                    CodeBlockKind::Indented => None,
+                };
+                BlockKind::CodeBlock {
+                    language,
+                    code: self.raw_text(),
+                }
+            }
+            Tag::HtmlBlock => BlockKind::Html(self.raw_text()),
+            Tag::List(start) => BlockKind::List {
+                start,
+                items: self.list_items(),
+            },
+            Tag::Table(alignments) => {
+                let (header, rows) = self.table();
+                BlockKind::Table {
+                    alignments,
+                    header,
+                    rows,
+                }
+            }
+            Tag::FootnoteDefinition(label) => BlockKind::FootnoteDefinition {
+                label: label.to_string(),
+                blocks: self.blocks(),
+            },
+            _ => {
+                self.skip_to_end();
+                return None;
+            }
+        })
+    }
+
+    fn list_items(&mut self) -> Vec<ListItem> {
+        let mut items = Vec::new();
+        while let Some((event, _)) = self.events.next() {
+            match event {
+                Event::Start(Tag::Item) => {
+                    self.task_markers.push(None);
+                    let blocks = self.blocks();
+                    let checked = self.task_markers.pop().flatten();
+                    items.push(ListItem { checked, blocks });
+                }
+                Event::End(_) => break,
+                _ => {}
+            }
+        }
+        items
+    }
+
+    fn table(&mut self) -> (Vec<Vec<Inline>>, Vec<Vec<Vec<Inline>>>) {
+        let mut header = Vec::new();
+        let mut rows = Vec::new();
+        while let Some((event, _)) = self.events.next() {
+            match event {
+                Event::Start(Tag::TableHead) => header = self.table_cells(),
+                Event::Start(Tag::TableRow) => rows.push(self.table_cells()),
+                Event::End(TagEnd::Table) => break,
+                _ => {}
+            }
+        }
+        (header, rows)
+    }
+
+    fn table_cells(&mut self) -> Vec<Vec<Inline>> {
+        let mut cells = Vec::new();
+        while let Some((event, _)) = self.events.next() {
+            match event {
+                Event::Start(Tag::TableCell) => cells.push(self.inlines()),
+                Event::End(_) => break,
+                _ => {}
+            }
+        }
+        cells
+    }
+
+    /// Inlines up to (and consuming) the end of the enclosing tag.
+    fn inlines(&mut self) -> Vec<Inline> {
+        let mut inlines = Vec::new();
+        while let Some((event, _)) = self.events.next() {
+            if matches!(event, Event::End(_)) {
+                break;
+            }
+            self.push_inline(event, &mut inlines);
+        }
+        inlines
+    }
+
+    /// Inlines up to the next block-level event, which is left unconsumed.
+    fn inline_run(&mut self) -> Vec<Inline> {
+        let mut inlines = Vec::new();
+        while self
+            .events
+            .peek()
+            .is_some_and(|(event, _)| is_inline_event(event))
+        {
+            if let Some((event, _)) = self.events.next() {
+                self.push_inline(event, &mut inlines);
+            }
+        }
+        inlines
+    }
+
+    fn push_inline(&mut self, event: Event<'a>, inlines: &mut Vec<Inline>) {
+        let inline = match event {
+            Event::Text(text) | Event::InlineMath(text) | Event::DisplayMath(text) => {
+                // Merge adjacent text (pulldown-cmark splits around escapes).
+                if let Some(Inline::Text(last)) = inlines.last_mut() {
+                    last.push_str(&text);
+                    return;
+                }
+                Inline::Text(text.to_string())
+            }
+            Event::Code(code) => Inline::Code(code.to_string()),
+            Event::InlineHtml(html) | Event::Html(html) => Inline::Html(html.to_string()),
+            Event::FootnoteReference(label) => Inline::FootnoteReference(label.to_string()),
+            Event::SoftBreak => Inline::SoftBreak,
+            Event::HardBreak => Inline::HardBreak,
+            Event::TaskListMarker(checked) => {
+                if let Some(marker) = self.task_markers.last_mut() {
+                    *marker = Some(checked);
+                }
+                return;
+            }
+            Event::Start(Tag::Emphasis) => Inline::Emphasis(self.inlines()),
+            Event::Start(Tag::Strong) => Inline::Strong(self.inlines()),
+            Event::Start(Tag::Strikethrough) => Inline::Strikethrough(self.inlines()),
+            Event::Start(Tag::Link {
+                dest_url, title, ..
+            }) => Inline::Link {
+                url: dest_url.to_string(),
+                title: title.to_string(),
+                content: self.inlines(),
+            },
+            Event::Start(Tag::Image {
+                dest_url, title, ..
+            }) => Inline::Image {
+                url: dest_url.to_string(),
+                title: title.to_string(),
+                alt: Inline::plain_text(&self.inlines()),
+            },
+            Event::Start(_) => Inline::Text(Inline::plain_text(&self.inlines())),
+            Event::End(_) | Event::Rule => return,
+        };
+        inlines.push(inline);
+    }
+
+    /// Concatenated text up to (and consuming) the end of the enclosing tag.
+    fn raw_text(&mut self) -> String {
+        let mut text = String::new();
+        for (event, _) in self.events.by_ref() {
+            match event {
+                Event::Text(s) | Event::Html(s) => text.push_str(&s),
+                Event::End(_) => break,
+                _ => {}
+            }
+        }
+        text
+    }
+
+    fn skip_to_end(&mut self) {
+        let mut depth = 0usize;
+        for (event, _) in self.events.by_ref() {
+            match event {
+                Event::Start(_) => depth += 1,
+                Event::End(_) if depth == 0 => break,
+                Event::End(_) => depth -= 1,
+                _ => {}
+            }
+        }
+    }
+}
+
+fn is_inline_event(event: &Event) -> bool {
+    match event {
+        Event::Start(tag) => matches!(
+            tag,
+            Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link { .. } | Tag::Image { .. }
+        ),
+        Event::Text(_)
+        | Event::Code(_)
+        | Event::InlineMath(_)
+        | Event::DisplayMath(_)
+        | Event::InlineHtml(_)
+        | Event::FootnoteReference(_)
+        | Event::SoftBreak
+        | Event::HardBreak
+        | Event::TaskListMarker(_) => true,
+        _ => false,
+    }
+}
+
+fn heading_level(level: HeadingLevel) -> u8 {
+    match level {
+        HeadingLevel::H1 => 1,
+        HeadingLevel::H2 => 2,
+        HeadingLevel::H3 => 3,
+        HeadingLevel::H4 => 4,
+        HeadingLevel::H5 => 5,
+        HeadingLevel::H6 => 6,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> Inline {
+        Inline::Text(s.to_string())
+    }
+
+    #[test]
+    fn builds_blocks_with_source_lines() {
+        let source =
+            "# Title\n\nSome *em* and `code`.\n\n> quoted\n\n```rust\nfn x() {}\n```\n\n---\n";
+        let doc = parse_markdown(source);
+        let lines: Vec<usize> = doc.blocks.iter().map(|b| b.line).collect();
+        assert_eq!(lines, vec![0, 2, 4, 6, 10]);
+
+        assert_eq!(
+            doc.blocks[0].kind,
+            BlockKind::Heading {
+                level: 1,
+                content: vec![text("Title")],
+            }
+        );
+        assert_eq!(
+            doc.blocks[1].kind,
+            BlockKind::Paragraph(vec![
+                text("Some "),
+                Inline::Emphasis(vec![text("em")]),
+                text(" and "),
+                Inline::Code("code".into()),
+                text("."),
+            ])
+        );
+        assert!(matches!(
+            &doc.blocks[3].kind,
+            BlockKind::CodeBlock { language: Some(lang), code } if lang == "rust" && code == "fn x() {}\n"
+        ));
+        assert_eq!(doc.blocks[4].kind, BlockKind::Rule);
+    }
+
+    #[test]
+    fn builds_lists_tables_and_footnotes() {
+        let source = "- [x] done\n- plain\n  - nested\n\n3. three\n\n\
+            | a | b |\n|:--|--:|\n| 1 | ![alt *x*](i.png) |\n\nNote[^n].\n\n[^n]: The note.\n";
+        let doc = parse_markdown(source);
+
+        let BlockKind::List { start: None, items } = &doc.blocks[0].kind else {
+            panic!("expected a bullet list: {:?}", doc.blocks[0]);
+        };
+        assert_eq!(items[0].checked, Some(true));
+        assert_eq!(items[1].checked, None);
+        assert_eq!(
+            items[0].blocks[0].kind,
+            BlockKind::Paragraph(vec![text("done")])
+        );
+        assert!(matches!(items[1].blocks[1].kind, BlockKind::List { .. }));
+        assert!(matches!(
+            doc.blocks[1].kind,
+            BlockKind::List { start: Some(3), .. }
+        ));
+
+        let BlockKind::Table {
+            alignments,
+            header,
+            rows,
+        } = &doc.blocks[2].kind
+        else {
+            panic!("expected a table");
+        };
+        assert_eq!(alignments, &[Alignment::Left, Alignment::Right]);
+        assert_eq!(header.len(), 2);
+        assert_eq!(
+            rows[0][1],
+            vec![Inline::Image {
+                url: "i.png".into(),
+                title: String::new(),
+                alt: "alt x".into(),
+            }]
+        );
+
+        assert_eq!(
+            doc.blocks[3].kind,
+            BlockKind::Paragraph(vec![
+                text("Note"),
+                Inline::FootnoteReference("n".into()),
+                text("."),
+            ])
+        );
+        assert!(matches!(
+            &doc.blocks[4].kind,
+            BlockKind::FootnoteDefinition { label, .. } if label == "n"
+        ));
+    }
+}
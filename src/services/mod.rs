@@ -1,6 +1,19 @@
 pub mod assets;
+pub mod clipboard;
+pub mod code_highlight;
+pub mod diff;
+pub mod export;
+pub mod format;
 pub mod fs;
+pub mod fuzzy;
+pub mod images;
 pub mod inline_markdown;
+pub mod lint;
+pub mod markdown_ast;
+pub mod recovery;
+pub mod session;
 pub mod settings;
+pub mod stats;
 pub mod syntax;
 pub mod tasks;
+pub mod watcher;
@@ -0,0 +1,251 @@
+//! Journal of documents with unsaved changes, kept in the data directory so
+//! their text survives a crash or power loss.
+
+use crate::error::AppResult;
+use crate::services::fs::write_atomic;
+use camino::Utf8PathBuf;
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, TryLockError};
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock, Mutex};
+
+/// A document's unsaved text as journaled.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecoveredDocument {
+    /// The document's file, or `None` for an untitled document.
+    pub path: Option<PathBuf>,
+    pub text: String,
+}
+
+/// Directory holding one journal entry per document, named by its key, and
+/// a `{pid}.lock` file locked by each running instance that journals there.
+#[derive(Clone, Debug)]
+pub struct RecoveryJournal {
+    dir: Option<Utf8PathBuf>,
+    state: Arc<Mutex<JournalState>>,
+}
+
+#[derive(Debug, Default)]
+struct JournalState {
+    next_ticket: u64,
+    /// Ticket of the last change applied to each key.
+    applied: HashMap<String, u64>,
+    /// This process's lock file, held while it runs.
+    lock: Option<File>,
+}
+
+/// The journal in the data directory, shared by every window.
+static JOURNAL: LazyLock<RecoveryJournal> = LazyLock::new(|| {
+    RecoveryJournal::new(
+        ProjectDirs::from("com", "kumarujjawal", "aster")
+            .and_then(|dirs| Utf8PathBuf::try_from(dirs.data_dir().join("recovery")).ok()),
+    )
+});
+
+impl Default for RecoveryJournal {
+    fn default() -> Self {
+        JOURNAL.clone()
+    }
+}
+
+impl RecoveryJournal {
+    /// Key of a document in this process; entries of earlier runs never
+    /// share it.
+    pub fn key(document_id: u64) -> String {
+        format!("{}-{document_id}", std::process::id())
+    }
+
+    fn new(dir: Option<Utf8PathBuf>) -> Self {
+        Self {
+            dir,
+            state: Arc::default(),
+        }
+    }
+
+    /// Orders changes to the journal, which may be applied on other threads:
+    /// take a ticket when scheduling a write or removal, and a change is
+    /// dropped if one with a later ticket for the same key came first.
+    pub fn ticket(&self) -> u64 {
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        state.next_ticket += 1;
+        state.next_ticket
+    }
+
+    pub fn write(&self, key: &str, ticket: u64, document: &RecoveredDocument) -> AppResult<()> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        if !state.apply(key, ticket) {
+            return Ok(());
+        }
+        fs::create_dir_all(dir)?;
+        if state.lock.is_none() {
+            state.lock = lock_file(dir);
+        }
+        let json = serde_json::to_string(document).unwrap_or_default();
+        write_atomic(&dir.join(format!("{key}.json")), json)
+    }
+
+    pub fn remove(&self, key: &str, ticket: u64) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        if state.apply(key, ticket) {
+            let _ = fs::remove_file(dir.join(format!("{key}.json")));
+        }
+    }
+
+    /// Journaled documents of instances no longer running, with their keys,
+    /// sorted by key. Unreadable entries are skipped.
+    pub fn entries(&self) -> Vec<(String, RecoveredDocument)> {
+        let Some(dir) = &self.dir else {
+            return Vec::new();
+        };
+        let Ok(entries) = dir.read_dir_utf8() else {
+            return Vec::new();
+        };
+        let mut documents: Vec<(String, RecoveredDocument)> = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let key = entry.path().file_stem()?.to_string();
+                (entry.path().extension() == Some("json")).then_some(())?;
+                let (pid, _) = key.split_once('-')?;
+                (!is_running(dir, pid)).then_some(())?;
+                let json = fs::read_to_string(entry.path()).ok()?;
+                Some((key, serde_json::from_str(&json).ok()?))
+            })
+            .collect();
+        documents.sort_by(|(a, _), (b, _)| a.cmp(b));
+        documents
+    }
+
+    /// Deletes the lock files of instances no longer running.
+    pub fn remove_stale_locks(&self) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let Ok(entries) = dir.read_dir_utf8() else {
+            return;
+        };
+        for entry in entries.filter_map(Result::ok) {
+            if entry.path().extension() == Some("lock")
+                && let Some(pid) = entry.path().file_stem()
+                && !is_running(dir, pid)
+            {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+impl JournalState {
+    /// Records `ticket` for `key`, or returns false if a later change to
+    /// `key` was already applied.
+    fn apply(&mut self, key: &str, ticket: u64) -> bool {
+        let applied = self.applied.entry(key.to_string()).or_default();
+        if *applied > ticket {
+            return false;
+        }
+        *applied = ticket;
+        true
+    }
+}
+
+/// Creates and locks this process's lock file in `dir`.
+fn lock_file(dir: &Utf8PathBuf) -> Option<File> {
+    let file = File::create(dir.join(format!("{}.lock", std::process::id()))).ok()?;
+    file.try_lock().ok()?;
+    Some(file)
+}
+
+/// Whether the instance with process id `pid` still holds its lock file.
+fn is_running(dir: &Utf8PathBuf, pid: &str) -> bool {
+    if pid == std::process::id().to_string() {
+        return true;
+    }
+    let Ok(file) = File::open(dir.join(format!("{pid}.lock"))) else {
+        return false;
+    };
+    matches!(file.try_lock(), Err(TryLockError::WouldBlock))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn journal(dir: &tempfile::TempDir) -> RecoveryJournal {
+        RecoveryJournal::new(Some(
+            Utf8PathBuf::try_from(dir.path().join("recovery")).unwrap(),
+        ))
+    }
+
+    #[test]
+    fn lists_written_entries_until_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = journal(&dir);
+        assert!(journal.entries().is_empty());
+
+        let untitled = RecoveredDocument {
+            path: None,
+            text: "draft".to_string(),
+        };
+        let note = RecoveredDocument {
+            path: Some(PathBuf::from("/notes/today.md")),
+            text: "# Today".to_string(),
+        };
+        journal.write("1-2", journal.ticket(), &untitled).unwrap();
+        journal.write("1-3", journal.ticket(), &note).unwrap();
+        std::fs::write(dir.path().join("recovery/1-4.json"), "not json").unwrap();
+        assert_eq!(
+            journal.entries(),
+            vec![
+                ("1-2".to_string(), untitled.clone()),
+                ("1-3".to_string(), note.clone())
+            ]
+        );
+
+        // A write scheduled before the removal can't bring the entry back.
+        let late = journal.ticket();
+        journal.remove("1-2", journal.ticket());
+        journal.write("1-2", late, &untitled).unwrap();
+        assert_eq!(journal.entries(), vec![("1-3".to_string(), note)]);
+    }
+
+    #[test]
+    fn leaves_entries_of_running_instances_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = journal(&dir);
+        let document = RecoveredDocument {
+            path: None,
+            text: "draft".to_string(),
+        };
+        let own = RecoveryJournal::key(7);
+        journal.write(&own, journal.ticket(), &document).unwrap();
+        journal
+            .write("4242-1", journal.ticket(), &document)
+            .unwrap();
+        journal
+            .write("4343-1", journal.ticket(), &document)
+            .unwrap();
+        // Instance 4242 is still running; 4343 quit without cleaning up.
+        let live = File::create(dir.path().join("recovery/4242.lock")).unwrap();
+        live.try_lock().unwrap();
+        std::fs::write(dir.path().join("recovery/4343.lock"), "").unwrap();
+
+        assert_eq!(journal.entries(), vec![("4343-1".to_string(), document)]);
+        journal.remove_stale_locks();
+        let recovery = dir.path().join("recovery");
+        assert!(recovery.join("4242.lock").exists());
+        assert!(!recovery.join("4343.lock").exists());
+        assert!(
+            recovery
+                .join(format!("{}.lock", std::process::id()))
+                .exists()
+        );
+        assert!(recovery.join("4242-1.json").exists());
+    }
+}
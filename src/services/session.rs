@@ -0,0 +1,190 @@
+//! The windows and documents open when Aster quits, reopened on the next
+//! launch.
+
+use crate::error::AppResult;
+use crate::model::pane_layout::PaneLayout;
+use crate::services::fs::write_atomic;
+use camino::Utf8PathBuf;
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::ops::Range;
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub windows: Vec<WindowSession>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WindowSession {
+    pub bounds: SavedBounds,
+    pub layout: PaneLayout,
+    pub panes: Vec<PaneSession>,
+    pub active_pane: usize,
+    pub sidebar_width: f32,
+    pub sidebar_visible: bool,
+    /// Workspace folder shown in the sidebar.
+    pub folder: Option<PathBuf>,
+}
+
+/// Window position and size in screen pixels, and the state it was in.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedBounds {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub state: SavedWindowState,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SavedWindowState {
+    Windowed,
+    #[default]
+    Maximized,
+    Fullscreen,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PaneSession {
+    pub id: usize,
+    pub active_tab: usize,
+    pub tabs: Vec<TabSession>,
+}
+
+/// A saved file open in a tab, with the view state of its editor.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TabSession {
+    pub path: PathBuf,
+    /// Cursor and selection as char indices.
+    pub cursor: usize,
+    pub selection: Option<Range<usize>>,
+    pub scroll_y: f32,
+    /// The find query, if the find bar was open.
+    pub find: Option<String>,
+}
+
+impl WindowSession {
+    /// Keeps only the tabs `keep` accepts. Panes left without tabs are
+    /// dropped from the layout; returns false if no pane is left.
+    pub fn retain_tabs(&mut self, mut keep: impl FnMut(&TabSession) -> bool) -> bool {
+        for pane in &mut self.panes {
+            let active = pane.tabs.get(pane.active_tab).cloned();
+            pane.tabs.retain(&mut keep);
+            pane.active_tab = active
+                .and_then(|active| pane.tabs.iter().position(|tab| *tab == active))
+                .unwrap_or(0);
+        }
+        let ids = self.layout.pane_ids();
+        self.panes
+            .retain(|pane| !pane.tabs.is_empty() && ids.contains(&pane.id));
+        let Some(first) = self.panes.first().map(|pane| pane.id) else {
+            return false;
+        };
+        for id in ids {
+            if !self.panes.iter().any(|pane| pane.id == id) {
+                self.layout.remove(id);
+            }
+        }
+        // The last pane of a layout can't be removed; it may be a dropped one.
+        if let PaneLayout::Pane(id) = self.layout
+            && id != first
+        {
+            self.layout = PaneLayout::Pane(first);
+        }
+        if !self.layout.pane_ids().contains(&self.active_pane) {
+            self.active_pane = self.layout.pane_ids()[0];
+        }
+        true
+    }
+}
+
+fn session_path() -> Option<Utf8PathBuf> {
+    ProjectDirs::from("com", "kumarujjawal", "aster")
+        .and_then(|dirs| Utf8PathBuf::try_from(dirs.data_dir().join("session.json")).ok())
+}
+
+/// The session saved at the last quit, if any.
+pub fn load_session() -> Option<Session> {
+    let json = fs::read_to_string(session_path()?).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+pub fn save_session(session: &Session) -> AppResult<()> {
+    let Some(path) = session_path() else {
+        return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let json = serde_json::to_string_pretty(session).unwrap_or_default();
+    write_atomic(&path, json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::pane_layout::SplitAxis;
+
+    fn tab(path: &str) -> TabSession {
+        TabSession {
+            path: PathBuf::from(path),
+            cursor: 3,
+            selection: Some(1..3),
+            scroll_y: -120.0,
+            find: None,
+        }
+    }
+
+    #[test]
+    fn dropping_tabs_collapses_emptied_panes() {
+        let mut session = WindowSession {
+            bounds: SavedBounds {
+                x: 10.0,
+                y: 20.0,
+                width: 800.0,
+                height: 600.0,
+                state: SavedWindowState::Windowed,
+            },
+            layout: PaneLayout::Split {
+                axis: SplitAxis::Horizontal,
+                children: vec![PaneLayout::Pane(0), PaneLayout::Pane(1)],
+            },
+            panes: vec![
+                PaneSession {
+                    id: 0,
+                    active_tab: 1,
+                    tabs: vec![tab("/notes/gone.md"), tab("/notes/a.md")],
+                },
+                PaneSession {
+                    id: 1,
+                    active_tab: 0,
+                    tabs: vec![tab("/notes/gone.md")],
+                },
+            ],
+            active_pane: 1,
+            sidebar_width: 240.0,
+            sidebar_visible: false,
+            folder: Some(PathBuf::from("/notes")),
+        };
+        let json = serde_json::to_string(&Session {
+            windows: vec![session.clone()],
+        })
+        .unwrap();
+        assert_eq!(
+            serde_json::from_str::<Session>(&json).unwrap().windows,
+            vec![session.clone()]
+        );
+
+        assert!(session.retain_tabs(|tab| !tab.path.ends_with("gone.md")));
+        assert_eq!(session.layout, PaneLayout::Pane(0));
+        assert_eq!(session.active_pane, 0);
+        assert_eq!(session.panes.len(), 1);
+        assert_eq!(session.panes[0].tabs, vec![tab("/notes/a.md")]);
+        assert_eq!(session.panes[0].active_tab, 0);
+
+        assert!(!session.retain_tabs(|_| false));
+    }
+}
@@ -1,8 +1,11 @@
+use crate::model::recent::RecentList;
+use crate::services::export::pdf::{PageSize, PdfOptions};
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 /// Application settings with persistence
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,16 +13,86 @@ pub struct Settings {
     /// Font size in points (8-32, default 14)
     #[serde(default = "default_font_size")]
     pub font_size: f32,
+    /// Whether Vim modal editing is enabled in the editor
+    #[serde(default)]
+    pub vim_mode: bool,
+    /// When inline Markdown markers (`**`, `#`, link URLs) are shown raw
+    #[serde(default)]
+    pub marker_visibility: MarkerVisibility,
+    /// Stylesheet used for HTML exports instead of the built-in theme
+    #[serde(default)]
+    pub export_css: Option<PathBuf>,
+    /// Paper size for PDF exports
+    #[serde(default)]
+    pub pdf_page_size: PageSize,
+    /// Page margin for PDF exports, in millimetres
+    #[serde(default = "default_pdf_margin_mm")]
+    pub pdf_margin_mm: f32,
+    /// Save documents after this many seconds without edits (0 = never)
+    #[serde(default)]
+    pub autosave_after_secs: u64,
+    /// Save documents when the window loses focus
+    #[serde(default)]
+    pub autosave_on_blur: bool,
+    /// Save a document when switching away from it to another tab or pane
+    #[serde(default)]
+    pub autosave_on_switch: bool,
+    /// Reopen the windows and documents of the last session on launch
+    #[serde(default = "default_restore_session")]
+    pub restore_session: bool,
+    /// Recently opened files and folders, for File → Open Recent
+    #[serde(default)]
+    pub recent: RecentList,
+}
+
+/// When documents with a file are saved without an explicit Save.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AutosavePolicy {
+    /// Idle time after the last edit, if saving after edits is on
+    pub after_idle: Option<Duration>,
+    pub on_blur: bool,
+    pub on_switch: bool,
+}
+
+/// Visibility of inline Markdown markers in the editor.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarkerVisibility {
+    /// Show markers only on the lines containing the cursor or selection
+    #[default]
+    Contextual,
+    /// Always show markers
+    Always,
+    /// Always hide markers
+    Never,
 }
 
 fn default_font_size() -> f32 {
     14.0
 }
 
+fn default_pdf_margin_mm() -> f32 {
+    PdfOptions::default().margin_mm
+}
+
+fn default_restore_session() -> bool {
+    true
+}
+
 impl Default for Settings {
     fn default() -> Self {
         Self {
             font_size: default_font_size(),
+            vim_mode: false,
+            marker_visibility: MarkerVisibility::default(),
+            export_css: None,
+            pdf_page_size: PageSize::default(),
+            pdf_margin_mm: default_pdf_margin_mm(),
+            autosave_after_secs: 0,
+            autosave_on_blur: false,
+            autosave_on_switch: false,
+            restore_session: default_restore_session(),
+            recent: RecentList::default(),
         }
     }
 }
@@ -119,3 +192,85 @@ pub fn set_font_size(size: f32) {
         manager.update(|s| s.font_size = clamped);
     }
 }
+
+/// Convenience function to check whether Vim mode is enabled
+pub fn get_vim_mode() -> bool {
+    settings().lock().map(|s| s.get().vim_mode).unwrap_or(false)
+}
+
+/// Convenience function to enable or disable Vim mode
+pub fn set_vim_mode(enabled: bool) {
+    if let Ok(mut manager) = settings().lock() {
+        manager.update(|s| s.vim_mode = enabled);
+    }
+}
+
+/// Convenience function to get the inline marker visibility
+pub fn get_marker_visibility() -> MarkerVisibility {
+    settings()
+        .lock()
+        .map(|s| s.get().marker_visibility)
+        .unwrap_or_default()
+}
+
+/// Convenience function to set the inline marker visibility
+pub fn set_marker_visibility(visibility: MarkerVisibility) {
+    if let Ok(mut manager) = settings().lock() {
+        manager.update(|s| s.marker_visibility = visibility);
+    }
+}
+
+/// Convenience function to get the custom HTML export stylesheet, if any
+pub fn get_export_css() -> Option<PathBuf> {
+    settings()
+        .lock()
+        .ok()
+        .and_then(|s| s.get().export_css.clone())
+}
+
+/// Convenience function to get the page setup for PDF exports
+pub fn get_pdf_options() -> PdfOptions {
+    settings()
+        .lock()
+        .map(|s| PdfOptions {
+            page_size: s.get().pdf_page_size,
+            margin_mm: s.get().pdf_margin_mm,
+        })
+        .unwrap_or_default()
+}
+
+/// Convenience function to get when documents are saved automatically
+pub fn get_autosave_policy() -> AutosavePolicy {
+    settings()
+        .lock()
+        .map(|s| AutosavePolicy {
+            after_idle: (s.get().autosave_after_secs > 0)
+                .then(|| Duration::from_secs(s.get().autosave_after_secs)),
+            on_blur: s.get().autosave_on_blur,
+            on_switch: s.get().autosave_on_switch,
+        })
+        .unwrap_or_default()
+}
+
+/// Convenience function to check whether the last session is reopened on launch
+pub fn get_restore_session() -> bool {
+    settings()
+        .lock()
+        .map(|s| s.get().restore_session)
+        .unwrap_or(true)
+}
+
+/// Convenience function to get the recently opened files and folders
+pub fn get_recent() -> RecentList {
+    settings()
+        .lock()
+        .map(|s| s.get().recent.clone())
+        .unwrap_or_default()
+}
+
+/// Convenience function to change the recently opened files and folders
+pub fn update_recent(f: impl FnOnce(&mut RecentList)) {
+    if let Ok(mut manager) = settings().lock() {
+        manager.update(|s| f(&mut s.recent));
+    }
+}
@@ -0,0 +1,109 @@
+use crate::services::markdown_ast::{Block, BlockKind, Inline, parse_markdown};
+use serde::Serialize;
+
+/// Reading speed used for [`DocumentStats::reading_minutes`].
+const WORDS_PER_MINUTE: usize = 200;
+
+/// Size and structure counts for a Markdown document.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct DocumentStats {
+    /// Whitespace-separated words, counted like the editor's status bar.
+    pub words: usize,
+    pub characters: usize,
+    pub lines: usize,
+    pub headings: usize,
+    pub links: usize,
+    pub images: usize,
+    pub code_blocks: usize,
+    pub tables: usize,
+    /// Estimated reading time, rounded up.
+    pub reading_minutes: usize,
+}
+
+pub fn document_stats(source: &str) -> DocumentStats {
+    let words = source.split_whitespace().count();
+    let mut stats = DocumentStats {
+        words,
+        characters: source.chars().count(),
+        lines: source.lines().count(),
+        reading_minutes: words.div_ceil(WORDS_PER_MINUTE),
+        ..DocumentStats::default()
+    };
+    count_blocks(&parse_markdown(source).blocks, &mut stats);
+    stats
+}
+
+fn count_blocks(blocks: &[Block], stats: &mut DocumentStats) {
+    for block in blocks {
+        match &block.kind {
+            BlockKind::Paragraph(inlines) => count_inlines(inlines, stats),
+            BlockKind::Heading { content, .. } => {
+                stats.headings += 1;
+                count_inlines(content, stats);
+            }
+            BlockKind::BlockQuote(children)
+            | BlockKind::FootnoteDefinition {
+                blocks: children, ..
+            } => {
+                count_blocks(children, stats);
+            }
+            BlockKind::List { items, .. } => {
+                for item in items {
+                    count_blocks(&item.blocks, stats);
+                }
+            }
+            BlockKind::CodeBlock { .. } => stats.code_blocks += 1,
+            BlockKind::Table { header, rows, .. } => {
+                stats.tables += 1;
+                for cell in header.iter().chain(rows.iter().flatten()) {
+                    count_inlines(cell, stats);
+                }
+            }
+            BlockKind::Rule | BlockKind::Html(_) => {}
+        }
+    }
+}
+
+fn count_inlines(inlines: &[Inline], stats: &mut DocumentStats) {
+    for inline in inlines {
+        match inline {
+            Inline::Link { content, .. } => {
+                stats.links += 1;
+                count_inlines(content, stats);
+            }
+            Inline::Image { .. } => stats.images += 1,
+            Inline::Emphasis(children)
+            | Inline::Strong(children)
+            | Inline::Strikethrough(children) => {
+                count_inlines(children, stats);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_structure_and_reading_time() {
+        let source = "# Title\n\nSee [a](x) and *[b](y)*.\n\n> ![img](i.png)\n\n\
+                      ```\ncode\n```\n\n| h |\n|---|\n| [c](z) |\n";
+        let stats = document_stats(source);
+        assert_eq!(
+            stats,
+            DocumentStats {
+                words: 18,
+                characters: source.chars().count(),
+                lines: 13,
+                headings: 1,
+                links: 3,
+                images: 1,
+                code_blocks: 1,
+                tables: 1,
+                reading_minutes: 1,
+            }
+        );
+    }
+}
@@ -1,3 +1,7 @@
+use pulldown_cmark::{
+    BrokenLink, CodeBlockKind, CowStr, Event, LinkType, Options, Parser, Tag, TagEnd,
+};
+use std::collections::HashSet;
 use std::ops::Range;
 
 /// Semantic token categories for lightweight Markdown syntax highlighting.
@@ -9,6 +13,7 @@ pub enum SyntaxKind {
     ListMarker,
     TaskMarker,
     CodeFence,
+    CodeBlock,
     InlineCodeMarker,
     InlineCode,
     LinkTextDelimiter,
@@ -18,287 +23,467 @@ pub enum SyntaxKind {
     EmphasisMarker,
     EmphasisText,
     StrongText,
+    StrikethroughText,
+    EscapeMarker,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct SyntaxSpan {
     pub range: Range<usize>,
     pub kind: SyntaxKind,
 }
 
-/// Scans Markdown source and returns byte-range spans for syntax highlighting.
+/// Spans of one top-level Markdown block, the unit of incremental re-parsing.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BlockSpans {
+    pub range: Range<usize>,
+    pub spans: Vec<SyntaxSpan>,
+    /// Normalized labels of link reference definitions inside the block.
+    pub definitions: Vec<String>,
+}
+
+impl BlockSpans {
+    /// Re-bases a block that lies after an edit so document offset `from` maps to `to`.
+    pub fn shifted(&self, from: usize, to: usize) -> Self {
+        let shift = |pos: usize| pos - from + to;
+        Self {
+            range: shift(self.range.start)..shift(self.range.end),
+            spans: self
+                .spans
+                .iter()
+                .map(|span| SyntaxSpan {
+                    range: shift(span.range.start)..shift(span.range.end),
+                    kind: span.kind,
+                })
+                .collect(),
+            definitions: self.definitions.clone(),
+        }
+    }
+}
+
+/// Parses Markdown source and returns byte-range spans for syntax highlighting.
 ///
-/// The scanner is intentionally lightweight and single-pass by line to keep
-/// editor rendering responsive for large files.
+/// Spans come from pulldown-cmark's offset iterator, so they follow CommonMark
+/// (plus GFM tables, task lists and strikethrough) rather than per-line
+/// guesses. Spans are sorted by start offset; inline spans may nest inside
+/// each other (e.g. emphasis within link text).
 pub fn markdown_spans(source: &str) -> Vec<SyntaxSpan> {
-    let mut spans = Vec::new();
-    let mut offset = 0usize;
-
-    for raw_line in source.split_inclusive('\n') {
-        let line = raw_line.trim_end_matches('\n').trim_end_matches('\r');
-        let line_len = line.len();
-        if line_len == 0 {
-            offset += raw_line.len();
-            continue;
+    flatten_blocks(&markdown_blocks(source, 0, &HashSet::new()))
+}
+
+/// Concatenates block spans back into one document-ordered list.
+pub fn flatten_blocks(blocks: &[BlockSpans]) -> Vec<SyntaxSpan> {
+    blocks
+        .iter()
+        .flat_map(|block| block.spans.iter().cloned())
+        .collect()
+}
+
+/// Parses `source` into top-level blocks with their spans.
+///
+/// `source` may be a slice of a larger document starting at byte `offset`;
+/// all returned ranges are document offsets. Reference links whose label is
+/// in `external_labels` (defined outside the slice) resolve as links.
+pub fn markdown_blocks(
+    source: &str,
+    offset: usize,
+    external_labels: &HashSet<String>,
+) -> Vec<BlockSpans> {
+    let mut builder = SpanBuilder {
+        source,
+        spans: Vec::new(),
+    };
+    let mut block_ranges: Vec<Range<usize>> = Vec::new();
+    let mut depth = 0usize;
+    let mut pending_item: Option<Range<usize>> = None;
+    let mut links: Vec<LinkFrame> = Vec::new();
+    let mut quote_depth = 0usize;
+    let mut in_code_block = false;
+
+    let resolve_external = |link: BrokenLink<'_>| {
+        external_labels
+            .contains(&normalize_label(&link.reference))
+            .then_some((CowStr::Borrowed(""), CowStr::Borrowed("")))
+    };
+    let parser =
+        Parser::new_with_broken_link_callback(source, parser_options(), Some(resolve_external));
+    let mut events = parser.into_offset_iter();
+
+    for (event, range) in events.by_ref() {
+        match &event {
+            Event::Start(_) => {
+                if depth == 0 {
+                    block_ranges.push(range.clone());
+                }
+                depth += 1;
+            }
+            Event::End(_) => depth = depth.saturating_sub(1),
+            _ if depth == 0 => block_ranges.push(range.clone()),
+            _ => {}
         }
 
-        let leading = leading_whitespace_bytes(line);
-        let content = &line[leading..];
-        let content_start = offset + leading;
-        let mut skip_inline = false;
-
-        if let Some(fence_len) = fence_prefix_len(content) {
-            spans.push(SyntaxSpan {
-                range: content_start..(content_start + fence_len),
-                kind: SyntaxKind::CodeFence,
-            });
-            if content.len() > fence_len {
-                spans.push(SyntaxSpan {
-                    range: (content_start + fence_len)..(content_start + content.len()),
-                    kind: SyntaxKind::CodeFence,
-                });
+        // A list item's marker is only known to be a task marker once the next event arrives.
+        if let Some(item) = pending_item.take() {
+            if let Event::TaskListMarker(_) = event {
+                builder.task_marker(item.start, range.end);
+                continue;
             }
-            skip_inline = true;
+            builder.list_marker(item.start);
+        }
+
+        if let Some(link) = links.last_mut()
+            && range != link.range
+        {
+            link.text_end = Some(link.text_end.unwrap_or(0).max(range.end));
         }
 
-        if !skip_inline {
-            if let Some((marker_len, _hashes)) = heading_prefix(content) {
-                spans.push(SyntaxSpan {
-                    range: content_start..(content_start + marker_len),
-                    kind: SyntaxKind::HeadingMarker,
-                });
-                let text_start = content_start + marker_len;
-                if text_start < content_start + content.len() {
-                    spans.push(SyntaxSpan {
-                        range: text_start..(content_start + content.len()),
-                        kind: SyntaxKind::HeadingText,
-                    });
+        match event {
+            Event::Start(Tag::Heading { .. }) => builder.heading(range),
+            Event::Start(Tag::BlockQuote(_)) => {
+                if quote_depth == 0 {
+                    builder.quote_markers(range);
                 }
+                quote_depth += 1;
             }
-
-            if content.starts_with('>') {
-                spans.push(SyntaxSpan {
-                    range: content_start..(content_start + 1),
-                    kind: SyntaxKind::QuoteMarker,
-                });
+            Event::End(TagEnd::BlockQuote(_)) => quote_depth = quote_depth.saturating_sub(1),
+            Event::Start(Tag::Item) => pending_item = Some(range),
+            Event::Start(Tag::CodeBlock(kind)) => {
+                in_code_block = true;
+                builder.code_block(range, matches!(kind, CodeBlockKind::Fenced(_)));
             }
-
-            if let Some(marker_len) = task_marker_len(content) {
-                spans.push(SyntaxSpan {
-                    range: content_start..(content_start + marker_len),
-                    kind: SyntaxKind::TaskMarker,
-                });
-            } else if let Some(marker_len) = list_marker_len(content) {
-                spans.push(SyntaxSpan {
-                    range: content_start..(content_start + marker_len),
-                    kind: SyntaxKind::ListMarker,
-                });
+            Event::End(TagEnd::CodeBlock) => in_code_block = false,
+            Event::Code(_) => builder.inline_code(range),
+            Event::Start(Tag::Emphasis) => builder.delimited(range, SyntaxKind::EmphasisText),
+            Event::Start(Tag::Strong) => builder.delimited(range, SyntaxKind::StrongText),
+            Event::Start(Tag::Strikethrough) => {
+                builder.delimited(range, SyntaxKind::StrikethroughText)
             }
+            Event::Start(Tag::Link { link_type, .. }) => links.push(LinkFrame {
+                range,
+                link_type,
+                image: false,
+                text_end: None,
+            }),
+            Event::Start(Tag::Image { link_type, .. }) => links.push(LinkFrame {
+                range,
+                link_type,
+                image: true,
+                text_end: None,
+            }),
+            Event::End(TagEnd::Link | TagEnd::Image) => {
+                if let Some(link) = links.pop() {
+                    builder.link(link);
+                }
+            }
+            Event::Text(_) if !in_code_block => builder.escape(range.start),
+            _ => {}
+        }
+    }
 
-            scan_inline(line, offset, &mut spans);
+    // Definitions emit no events; ones outside any block become blocks of their own.
+    let mut definitions: Vec<(usize, String)> = events
+        .reference_definitions()
+        .iter()
+        .map(|(label, def)| (def.span.start, normalize_label(label)))
+        .collect();
+    definitions.sort();
+    for (start, _) in &definitions {
+        if !block_ranges.iter().any(|range| range.contains(start)) {
+            let end = builder.line_end(*start);
+            block_ranges.push(*start..end);
         }
+    }
+    block_ranges.sort_by_key(|range| range.start);
+
+    let mut spans = builder.spans;
+    spans.sort_by_key(|span| (span.range.start, span.range.end));
 
-        offset += raw_line.len();
+    let mut blocks: Vec<BlockSpans> = block_ranges
+        .into_iter()
+        .map(|range| BlockSpans {
+            range: range.start + offset..range.end + offset,
+            spans: Vec::new(),
+            definitions: Vec::new(),
+        })
+        .collect();
+    let block_for = |pos: usize| {
+        blocks
+            .partition_point(|block| block.range.start <= pos + offset)
+            .saturating_sub(1)
+    };
+    let span_blocks: Vec<usize> = spans
+        .iter()
+        .map(|span| block_for(span.range.start))
+        .collect();
+    let definition_blocks: Vec<usize> = definitions
+        .iter()
+        .map(|(start, _)| block_for(*start))
+        .collect();
+    for (mut span, ix) in spans.into_iter().zip(span_blocks) {
+        span.range = span.range.start + offset..span.range.end + offset;
+        blocks[ix].spans.push(span);
+    }
+    for ((_, label), ix) in definitions.into_iter().zip(definition_blocks) {
+        blocks[ix].definitions.push(label);
     }
+    blocks
+}
 
-    spans
+/// Normalizes a link label for matching: case-folded with whitespace collapsed.
+pub fn normalize_label(label: &str) -> String {
+    label
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
 }
 
-fn leading_whitespace_bytes(line: &str) -> usize {
-    line.char_indices()
-        .find_map(|(idx, ch)| if ch.is_whitespace() { None } else { Some(idx) })
-        .unwrap_or(line.len())
+fn parser_options() -> Options {
+    Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS
 }
 
-fn fence_prefix_len(content: &str) -> Option<usize> {
-    if content.starts_with("```") {
-        Some(3)
-    } else if content.starts_with("~~~") {
-        Some(3)
-    } else {
-        None
-    }
+/// A link or image whose delimiters are resolved once its end event arrives.
+struct LinkFrame {
+    range: Range<usize>,
+    link_type: LinkType,
+    image: bool,
+    /// End of the last event nested in the link text.
+    text_end: Option<usize>,
 }
 
-fn heading_prefix(content: &str) -> Option<(usize, usize)> {
-    let bytes = content.as_bytes();
-    let mut i = 0usize;
-    while i < bytes.len() && bytes[i] == b'#' {
-        i += 1;
-    }
-    let hash_count = i;
-    if hash_count == 0 || hash_count > 6 {
-        return None;
-    }
-    if i < bytes.len() && bytes[i].is_ascii_whitespace() {
-        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
-            i += 1;
-        }
-        return Some((i, hash_count));
-    }
-    None
+struct SpanBuilder<'a> {
+    source: &'a str,
+    spans: Vec<SyntaxSpan>,
 }
 
-fn task_marker_len(content: &str) -> Option<usize> {
-    let bytes = content.as_bytes();
-    if bytes.len() < 6 {
-        return None;
+impl SpanBuilder<'_> {
+    fn push(&mut self, range: Range<usize>, kind: SyntaxKind) {
+        if range.start < range.end {
+            self.spans.push(SyntaxSpan { range, kind });
+        }
     }
-    if (bytes[0] == b'-' || bytes[0] == b'*' || bytes[0] == b'+')
-        && bytes[1] == b' '
-        && bytes[2] == b'['
-        && (bytes[3] == b' ' || bytes[3] == b'x' || bytes[3] == b'X')
-        && bytes[4] == b']'
-        && bytes[5].is_ascii_whitespace()
-    {
-        return Some(6);
+
+    /// End of the line containing `pos`, excluding the line break.
+    fn line_end(&self, pos: usize) -> usize {
+        let end = self.source[pos..]
+            .find('\n')
+            .map_or(self.source.len(), |ix| pos + ix);
+        if end > pos && self.source.as_bytes()[end - 1] == b'\r' {
+            end - 1
+        } else {
+            end
+        }
     }
-    None
-}
 
-fn list_marker_len(content: &str) -> Option<usize> {
-    let bytes = content.as_bytes();
-    if bytes.len() >= 2
-        && (bytes[0] == b'-' || bytes[0] == b'*' || bytes[0] == b'+')
-        && bytes[1].is_ascii_whitespace()
-    {
-        return Some(2);
+    /// Shrinks `end` so the range stops before trailing whitespace and line breaks.
+    fn trim_end(&self, start: usize, end: usize) -> usize {
+        start + self.source[start..end].trim_end().len()
     }
 
-    let mut i = 0usize;
-    while i < bytes.len() && bytes[i].is_ascii_digit() {
-        i += 1;
+    fn skip_blanks(&self, pos: usize, end: usize) -> usize {
+        pos + self.source[pos..end]
+            .bytes()
+            .take_while(|b| *b == b' ' || *b == b'\t')
+            .count()
     }
-    if i > 0 && i + 1 < bytes.len() && bytes[i] == b'.' && bytes[i + 1].is_ascii_whitespace() {
-        return Some(i + 2);
+
+    fn heading(&mut self, range: Range<usize>) {
+        let end = self.trim_end(range.start, range.end);
+        if self.source[range.start..].starts_with('#') {
+            let hashes = self.source[range.start..end]
+                .bytes()
+                .take_while(|b| *b == b'#')
+                .count();
+            let text_start = self.skip_blanks(range.start + hashes, end);
+            self.push(range.start..text_start, SyntaxKind::HeadingMarker);
+
+            // Optional closing sequence: `# Title ##`.
+            let text = &self.source[text_start..end];
+            let without_hashes = text.trim_end_matches('#');
+            let mut text_end = end;
+            if without_hashes.len() < text.len()
+                && (without_hashes.is_empty() || without_hashes.ends_with([' ', '\t']))
+            {
+                text_end = self.trim_end(text_start, text_start + without_hashes.len());
+                self.push(text_end..end, SyntaxKind::HeadingMarker);
+            }
+            self.push(text_start..text_end, SyntaxKind::HeadingText);
+        } else {
+            // Setext heading: text lines followed by a `===` / `---` underline.
+            let underline_start = self.source[range.start..end]
+                .rfind('\n')
+                .map_or(range.start, |ix| range.start + ix + 1);
+            let marker_start = self.skip_blanks(underline_start, end);
+            let text_end = self.trim_end(range.start, underline_start);
+            self.push(range.start..text_end, SyntaxKind::HeadingText);
+            self.push(marker_start..end, SyntaxKind::HeadingMarker);
+        }
     }
-    None
-}
 
-fn scan_inline(line: &str, line_start: usize, spans: &mut Vec<SyntaxSpan>) {
-    let bytes = line.as_bytes();
-    let mut i = 0usize;
-
-    while i < bytes.len() {
-        match bytes[i] {
-            b'`' => {
-                if let Some(close) = find_next_byte(bytes, i + 1, b'`') {
-                    spans.push(SyntaxSpan {
-                        range: (line_start + i)..(line_start + i + 1),
-                        kind: SyntaxKind::InlineCodeMarker,
-                    });
-                    if close > i + 1 {
-                        spans.push(SyntaxSpan {
-                            range: (line_start + i + 1)..(line_start + close),
-                            kind: SyntaxKind::InlineCode,
-                        });
-                    }
-                    spans.push(SyntaxSpan {
-                        range: (line_start + close)..(line_start + close + 1),
-                        kind: SyntaxKind::InlineCodeMarker,
-                    });
-                    i = close + 1;
-                    continue;
-                }
+    fn quote_markers(&mut self, range: Range<usize>) {
+        let mut line_start = range.start;
+        while line_start < range.end {
+            let line_end = self.line_end(line_start).min(range.end);
+            let mut pos = self.skip_blanks(line_start, line_end);
+            while pos < line_end && self.source.as_bytes()[pos] == b'>' {
+                self.push(pos..pos + 1, SyntaxKind::QuoteMarker);
+                pos = self.skip_blanks(pos + 1, line_end);
             }
-            b'[' => {
-                if let Some(close_bracket) = find_next_byte(bytes, i + 1, b']') {
-                    let open_paren = close_bracket + 1;
-                    if open_paren < bytes.len()
-                        && bytes[open_paren] == b'('
-                        && let Some(close_paren) = find_next_byte(bytes, open_paren + 1, b')')
-                    {
-                        spans.push(SyntaxSpan {
-                            range: (line_start + i)..(line_start + i + 1),
-                            kind: SyntaxKind::LinkTextDelimiter,
-                        });
-                        if close_bracket > i + 1 {
-                            spans.push(SyntaxSpan {
-                                range: (line_start + i + 1)..(line_start + close_bracket),
-                                kind: SyntaxKind::LinkText,
-                            });
-                        }
-                        spans.push(SyntaxSpan {
-                            range: (line_start + close_bracket)..(line_start + close_bracket + 1),
-                            kind: SyntaxKind::LinkTextDelimiter,
-                        });
-                        spans.push(SyntaxSpan {
-                            range: (line_start + open_paren)..(line_start + open_paren + 1),
-                            kind: SyntaxKind::LinkUrlDelimiter,
-                        });
-                        if close_paren > open_paren + 1 {
-                            spans.push(SyntaxSpan {
-                                range: (line_start + open_paren + 1)..(line_start + close_paren),
-                                kind: SyntaxKind::LinkUrl,
-                            });
-                        }
-                        spans.push(SyntaxSpan {
-                            range: (line_start + close_paren)..(line_start + close_paren + 1),
-                            kind: SyntaxKind::LinkUrlDelimiter,
-                        });
-                        i = close_paren + 1;
-                        continue;
-                    }
-                }
+            line_start = match self.source[line_end..range.end].find('\n') {
+                Some(ix) => line_end + ix + 1,
+                None => break,
+            };
+        }
+    }
+
+    fn list_marker(&mut self, start: usize) {
+        let end = self.line_end(start);
+        let bytes = self.source.as_bytes();
+        let mut marker_end = start;
+        if matches!(bytes.get(start), Some(b'-' | b'*' | b'+')) {
+            marker_end += 1;
+        } else {
+            while marker_end < end && bytes[marker_end].is_ascii_digit() {
+                marker_end += 1;
             }
-            b'*' | b'_' => {
-                let marker = bytes[i];
-                let marker_len = if i + 1 < bytes.len() && bytes[i + 1] == marker {
-                    2
-                } else {
-                    1
-                };
-                if let Some(close) = find_emphasis_close(bytes, i + marker_len, marker, marker_len)
-                {
-                    spans.push(SyntaxSpan {
-                        range: (line_start + i)..(line_start + i + marker_len),
-                        kind: SyntaxKind::EmphasisMarker,
-                    });
-                    if close > i + marker_len {
-                        spans.push(SyntaxSpan {
-                            range: (line_start + i + marker_len)..(line_start + close),
-                            kind: if marker_len == 2 {
-                                SyntaxKind::StrongText
-                            } else {
-                                SyntaxKind::EmphasisText
-                            },
-                        });
-                    }
-                    spans.push(SyntaxSpan {
-                        range: (line_start + close)..(line_start + close + marker_len),
-                        kind: SyntaxKind::EmphasisMarker,
-                    });
-                    i = close + marker_len;
-                    continue;
-                }
+            if matches!(bytes.get(marker_end), Some(b'.' | b')')) {
+                marker_end += 1;
             }
-            _ => {}
         }
-        i += 1;
+        let marker_end = self.skip_blanks(marker_end, end);
+        self.push(start..marker_end, SyntaxKind::ListMarker);
     }
-}
 
-fn find_next_byte(bytes: &[u8], start: usize, needle: u8) -> Option<usize> {
-    bytes[start..]
-        .iter()
-        .position(|b| *b == needle)
-        .map(|pos| start + pos)
-}
+    fn task_marker(&mut self, item_start: usize, checkbox_end: usize) {
+        let end = self.line_end(checkbox_end);
+        let marker_end = self.skip_blanks(checkbox_end, end).min(checkbox_end + 1);
+        self.push(item_start..marker_end, SyntaxKind::TaskMarker);
+    }
 
-fn find_emphasis_close(bytes: &[u8], start: usize, marker: u8, marker_len: usize) -> Option<usize> {
-    let mut i = start;
-    while i + marker_len <= bytes.len() {
-        if bytes[i] == marker {
-            if marker_len == 1 {
-                return Some(i);
-            }
-            if i + 1 < bytes.len() && bytes[i + 1] == marker {
-                return Some(i);
-            }
+    fn code_block(&mut self, range: Range<usize>, fenced: bool) {
+        let end = self.trim_end(range.start, range.end);
+        if !fenced {
+            self.push(range.start..end, SyntaxKind::CodeBlock);
+            return;
+        }
+
+        let open_end = self.line_end(range.start).min(end);
+        self.push(range.start..open_end, SyntaxKind::CodeFence);
+        if open_end >= end {
+            return;
+        }
+
+        let last_line = self.source[open_end..end]
+            .rfind('\n')
+            .map_or(open_end, |ix| open_end + ix + 1);
+        let fence = self.source.as_bytes()[range.start];
+        let close_start = self.skip_blanks(last_line, end);
+        let closed = last_line > open_end
+            && self.source[close_start..end].len() >= 3
+            && self.source[close_start..end].bytes().all(|b| b == fence);
+        let content_end = if closed { last_line } else { end };
+        let newline = if self.source[open_end..].starts_with("\r\n") {
+            2
+        } else {
+            1
+        };
+        let content_start = (open_end + newline).min(content_end);
+        let content_end = self.trim_end(content_start, content_end);
+        self.push(content_start..content_end, SyntaxKind::CodeBlock);
+        if closed {
+            self.push(close_start..end, SyntaxKind::CodeFence);
+        }
+    }
+
+    fn inline_code(&mut self, range: Range<usize>) {
+        let ticks = self.source[range.clone()]
+            .bytes()
+            .take_while(|b| *b == b'`')
+            .count();
+        let inner_end = range.end.saturating_sub(ticks).max(range.start + ticks);
+        self.push(
+            range.start..range.start + ticks,
+            SyntaxKind::InlineCodeMarker,
+        );
+        self.push(range.start + ticks..inner_end, SyntaxKind::InlineCode);
+        self.push(inner_end..range.end, SyntaxKind::InlineCodeMarker);
+    }
+
+    /// Emphasis, strong and strikethrough: a run of one delimiter character at each end.
+    fn delimited(&mut self, range: Range<usize>, kind: SyntaxKind) {
+        let bytes = self.source.as_bytes();
+        let delimiter = bytes[range.start];
+        let max = if kind == SyntaxKind::EmphasisText {
+            1
+        } else {
+            2
+        };
+        let len = bytes[range.clone()]
+            .iter()
+            .take_while(|b| **b == delimiter)
+            .count()
+            .min(max);
+        if range.len() < len * 2 {
+            return;
+        }
+        self.push(range.start..range.start + len, SyntaxKind::EmphasisMarker);
+        self.push(range.start + len..range.end - len, kind);
+        self.push(range.end - len..range.end, SyntaxKind::EmphasisMarker);
+    }
+
+    fn link(&mut self, link: LinkFrame) {
+        let range = link.range;
+        if matches!(link.link_type, LinkType::Autolink | LinkType::Email) {
+            // `<https://example.com>`
+            self.push(range.start..range.start + 1, SyntaxKind::LinkTextDelimiter);
+            self.push(range.start + 1..range.end - 1, SyntaxKind::LinkText);
+            self.push(range.end - 1..range.end, SyntaxKind::LinkTextDelimiter);
+            return;
+        }
+
+        let text_start = range.start + if link.image { 2 } else { 1 };
+        let search_from = link.text_end.unwrap_or(text_start).max(text_start);
+        let Some(close) = self.source[search_from..range.end]
+            .find(']')
+            .map(|ix| search_from + ix)
+        else {
+            return;
+        };
+        self.push(range.start..text_start, SyntaxKind::LinkTextDelimiter);
+        self.push(text_start..close, SyntaxKind::LinkText);
+        self.push(close..close + 1, SyntaxKind::LinkTextDelimiter);
+
+        // Destination part: `(url "title")` for inline links, `[label]` for full references.
+        // Collapsed references (`[foo][]`) end before their empty `[]`.
+        let mut rest = close + 1..range.end;
+        if matches!(
+            link.link_type,
+            LinkType::Collapsed | LinkType::CollapsedUnknown
+        ) && rest.is_empty()
+            && self.source[rest.end..].starts_with("[]")
+        {
+            rest.end += 2;
+        }
+        if rest.len() >= 2 {
+            self.push(rest.start..rest.start + 1, SyntaxKind::LinkUrlDelimiter);
+            self.push(rest.start + 1..rest.end - 1, SyntaxKind::LinkUrl);
+            self.push(rest.end - 1..rest.end, SyntaxKind::LinkUrlDelimiter);
+        }
+    }
+
+    /// Marks the backslash of an escape such as `\*` that precedes a text event.
+    fn escape(&mut self, text_start: usize) {
+        let bytes = self.source.as_bytes();
+        let escaped = bytes.get(text_start).is_some_and(u8::is_ascii_punctuation);
+        let backslashes = bytes[..text_start]
+            .iter()
+            .rev()
+            .take_while(|b| **b == b'\\')
+            .count();
+        if escaped && backslashes % 2 == 1 {
+            self.push(text_start - 1..text_start, SyntaxKind::EscapeMarker);
         }
-        i += 1;
     }
-    None
 }
 
 #[cfg(test)]
@@ -349,4 +534,34 @@ dn’t require a patchwork of vendors.
         assert!(spans.iter().any(|s| s.kind == SyntaxKind::EmphasisText));
         assert!(spans.iter().any(|s| s.kind == SyntaxKind::StrongText));
     }
+
+    /// Renders spans as `Kind "text"` lines, one per span.
+    fn describe_spans(source: &str) -> String {
+        markdown_spans(source)
+            .iter()
+            .map(|span| format!("{:?} {:?}\n", span.kind, &source[span.range.clone()]))
+            .collect()
+    }
+
+    #[test]
+    fn commonmark_corpus() {
+        let corpus = include_str!("testdata/commonmark_spans.txt");
+        let mut failures = Vec::new();
+        let mut cases = 0;
+
+        for case in corpus.split("=== ").skip(1) {
+            let (title, rest) = case.split_once('\n').unwrap();
+            let (source, expected) = rest.split_once("--- spans\n").unwrap();
+            let actual = describe_spans(source);
+            if actual.trim_end() != expected.trim_end() {
+                failures.push(format!(
+                    "=== {title}\n{source}--- expected\n{expected}--- actual\n{actual}"
+                ));
+            }
+            cases += 1;
+        }
+
+        assert!(cases >= 40);
+        assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+    }
 }
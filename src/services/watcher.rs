@@ -0,0 +1,207 @@
+//! Watches the files open in a window, so changes other programs (git, sync
+//! clients, other editors) make to them reach the documents showing them.
+
+use camino::{Utf8Path, Utf8PathBuf};
+use futures::channel::mpsc::{UnboundedReceiver, unbounded};
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// What happened to a watched file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FileChange {
+    /// The file was written or replaced.
+    Modified(Utf8PathBuf),
+    /// The file was moved to `to`.
+    Renamed { from: Utf8PathBuf, to: Utf8PathBuf },
+    /// The file was deleted, or moved somewhere unknown.
+    Removed(Utf8PathBuf),
+}
+
+pub struct FileWatcher {
+    watcher: Option<RecommendedWatcher>,
+    /// Document paths being watched.
+    paths: HashSet<Utf8PathBuf>,
+    /// The same files as events name them, mapped back to the document path.
+    files: HashMap<PathBuf, Utf8PathBuf>,
+    /// Parent directories of `files`. Watching the directory rather than the
+    /// file also catches files replaced by atomic saves.
+    dirs: HashSet<PathBuf>,
+}
+
+impl FileWatcher {
+    /// A watcher and the stream of events it reports. Without OS support the
+    /// watcher does nothing and the stream stays empty.
+    pub fn new() -> (Self, UnboundedReceiver<Event>) {
+        let (sender, receiver) = unbounded();
+        let watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            if let Ok(event) = event {
+                let _ = sender.unbounded_send(event);
+            }
+        })
+        .map_err(|err| tracing::warn!("file watching unavailable: {err}"))
+        .ok();
+        let watcher = Self {
+            watcher,
+            paths: HashSet::new(),
+            files: HashMap::new(),
+            dirs: HashSet::new(),
+        };
+        (watcher, receiver)
+    }
+
+    /// Watches exactly the files in `paths`.
+    pub fn set_files(&mut self, paths: HashSet<Utf8PathBuf>) {
+        if paths == self.paths {
+            return;
+        }
+        let files: HashMap<PathBuf, Utf8PathBuf> = paths
+            .iter()
+            .filter_map(|path| Some((resolve(path)?, path.clone())))
+            .collect();
+        let dirs: HashSet<PathBuf> = files
+            .keys()
+            .filter_map(|file| file.parent().map(Path::to_path_buf))
+            .collect();
+        if let Some(watcher) = &mut self.watcher {
+            for dir in self.dirs.difference(&dirs) {
+                let _ = watcher.unwatch(dir);
+            }
+            for dir in dirs.difference(&self.dirs) {
+                if let Err(err) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+                    tracing::warn!("can't watch {}: {err}", dir.display());
+                }
+            }
+        }
+        self.paths = paths;
+        self.files = files;
+        self.dirs = dirs;
+    }
+
+    /// Changes to watched files among `events`, once per file and kind.
+    pub fn changes(&self, events: &[Event]) -> Vec<FileChange> {
+        let mut changes = Vec::new();
+        for event in events {
+            match (&event.kind, &event.paths[..]) {
+                (EventKind::Access(_), _) => {}
+                (EventKind::Modify(ModifyKind::Name(RenameMode::Both)), [from, to]) => {
+                    if let Some(path) = self.files.get(from)
+                        && let Ok(to) = Utf8PathBuf::try_from(to.clone())
+                    {
+                        changes.push(FileChange::Renamed {
+                            from: path.clone(),
+                            to,
+                        });
+                    }
+                    // Moving another file onto a watched one replaces it.
+                    if let Some(path) = self.files.get(to) {
+                        changes.push(FileChange::Modified(path.clone()));
+                    }
+                }
+                (_, paths) => {
+                    for file in paths {
+                        if let Some(path) = self.files.get(file) {
+                            // Judge by what is there now: a delete followed
+                            // by a create is a replacement.
+                            changes.push(if file.exists() {
+                                FileChange::Modified(path.clone())
+                            } else {
+                                FileChange::Removed(path.clone())
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        // The old name of a renamed file is also reported on its own.
+        let renamed: Vec<Utf8PathBuf> = changes
+            .iter()
+            .filter_map(|change| match change {
+                FileChange::Renamed { from, .. } => Some(from.clone()),
+                _ => None,
+            })
+            .collect();
+        changes.retain(
+            |change| !matches!(change, FileChange::Removed(path) if renamed.contains(path)),
+        );
+        let mut seen = Vec::new();
+        changes.retain(|change| {
+            let new = !seen.contains(change);
+            seen.push(change.clone());
+            new
+        });
+        changes
+    }
+}
+
+/// `path` the way events name it: absolute, with symlinked directories
+/// resolved.
+fn resolve(path: &Utf8Path) -> Option<PathBuf> {
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_str().is_empty())
+        .unwrap_or(Utf8Path::new("."));
+    Some(dir.canonicalize().ok()?.join(path.file_name()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, DataChange, RemoveKind};
+
+    #[test]
+    fn reports_changes_to_watched_files_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = Utf8PathBuf::try_from(dir.path().canonicalize().unwrap()).unwrap();
+        let (note, other, moved) = (
+            root.join("note.md"),
+            root.join("other.md"),
+            root.join("moved.md"),
+        );
+        std::fs::write(&note, "# Note").unwrap();
+        std::fs::write(&other, "# Other").unwrap();
+
+        let (mut watcher, _events) = FileWatcher::new();
+        watcher.set_files(HashSet::from([note.clone()]));
+        let event = |kind, paths: &[&Utf8PathBuf]| {
+            paths.iter().fold(Event::new(kind), |event, path| {
+                event.add_path(path.as_std_path().to_path_buf())
+            })
+        };
+
+        let modified = event(
+            EventKind::Modify(ModifyKind::Data(DataChange::Content)),
+            &[&note],
+        );
+        let untouched = event(EventKind::Create(CreateKind::File), &[&other]);
+        assert_eq!(
+            watcher.changes(&[modified.clone(), modified, untouched]),
+            vec![FileChange::Modified(note.clone())]
+        );
+
+        std::fs::rename(&note, &moved).unwrap();
+        let renamed = [
+            event(
+                EventKind::Modify(ModifyKind::Name(RenameMode::From)),
+                &[&note],
+            ),
+            event(
+                EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+                &[&note, &moved],
+            ),
+        ];
+        assert_eq!(
+            watcher.changes(&renamed),
+            vec![FileChange::Renamed {
+                from: note.clone(),
+                to: moved.clone()
+            }]
+        );
+        assert_eq!(
+            watcher.changes(&[event(EventKind::Remove(RemoveKind::File), &[&note])]),
+            vec![FileChange::Removed(note)]
+        );
+    }
+}
@@ -1,16 +1,28 @@
-use crate::commands::{Copy, Cut, Find, FindNext, FindPrevious, Paste, Redo, SelectAll, Undo};
-use crate::model::document::DocumentState;
+use crate::commands::{
+    Copy, CopyAsPlainText, CopyAsRichText, Cut, Find, FindNext, FindPrevious, GoToHeading,
+    GoToLine, Paste, Redo, SelectAll, Undo,
+};
+use crate::model::document::{CursorState, DocumentState};
 use crate::model::inline_markdown::InlineMarkdownState;
-use crate::services::settings;
-use crate::services::syntax::{SyntaxKind, SyntaxSpan};
+use crate::services::clipboard;
+use crate::services::code_highlight::CodeTokenKind;
+use crate::services::export::html::render_html_fragment;
+use crate::services::images::{image_refs, resolve_local_image};
+use crate::services::settings::{self, MarkerVisibility};
+use crate::services::syntax::{SyntaxKind, SyntaxSpan, markdown_spans};
+use crate::ui::file_explorer::parse_outline_items;
+use crate::ui::quick_nav::{QuickNavKind, QuickNavState, line_column_to_char, parse_line_column};
 use crate::ui::text_utils::ellipsize_chars;
 use crate::ui::theme::Theme;
+use crate::ui::vim::{VimEffect, VimKey, VimMode, VimState};
 use gpui::prelude::FluentBuilder as _;
 use gpui::{
-    App, Bounds, ClipboardItem, Context, Entity, FocusHandle, Focusable, FontStyle, FontWeight,
-    HighlightStyle, InteractiveElement, IntoElement, KeyDownEvent, MouseButton, MouseDownEvent,
-    MouseMoveEvent, ParentElement, Render, ScrollHandle, StatefulInteractiveElement, Styled,
-    StyledText, UnderlineStyle, Window, canvas, combine_highlights, div, fill, point, px, size,
+    App, Bounds, ClipboardItem, Context, Corners, Entity, FocusHandle, Focusable, FontStyle,
+    FontWeight, HighlightStyle, ImgResourceLoader, InteractiveElement, IntoElement, KeyDownEvent,
+    MouseButton, MouseDownEvent, MouseMoveEvent, ParentElement, Pixels, Render, RenderImage,
+    Resource, ScrollHandle, Size, StatefulInteractiveElement, StrikethroughStyle, Styled,
+    StyledText, UnderlineStyle, Window, canvas, combine_highlights, div, fill, phi, point, px,
+    size,
 };
 use std::ops::Range;
 use std::panic::AssertUnwindSafe;
@@ -27,7 +39,22 @@ struct SearchCache {
 struct ProjectionSegment {
     source: Range<usize>,
     display_start: usize,
-    hidden: bool,
+    /// Equal to the source length for visible text, zero for hidden source
+    /// text, and the inserted length for display-only text (empty source).
+    display_len: usize,
+}
+
+impl ProjectionSegment {
+    fn is_verbatim(&self) -> bool {
+        self.display_len == self.source.len()
+    }
+}
+
+/// Display-only text inserted at a source position, e.g. room for an inline image.
+#[derive(Clone, Debug)]
+struct Insertion {
+    at: usize,
+    text: String,
 }
 
 #[derive(Clone, Debug)]
@@ -38,75 +65,76 @@ struct DisplayProjection {
 }
 
 impl DisplayProjection {
+    #[cfg(test)]
     fn from_source(source: &str, spans: &[SyntaxSpan]) -> Self {
-        let source_len = source.len();
-        let hidden = merged_hidden_ranges(spans);
-        if hidden.is_empty() {
-            return Self {
-                display_text: source.to_string(),
-                source_len,
-                segments: vec![ProjectionSegment {
-                    source: 0..source_len,
-                    display_start: 0,
-                    hidden: false,
-                }],
-            };
-        }
+        Self::build(source, merged_hidden_ranges(spans, &[]), &[])
+    }
 
-        let mut display_text = String::with_capacity(source_len);
-        let mut segments = Vec::new();
+    /// Projects `source` for display, dropping the `hidden` ranges (sorted and
+    /// disjoint) and adding `insertions` (sorted by position) as display-only text.
+    fn build(source: &str, hidden: Vec<Range<usize>>, insertions: &[Insertion]) -> Self {
+        let source_len = source.len();
+        let mut projection = Self {
+            display_text: String::with_capacity(source_len),
+            source_len,
+            segments: Vec::new(),
+        };
+        let mut insertions = insertions.iter().peekable();
         let mut source_ix = 0usize;
-        let mut display_ix = 0usize;
-
-        for hidden_range in hidden {
-            if hidden_range.start > source_ix {
-                let visible = source_ix..hidden_range.start;
-                let slice = &source[visible.clone()];
-                display_text.push_str(slice);
-                segments.push(ProjectionSegment {
-                    source: visible,
-                    display_start: display_ix,
-                    hidden: false,
-                });
-                display_ix += slice.len();
-            }
 
-            if hidden_range.end > hidden_range.start {
-                segments.push(ProjectionSegment {
-                    source: hidden_range.clone(),
-                    display_start: display_ix,
-                    hidden: true,
-                });
+        // A final empty range flushes the trailing text and insertions at the end.
+        for hidden_range in hidden
+            .into_iter()
+            .chain(std::iter::once(source_len..source_len))
+        {
+            while let Some(insertion) = insertions.next_if(|i| i.at <= hidden_range.start) {
+                let at = insertion.at.max(source_ix);
+                projection.push_visible(source, source_ix..at);
+                projection.push_segment(at..at, &insertion.text);
+                source_ix = at;
             }
-            source_ix = hidden_range.end;
+            projection.push_visible(source, source_ix..hidden_range.start);
+            projection.push_segment(hidden_range.clone(), "");
+            source_ix = source_ix.max(hidden_range.end);
         }
 
-        if source_ix < source_len {
-            let visible = source_ix..source_len;
-            let slice = &source[visible.clone()];
-            display_text.push_str(slice);
-            segments.push(ProjectionSegment {
-                source: visible,
-                display_start: display_ix,
-                hidden: false,
-            });
-        }
-
-        if segments.is_empty() {
-            segments.push(ProjectionSegment {
+        if projection.segments.is_empty() {
+            projection.segments.push(ProjectionSegment {
                 source: 0..0,
                 display_start: 0,
-                hidden: false,
+                display_len: 0,
             });
         }
+        projection
+    }
 
-        Self {
-            display_text,
-            source_len,
-            segments,
+    fn push_visible(&mut self, source: &str, range: Range<usize>) {
+        if range.start < range.end {
+            let text = &source[range.clone()];
+            self.push_segment(range, text);
         }
     }
 
+    fn push_segment(&mut self, source: Range<usize>, display: &str) {
+        if source.is_empty() && display.is_empty() {
+            return;
+        }
+        self.segments.push(ProjectionSegment {
+            source,
+            display_start: self.display_text.len(),
+            display_len: display.len(),
+        });
+        self.display_text.push_str(display);
+    }
+
+    /// Display ranges of the insertions, in insertion order.
+    fn inserted_ranges(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        self.segments
+            .iter()
+            .filter(|segment| segment.source.is_empty() && segment.display_len > 0)
+            .map(|segment| segment.display_start..segment.display_start + segment.display_len)
+    }
+
     fn source_to_display_byte(&self, source_byte: usize) -> usize {
         let source_byte = source_byte.min(self.source_len);
         let mut mapped = 0usize;
@@ -115,17 +143,13 @@ impl DisplayProjection {
                 return mapped;
             }
             if source_byte <= segment.source.end {
-                if segment.hidden {
+                if !segment.is_verbatim() {
                     return segment.display_start;
                 }
                 return segment.display_start + (source_byte - segment.source.start);
             }
 
-            mapped = if segment.hidden {
-                segment.display_start
-            } else {
-                segment.display_start + segment.source.len()
-            };
+            mapped = segment.display_start + segment.display_len;
         }
         self.display_text.len()
     }
@@ -136,12 +160,15 @@ impl DisplayProjection {
             if display_byte < segment.display_start {
                 return segment.source.start;
             }
-            if segment.hidden {
+            if segment.display_len == 0 {
                 continue;
             }
 
-            let display_end = segment.display_start + segment.source.len();
+            let display_end = segment.display_start + segment.display_len;
             if display_byte <= display_end {
+                if !segment.is_verbatim() {
+                    return segment.source.start;
+                }
                 return segment.source.start + (display_byte - segment.display_start);
             }
         }
@@ -183,6 +210,16 @@ pub struct EditorView {
     cached_search: Option<SearchCache>,
     /// Byte offset that should be revealed after next layout.
     pending_scroll_to_byte: Option<usize>,
+    /// Vim modal editing state, present only while Vim mode is enabled.
+    vim: Option<VimState>,
+    /// Go-to-line / go-to-heading overlay state.
+    quick_nav: Option<QuickNavState>,
+    /// Text layout, projection and scroll offset of the last frame, for scroll
+    /// synchronisation.
+    last_layout: Option<(gpui::TextLayout, Arc<DisplayProjection>, Pixels)>,
+    /// This pane's cursor while another pane on the same document is active
+    /// and owns `DocumentState::cursor`.
+    parked: Option<CursorState>,
 }
 
 impl EditorView {
@@ -203,9 +240,118 @@ impl EditorView {
             search_current_match: 0,
             cached_search: None,
             pending_scroll_to_byte: None,
+            vim: settings::get_vim_mode().then(VimState::new),
+            quick_nav: None,
+            last_layout: None,
+            parked: None,
+        }
+    }
+
+    /// Keeps this pane's cursor aside while another pane edits the document.
+    pub fn park(&mut self, cx: &mut Context<Self>) {
+        if self.parked.is_none() {
+            self.parked = Some(self.document.read(cx).cursor_state());
+            cx.notify();
         }
     }
 
+    /// Hands this pane's cursor back to the document as the pane becomes active.
+    pub fn unpark(&mut self, cx: &mut Context<Self>) {
+        if let Some(state) = self.parked.take() {
+            self.document
+                .update(cx, |doc, _| doc.restore_cursor_state(&state));
+            cx.notify();
+        }
+    }
+
+    /// Scrolls the cursor into view after the next layout.
+    pub fn scroll_to_cursor(&mut self, cx: &mut Context<Self>) {
+        let doc = self.document.read(cx);
+        self.pending_scroll_to_byte = Some(doc.char_to_byte(doc.cursor));
+        cx.notify();
+    }
+
+    /// This pane's cursor, whether or not it is parked.
+    pub fn cursor_state(&self, cx: &App) -> CursorState {
+        let doc = self.document.read(cx);
+        match &self.parked {
+            Some(parked) => doc.rebase_cursor_state(parked),
+            None => doc.cursor_state(),
+        }
+    }
+
+    /// Vertical scroll offset; negative once scrolled down.
+    pub fn scroll_offset(&self) -> Pixels {
+        self.scroll_handle.offset().y
+    }
+
+    /// Query of the find bar, if it is open.
+    pub fn find_query(&self) -> Option<&str> {
+        self.search_active.then_some(self.search_query.as_str())
+    }
+
+    /// Puts back the view of an earlier session. The cursor stays parked
+    /// until the pane becomes active.
+    pub fn restore_view(
+        &mut self,
+        cursor: CursorState,
+        scroll_y: Pixels,
+        find: Option<String>,
+        cx: &mut Context<Self>,
+    ) {
+        self.parked = Some(cursor);
+        self.scroll_handle.set_offset(point(px(0.), scroll_y));
+        if let Some(query) = find {
+            self.search_active = true;
+            self.search_query = query;
+            self.invalidate_search_cache();
+        }
+        cx.notify();
+    }
+
+    /// Source line (0-based) shown at the top of the editor viewport.
+    pub fn top_visible_source_line(&self, cx: &App) -> Option<usize> {
+        let (layout, projection, painted_offset) = self.last_layout.as_ref()?;
+        let bounds = self.scroll_handle.bounds();
+        if bounds.size.height <= px(0.) {
+            return None;
+        }
+        // The layout was painted at `painted_offset`; account for scrolling since.
+        let scrolled = *painted_offset - self.scroll_handle.offset().y;
+        let position = point(bounds.left() + px(18.), bounds.top() + px(18.) + scrolled);
+        let display_byte =
+            std::panic::catch_unwind(AssertUnwindSafe(|| layout.index_for_position(position)))
+                .ok()
+                .map(|res| res.unwrap_or_else(|ix| ix))?;
+        let doc = self.document.read(cx);
+        let source_byte = projection
+            .display_to_source_byte(display_byte)
+            .min(doc.len_bytes());
+        Some(doc.rope.byte_to_line(source_byte))
+    }
+
+    /// Focuses the editor if it has been rendered; new editors focus themselves
+    /// on their first frame.
+    pub fn focus(&self, window: &mut Window) {
+        if let Some(handle) = &self.focus_handle {
+            handle.focus(window);
+        }
+    }
+
+    pub fn set_vim_enabled(&mut self, enabled: bool, cx: &mut Context<Self>) {
+        if !enabled {
+            self.vim = None;
+        } else if self.vim.is_none() {
+            self.vim = Some(VimState::new());
+        }
+        cx.notify();
+    }
+
+    /// Current Vim mode, or `None` when Vim mode is disabled.
+    pub fn vim_mode(&self) -> Option<VimMode> {
+        self.vim.as_ref().map(VimState::mode)
+    }
+
     fn start_cursor_blink(&mut self, cx: &mut Context<Self>) {
         if self.blink_task.is_some() {
             return;
@@ -224,8 +370,13 @@ impl EditorView {
         }));
     }
 
-    fn selection_highlights(&self, doc: &DocumentState) -> Vec<(Range<usize>, HighlightStyle)> {
-        doc.selection_bytes().map_or_else(Vec::new, |range| {
+    fn selection_highlights(
+        &self,
+        doc: &DocumentState,
+        selection: Option<Range<usize>>,
+    ) -> Vec<(Range<usize>, HighlightStyle)> {
+        let selection = selection.map(|range| doc.char_range_to_bytes(range));
+        selection.map_or_else(Vec::new, |range| {
             vec![(
                 range,
                 HighlightStyle {
@@ -239,14 +390,93 @@ impl EditorView {
     fn inline_syntax_highlights(
         &self,
         spans: &[SyntaxSpan],
+        code: CodeHighlights,
+        revealed: &[Range<usize>],
     ) -> Vec<(Range<usize>, HighlightStyle)> {
-        spans
+        let highlights = spans
             .iter()
+            // Highlighted code bodies get token colors instead of the flat code color.
+            .filter(|span| span.kind != SyntaxKind::CodeBlock || !code.bodies.contains(&span.range))
             .map(|span| {
-                let hide_markers = is_inline_hidden_kind(span.kind);
+                let hide_markers =
+                    is_inline_hidden_kind(span.kind) && !is_revealed(&span.range, revealed);
                 (span.range.clone(), syntax_style(span.kind, hide_markers))
             })
-            .collect()
+            .chain(code.tokens);
+        // Spans nest (e.g. emphasis inside link text); flatten into disjoint runs.
+        combine_highlights(highlights, Vec::new()).collect()
+    }
+
+    /// Lays out local images below their source lines. Decoding runs in GPUI's
+    /// asset loader on a background thread; until it finishes (or if the file is
+    /// missing) a one-line placeholder is shown instead.
+    fn inline_images(
+        &self,
+        text: &str,
+        spans: &[SyntaxSpan],
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> InlineImages {
+        let mut layout = InlineImages::default();
+        let refs = image_refs(text, spans);
+        if refs.is_empty() {
+            return layout;
+        }
+
+        let (doc_path, cursor_line) = {
+            let doc = self.document.read(cx);
+            let line = doc.rope.char_to_line(doc.cursor.min(doc.len_chars()));
+            let start = doc.rope.line_to_byte(line);
+            let end = start + doc.rope.line(line).len_bytes();
+            (doc.path.clone(), start..end)
+        };
+        let line_height = phi().to_pixels(px(settings::get_font_size()).into(), window.rem_size());
+        let max_width = self.scroll_handle.bounds().size.width - px(18. * 2. + 10.);
+        let max_width = if max_width > px(0.) {
+            max_width
+        } else {
+            px(640.)
+        };
+
+        for image_ref in refs {
+            let Some(path) = resolve_local_image(doc_path.as_deref(), &image_ref.destination)
+            else {
+                continue;
+            };
+            if cursor_line.contains(&image_ref.range.start) {
+                layout.revealed.push(image_ref.range.clone());
+            }
+
+            let resource = Resource::Path(Arc::from(path.as_path()));
+            let (text, image) = match window.use_asset::<ImgResourceLoader>(&resource, cx) {
+                Some(Ok(image)) => {
+                    let natural = image.size(0);
+                    let scale = window.scale_factor();
+                    let width = px(natural.width.0 as f32 / scale);
+                    let height = px(natural.height.0 as f32 / scale);
+                    if width <= px(0.) || height <= px(0.) {
+                        continue;
+                    }
+                    let fitted = size(
+                        width.min(max_width),
+                        height * (width.min(max_width) / width),
+                    );
+                    let lines = (fitted.height / line_height).ceil().max(1.) as usize;
+                    ("\n".repeat(lines), Some((image, fitted)))
+                }
+                Some(Err(_)) => (
+                    format!("\n[Image not found: {}]", image_ref.destination),
+                    None,
+                ),
+                None => ("\n[Loading image…]".to_string(), None),
+            };
+            layout.insertions.push(Insertion {
+                at: image_ref.line_end,
+                text,
+            });
+            layout.images.push(image);
+        }
+        layout
     }
 
     fn current_text_and_revision(&mut self, cx: &mut Context<Self>) -> (String, u64) {
@@ -333,7 +563,58 @@ impl EditorView {
 
     fn close_search(&mut self, cx: &mut Context<Self>) {
         self.search_active = false;
+        self.collapse_vim_selection(cx);
+        cx.notify();
+    }
+
+    /// Turns a search match selection back into a normal-mode cursor.
+    fn collapse_vim_selection(&mut self, cx: &mut Context<Self>) {
+        if let Some(vim) = self.vim.as_mut() {
+            self.document.update(cx, |doc, cx| {
+                vim.collapse_to_normal(doc);
+                cx.notify();
+            });
+        }
+    }
+
+    /// Routes a key press through the Vim layer. Returns false when the key
+    /// should fall through to the regular editor handling.
+    fn handle_vim_key(&mut self, key: VimKey, cx: &mut Context<Self>) -> bool {
+        let clipboard = if VimState::wants_clipboard(key) {
+            cx.read_from_clipboard().and_then(|item| item.text())
+        } else {
+            None
+        };
+        let Some(vim) = self.vim.as_mut() else {
+            return false;
+        };
+        let (outcome, cursor_byte) = self.document.update(cx, |doc, cx| {
+            let outcome = vim.handle_key(key, doc, clipboard.as_deref());
+            cx.notify();
+            (outcome, doc.char_to_byte(doc.cursor))
+        });
+
+        for effect in outcome.effects {
+            match effect {
+                VimEffect::SetClipboard(text) => {
+                    cx.write_to_clipboard(ClipboardItem::new_string(text));
+                }
+                VimEffect::StartSearch => {
+                    self.search_query.clear();
+                    self.activate_search(cx);
+                }
+                VimEffect::SearchNext { forward } => {
+                    self.jump_search(cx, forward);
+                    self.collapse_vim_selection(cx);
+                }
+            }
+        }
+
+        if outcome.handled && !self.search_active {
+            self.pending_scroll_to_byte = Some(cursor_byte);
+        }
         cx.notify();
+        outcome.handled
     }
 
     fn select_current_search_match(&mut self, cx: &mut Context<Self>) {
@@ -359,6 +640,7 @@ impl EditorView {
             let _ = self.document.update(cx, |doc, cx| {
                 let start = doc.byte_to_char(range.start);
                 let end = doc.byte_to_char(range.end);
+                doc.record_jump(start);
                 doc.set_selection(start, end);
                 cx.notify();
             });
@@ -429,6 +711,178 @@ impl EditorView {
         }
     }
 
+    fn open_quick_nav(&mut self, kind: QuickNavKind, cx: &mut Context<Self>) {
+        self.search_active = false;
+        self.quick_nav = Some(match kind {
+            QuickNavKind::Line => QuickNavState::line(),
+            QuickNavKind::Heading => {
+                let (text, _) = self.current_text_and_revision(cx);
+                QuickNavState::heading(parse_outline_items(&text))
+            }
+        });
+        cx.notify();
+    }
+
+    fn handle_quick_nav_key(&mut self, event: &KeyDownEvent, cx: &mut Context<Self>) {
+        let key = event.keystroke.key.to_lowercase();
+        let modifiers = event.keystroke.modifiers;
+        let Some(nav) = self.quick_nav.as_mut() else {
+            return;
+        };
+
+        match key.as_str() {
+            "escape" => self.quick_nav = None,
+            "enter" | "return" => self.accept_quick_nav(cx),
+            "up" | "arrowup" => nav.select_previous(),
+            "down" | "arrowdown" => nav.select_next(),
+            "backspace" => nav.pop_char(),
+            _ => {
+                if modifiers.platform || modifiers.control {
+                    return;
+                }
+                if let Some(raw) = &event.keystroke.key_char
+                    && raw != "\n"
+                    && raw != "\r"
+                {
+                    nav.push_str(raw);
+                }
+            }
+        }
+        cx.notify();
+    }
+
+    fn accept_quick_nav(&mut self, cx: &mut Context<Self>) {
+        let Some(nav) = self.quick_nav.take() else {
+            return;
+        };
+        let target = match nav.kind {
+            QuickNavKind::Line => parse_line_column(&nav.query).map(|target| {
+                let doc = self.document.read(cx);
+                line_column_to_char(&doc.rope, target)
+            }),
+            QuickNavKind::Heading => nav
+                .selected_heading()
+                .map(|item| self.document.read(cx).byte_to_char(item.byte_start)),
+        };
+        if let Some(char_idx) = target {
+            self.jump_to_char(char_idx, cx);
+        }
+        cx.notify();
+    }
+
+    /// Moves the cursor to `char_idx`, recording a jump, and scrolls it into view.
+    fn jump_to_char(&mut self, char_idx: usize, cx: &mut Context<Self>) {
+        self.document.update(cx, |doc, _| doc.record_jump(char_idx));
+        self.move_cursor_to(char_idx, cx);
+    }
+
+    /// Moves the cursor to `char_idx` without touching the jump list and scrolls it into view.
+    pub fn move_cursor_to(&mut self, char_idx: usize, cx: &mut Context<Self>) {
+        let byte = self.document.update(cx, |doc, cx| {
+            doc.set_cursor(char_idx);
+            cx.notify();
+            doc.char_to_byte(doc.cursor)
+        });
+        self.pending_scroll_to_byte = Some(byte);
+        cx.notify();
+    }
+
+    fn render_quick_nav(&self, nav: &QuickNavState, cx: &mut Context<Self>) -> impl IntoElement {
+        let (title, placeholder) = match nav.kind {
+            QuickNavKind::Line => {
+                let lines = self.document.read(cx).rope.len_lines();
+                ("GO TO LINE", format!("Line 1–{lines}, optionally :column"))
+            }
+            QuickNavKind::Heading => ("GO TO HEADING", "Type to filter headings".to_string()),
+        };
+
+        let entries: Vec<_> = if nav.kind == QuickNavKind::Heading {
+            nav.heading_matches()
+                .into_iter()
+                .enumerate()
+                .map(|(ix, item)| {
+                    let indent = (item.level.saturating_sub(1) as f32) * 10.0;
+                    div()
+                        .id(("quick-nav-entry", ix))
+                        .pl(px(8. + indent))
+                        .pr(px(8.))
+                        .py(px(3.))
+                        .rounded(px(4.))
+                        .text_sm()
+                        .cursor_pointer()
+                        .when(ix == nav.selected, |this| this.bg(Theme::selection_bg()))
+                        .hover(|this| this.bg(Theme::panel()))
+                        .on_mouse_down(
+                            MouseButton::Left,
+                            cx.listener(move |this, _: &MouseDownEvent, _, cx| {
+                                cx.stop_propagation();
+                                if let Some(nav) = this.quick_nav.as_mut() {
+                                    nav.selected = ix;
+                                }
+                                this.accept_quick_nav(cx);
+                            }),
+                        )
+                        .child(ellipsize_chars(&item.title, 64))
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let no_matches = nav.kind == QuickNavKind::Heading && entries.is_empty();
+
+        div()
+            .absolute()
+            .top(px(8.))
+            .right(px(12.))
+            .w(px(360.))
+            .flex()
+            .flex_col()
+            .gap_1()
+            .px(px(10.))
+            .py(px(6.))
+            .rounded(px(6.))
+            .bg(Theme::panel_alt())
+            .border_1()
+            .border_color(Theme::border())
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .gap_2()
+                    .child(
+                        div()
+                            .text_xs()
+                            .font_weight(FontWeight::BOLD)
+                            .text_color(Theme::muted())
+                            .child(title),
+                    )
+                    .child(
+                        div()
+                            .text_sm()
+                            .overflow_hidden()
+                            .text_color(if nav.query.is_empty() {
+                                Theme::muted()
+                            } else {
+                                Theme::text()
+                            })
+                            .child(if nav.query.is_empty() {
+                                placeholder
+                            } else {
+                                ellipsize_chars(&nav.query, 48)
+                            }),
+                    ),
+            )
+            .children(entries)
+            .when(no_matches, |this| {
+                this.child(
+                    div()
+                        .text_sm()
+                        .text_color(Theme::muted())
+                        .child("No matching headings"),
+                )
+            })
+    }
+
     fn reveal_pending_byte(
         &mut self,
         text_layout: &gpui::TextLayout,
@@ -525,25 +979,82 @@ impl Render for EditorView {
             self.cached_text = Some((doc_revision, text_owned.clone()));
         }
 
+        let quick_nav_overlay = self.quick_nav.take().map(|nav| {
+            let overlay = self.render_quick_nav(&nav, cx).into_any_element();
+            self.quick_nav = Some(nav);
+            overlay
+        });
+
+        let (inline_spans, code_highlights) = {
+            let inline = self.inline_markdown.read(cx);
+            (inline.spans.clone(), code_highlights(inline, &text_owned))
+        };
+        let inline_images = self.inline_images(&text_owned, &inline_spans, window, cx);
+
         let doc = self.document.read(cx);
-        let cursor_source_byte = doc.char_to_byte(doc.cursor);
-        let show_caret = doc.selection.is_none();
+        let cursor = match &self.parked {
+            Some(parked) => doc.rebase_cursor_state(parked),
+            None => doc.cursor_state(),
+        };
+        let cursor_source_byte = doc.char_to_byte(cursor.cursor);
+        let show_caret = cursor.selection.is_none();
         let draw_caret = show_caret && is_focused && self.caret_visible;
-        let inline_spans = {
-            let inline = self.inline_markdown.read(cx);
-            inline.spans.clone()
+        let mut revealed = match settings::get_marker_visibility() {
+            MarkerVisibility::Always => std::iter::once(0..text_owned.len()).collect(),
+            MarkerVisibility::Never => Vec::new(),
+            MarkerVisibility::Contextual => {
+                let focus = cursor
+                    .selection
+                    .clone()
+                    .map(|range| doc.char_range_to_bytes(range))
+                    .unwrap_or(cursor_source_byte..cursor_source_byte);
+                contextual_reveal(&text_owned, &inline_spans, focus)
+            }
         };
-        let projection = Arc::new(DisplayProjection::from_source(
+        revealed.extend(inline_images.revealed);
+        let projection = Arc::new(DisplayProjection::build(
             &text_owned,
-            inline_spans.as_ref(),
+            merged_hidden_ranges(&inline_spans, &revealed),
+            &inline_images.insertions,
         ));
+        let mut placed_images = Vec::new();
+        let mut placeholder_highlights = Vec::new();
+        for (range, image) in projection.inserted_ranges().zip(inline_images.images) {
+            // Skip the newline that ends the image's source line.
+            match image {
+                Some((image, fitted)) => placed_images.push((range.start + 1, fitted, image)),
+                None => placeholder_highlights.push((
+                    range.start + 1..range.end,
+                    HighlightStyle {
+                        color: Some(Theme::muted().into()),
+                        font_style: Some(FontStyle::Italic),
+                        ..Default::default()
+                    },
+                )),
+            }
+        }
         let cursor_display_byte = projection.source_to_display_byte(cursor_source_byte);
-        let syntax_highlights =
-            projection.project_highlights(self.inline_syntax_highlights(inline_spans.as_ref()));
+        let block_caret = self.vim_mode().is_some_and(|mode| mode != VimMode::Insert);
+        let caret_char_len = projection
+            .display_text
+            .get(cursor_display_byte..)
+            .and_then(|rest| rest.chars().next())
+            .map_or(0, char::len_utf8);
+        let syntax_highlights = projection.project_highlights(self.inline_syntax_highlights(
+            inline_spans.as_ref(),
+            code_highlights,
+            &revealed,
+        ));
+        let syntax_highlights = if placeholder_highlights.is_empty() {
+            syntax_highlights
+        } else {
+            combine_highlights(syntax_highlights, placeholder_highlights).collect()
+        };
         let (search_highlights, search_match_count) =
             self.search_highlights(&text_owned, doc_revision);
         let search_highlights = projection.project_highlights(search_highlights);
-        let selection_highlights = projection.project_highlights(self.selection_highlights(&doc));
+        let selection_highlights =
+            projection.project_highlights(self.selection_highlights(doc, cursor.selection));
 
         let syntax_and_search = if search_highlights.is_empty() {
             syntax_highlights
@@ -569,7 +1080,13 @@ impl Render for EditorView {
         }
 
         let text_layout = styled.layout().clone();
+        let image_layout = text_layout.clone();
         self.reveal_pending_byte(&text_layout, projection.as_ref(), window);
+        self.last_layout = Some((
+            text_layout.clone(),
+            projection.clone(),
+            self.scroll_handle.offset().y,
+        ));
 
         let search_match_display = if search_match_count == 0 {
             0
@@ -613,6 +1130,39 @@ impl Render for EditorView {
                     }
                 }
             })
+            .on_action({
+                let doc_handle = self.document.clone();
+                move |_: &CopyAsRichText, _window: &mut Window, cx_app: &mut App| {
+                    let Some((markdown, plain, path)) = doc_handle.read_with(cx_app, |d, _| {
+                        let selection = d.selection_bytes().filter(|r| !r.is_empty())?;
+                        let text = d.text();
+                        Some((
+                            text[selection.clone()].to_string(),
+                            plain_text(&text, selection),
+                            d.path.clone(),
+                        ))
+                    }) else {
+                        return;
+                    };
+                    let html = render_html_fragment(&markdown, path.as_deref());
+                    if let Err(err) = clipboard::write_html(&html, &plain) {
+                        tracing::warn!("copying as rich text failed: {err}");
+                        cx_app.write_to_clipboard(ClipboardItem::new_string(plain));
+                    }
+                }
+            })
+            .on_action({
+                let doc_handle = self.document.clone();
+                move |_: &CopyAsPlainText, _window: &mut Window, cx_app: &mut App| {
+                    let plain = doc_handle.read_with(cx_app, |d, _| {
+                        let selection = d.selection_bytes().filter(|r| !r.is_empty())?;
+                        Some(plain_text(&d.text(), selection))
+                    });
+                    if let Some(plain) = plain {
+                        cx_app.write_to_clipboard(ClipboardItem::new_string(plain));
+                    }
+                }
+            })
             .on_action({
                 let doc_handle = self.document.clone();
                 move |_: &Cut, _window: &mut Window, cx_app: &mut App| {
@@ -681,6 +1231,20 @@ impl Render for EditorView {
                     this.activate_search(cx);
                 })
             })
+            .on_action({
+                let focus_handle = focus_handle.clone();
+                cx.listener(move |this, _: &GoToLine, window, cx| {
+                    focus_handle.focus(window);
+                    this.open_quick_nav(QuickNavKind::Line, cx);
+                })
+            })
+            .on_action({
+                let focus_handle = focus_handle.clone();
+                cx.listener(move |this, _: &GoToHeading, window, cx| {
+                    focus_handle.focus(window);
+                    this.open_quick_nav(QuickNavKind::Heading, cx);
+                })
+            })
             .on_action(cx.listener(|this, _: &FindNext, _window, cx| {
                 if !this.search_active {
                     this.activate_search(cx);
@@ -718,7 +1282,7 @@ impl Render for EditorView {
                                 let anchor = doc.selection_anchor.unwrap_or(doc.cursor);
                                 doc.set_selection(anchor, byte_idx);
                             } else {
-                                doc.set_cursor(byte_idx);
+                                doc.jump_to(byte_idx);
                             }
                             cx.notify();
                         }
@@ -761,7 +1325,7 @@ impl Render for EditorView {
 
                     let key = event.keystroke.key.to_lowercase();
                     let modifiers = event.keystroke.modifiers;
-                    let is_cmd = modifiers.platform || modifiers.control;
+                    let is_cmd = modifiers.secondary();
                     let shift = modifiers.shift;
 
                     if is_cmd && key == "f" {
@@ -778,12 +1342,46 @@ impl Render for EditorView {
                         return;
                     }
 
+                    if this.quick_nav.is_some() {
+                        this.handle_quick_nav_key(event, cx);
+                        return;
+                    }
+
                     if this.search_active {
                         this.handle_search_key(event, cx);
                         return;
                     }
 
-                    if is_cmd {
+                    if this.vim.is_some()
+                        && let Some(vim_key) = vim_key_from_event(event)
+                        && this.handle_vim_key(vim_key, cx)
+                    {
+                        return;
+                    }
+
+                    if is_cmd && (key == "home" || key == "end") {
+                        let target = if key == "home" {
+                            0
+                        } else {
+                            this.document.read(cx).len_chars()
+                        };
+                        if shift {
+                            let byte = this.document.update(cx, |doc, cx| {
+                                let anchor = doc.selection_anchor.unwrap_or(doc.cursor);
+                                doc.set_selection(anchor, target);
+                                cx.notify();
+                                doc.char_to_byte(target)
+                            });
+                            this.pending_scroll_to_byte = Some(byte);
+                            cx.notify();
+                        } else {
+                            this.jump_to_char(target, cx);
+                        }
+                        return;
+                    }
+
+                    // Let other modifier chords (e.g. Ctrl on macOS) fall through to key bindings.
+                    if modifiers.platform || modifiers.control {
                         return;
                     }
 
@@ -955,47 +1553,103 @@ impl Render for EditorView {
                 })
             })
             .child(
-                div().relative().child(styled).child(
-                    canvas(
-                        move |_, _, _| {},
-                        move |_bounds: Bounds<_>, (), window: &mut Window, _cx: &mut App| {
-                            if !draw_caret {
-                                return;
-                            }
+                div()
+                    .relative()
+                    .child(styled)
+                    .child(
+                        canvas(
+                            move |_, _, _| {},
+                            move |_bounds: Bounds<_>, (), window: &mut Window, _cx: &mut App| {
+                                for (display_byte, fitted, image) in &placed_images {
+                                    let origin = std::panic::catch_unwind(AssertUnwindSafe(|| {
+                                        image_layout.position_for_index(*display_byte)
+                                    }))
+                                    .ok()
+                                    .flatten();
+                                    if let Some(origin) = origin {
+                                        let _ = window.paint_image(
+                                            Bounds {
+                                                origin,
+                                                size: *fitted,
+                                            },
+                                            Corners::all(px(4.)),
+                                            image.clone(),
+                                            0,
+                                            false,
+                                        );
+                                    }
+                                }
+                            },
+                        )
+                        .absolute()
+                        .top_0()
+                        .left_0()
+                        .size_full(),
+                    )
+                    .child(
+                        canvas(
+                            move |_, _, _| {},
+                            move |_bounds: Bounds<_>, (), window: &mut Window, _cx: &mut App| {
+                                if !draw_caret {
+                                    return;
+                                }
 
-                            let caret_pos = std::panic::catch_unwind(AssertUnwindSafe(|| {
-                                text_layout.position_for_index(cursor_display_byte)
-                            }))
-                            .ok()
-                            .flatten();
-                            let Some(caret_pos) = caret_pos else {
-                                return;
-                            };
-
-                            let line_height = std::panic::catch_unwind(AssertUnwindSafe(|| {
-                                text_layout.line_height()
-                            }))
-                            .ok()
-                            .unwrap_or(px(0.));
-                            if line_height <= px(0.) {
-                                return;
-                            }
+                                let caret_pos = std::panic::catch_unwind(AssertUnwindSafe(|| {
+                                    text_layout.position_for_index(cursor_display_byte)
+                                }))
+                                .ok()
+                                .flatten();
+                                let Some(caret_pos) = caret_pos else {
+                                    return;
+                                };
 
-                            window.paint_quad(fill(
-                                Bounds {
-                                    origin: point(caret_pos.x, caret_pos.y),
-                                    size: size(px(1.), line_height),
-                                },
-                                Theme::accent(),
-                            ));
-                        },
-                    )
-                    .absolute()
-                    .top_0()
-                    .left_0()
-                    .size_full(),
-                ),
+                                let line_height =
+                                    std::panic::catch_unwind(AssertUnwindSafe(|| {
+                                        text_layout.line_height()
+                                    }))
+                                    .ok()
+                                    .unwrap_or(px(0.));
+                                if line_height <= px(0.) {
+                                    return;
+                                }
+
+                                if block_caret {
+                                    // Vim normal mode draws a block over the character under the cursor.
+                                    let width = std::panic::catch_unwind(AssertUnwindSafe(|| {
+                                        text_layout.position_for_index(
+                                            cursor_display_byte + caret_char_len,
+                                        )
+                                    }))
+                                    .ok()
+                                    .flatten()
+                                    .filter(|next| next.y == caret_pos.y && next.x > caret_pos.x)
+                                    .map_or(line_height * 0.5, |next| next.x - caret_pos.x);
+                                    window.paint_quad(fill(
+                                        Bounds {
+                                            origin: point(caret_pos.x, caret_pos.y),
+                                            size: size(width, line_height),
+                                        },
+                                        hsla_with_alpha(Theme::accent(), 0.35),
+                                    ));
+                                    return;
+                                }
+
+                                window.paint_quad(fill(
+                                    Bounds {
+                                        origin: point(caret_pos.x, caret_pos.y),
+                                        size: size(px(1.), line_height),
+                                    },
+                                    Theme::accent(),
+                                ));
+                            },
+                        )
+                        .absolute()
+                        .top_0()
+                        .left_0()
+                        .size_full(),
+                    ),
             )
+            .children(quick_nav_overlay)
             .when(self.search_active, |this| {
                 this.child(
                     div()
@@ -1041,6 +1695,63 @@ impl Render for EditorView {
     }
 }
 
+/// Inline images of the current frame, laid out as display-only insertions.
+#[derive(Default)]
+struct InlineImages {
+    insertions: Vec<Insertion>,
+    /// Per insertion: the decoded image and its fitted size, or `None` for a placeholder.
+    images: Vec<Option<(Arc<RenderImage>, Size<Pixels>)>>,
+    /// Image syntax on the cursor line, shown raw instead of hidden.
+    revealed: Vec<Range<usize>>,
+}
+
+/// Token highlights of fenced code blocks, in source bytes.
+struct CodeHighlights {
+    tokens: Vec<(Range<usize>, HighlightStyle)>,
+    /// Bodies covered by `tokens`.
+    bodies: Vec<Range<usize>>,
+}
+
+fn code_highlights(inline: &InlineMarkdownState, text: &str) -> CodeHighlights {
+    let mut highlights = CodeHighlights {
+        tokens: Vec::new(),
+        bodies: Vec::new(),
+    };
+    for block in inline.code_blocks.iter() {
+        let Some(tokens) = inline.highlights_for(block) else {
+            continue;
+        };
+        highlights.bodies.push(block.body.clone());
+        highlights.tokens.extend(tokens.iter().filter_map(|token| {
+            // Stale tokens may come from a different body; keep them inside this one.
+            let start = block.body.start + token.range.start;
+            let end = (block.body.start + token.range.end).min(block.body.end);
+            (start < end && text.is_char_boundary(start) && text.is_char_boundary(end))
+                .then(|| (start..end, code_token_style(token.kind)))
+        }));
+    }
+    highlights
+}
+
+pub(crate) fn code_token_style(kind: CodeTokenKind) -> HighlightStyle {
+    let color = match kind {
+        CodeTokenKind::Keyword => Theme::code_keyword(),
+        CodeTokenKind::String => Theme::code_string(),
+        CodeTokenKind::Comment => Theme::code_comment(),
+        CodeTokenKind::Number | CodeTokenKind::Constant => Theme::code_constant(),
+        CodeTokenKind::Function => Theme::code_function(),
+        CodeTokenKind::Type => Theme::code_type(),
+        CodeTokenKind::Tag => Theme::code_tag(),
+        CodeTokenKind::Attribute => Theme::code_constant(),
+        CodeTokenKind::Operator => Theme::code_operator(),
+    };
+    HighlightStyle {
+        color: Some(color.into()),
+        font_style: (kind == CodeTokenKind::Comment).then_some(FontStyle::Italic),
+        ..Default::default()
+    }
+}
+
 fn syntax_style(kind: SyntaxKind, hide_markers: bool) -> HighlightStyle {
     if hide_markers {
         let hidden_color = match kind {
@@ -1082,6 +1793,10 @@ fn syntax_style(kind: SyntaxKind, hide_markers: bool) -> HighlightStyle {
             color: Some(Theme::muted().into()),
             ..Default::default()
         },
+        SyntaxKind::CodeBlock => HighlightStyle {
+            color: Some(gpui::rgb(0x1f6f8b).into()),
+            ..Default::default()
+        },
         SyntaxKind::InlineCodeMarker | SyntaxKind::InlineCode => HighlightStyle {
             color: Some(gpui::rgb(0x1f6f8b).into()),
             background_color: Some(hsla_with_alpha(Theme::code_block_bg(), 1.0)),
@@ -1105,7 +1820,7 @@ fn syntax_style(kind: SyntaxKind, hide_markers: bool) -> HighlightStyle {
             font_style: Some(FontStyle::Italic),
             ..Default::default()
         },
-        SyntaxKind::EmphasisMarker => HighlightStyle {
+        SyntaxKind::EmphasisMarker | SyntaxKind::EscapeMarker => HighlightStyle {
             color: Some(Theme::muted().into()),
             ..Default::default()
         },
@@ -1117,6 +1832,13 @@ fn syntax_style(kind: SyntaxKind, hide_markers: bool) -> HighlightStyle {
             font_weight: Some(FontWeight::BOLD),
             ..Default::default()
         },
+        SyntaxKind::StrikethroughText => HighlightStyle {
+            strikethrough: Some(StrikethroughStyle {
+                thickness: px(1.),
+                color: None,
+            }),
+            ..Default::default()
+        },
     }
 }
 
@@ -1127,6 +1849,7 @@ fn is_inline_hidden_kind(kind: SyntaxKind) -> bool {
             | SyntaxKind::QuoteMarker
             | SyntaxKind::InlineCodeMarker
             | SyntaxKind::EmphasisMarker
+            | SyntaxKind::EscapeMarker
             | SyntaxKind::CodeFence
             | SyntaxKind::LinkTextDelimiter
             | SyntaxKind::LinkUrlDelimiter
@@ -1134,10 +1857,11 @@ fn is_inline_hidden_kind(kind: SyntaxKind) -> bool {
     )
 }
 
-fn merged_hidden_ranges(spans: &[SyntaxSpan]) -> Vec<Range<usize>> {
+/// Source ranges of hidden markers, skipping those that overlap `revealed`.
+fn merged_hidden_ranges(spans: &[SyntaxSpan], revealed: &[Range<usize>]) -> Vec<Range<usize>> {
     let mut hidden = spans
         .iter()
-        .filter(|span| is_inline_hidden_kind(span.kind))
+        .filter(|span| is_inline_hidden_kind(span.kind) && !is_revealed(&span.range, revealed))
         .map(|span| span.range.clone())
         .collect::<Vec<_>>();
     if hidden.is_empty() {
@@ -1160,6 +1884,83 @@ fn merged_hidden_ranges(spans: &[SyntaxSpan]) -> Vec<Range<usize>> {
     merged
 }
 
+/// `source[range]` as the editor shows it with every marker hidden, for
+/// copying without Markdown syntax.
+fn plain_text(source: &str, range: Range<usize>) -> String {
+    let hidden = merged_hidden_ranges(&markdown_spans(source), &[]);
+    let projection = DisplayProjection::build(source, hidden, &[]);
+    let start = projection.source_to_display_byte(range.start);
+    let end = projection.source_to_display_byte(range.end).max(start);
+    projection.display_text[start..end].to_string()
+}
+
+/// Ranges whose markers are shown raw around `focus` (the cursor or selection):
+/// the lines it touches, plus the delimiters of elements that continue past them.
+fn contextual_reveal(text: &str, spans: &[SyntaxSpan], focus: Range<usize>) -> Vec<Range<usize>> {
+    let start = text[..focus.start.min(text.len())]
+        .rfind('\n')
+        .map_or(0, |ix| ix + 1);
+    let end = focus.end.min(text.len());
+    let end = text[end..].find('\n').map_or(text.len(), |ix| end + ix);
+    let lines = start..end;
+
+    let mut revealed = Vec::new();
+    for span in spans {
+        let crosses = span.range.start < lines.start || span.range.end > lines.end;
+        if is_inline_hidden_kind(span.kind)
+            || !crosses
+            || !is_revealed(&span.range, std::slice::from_ref(&lines))
+        {
+            continue;
+        }
+        // e.g. `*emphasis\nacross lines*`: reveal the markers on the other line too.
+        revealed.extend(
+            spans
+                .iter()
+                .filter(|marker| is_inline_hidden_kind(marker.kind))
+                .filter(|marker| {
+                    marker.range.end == span.range.start || marker.range.start == span.range.end
+                })
+                .map(|marker| marker.range.clone()),
+        );
+    }
+    revealed.push(lines);
+    revealed
+}
+
+fn is_revealed(range: &Range<usize>, revealed: &[Range<usize>]) -> bool {
+    revealed
+        .iter()
+        .any(|r| range.start < r.end && r.start < range.end)
+}
+
+fn vim_key_from_event(event: &KeyDownEvent) -> Option<VimKey> {
+    let modifiers = event.keystroke.modifiers;
+    if modifiers.platform {
+        return None;
+    }
+    let key = event.keystroke.key.to_lowercase();
+    if modifiers.control {
+        return (key == "r").then_some(VimKey::Ctrl('r'));
+    }
+    match key.as_str() {
+        "escape" => Some(VimKey::Escape),
+        "enter" | "return" => Some(VimKey::Enter),
+        "backspace" => Some(VimKey::Backspace),
+        "delete" => Some(VimKey::Delete),
+        "left" | "arrowleft" => Some(VimKey::Left),
+        "right" | "arrowright" => Some(VimKey::Right),
+        "up" | "arrowup" => Some(VimKey::Up),
+        "down" | "arrowdown" => Some(VimKey::Down),
+        _ => event
+            .keystroke
+            .key_char
+            .as_ref()
+            .and_then(|s| s.chars().next())
+            .map(VimKey::Char),
+    }
+}
+
 fn pop_last_char(s: &mut String) {
     if let Some((idx, _)) = s.char_indices().next_back() {
         s.truncate(idx);
@@ -1256,7 +2057,17 @@ fn sanitize_highlights(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::services::syntax::markdown_spans;
+
+    #[test]
+    fn plain_text_drops_hidden_markers() {
+        let source = "# Title\n\nSome **bold** and [a link](https://x.y).\n";
+        assert_eq!(
+            plain_text(source, 0..source.len()),
+            "Title\n\nSome bold and a link.\n"
+        );
+        let start = source.find("**bold").unwrap();
+        assert_eq!(plain_text(source, start..start + 8), "bold");
+    }
 
     #[test]
     fn find_matches_ascii_case_insensitive() {
@@ -1304,4 +2115,55 @@ mod tests {
         let projection = DisplayProjection::from_source(source, &spans);
         assert_eq!(projection.display_text, "Hi");
     }
+
+    #[test]
+    fn display_projection_maps_around_inserted_image_space() {
+        let source = "![a](x.png)\nnext";
+        let spans = markdown_spans(source);
+        let line_end = source.find('\n').unwrap();
+        let insertions = [Insertion {
+            at: line_end,
+            text: "\n\n".to_string(),
+        }];
+        let projection =
+            DisplayProjection::build(source, merged_hidden_ranges(&spans, &[]), &insertions);
+        assert_eq!(projection.display_text, "a\n\n\nnext");
+        assert_eq!(projection.inserted_ranges().collect::<Vec<_>>(), vec![1..3]);
+
+        // The end of the image line stays before the inserted space, the next line after it.
+        assert_eq!(projection.source_to_display_byte(line_end), 1);
+        assert_eq!(projection.source_to_display_byte(line_end + 1), 4);
+        assert_eq!(projection.display_to_source_byte(2), line_end);
+        assert_eq!(projection.display_to_source_byte(4), line_end + 1);
+
+        // Revealed image syntax is shown raw.
+        let revealed = merged_hidden_ranges(&spans, std::slice::from_ref(&(0..line_end)));
+        let projection = DisplayProjection::build(source, revealed, &insertions);
+        assert_eq!(projection.display_text, "![a](x.png)\n\n\nnext");
+    }
+
+    #[test]
+    fn contextual_reveal_shows_markers_on_the_cursor_line_only() {
+        let source = "**bold** one\n*it*\nlast **x**";
+        let spans = markdown_spans(source);
+        let display = |focus: Range<usize>| {
+            let revealed = contextual_reveal(source, &spans, focus);
+            DisplayProjection::build(source, merged_hidden_ranges(&spans, &revealed), &[])
+                .display_text
+        };
+
+        assert_eq!(display(3..3), "**bold** one\nit\nlast x");
+        // A selection reveals every line it touches.
+        assert_eq!(display(3..15), "**bold** one\n*it*\nlast x");
+
+        // Emphasis across lines shows both delimiters.
+        let source = "*one\ntwo*";
+        let spans = markdown_spans(source);
+        let revealed = contextual_reveal(source, &spans, 6..6);
+        let projection =
+            DisplayProjection::build(source, merged_hidden_ranges(&spans, &revealed), &[]);
+        assert_eq!(projection.display_text, source);
+        // The caret maps one-to-one inside revealed text.
+        assert_eq!(projection.source_to_display_byte(6), 6);
+    }
 }
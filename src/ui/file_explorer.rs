@@ -1,34 +1,62 @@
 use crate::model::document::DocumentState;
+use crate::model::file_tree::{FileTree, TreeRow};
+use crate::services::fs::list_markdown_dir;
 use crate::ui::text_utils::ellipsize_chars;
 use crate::ui::theme::Theme;
+use camino::{Utf8Path, Utf8PathBuf};
 use gpui::prelude::FluentBuilder as _;
 use gpui::{
-    Context, Entity, InteractiveElement, IntoElement, MouseButton, MouseDownEvent, ParentElement,
-    Render, ScrollHandle, StatefulInteractiveElement, Styled, Window, div, px,
+    AnyElement, Context, Entity, EventEmitter, InteractiveElement, IntoElement, MouseButton,
+    MouseDownEvent, ParentElement, Render, ScrollHandle, StatefulInteractiveElement, Styled,
+    Window, div, px,
 };
 
 #[derive(Clone, Debug)]
-struct OutlineItem {
-    ordinal: usize,
-    level: u32,
-    title: String,
-    byte_start: usize,
+pub struct OutlineItem {
+    pub ordinal: usize,
+    pub level: u32,
+    pub title: String,
+    pub byte_start: usize,
+}
+
+/// Sidebar tab shown while a workspace folder is open.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SidebarSection {
+    Files,
+    Outline,
+}
+
+pub enum FileExplorerEvent {
+    /// A file was clicked in the workspace tree.
+    OpenFile(Utf8PathBuf),
 }
 
 pub struct FileExplorerView {
     document: Entity<DocumentState>,
     outline_scroll_handle: ScrollHandle,
+    files_scroll_handle: ScrollHandle,
     width: f32,
     cached_outline: Option<(u64, Vec<OutlineItem>)>,
+    /// Open workspace folder, if any. Without one only the outline is shown.
+    workspace: Option<FileTree>,
+    section: SidebarSection,
+    /// File to scroll to once the folders above it have been listed.
+    pending_reveal: Option<Utf8PathBuf>,
 }
 
+impl EventEmitter<FileExplorerEvent> for FileExplorerView {}
+
 impl FileExplorerView {
     pub fn new(document: Entity<DocumentState>) -> Self {
         Self {
             document,
             outline_scroll_handle: ScrollHandle::new(),
+            files_scroll_handle: ScrollHandle::new(),
             width: 200.0,
             cached_outline: None,
+            workspace: None,
+            section: SidebarSection::Outline,
+            pending_reveal: None,
         }
     }
 
@@ -36,26 +64,99 @@ impl FileExplorerView {
         self.width = width;
         cx.notify();
     }
-}
 
-impl Render for FileExplorerView {
-    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        let doc_revision = self.document.read(cx).revision;
-        let outline_items = if let Some((cached_revision, items)) = &self.cached_outline {
-            if *cached_revision == doc_revision {
-                items.clone()
-            } else {
-                let text = self.document.read(cx).text();
-                let parsed = parse_outline_items(&text);
-                self.cached_outline = Some((doc_revision, parsed.clone()));
-                parsed
-            }
-        } else {
-            let text = self.document.read(cx).text();
-            let parsed = parse_outline_items(&text);
-            self.cached_outline = Some((doc_revision, parsed.clone()));
-            parsed
+    /// Shows the outline of `document`, e.g. after switching tabs.
+    pub fn set_document(&mut self, document: Entity<DocumentState>, cx: &mut Context<Self>) {
+        self.document = document;
+        self.cached_outline = None;
+        cx.notify();
+    }
+
+    /// Root of the open workspace folder, if any.
+    pub fn folder(&self) -> Option<&Utf8Path> {
+        self.workspace.as_ref().map(FileTree::root)
+    }
+
+    /// Shows the file tree of `root` and reveals the active file in it.
+    pub fn open_folder(&mut self, root: Utf8PathBuf, cx: &mut Context<Self>) {
+        self.workspace = Some(FileTree::new(root));
+        self.section = SidebarSection::Files;
+        self.pending_reveal = None;
+        self.reveal_active_file(cx);
+        self.list_shown_folders(cx);
+        cx.notify();
+    }
+
+    /// Expands the tree down to the active file and scrolls it into view.
+    pub fn reveal_active_file(&mut self, cx: &mut Context<Self>) {
+        let Some(path) = self.document.read(cx).path.clone() else {
+            return;
+        };
+        let Some(tree) = self.workspace.as_mut() else {
+            return;
+        };
+        if !tree.reveal(&path) {
+            return;
+        }
+        self.section = SidebarSection::Files;
+        self.pending_reveal = Some(path);
+        self.scroll_to_pending_reveal();
+        self.list_shown_folders(cx);
+        cx.notify();
+    }
+
+    fn scroll_to_pending_reveal(&mut self) {
+        let (Some(path), Some(tree)) = (self.pending_reveal.as_ref(), self.workspace.as_ref())
+        else {
+            return;
+        };
+        if let Some(ix) = tree.rows().iter().position(|row| &row.path == path) {
+            self.files_scroll_handle.scroll_to_item(ix);
+            self.pending_reveal = None;
+        }
+    }
+
+    /// Lists shown folders that haven't been read yet on the background
+    /// executor, so a large or slow folder never blocks rendering.
+    fn list_shown_folders(&mut self, cx: &mut Context<Self>) {
+        let Some(tree) = self.workspace.as_mut() else {
+            return;
         };
+        for dir in tree.take_unlisted() {
+            cx.spawn(async move |this, cx| {
+                let listed = dir.clone();
+                let entries = cx
+                    .background_executor()
+                    .spawn(async move { list_markdown_dir(&listed) })
+                    .await;
+                let _ = this.update(cx, |this, cx| {
+                    if let Some(tree) = this.workspace.as_mut() {
+                        tree.set_children(dir, entries);
+                    }
+                    this.scroll_to_pending_reveal();
+                    this.list_shown_folders(cx);
+                    cx.notify();
+                });
+            })
+            .detach();
+        }
+    }
+
+    fn outline_items(&mut self, cx: &mut Context<Self>) -> Vec<OutlineItem> {
+        let doc_revision = self.document.read(cx).revision;
+        if let Some((cached_revision, items)) = &self.cached_outline
+            && *cached_revision == doc_revision
+        {
+            return items.clone();
+        }
+        let text = self.document.read(cx).text();
+        let parsed = parse_outline_items(&text);
+        self.cached_outline = Some((doc_revision, parsed.clone()));
+        parsed
+    }
+
+    fn render_outline(&mut self, cx: &mut Context<Self>) -> AnyElement {
+        let outline_items = self.outline_items(cx);
         let has_outline = !outline_items.is_empty();
         let document = self.document.clone();
 
@@ -83,7 +184,7 @@ impl Render for FileExplorerView {
                         cx.listener(move |_this, _: &MouseDownEvent, _, cx| {
                             let _ = document.update(cx, |doc, cx| {
                                 let cursor = doc.byte_to_char(byte_start);
-                                doc.set_cursor(cursor);
+                                doc.jump_to(cursor);
                                 cx.notify();
                             });
                         }),
@@ -107,6 +208,154 @@ impl Render for FileExplorerView {
             })
             .collect();
 
+        div()
+            .id("outline-scroll")
+            .flex_1()
+            .overflow_y_scroll()
+            .track_scroll(&self.outline_scroll_handle)
+            .when(has_outline, |this| this.children(outline_elements))
+            .when(!has_outline, |this| {
+                this.child(empty_message("No headings"))
+            })
+            .into_any_element()
+    }
+
+    fn render_files(&mut self, cx: &mut Context<Self>) -> AnyElement {
+        let rows = self
+            .workspace
+            .as_ref()
+            .map(FileTree::rows)
+            .unwrap_or_default();
+        let active_path = self.document.read(cx).path.clone();
+        let has_rows = !rows.is_empty();
+
+        let row_elements: Vec<_> = rows
+            .into_iter()
+            .enumerate()
+            .map(|(ix, row)| {
+                let TreeRow {
+                    path,
+                    name,
+                    is_dir,
+                    expanded,
+                    depth,
+                } = row;
+                let active = active_path.as_ref() == Some(&path);
+                let chevron = match (is_dir, expanded) {
+                    (true, true) => "▾",
+                    (true, false) => "▸",
+                    (false, _) => "",
+                };
+                div()
+                    .id(("file-entry", ix))
+                    .flex()
+                    .items_center()
+                    .gap(px(4.))
+                    .pl(px(8. + depth as f32 * 12.))
+                    .pr(px(8.))
+                    .py(px(3.))
+                    .cursor_pointer()
+                    .when(active, |this| this.bg(Theme::panel_alt()))
+                    .hover(|this| this.bg(Theme::panel_alt()))
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |this, _: &MouseDownEvent, _, cx| {
+                            if is_dir {
+                                if let Some(tree) = this.workspace.as_mut() {
+                                    tree.toggle(&path);
+                                }
+                                this.list_shown_folders(cx);
+                                cx.notify();
+                            } else {
+                                cx.emit(FileExplorerEvent::OpenFile(path.clone()));
+                            }
+                        }),
+                    )
+                    .child(
+                        div()
+                            .w(px(10.))
+                            .flex_shrink_0()
+                            .text_xs()
+                            .text_color(Theme::muted())
+                            .child(chevron),
+                    )
+                    .child(
+                        div()
+                            .text_sm()
+                            .overflow_hidden()
+                            .flex_1()
+                            .text_color(if active {
+                                Theme::accent()
+                            } else {
+                                Theme::text()
+                            })
+                            .child(ellipsize_chars(&name, 64)),
+                    )
+            })
+            .collect();
+
+        div()
+            .id("files-scroll")
+            .flex_1()
+            .overflow_y_scroll()
+            .track_scroll(&self.files_scroll_handle)
+            .when(has_rows, |this| this.children(row_elements))
+            .when(!has_rows, |this| {
+                this.child(empty_message("No Markdown files"))
+            })
+            .into_any_element()
+    }
+
+    fn section_tab(
+        &self,
+        section: SidebarSection,
+        label: &'static str,
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        let selected = self.section == section;
+        div()
+            .id(label)
+            .cursor_pointer()
+            .text_color(if selected {
+                Theme::text()
+            } else {
+                Theme::muted()
+            })
+            .hover(|this| this.text_color(Theme::text()))
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _: &MouseDownEvent, _, cx| {
+                    this.section = section;
+                    cx.notify();
+                }),
+            )
+            .child(label)
+    }
+}
+
+impl Render for FileExplorerView {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let header = div()
+            .flex()
+            .gap(px(12.))
+            .px(px(10.))
+            .py(px(6.))
+            .text_xs()
+            .font_weight(gpui::FontWeight::BOLD)
+            .text_color(Theme::muted());
+        let (header, body) = if self.workspace.is_some() {
+            let header = header
+                .child(self.section_tab(SidebarSection::Files, "FILES", cx))
+                .child(self.section_tab(SidebarSection::Outline, "OUTLINE", cx));
+            let body = match self.section {
+                SidebarSection::Files => self.render_files(cx),
+                SidebarSection::Outline => self.render_outline(cx),
+            };
+            (header, body)
+        } else {
+            (header.child("OUTLINE"), self.render_outline(cx))
+        };
+
         div()
             .flex()
             .flex_col()
@@ -114,37 +363,21 @@ impl Render for FileExplorerView {
             .w(px(self.width))
             .bg(Theme::sidebar())
             .flex_shrink_0()
-            .child(
-                div()
-                    .px(px(10.))
-                    .py(px(6.))
-                    .text_xs()
-                    .font_weight(gpui::FontWeight::BOLD)
-                    .text_color(Theme::muted())
-                    .child("OUTLINE"),
-            )
-            .child(
-                div()
-                    .id("outline-scroll")
-                    .flex_1()
-                    .overflow_y_scroll()
-                    .track_scroll(&self.outline_scroll_handle)
-                    .when(has_outline, |this| this.children(outline_elements))
-                    .when(!has_outline, |this| {
-                        this.child(
-                            div()
-                                .px(px(10.))
-                                .py(px(8.))
-                                .text_sm()
-                                .text_color(Theme::muted())
-                                .child("No headings"),
-                        )
-                    }),
-            )
+            .child(header)
+            .child(body)
     }
 }
 
-fn parse_outline_items(text: &str) -> Vec<OutlineItem> {
+fn empty_message(text: &'static str) -> impl IntoElement {
+    div()
+        .px(px(10.))
+        .py(px(8.))
+        .text_sm()
+        .text_color(Theme::muted())
+        .child(text)
+}
+
+pub fn parse_outline_items(text: &str) -> Vec<OutlineItem> {
     let mut items = Vec::new();
     let mut byte_offset = 0usize;
 
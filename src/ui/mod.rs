@@ -1,5 +1,9 @@
 pub mod editor;
 pub mod file_explorer;
+pub mod preview;
+pub mod quick_nav;
+pub mod quick_open;
 pub mod root;
 pub mod text_utils;
 pub mod theme;
+pub mod vim;
@@ -0,0 +1,530 @@
+use crate::services::code_highlight::{CodeToken, code_hash, highlight_code};
+use crate::services::images::resolve_local_image;
+use crate::services::markdown_ast::{
+    Block, BlockKind, Inline, ListItem, MarkdownDocument, parse_markdown,
+};
+use crate::services::settings;
+use crate::ui::editor::code_token_style;
+use crate::ui::theme::Theme;
+use camino::{Utf8Path, Utf8PathBuf};
+use gpui::prelude::FluentBuilder as _;
+use gpui::{
+    AnyElement, Context, EntityId, FontStyle, FontWeight, HighlightStyle, InteractiveElement,
+    IntoElement, ParentElement, Render, ScrollHandle, StatefulInteractiveElement,
+    StrikethroughStyle, Styled, StyledImage, StyledText, UnderlineStyle, Window, div, img, point,
+    px,
+};
+use pulldown_cmark::Alignment;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Rendered preview of a document revision, built off the UI thread.
+#[derive(Clone, Default)]
+pub struct PreviewContent {
+    pub document: MarkdownDocument,
+    /// Code block highlights keyed by [`code_hash`].
+    pub code: HashMap<u64, Arc<Vec<CodeToken>>>,
+}
+
+/// Parses `source` for the preview, reusing code highlights from `previous`.
+pub fn build_preview(source: &str, previous: &HashMap<u64, Arc<Vec<CodeToken>>>) -> PreviewContent {
+    let document = parse_markdown(source);
+    let mut code = HashMap::new();
+    let mut pending = Vec::new();
+    collect_code_blocks(&document.blocks, &mut pending);
+    for (language, body) in pending {
+        let hash = code_hash(language, body);
+        let tokens = previous
+            .get(&hash)
+            .cloned()
+            .unwrap_or_else(|| Arc::new(highlight_code(language, body)));
+        code.insert(hash, tokens);
+    }
+    PreviewContent { document, code }
+}
+
+fn collect_code_blocks<'a>(blocks: &'a [Block], out: &mut Vec<(&'a str, &'a str)>) {
+    for block in blocks {
+        match &block.kind {
+            BlockKind::CodeBlock {
+                language: Some(language),
+                code,
+            } => out.push((language, code)),
+            BlockKind::BlockQuote(children)
+            | BlockKind::FootnoteDefinition {
+                blocks: children, ..
+            } => collect_code_blocks(children, out),
+            BlockKind::List { items, .. } => {
+                for item in items {
+                    collect_code_blocks(&item.blocks, out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Side-by-side preview pane rendering the document as native elements.
+pub struct PreviewView {
+    /// Document (tab) the preview follows; builds for other documents are dropped.
+    document: Option<EntityId>,
+    content: Arc<PreviewContent>,
+    /// Document revision `content` was built from.
+    revision: u64,
+    /// Document path, for resolving relative image paths.
+    doc_path: Option<Utf8PathBuf>,
+    scroll_handle: ScrollHandle,
+    /// Editor source line the preview was last scrolled to.
+    synced_line: Option<usize>,
+}
+
+impl PreviewView {
+    pub fn new() -> Self {
+        Self {
+            document: None,
+            content: Arc::new(PreviewContent::default()),
+            revision: 0,
+            doc_path: None,
+            scroll_handle: ScrollHandle::new(),
+            synced_line: None,
+        }
+    }
+
+    pub fn content(&self) -> Arc<PreviewContent> {
+        self.content.clone()
+    }
+
+    /// Switch to previewing `document`, clearing what was shown for another one.
+    pub fn follow(&mut self, document: EntityId, cx: &mut Context<Self>) {
+        if self.document == Some(document) {
+            return;
+        }
+        self.document = Some(document);
+        self.content = Arc::new(PreviewContent::default());
+        self.revision = 0;
+        self.synced_line = None;
+        cx.notify();
+    }
+
+    /// Show `content` built from `revision` of `document` unless a newer build
+    /// already landed or the preview follows another document. Returns whether
+    /// the preview changed.
+    pub fn apply(
+        &mut self,
+        document: EntityId,
+        revision: u64,
+        content: PreviewContent,
+        doc_path: Option<Utf8PathBuf>,
+        cx: &mut Context<Self>,
+    ) -> bool {
+        if self.document != Some(document) || revision < self.revision {
+            return false;
+        }
+        self.content = Arc::new(content);
+        self.revision = revision;
+        self.doc_path = doc_path;
+        // Block positions change with the content; scroll again on the next sync.
+        self.synced_line = None;
+        cx.notify();
+        true
+    }
+
+    /// Scroll so the block containing source `line` is at the top, interpolating
+    /// between block positions to follow the editor smoothly.
+    pub fn sync_to_line(&mut self, line: usize) {
+        if self.synced_line == Some(line) {
+            return;
+        }
+        let blocks = &self.content.document.blocks;
+        let ix = blocks.partition_point(|block| block.line <= line);
+        if ix == 0 {
+            self.scroll_handle.set_offset(point(px(0.), px(0.)));
+            self.synced_line = Some(line);
+            return;
+        }
+        let ix = ix - 1;
+        let Some(bounds) = self.scroll_handle.bounds_for_item(ix) else {
+            return;
+        };
+        let mut top = bounds.top();
+        if let (Some(next_block), Some(next)) = (
+            blocks.get(ix + 1),
+            self.scroll_handle.bounds_for_item(ix + 1),
+        ) {
+            let span = next_block.line.saturating_sub(blocks[ix].line).max(1);
+            let fraction = (line - blocks[ix].line) as f32 / span as f32;
+            top += (next.top() - bounds.top()) * fraction.min(1.);
+        }
+
+        let max = self.scroll_handle.max_offset().height;
+        let offset_y = (self.scroll_handle.bounds().top() - top).clamp(-max, px(0.));
+        self.scroll_handle.set_offset(point(px(0.), offset_y));
+        self.synced_line = Some(line);
+    }
+}
+
+impl Render for PreviewView {
+    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+        let font_size = settings::get_font_size();
+        let renderer = BlockRenderer {
+            content: self.content.clone(),
+            doc_path: self.doc_path.clone(),
+            font_size,
+        };
+
+        // Blocks are direct children so the scroll handle tracks their bounds.
+        div()
+            .id("preview_scroll")
+            .size_full()
+            .min_w(px(0.))
+            .bg(Theme::panel())
+            .px(px(24.))
+            .py(px(18.))
+            .flex()
+            .flex_col()
+            .gap(px(font_size * 0.75))
+            .text_size(px(font_size))
+            .text_color(Theme::text())
+            .overflow_y_scroll()
+            .overflow_x_hidden()
+            .track_scroll(&self.scroll_handle)
+            .children(
+                self.content
+                    .document
+                    .blocks
+                    .iter()
+                    .map(|block| renderer.block(block)),
+            )
+    }
+}
+
+struct BlockRenderer {
+    content: Arc<PreviewContent>,
+    doc_path: Option<Utf8PathBuf>,
+    font_size: f32,
+}
+
+impl BlockRenderer {
+    fn blocks(&self, blocks: &[Block]) -> AnyElement {
+        div()
+            .flex()
+            .flex_col()
+            .gap(px(self.font_size * 0.5))
+            .children(blocks.iter().map(|block| self.block(block)))
+            .into_any_element()
+    }
+
+    fn block(&self, block: &Block) -> AnyElement {
+        match &block.kind {
+            BlockKind::Paragraph(inlines) => self.inlines(inlines),
+            BlockKind::Heading { level, content } => {
+                let scale = match level {
+                    1 => 2.0,
+                    2 => 1.6,
+                    3 => 1.3,
+                    4 => 1.15,
+                    _ => 1.0,
+                };
+                div()
+                    .text_size(px(self.font_size * scale))
+                    .font_weight(FontWeight::BOLD)
+                    .when(*level <= 2, |this| {
+                        this.pb(px(4.)).border_b_1().border_color(Theme::border())
+                    })
+                    .child(self.inlines(content))
+                    .into_any_element()
+            }
+            BlockKind::BlockQuote(children) => div()
+                .pl(px(12.))
+                .border_l(px(3.))
+                .border_color(Theme::border())
+                .text_color(Theme::muted())
+                .child(self.blocks(children))
+                .into_any_element(),
+            BlockKind::List { start, items } => self.list(*start, items),
+            BlockKind::CodeBlock { language, code } => self.code_block(language.as_deref(), code),
+            BlockKind::Table {
+                alignments,
+                header,
+                rows,
+            } => self.table(alignments, header, rows),
+            BlockKind::Rule => div().h(px(1.)).bg(Theme::border()).into_any_element(),
+            BlockKind::Html(html) => div()
+                .font_family("Menlo")
+                .text_color(Theme::muted())
+                .child(html.trim_end().to_string())
+                .into_any_element(),
+            BlockKind::FootnoteDefinition { label, blocks } => div()
+                .flex()
+                .gap_2()
+                .text_size(px(self.font_size * 0.9))
+                .child(
+                    div()
+                        .flex_shrink_0()
+                        .text_color(Theme::accent())
+                        .child(format!("[{label}]")),
+                )
+                .child(div().flex_1().min_w(px(0.)).child(self.blocks(blocks)))
+                .into_any_element(),
+        }
+    }
+
+    fn list(&self, start: Option<u64>, items: &[ListItem]) -> AnyElement {
+        div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .children(items.iter().enumerate().map(|(ix, item)| {
+                let marker = match (item.checked, start) {
+                    (Some(true), _) => "☑".to_string(),
+                    (Some(false), _) => "☐".to_string(),
+                    (None, Some(start)) => format!("{}.", start + ix as u64),
+                    (None, None) => "•".to_string(),
+                };
+                div()
+                    .flex()
+                    .gap_2()
+                    .child(
+                        div()
+                            .flex_shrink_0()
+                            .min_w(px(self.font_size * 1.2))
+                            .text_color(Theme::muted())
+                            .child(marker),
+                    )
+                    .child(
+                        div()
+                            .flex_1()
+                            .min_w(px(0.))
+                            .child(self.blocks(&item.blocks)),
+                    )
+            }))
+            .into_any_element()
+    }
+
+    fn code_block(&self, language: Option<&str>, code: &str) -> AnyElement {
+        let tokens = language
+            .and_then(|language| self.content.code.get(&code_hash(language, code)))
+            .map(|tokens| {
+                tokens
+                    .iter()
+                    .filter(|token| token.range.end <= code.len())
+                    .map(|token| (token.range.clone(), code_token_style(token.kind)))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let code = code.strip_suffix('\n').unwrap_or(code);
+        div()
+            .p(px(10.))
+            .rounded(px(6.))
+            .bg(Theme::code_block_bg())
+            .font_family("Menlo")
+            .text_size(px(self.font_size * 0.9))
+            .overflow_x_hidden()
+            .child(StyledText::new(code.to_string()).with_highlights(tokens))
+            .into_any_element()
+    }
+
+    fn table(
+        &self,
+        alignments: &[Alignment],
+        header: &[Vec<Inline>],
+        rows: &[Vec<Vec<Inline>>],
+    ) -> AnyElement {
+        let row = |cells: &[Vec<Inline>], is_header: bool| {
+            div()
+                .flex()
+                .border_b_1()
+                .border_color(Theme::border())
+                .when(is_header, |this| {
+                    this.bg(Theme::panel_alt()).font_weight(FontWeight::BOLD)
+                })
+                .children(cells.iter().enumerate().map(|(ix, cell)| {
+                    let cell_div = div().flex_1().min_w(px(0.)).px_2().py_1().flex();
+                    match alignments.get(ix) {
+                        Some(Alignment::Center) => cell_div.justify_center(),
+                        Some(Alignment::Right) => cell_div.justify_end(),
+                        _ => cell_div,
+                    }
+                    .child(self.inlines(cell))
+                }))
+        };
+        div()
+            .flex()
+            .flex_col()
+            .border_1()
+            .border_color(Theme::border())
+            .child(row(header, true))
+            .children(rows.iter().map(|cells| row(cells, false)))
+            .into_any_element()
+    }
+
+    /// Inline content as styled text, with images as separate elements.
+    fn inlines(&self, inlines: &[Inline]) -> AnyElement {
+        let mut pieces = Vec::new();
+        let mut text = InlineText::default();
+        text.push_all(inlines, HighlightStyle::default(), &mut pieces);
+        pieces.push(Piece::Text(text));
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .children(pieces.into_iter().filter_map(|piece| {
+                match piece {
+                    Piece::Text(text) if text.text.trim().is_empty() => None,
+                    Piece::Text(text) => Some(
+                        StyledText::new(text.text)
+                            .with_highlights(text.highlights)
+                            .into_any_element(),
+                    ),
+                    Piece::Image { url, alt } => Some(self.image(&url, alt)),
+                }
+            }))
+            .into_any_element()
+    }
+
+    fn image(&self, url: &str, alt: String) -> AnyElement {
+        let Some(path) = resolve_local_image(self.doc_path.as_deref().map(Utf8Path::new), url)
+        else {
+            return div()
+                .text_color(Theme::muted())
+                .italic()
+                .child(format!("[{alt}]"))
+                .into_any_element();
+        };
+        let missing = format!("[Image not found: {url}]");
+        img(path)
+            .max_w_full()
+            .with_fallback(move || {
+                div()
+                    .text_color(Theme::muted())
+                    .italic()
+                    .child(missing.clone())
+                    .into_any_element()
+            })
+            .into_any_element()
+    }
+}
+
+enum Piece {
+    Text(InlineText),
+    Image { url: String, alt: String },
+}
+
+#[derive(Default)]
+struct InlineText {
+    text: String,
+    highlights: Vec<(Range<usize>, HighlightStyle)>,
+}
+
+impl InlineText {
+    fn push_all(&mut self, inlines: &[Inline], style: HighlightStyle, pieces: &mut Vec<Piece>) {
+        for inline in inlines {
+            self.push(inline, style, pieces);
+        }
+    }
+
+    fn push(&mut self, inline: &Inline, style: HighlightStyle, pieces: &mut Vec<Piece>) {
+        match inline {
+            Inline::Text(text) => self.push_str(text, style),
+            Inline::SoftBreak => self.push_str(" ", style),
+            Inline::HardBreak => self.push_str("\n", style),
+            Inline::Code(code) => self.push_str(
+                code,
+                HighlightStyle {
+                    color: Some(gpui::rgb(0x1f6f8b).into()),
+                    background_color: Some(Theme::code_block_bg().into()),
+                    ..style
+                },
+            ),
+            Inline::Html(html) => self.push_str(
+                html,
+                HighlightStyle {
+                    color: Some(Theme::muted().into()),
+                    ..style
+                },
+            ),
+            Inline::FootnoteReference(label) => self.push_str(
+                &format!("[{label}]"),
+                HighlightStyle {
+                    color: Some(Theme::accent().into()),
+                    ..style
+                },
+            ),
+            Inline::Emphasis(children) => self.push_all(
+                children,
+                HighlightStyle {
+                    font_style: Some(FontStyle::Italic),
+                    ..style
+                },
+                pieces,
+            ),
+            Inline::Strong(children) => self.push_all(
+                children,
+                HighlightStyle {
+                    font_weight: Some(FontWeight::BOLD),
+                    ..style
+                },
+                pieces,
+            ),
+            Inline::Strikethrough(children) => self.push_all(
+                children,
+                HighlightStyle {
+                    strikethrough: Some(StrikethroughStyle {
+                        thickness: px(1.),
+                        color: None,
+                    }),
+                    ..style
+                },
+                pieces,
+            ),
+            Inline::Link { content, .. } => self.push_all(
+                content,
+                HighlightStyle {
+                    color: Some(Theme::accent().into()),
+                    underline: Some(UnderlineStyle {
+                        thickness: px(1.),
+                        color: Some(Theme::accent().into()),
+                        wavy: false,
+                    }),
+                    ..style
+                },
+                pieces,
+            ),
+            Inline::Image { url, alt, .. } => {
+                pieces.push(Piece::Text(std::mem::take(self)));
+                pieces.push(Piece::Image {
+                    url: url.clone(),
+                    alt: alt.clone(),
+                });
+            }
+        }
+    }
+
+    fn push_str(&mut self, text: &str, style: HighlightStyle) {
+        let start = self.text.len();
+        self.text.push_str(text);
+        if style != HighlightStyle::default() && !text.is_empty() {
+            self.highlights.push((start..self.text.len(), style));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_preview_reuses_highlights_of_unchanged_code() {
+        let source = "# Title\n\n```rust\nfn main() {}\n```\n\n- ```python\n  x = \"a\"\n  ```\n";
+        let first = build_preview(source, &HashMap::new());
+        assert_eq!(first.code.len(), 2);
+        assert!(first.code.values().all(|tokens| !tokens.is_empty()));
+
+        let second = build_preview(&source.replace("Title", "Renamed"), &first.code);
+        for (hash, tokens) in &second.code {
+            assert!(Arc::ptr_eq(tokens, &first.code[hash]));
+        }
+    }
+}
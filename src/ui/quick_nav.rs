@@ -0,0 +1,180 @@
+use crate::services::fuzzy::fuzzy_score;
+use crate::ui::file_explorer::OutlineItem;
+use ropey::Rope;
+
+/// Maximum number of heading candidates listed in the picker.
+const MAX_HEADING_RESULTS: usize = 12;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum QuickNavKind {
+    /// "Go to line[:column]" prompt.
+    Line,
+    /// Fuzzy picker over the document outline.
+    Heading,
+}
+
+/// 1-based line and optional column typed into the go-to-line prompt.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LineColumn {
+    pub line: usize,
+    pub column: Option<usize>,
+}
+
+/// State of the go-to-line / go-to-heading overlay in `EditorView`.
+pub struct QuickNavState {
+    pub kind: QuickNavKind,
+    pub query: String,
+    pub selected: usize,
+    headings: Vec<OutlineItem>,
+}
+
+impl QuickNavState {
+    pub fn line() -> Self {
+        Self {
+            kind: QuickNavKind::Line,
+            query: String::new(),
+            selected: 0,
+            headings: Vec::new(),
+        }
+    }
+
+    pub fn heading(headings: Vec<OutlineItem>) -> Self {
+        Self {
+            kind: QuickNavKind::Heading,
+            query: String::new(),
+            selected: 0,
+            headings,
+        }
+    }
+
+    pub fn push_str(&mut self, text: &str) {
+        self.query.push_str(text);
+        self.selected = 0;
+    }
+
+    pub fn pop_char(&mut self) {
+        self.query.pop();
+        self.selected = 0;
+    }
+
+    pub fn select_next(&mut self) {
+        let count = self.heading_matches().len();
+        if count > 0 {
+            self.selected = (self.selected + 1) % count;
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        let count = self.heading_matches().len();
+        if count > 0 {
+            self.selected = (self.selected + count - 1) % count;
+        }
+    }
+
+    /// Headings matching the query, best match first.
+    pub fn heading_matches(&self) -> Vec<&OutlineItem> {
+        let mut scored: Vec<(i64, &OutlineItem)> = self
+            .headings
+            .iter()
+            .filter_map(|item| fuzzy_score(&self.query, &item.title).map(|score| (score, item)))
+            .collect();
+        // Stable sort keeps document order for equal scores.
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        scored
+            .into_iter()
+            .take(MAX_HEADING_RESULTS)
+            .map(|(_, item)| item)
+            .collect()
+    }
+
+    pub fn selected_heading(&self) -> Option<&OutlineItem> {
+        self.heading_matches().get(self.selected).copied()
+    }
+}
+
+/// Parses `line`, `line:column` (1-based) from the go-to-line prompt.
+pub fn parse_line_column(query: &str) -> Option<LineColumn> {
+    let query = query.trim();
+    let (line, column) = match query.split_once(':') {
+        Some((line, column)) => (line.trim(), Some(column.trim())),
+        None => (query, None),
+    };
+    let line = line.parse::<usize>().ok()?;
+    let column = match column {
+        Some("") | None => None,
+        Some(column) => Some(column.parse::<usize>().ok()?),
+    };
+    Some(LineColumn { line, column })
+}
+
+/// Resolves a 1-based line/column to a char index, clamped to the document.
+pub fn line_column_to_char(rope: &Rope, target: LineColumn) -> usize {
+    let line = target.line.saturating_sub(1).min(rope.len_lines() - 1);
+    let line_start = rope.line_to_char(line);
+    let slice = rope.line(line);
+    let mut line_len = slice.len_chars();
+    if line_len > 0 && slice.char(line_len - 1) == '\n' {
+        line_len -= 1;
+    }
+    let column = target.column.unwrap_or(1).saturating_sub(1).min(line_len);
+    line_start + column
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::file_explorer::parse_outline_items;
+
+    #[test]
+    fn parses_line_and_optional_column() {
+        assert_eq!(
+            parse_line_column("12"),
+            Some(LineColumn {
+                line: 12,
+                column: None
+            })
+        );
+        assert_eq!(
+            parse_line_column(" 3:7 "),
+            Some(LineColumn {
+                line: 3,
+                column: Some(7)
+            })
+        );
+        assert_eq!(parse_line_column("4:").map(|t| t.column), Some(None));
+        assert_eq!(parse_line_column("abc"), None);
+        assert_eq!(parse_line_column("2:x"), None);
+    }
+
+    #[test]
+    fn line_column_is_clamped_to_document() {
+        let rope = Rope::from_str("first\nsecond\nthird");
+        let at = |line, column| line_column_to_char(&rope, LineColumn { line, column });
+        assert_eq!(at(1, None), 0);
+        assert_eq!(at(2, Some(3)), 8);
+        assert_eq!(at(2, Some(99)), 12);
+        assert_eq!(at(99, None), 13);
+        assert_eq!(at(0, None), 0);
+    }
+
+    #[test]
+    fn heading_picker_ranks_fuzzy_matches() {
+        let outline =
+            parse_outline_items("# Intro\n## Installation\n## Usage\n### Install notes\n");
+        let mut nav = QuickNavState::heading(outline);
+        assert_eq!(nav.heading_matches().len(), 4);
+
+        nav.push_str("inst");
+        let titles: Vec<_> = nav
+            .heading_matches()
+            .iter()
+            .map(|item| item.title.as_str())
+            .collect();
+        assert_eq!(titles, vec!["Installation", "Install notes"]);
+
+        nav.select_next();
+        assert_eq!(nav.selected_heading().unwrap().title, "Install notes");
+        nav.select_next();
+        assert_eq!(nav.selected_heading().unwrap().title, "Installation");
+    }
+}
@@ -0,0 +1,368 @@
+use crate::services::fs::walk_markdown_files;
+use crate::services::fuzzy::fuzzy_score;
+use crate::services::settings;
+use crate::ui::file_explorer::parse_outline_items;
+use crate::ui::text_utils::ellipsize_chars;
+use crate::ui::theme::Theme;
+use camino::{Utf8Path, Utf8PathBuf};
+use gpui::prelude::FluentBuilder as _;
+use gpui::{
+    App, Context, EventEmitter, FocusHandle, Focusable, FontWeight, InteractiveElement,
+    IntoElement, KeyDownEvent, MouseButton, MouseDownEvent, ParentElement, Render, Styled, Window,
+    div, px,
+};
+use std::cmp::Reverse;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// Maximum number of files listed in the picker.
+const MAX_RESULTS: usize = 12;
+/// Bytes read from the top of each file to find its first heading.
+const HEADING_SCAN_BYTES: u64 = 4096;
+/// Score bonus for the most recently opened file; older ones get less.
+const RECENT_BONUS: i64 = 20;
+
+/// A Markdown file of the workspace as listed by quick open.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexedFile {
+    pub path: Utf8PathBuf,
+    /// Path relative to the workspace root, which the query is matched against.
+    pub relative: String,
+    pub heading: Option<String>,
+    pub modified: Option<SystemTime>,
+}
+
+/// Indexes the Markdown files under `root`. Reads the top of every file, so
+/// run it on the background executor.
+pub fn index_workspace(root: &Utf8Path) -> Vec<IndexedFile> {
+    walk_markdown_files(root)
+        .into_iter()
+        .map(|path| IndexedFile {
+            relative: path.strip_prefix(root).unwrap_or(&path).to_string(),
+            heading: first_heading(&path),
+            modified: path.metadata().and_then(|meta| meta.modified()).ok(),
+            path,
+        })
+        .collect()
+}
+
+fn first_heading(path: &Utf8Path) -> Option<String> {
+    let mut head = Vec::new();
+    std::fs::File::open(path)
+        .ok()?
+        .take(HEADING_SCAN_BYTES)
+        .read_to_end(&mut head)
+        .ok()?;
+    let text = String::from_utf8_lossy(&head);
+    parse_outline_items(&text)
+        .into_iter()
+        .next()
+        .map(|item| item.title)
+}
+
+/// Indices of the files matching `query`, best first: the fuzzy score of the path (or of the
+/// file name alone, if better) plus a bonus for files in `recent`, most recent
+/// first. Ties go to the file modified last.
+pub fn rank_files(files: &[IndexedFile], query: &str, recent: &[PathBuf]) -> Vec<usize> {
+    let mut scored: Vec<(i64, usize)> = files
+        .iter()
+        .enumerate()
+        .filter_map(|(ix, file)| {
+            let name = file.path.file_name().unwrap_or_default();
+            let score = fuzzy_score(query, &file.relative)
+                .into_iter()
+                .chain(fuzzy_score(query, name))
+                .max()?;
+            let bonus = recent
+                .iter()
+                .position(|path| path == file.path.as_std_path())
+                .map_or(0, |ix| (RECENT_BONUS - ix as i64).max(1));
+            Some((score + bonus, ix))
+        })
+        .collect();
+    scored.sort_by_key(|(score, ix)| (Reverse(*score), Reverse(files[*ix].modified)));
+    scored.into_iter().map(|(_, ix)| ix).collect()
+}
+
+pub enum QuickOpenEvent {
+    /// A file was chosen.
+    Open(Utf8PathBuf),
+    /// The picker was closed without a choice.
+    Dismiss,
+}
+
+/// Fuzzy finder over the workspace's Markdown files, shown over the editor.
+pub struct QuickOpenView {
+    focus_handle: FocusHandle,
+    /// Workspace files; `None` until the first index arrives.
+    files: Option<Arc<Vec<IndexedFile>>>,
+    /// Recently opened files, most recent first.
+    recent: Vec<PathBuf>,
+    query: String,
+    /// Indices into `files` of the listed matches, ranked when the query or
+    /// the files change.
+    matches: Vec<usize>,
+    selected: usize,
+}
+
+impl EventEmitter<QuickOpenEvent> for QuickOpenView {}
+
+impl QuickOpenView {
+    pub fn new(files: Option<Arc<Vec<IndexedFile>>>, cx: &mut Context<Self>) -> Self {
+        let recent = settings::get_recent()
+            .entries()
+            .map(|entry| entry.path.clone())
+            .collect();
+        let mut view = Self {
+            focus_handle: cx.focus_handle(),
+            files,
+            recent,
+            query: String::new(),
+            matches: Vec::new(),
+            selected: 0,
+        };
+        view.rank();
+        view
+    }
+
+    /// Replaces the listed files with a fresh index.
+    pub fn set_files(&mut self, files: Arc<Vec<IndexedFile>>, cx: &mut Context<Self>) {
+        self.files = Some(files);
+        self.rank();
+        cx.notify();
+    }
+
+    fn rank(&mut self) {
+        self.matches = self
+            .files
+            .as_ref()
+            .map(|files| rank_files(files, &self.query, &self.recent))
+            .unwrap_or_default();
+        self.matches.truncate(MAX_RESULTS);
+        self.selected = 0;
+    }
+
+    fn matches(&self) -> impl Iterator<Item = &IndexedFile> {
+        let files = self.files.as_deref().map_or(&[][..], Vec::as_slice);
+        self.matches.iter().filter_map(|ix| files.get(*ix))
+    }
+
+    fn accept(&mut self, cx: &mut Context<Self>) {
+        if let Some(file) = self.matches().nth(self.selected) {
+            cx.emit(QuickOpenEvent::Open(file.path.clone()));
+        }
+    }
+
+    fn handle_key(&mut self, event: &KeyDownEvent, cx: &mut Context<Self>) {
+        let count = self.matches.len();
+        match event.keystroke.key.to_lowercase().as_str() {
+            "escape" => cx.emit(QuickOpenEvent::Dismiss),
+            "enter" | "return" => self.accept(cx),
+            "up" | "arrowup" if count > 0 => self.selected = (self.selected + count - 1) % count,
+            "down" | "arrowdown" if count > 0 => self.selected = (self.selected + 1) % count,
+            "backspace" => {
+                self.query.pop();
+                self.rank();
+            }
+            _ => {
+                let modifiers = event.keystroke.modifiers;
+                if modifiers.platform || modifiers.control {
+                    return;
+                }
+                if let Some(raw) = &event.keystroke.key_char
+                    && raw != "\n"
+                    && raw != "\r"
+                {
+                    self.query.push_str(raw);
+                    self.rank();
+                }
+            }
+        }
+        cx.notify();
+    }
+}
+
+impl Focusable for QuickOpenView {
+    fn focus_handle(&self, _: &App) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Render for QuickOpenView {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let entries: Vec<_> = self
+            .matches()
+            .enumerate()
+            .map(|(ix, file)| {
+                div()
+                    .id(("quick-open-entry", ix))
+                    .flex()
+                    .flex_col()
+                    .px(px(8.))
+                    .py(px(3.))
+                    .rounded(px(4.))
+                    .cursor_pointer()
+                    .when(ix == self.selected, |this| this.bg(Theme::selection_bg()))
+                    .hover(|this| this.bg(Theme::panel()))
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |this, _: &MouseDownEvent, _, cx| {
+                            cx.stop_propagation();
+                            this.selected = ix;
+                            this.accept(cx);
+                        }),
+                    )
+                    .child(div().text_sm().child(ellipsize_chars(&file.relative, 64)))
+                    .when_some(file.heading.as_ref(), |this, heading| {
+                        this.child(
+                            div()
+                                .text_xs()
+                                .text_color(Theme::muted())
+                                .child(ellipsize_chars(heading, 72)),
+                        )
+                    })
+            })
+            .collect();
+        let status = match &self.files {
+            None => Some("Indexing workspace…"),
+            Some(_) if entries.is_empty() => Some("No matching files"),
+            Some(_) => None,
+        };
+
+        div()
+            .id("quick-open")
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(|this, event: &KeyDownEvent, _, cx| {
+                this.handle_key(event, cx);
+            }))
+            .absolute()
+            .top(px(40.))
+            .left_0()
+            .right_0()
+            .flex()
+            .justify_center()
+            .child(
+                div()
+                    .w(px(480.))
+                    .flex()
+                    .flex_col()
+                    .gap_1()
+                    .px(px(10.))
+                    .py(px(6.))
+                    .rounded(px(6.))
+                    .bg(Theme::panel_alt())
+                    .border_1()
+                    .border_color(Theme::border())
+                    .child(
+                        div()
+                            .flex()
+                            .items_center()
+                            .gap_2()
+                            .child(
+                                div()
+                                    .text_xs()
+                                    .font_weight(FontWeight::BOLD)
+                                    .text_color(Theme::muted())
+                                    .child("OPEN FILE"),
+                            )
+                            .child(
+                                div()
+                                    .text_sm()
+                                    .overflow_hidden()
+                                    .text_color(if self.query.is_empty() {
+                                        Theme::muted()
+                                    } else {
+                                        Theme::text()
+                                    })
+                                    .child(if self.query.is_empty() {
+                                        "Type to filter workspace files".to_string()
+                                    } else {
+                                        ellipsize_chars(&self.query, 48)
+                                    }),
+                            ),
+                    )
+                    .children(entries)
+                    .when_some(status, |this, status| {
+                        this.child(div().text_sm().text_color(Theme::muted()).child(status))
+                    }),
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn file(relative: &str, age_secs: u64) -> IndexedFile {
+        IndexedFile {
+            path: Utf8PathBuf::from("/vault").join(relative),
+            relative: relative.to_string(),
+            heading: None,
+            modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000 - age_secs)),
+        }
+    }
+
+    #[test]
+    fn ranks_by_match_then_recency() {
+        let files = vec![
+            file("journal/2024-05-01.md", 50),
+            file("projects/aster/roadmap.md", 30),
+            file("reading-list.md", 10),
+            file("recipes.md", 20),
+        ];
+        let relative = |ranked: Vec<usize>| -> Vec<String> {
+            ranked
+                .iter()
+                .map(|ix| files[*ix].relative.clone())
+                .collect()
+        };
+
+        // Without a query, recently opened files lead, then the newest.
+        let recent = vec![PathBuf::from("/vault/projects/aster/roadmap.md")];
+        assert_eq!(
+            relative(rank_files(&files, "", &recent)),
+            vec![
+                "projects/aster/roadmap.md",
+                "reading-list.md",
+                "recipes.md",
+                "journal/2024-05-01.md"
+            ]
+        );
+
+        assert_eq!(
+            relative(rank_files(&files, "rec", &[])),
+            vec!["recipes.md", "projects/aster/roadmap.md"]
+        );
+        let reading = vec![PathBuf::from("/vault/reading-list.md")];
+        assert_eq!(
+            relative(rank_files(&files, "rl", &reading))[0],
+            "reading-list.md"
+        );
+    }
+
+    #[test]
+    fn indexes_first_heading_of_each_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = Utf8Path::from_path(dir.path()).unwrap();
+        std::fs::create_dir(root.join("notes")).unwrap();
+        std::fs::write(
+            root.join("notes/plan.md"),
+            "---\ntags: [a]\n---\n\n## Plan\n# Later\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("empty.md"), "no headings").unwrap();
+
+        let index = index_workspace(root);
+        let headings: Vec<(&str, Option<&str>)> = index
+            .iter()
+            .map(|file| (file.relative.as_str(), file.heading.as_deref()))
+            .collect();
+        assert_eq!(
+            headings,
+            vec![("empty.md", None), ("notes/plan.md", Some("Plan"))]
+        );
+    }
+}
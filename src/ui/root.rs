@@ -1,69 +1,575 @@
 use crate::commands::{
-    CloseWindow, FontSizeDecrease, FontSizeIncrease, FontSizeReset, NewFile, OpenFile, SaveFile,
-    SaveFileAs,
+    ActivateTab, CloseTab, CloseWindow, ExportDocx, ExportEpub, ExportHtml, ExportPdf,
+    FontSizeDecrease, FontSizeIncrease, FontSizeReset, HideAllMarkers, NavigateBack,
+    NavigateForward, NewFile, NextPane, NextTab, OpenFile, OpenFolder, OpenRecent, PreviousPane,
+    PreviousTab, QuickOpen, RevealActiveFile, RevealMarkersAtCursor, SaveFile, SaveFileAs,
+    ShowAllMarkers, SplitDown, SplitRight, TogglePreview, ToggleSidebar, ToggleVimMode,
 };
-use crate::model::document::DocumentState;
+use crate::model::document::{CursorState, DocumentState};
 use crate::model::inline_markdown::InlineMarkdownState;
+use crate::model::jump_list::JumpList;
+use crate::model::pane_layout::{PaneLayout, SplitAxis};
+use crate::model::recent::{RecentEntry, RecentKind, RecentList};
+use crate::model::tabs::TabList;
+use crate::services::code_highlight::highlight_code;
+use crate::services::diff::unified_diff;
+use crate::services::export::{ExportFormat, ExportOptions, export_to_path};
 use crate::services::fs::{
-    pick_open_markdown_path_async, pick_save_path_async, read_to_string, write_atomic,
+    DiskState, pick_export_path_async, pick_folder_async, pick_open_markdown_path_async,
+    pick_save_path_async, read_to_string, write_atomic,
 };
-use crate::services::inline_markdown::compute_inline_spans;
-use crate::services::settings::{self, Settings};
+use crate::services::inline_markdown::{compute_inline_spans, compute_inline_spans_incremental};
+use crate::services::recovery::{RecoveredDocument, RecoveryJournal};
+use crate::services::session::{
+    PaneSession, SavedBounds, SavedWindowState, TabSession, WindowSession,
+};
+use crate::services::settings::{self, AutosavePolicy, MarkerVisibility, Settings};
 use crate::services::tasks::Debouncer;
+use crate::services::watcher::{FileChange, FileWatcher};
 use crate::ui::editor::EditorView;
-use crate::ui::file_explorer::FileExplorerView;
+use crate::ui::file_explorer::{FileExplorerEvent, FileExplorerView};
+use crate::ui::preview::{PreviewView, build_preview};
+use crate::ui::quick_open::{IndexedFile, QuickOpenEvent, QuickOpenView, index_workspace};
 use crate::ui::theme::Theme;
 
-use camino::Utf8PathBuf;
+use camino::{Utf8Path, Utf8PathBuf};
+use futures::StreamExt as _;
+use gpui::prelude::FluentBuilder as _;
 use gpui::{
-    Context, Entity, InteractiveElement, IntoElement, MouseButton, MouseDownEvent, MouseMoveEvent,
-    ParentElement, Render, Styled, Window, div, px,
+    AnyElement, App, AppContext, Context, Div, Entity, EntityId, Focusable, InteractiveElement,
+    IntoElement, MouseButton, MouseDownEvent, MouseMoveEvent, NavigationDirection, ParentElement,
+    Render, SharedString, Stateful, StatefulInteractiveElement, Styled, Subscription, Window,
+    WindowBounds, div, px, svg,
 };
-use gpui_component::notification::NotificationList;
+use gpui_component::notification::{Notification, NotificationList};
 use rfd::{MessageButtons, MessageDialog, MessageDialogResult, MessageLevel};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
 const INLINE_SYNC_PARSE_MAX_BYTES: usize = 64 * 1024;
+/// How often unsaved documents are written to the recovery journal.
+const RECOVERY_JOURNAL_INTERVAL: Duration = Duration::from_secs(5);
 
-pub struct RootView {
+/// A document open in a tab, with the editor and parse state that belong to it.
+struct DocumentTab {
     document: Entity<DocumentState>,
     inline_markdown: Entity<InlineMarkdownState>,
-    editor_view: Entity<crate::ui::editor::EditorView>,
+    editor_view: Entity<EditorView>,
+}
+
+impl DocumentTab {
+    fn new_empty(cx: &mut App) -> Self {
+        let document = cx.new(|_| RootView::new_document());
+        let inline_markdown = cx.new(|_| RootView::new_inline_markdown());
+        let editor_view =
+            cx.new(|_| RootView::build_editor(document.clone(), inline_markdown.clone()));
+        Self {
+            document,
+            inline_markdown,
+            editor_view,
+        }
+    }
+
+    /// Another view of the same document, with its own editor (and so its own
+    /// scroll position and cursor).
+    fn share(&self, cx: &mut App) -> Self {
+        let editor_view =
+            cx.new(|_| RootView::build_editor(self.document.clone(), self.inline_markdown.clone()));
+        Self {
+            document: self.document.clone(),
+            inline_markdown: self.inline_markdown.clone(),
+            editor_view,
+        }
+    }
+
+    fn title(&self, cx: &App) -> SharedString {
+        let doc = self.document.read(cx);
+        doc.path
+            .as_ref()
+            .and_then(|p| p.file_name())
+            .unwrap_or("untitled.md")
+            .to_string()
+            .into()
+    }
+}
+
+/// A region of the editor area with its own tabs.
+struct Pane {
+    id: usize,
+    tabs: TabList<DocumentTab>,
+}
+
+/// Drag payload and drag preview for reordering tabs.
+#[derive(Clone)]
+struct DraggedTab {
+    pane: usize,
+    ix: usize,
+    title: SharedString,
+}
+
+impl Render for DraggedTab {
+    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .px(px(12.))
+            .py(px(4.))
+            .rounded(px(4.))
+            .bg(Theme::panel_alt())
+            .border_1()
+            .border_color(Theme::border())
+            .text_sm()
+            .text_color(Theme::text())
+            .child(self.title.clone())
+    }
+}
+
+/// File name of `path` for messages about it.
+/// A file's text as read on the background executor.
+struct DiskRead {
+    text: String,
+    /// `DocumentState::text_hash` of the text.
+    hash: u64,
+    disk_state: Option<DiskState>,
+}
+
+fn read_from_disk(path: &Utf8PathBuf) -> Option<DiskRead> {
+    let text = read_to_string(path).ok()?;
+    Some(DiskRead {
+        hash: DocumentState::text_hash(&text),
+        disk_state: DiskState::new(path, text.as_bytes()),
+        text,
+    })
+}
+
+fn file_label(path: &Utf8PathBuf) -> &str {
+    path.file_name().unwrap_or(path.as_str())
+}
+
+/// Adds `path` to File → Open Recent and, for files, the platform's list of
+/// recent documents.
+fn remember_recent(path: &Utf8PathBuf, kind: RecentKind, cx: &mut App) {
+    if kind == RecentKind::File {
+        cx.add_recent_document(path.as_std_path());
+    }
+    settings::update_recent(|recent| recent.add(path.as_std_path().to_path_buf(), kind));
+    crate::app::set_app_menus(cx);
+}
+
+/// A small bordered button for a notification's choices.
+fn notification_button(id: &'static str, label: &'static str) -> Stateful<Div> {
+    div()
+        .id(id)
+        .px(px(8.))
+        .py(px(2.))
+        .rounded(px(4.))
+        .border_1()
+        .border_color(Theme::border())
+        .cursor_pointer()
+        .text_sm()
+        .hover(|s| s.bg(Theme::panel_alt()))
+        .child(label)
+}
+
+pub struct RootView {
+    panes: Vec<Pane>,
+    /// Arrangement of `panes` in the editor area.
+    pane_layout: PaneLayout,
+    /// Id of the pane holding keyboard focus.
+    active_pane: usize,
+    next_pane_id: usize,
+    /// The editor that owns its document's cursor; other editors on the same
+    /// document keep theirs parked.
+    active_editor: Entity<EditorView>,
     file_explorer_view: Entity<crate::ui::file_explorer::FileExplorerView>,
+    preview_view: Entity<PreviewView>,
     notifications: Entity<NotificationList>,
+    /// Watches the files of open documents for changes made by other programs.
+    file_watcher: FileWatcher,
     inline_debounce: Debouncer<RootView>,
+    preview_debounce: Debouncer<RootView>,
+    /// When documents are saved without an explicit Save.
+    autosave: AutosavePolicy,
+    autosave_debounce: Debouncer<RootView>,
+    /// Document and revision the pending idle autosave was scheduled for.
+    scheduled_autosave: Option<(EntityId, u64)>,
+    recovery_journal: RecoveryJournal,
+    /// Revision of each document last written to the recovery journal.
+    journaled: HashMap<EntityId, u64>,
+    /// Whether the rendered preview pane is shown beside the editor.
+    preview_visible: bool,
+    /// Latest document revision a preview build has been scheduled for.
+    scheduled_preview_revision: Option<u64>,
     /// Highest document revision for which an inline parse has been scheduled.
     scheduled_inline_revision: u64,
+    /// Content hashes of fenced code blocks being highlighted in the background.
+    pending_code_highlights: HashSet<u64>,
     /// Cached document text to avoid O(n) rope-to-string conversion every frame
     cached_doc_text: Option<(u64, String)>,
     /// Current font size in points (8-32)
     font_size: f32,
     /// Current sidebar width in pixels
     sidebar_width: f32,
+    /// Whether the file explorer sidebar is shown
+    sidebar_visible: bool,
     /// Whether we're currently resizing the sidebar
     resizing_sidebar: bool,
+    /// Move keyboard focus to the active tab's editor on the next frame.
+    focus_editor: bool,
+    /// Whether New was chosen on the welcome screen, showing the blank tab.
+    welcome_dismissed: bool,
+    /// Recent list shown on the welcome screen, with whether each entry
+    /// still exists. Checked when the list changes, not every frame.
+    welcome_recent: Option<(RecentList, Vec<bool>)>,
+    /// The quick open picker while it is shown.
+    quick_open: Option<(Entity<QuickOpenView>, Subscription)>,
+    /// Last index of the workspace folder, listed while quick open re-indexes.
+    workspace_index: Option<(Utf8PathBuf, Arc<Vec<IndexedFile>>)>,
+    _subscriptions: Vec<Subscription>,
 }
 
 impl RootView {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         document: Entity<DocumentState>,
         inline_markdown: Entity<InlineMarkdownState>,
         editor_view: Entity<crate::ui::editor::EditorView>,
         file_explorer_view: Entity<crate::ui::file_explorer::FileExplorerView>,
+        preview_view: Entity<PreviewView>,
         notifications: Entity<NotificationList>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
     ) -> Self {
+        let subscriptions = vec![
+            cx.subscribe_in(
+                &file_explorer_view,
+                window,
+                |this, _, event: &FileExplorerEvent, window, cx| match event {
+                    FileExplorerEvent::OpenFile(path) => {
+                        this.action_open_path(path.clone(), window, cx);
+                    }
+                },
+            ),
+            cx.observe_window_activation(window, |this, window, cx| {
+                if this.autosave.on_blur && !window.is_window_active() {
+                    for document in this.documents() {
+                        this.autosave_document(&document, cx);
+                    }
+                }
+            }),
+        ];
+        let autosave = settings::get_autosave_policy();
+        preview_view.update(cx, |preview, cx| preview.follow(document.entity_id(), cx));
+        let (file_watcher, mut file_events) = FileWatcher::new();
+        cx.spawn_in(window, async move |this, cx| {
+            while let Some(event) = file_events.next().await {
+                // A save often arrives as several events; handle them together.
+                cx.background_executor()
+                    .timer(Duration::from_millis(100))
+                    .await;
+                let mut events = vec![event];
+                while let Ok(Some(event)) = file_events.try_next() {
+                    events.push(event);
+                }
+                let handled = this.update_in(cx, |this, window, cx| {
+                    this.files_changed(&events, window, cx);
+                });
+                if handled.is_err() {
+                    break;
+                }
+            }
+        })
+        .detach();
+        cx.spawn(async move |this, cx| {
+            loop {
+                cx.background_executor()
+                    .timer(RECOVERY_JOURNAL_INTERVAL)
+                    .await;
+                if this
+                    .update(cx, |this, cx| this.journal_unsaved_documents(cx))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        })
+        .detach();
         Self {
-            document,
-            inline_markdown,
-            editor_view,
+            panes: vec![Pane {
+                id: 0,
+                tabs: TabList::new(DocumentTab {
+                    document,
+                    inline_markdown,
+                    editor_view: editor_view.clone(),
+                }),
+            }],
+            pane_layout: PaneLayout::Pane(0),
+            active_pane: 0,
+            next_pane_id: 1,
+            active_editor: editor_view,
             file_explorer_view,
+            preview_view,
             notifications,
+            file_watcher,
             inline_debounce: Debouncer::new(Duration::from_millis(35)),
+            preview_debounce: Debouncer::new(Duration::from_millis(100)),
+            autosave,
+            autosave_debounce: Debouncer::new(autosave.after_idle.unwrap_or_default()),
+            scheduled_autosave: None,
+            recovery_journal: RecoveryJournal::default(),
+            journaled: HashMap::new(),
+            preview_visible: false,
+            scheduled_preview_revision: None,
             scheduled_inline_revision: 0,
+            pending_code_highlights: HashSet::new(),
             cached_doc_text: None,
             font_size: settings::get_font_size(),
             sidebar_width: 200.0,
+            sidebar_visible: true,
             resizing_sidebar: false,
+            focus_editor: false,
+            welcome_dismissed: false,
+            welcome_recent: None,
+            quick_open: None,
+            workspace_index: None,
+            _subscriptions: subscriptions,
+        }
+    }
+
+    fn pane_index(&self, id: usize) -> usize {
+        self.panes
+            .iter()
+            .position(|pane| pane.id == id)
+            .unwrap_or(0)
+    }
+
+    /// Tabs of the active pane.
+    fn tabs(&self) -> &TabList<DocumentTab> {
+        &self.panes[self.pane_index(self.active_pane)].tabs
+    }
+
+    fn tabs_mut(&mut self) -> &mut TabList<DocumentTab> {
+        let ix = self.pane_index(self.active_pane);
+        &mut self.panes[ix].tabs
+    }
+
+    /// Document of the active tab.
+    fn document(&self) -> &Entity<DocumentState> {
+        &self.tabs().active().document
+    }
+
+    fn inline_markdown(&self) -> &Entity<InlineMarkdownState> {
+        &self.tabs().active().inline_markdown
+    }
+
+    fn editor_view(&self) -> &Entity<EditorView> {
+        &self.tabs().active().editor_view
+    }
+
+    fn tab_for_path(&self, path: &Utf8PathBuf, cx: &App) -> Option<usize> {
+        self.tabs()
+            .position(|tab| tab.document.read(cx).path.as_ref() == Some(path))
+    }
+
+    /// A tab in any pane showing `path`.
+    fn any_tab_for_path(&self, path: &Utf8PathBuf, cx: &App) -> Option<&DocumentTab> {
+        self.panes
+            .iter()
+            .flat_map(|pane| pane.tabs.iter())
+            .find(|tab| tab.document.read(cx).path.as_ref() == Some(path))
+    }
+
+    /// Editors of every tab in every pane.
+    fn editors(&self) -> Vec<Entity<EditorView>> {
+        self.panes
+            .iter()
+            .flat_map(|pane| pane.tabs.iter())
+            .map(|tab| tab.editor_view.clone())
+            .collect()
+    }
+
+    /// Every open document, once each.
+    fn documents(&self) -> Vec<Entity<DocumentState>> {
+        let mut documents: Vec<Entity<DocumentState>> = Vec::new();
+        for tab in self.panes.iter().flat_map(|pane| pane.tabs.iter()) {
+            if !documents.contains(&tab.document) {
+                documents.push(tab.document.clone());
+            }
+        }
+        documents
+    }
+
+    /// Whether `document` is shown by more than one tab across all panes.
+    fn is_shared(&self, document: &Entity<DocumentState>) -> bool {
+        self.panes
+            .iter()
+            .flat_map(|pane| pane.tabs.iter())
+            .filter(|tab| tab.document == *document)
+            .count()
+            > 1
+    }
+
+    /// Whether the active tab is an untouched untitled document that opening a
+    /// file can reuse.
+    fn active_tab_is_blank(&self, cx: &App) -> bool {
+        let doc = self.document().read(cx);
+        doc.path.is_none() && !doc.dirty && doc.len_bytes() == 0
+    }
+
+    fn activate_tab(&mut self, ix: usize, cx: &mut Context<Self>) {
+        if ix == self.tabs().active_index() {
+            return;
+        }
+        let jumps = self.document().read(cx).jumps.clone();
+        if self.tabs_mut().activate(ix) {
+            self.tab_switched(jumps, cx);
+        }
+    }
+
+    fn activate_relative_tab(&mut self, offset: isize, cx: &mut Context<Self>) {
+        let jumps = self.document().read(cx).jumps.clone();
+        let previous = self.tabs().active_index();
+        self.tabs_mut().activate_relative(offset);
+        if self.tabs().active_index() != previous {
+            self.tab_switched(jumps, cx);
+        }
+    }
+
+    /// Opens an empty untitled tab after the active one.
+    fn open_tab(&mut self, cx: &mut Context<Self>) {
+        let tab = DocumentTab::new_empty(cx);
+        self.open_tab_with(tab, cx);
+    }
+
+    fn open_tab_with(&mut self, tab: DocumentTab, cx: &mut Context<Self>) {
+        let jumps = self.document().read(cx).jumps.clone();
+        self.tabs_mut().open(tab);
+        self.tab_switched(jumps, cx);
+    }
+
+    /// Moves keyboard focus to pane `id`.
+    fn focus_pane(&mut self, id: usize, cx: &mut Context<Self>) {
+        if id == self.active_pane || !self.panes.iter().any(|pane| pane.id == id) {
+            return;
+        }
+        let jumps = self.document().read(cx).jumps.clone();
+        self.active_pane = id;
+        self.tab_switched(jumps, cx);
+    }
+
+    fn focus_relative_pane(&mut self, offset: isize, cx: &mut Context<Self>) {
+        let ids = self.pane_layout.pane_ids();
+        let Some(current) = ids.iter().position(|id| *id == self.active_pane) else {
+            return;
+        };
+        let next = (current as isize + offset).rem_euclid(ids.len() as isize) as usize;
+        self.focus_pane(ids[next], cx);
+    }
+
+    /// Opens the active document in a new pane beside (or below) the active one.
+    fn split_pane(&mut self, axis: SplitAxis, cx: &mut Context<Self>) {
+        let tab = self.tabs().active().share(cx);
+        self.split_pane_with(tab, axis, cx);
+    }
+
+    /// Opens `tab` in a new pane beside (or below) the active one.
+    fn split_pane_with(&mut self, tab: DocumentTab, axis: SplitAxis, cx: &mut Context<Self>) {
+        let editor_view = tab.editor_view.clone();
+        let id = self.next_pane_id;
+        self.next_pane_id += 1;
+        self.pane_layout.split(self.active_pane, id, axis);
+        self.panes.push(Pane {
+            id,
+            tabs: TabList::new(tab),
+        });
+        self.focus_pane(id, cx);
+        editor_view.update(cx, |editor, cx| editor.scroll_to_cursor(cx));
+    }
+
+    /// Closes the active pane after offering to save its documents.
+    fn close_pane(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.panes.len() == 1 {
+            self.action_close_window(window, cx);
+            return;
+        }
+        for ix in 0..self.tabs().len() {
+            let document = self.tabs().get(ix).map(|tab| tab.document.clone());
+            if document.is_some_and(|doc| doc.read(cx).dirty && !self.is_shared(&doc)) {
+                self.activate_tab(ix, cx);
+                if !self.confirm_can_discard_changes(window, cx, "Save changes before closing?") {
+                    return;
+                }
+            }
+        }
+        let closed = self.active_pane;
+        let ids = self.pane_layout.pane_ids();
+        let position = ids.iter().position(|id| *id == closed).unwrap_or(0);
+        let jumps = self.document().read(cx).jumps.clone();
+        self.pane_layout.remove(closed);
+        self.panes.retain(|pane| pane.id != closed);
+        let remaining = self.pane_layout.pane_ids();
+        self.active_pane = remaining[position.min(remaining.len() - 1)];
+        self.tab_switched(jumps, cx);
+    }
+
+    /// Points the window at the newly active tab. The jump list follows the
+    /// active tab so Back and Forward work across tabs.
+    fn tab_switched(&mut self, jumps: JumpList, cx: &mut Context<Self>) {
+        self.document().update(cx, |d, _| d.jumps = jumps);
+        let editor_view = self.editor_view().clone();
+        if editor_view != self.active_editor {
+            let previous = std::mem::replace(&mut self.active_editor, editor_view.clone());
+            // A closed tab's document was already offered for saving.
+            let left = self
+                .panes
+                .iter()
+                .flat_map(|pane| pane.tabs.iter())
+                .find(|tab| tab.editor_view == previous)
+                .map(|tab| tab.document.clone());
+            if self.autosave.on_switch
+                && let Some(document) = left
+                && document != *self.document()
+            {
+                self.autosave_document(&document, cx);
+            }
+            previous.update(cx, |editor, cx| editor.park(cx));
+            editor_view.update(cx, |editor, cx| editor.unpark(cx));
+        }
+        // Cached text and scheduled revisions belong to the previous document.
+        self.cached_doc_text = None;
+        self.scheduled_preview_revision = None;
+        self.scheduled_inline_revision = 0;
+        self.focus_editor = true;
+        let document = self.document().clone();
+        self.preview_view
+            .update(cx, |preview, cx| preview.follow(document.entity_id(), cx));
+        self.file_explorer_view
+            .update(cx, |explorer, cx| explorer.set_document(document, cx));
+        cx.notify();
+    }
+
+    /// Closes tab `ix` of the active pane after offering to save it. Closing
+    /// the last tab closes the pane, or the window if it is the only pane.
+    fn close_tab(&mut self, ix: usize, window: &mut Window, cx: &mut Context<Self>) {
+        if self.tabs().len() == 1 {
+            self.close_pane(window, cx);
+            return;
+        }
+        let document = self.tabs().get(ix).map(|tab| tab.document.clone());
+        // A document still shown in another tab isn't closed, so nothing is lost.
+        if document.is_some_and(|doc| doc.read(cx).dirty && !self.is_shared(&doc)) {
+            self.activate_tab(ix, cx);
+            if !self.confirm_can_discard_changes(window, cx, "Save changes before closing?") {
+                return;
+            }
+        }
+        let was_active = ix == self.tabs().active_index();
+        let jumps = self.document().read(cx).jumps.clone();
+        if self.tabs_mut().close(ix).is_some() {
+            if was_active {
+                self.tab_switched(jumps, cx);
+            }
+            cx.notify();
         }
     }
 
@@ -89,7 +595,7 @@ impl RootView {
     }
 
     fn save_document(&mut self, cx: &mut Context<Self>, force_save_as: bool) {
-        let current_path = self.document.read(cx).path.clone();
+        let current_path = self.document().read(cx).path.clone();
 
         // If we have a path and not forcing save-as, save directly
         if !force_save_as {
@@ -101,6 +607,8 @@ impl RootView {
 
         // Need to show file picker - use async dialog
         let receiver = pick_save_path_async(cx, current_path.as_ref());
+        // Save the tab that asked, even if another one is active by then.
+        let document = self.document().clone();
 
         cx.spawn(async move |this, cx| {
             if let Ok(Ok(Some(path))) = receiver.await {
@@ -110,18 +618,19 @@ impl RootView {
                     }
 
                     // Read document contents and write synchronously
-                    let contents_result =
-                        this.update(&mut *cx, |this, cx| this.document.read(cx).text());
+                    let contents_result = document.read_with(&*cx, |d, _| d.text());
 
                     if let Ok(contents) = contents_result {
                         if write_atomic(&utf8_path, &contents).is_ok() {
                             let _ = this.update(&mut *cx, |this, cx| {
-                                let _ = this.document.update(cx, |d, cx| {
+                                let _ = document.update(cx, |d, cx| {
+                                    d.disk_state = DiskState::new(&utf8_path, contents.as_bytes());
                                     d.path = Some(utf8_path.clone());
                                     d.save_snapshot();
                                     cx.notify();
                                 });
-                                cx.add_recent_document(utf8_path.as_std_path());
+                                this.forget_journaled(&document, cx);
+                                remember_recent(&utf8_path, RecentKind::File, cx);
                                 // Note: Notifications require window context, skipping in async
                             });
                         }
@@ -132,26 +641,136 @@ impl RootView {
         .detach();
     }
 
-    /// Synchronous save for when we have a path and window context
-    fn do_save_to_path_sync(&mut self, mut path: Utf8PathBuf, cx: &mut Context<Self>) {
+    /// Synchronous save for when we have a path and window context. Returns
+    /// false if the file wasn't written.
+    fn do_save_to_path_sync(&mut self, mut path: Utf8PathBuf, cx: &mut Context<Self>) -> bool {
         if path.extension().is_none() {
             path.set_extension("md");
         }
+        if !self.confirm_overwrite_changed_file(&path, cx) {
+            return false;
+        }
 
-        let contents = self.document.read(cx).text();
+        let contents = self.document().read(cx).text();
         match write_atomic(&path, &contents) {
             Ok(()) => {
-                let _ = self.document.update(cx, |d, cx| {
+                let _ = self.document().update(cx, |d, cx| {
+                    d.disk_state = DiskState::new(&path, contents.as_bytes());
                     d.path = Some(path.clone());
                     d.save_snapshot();
                     cx.notify();
                 });
-                cx.add_recent_document(path.as_std_path());
+                let document = self.document().clone();
+                self.forget_journaled(&document, cx);
+                remember_recent(&path, RecentKind::File, cx);
                 // Skip notification here too - simplifies and avoids window context issues
+                true
             }
             Err(_err) => {
                 // Silently fail for now - window context not available for notification
+                false
+            }
+        }
+    }
+
+    /// Saves `document` if it has a file and unsaved changes. A file someone
+    /// else changed or deleted meanwhile is left for an explicit save.
+    fn autosave_document(&mut self, document: &Entity<DocumentState>, cx: &mut Context<Self>) {
+        let (path, contents) = {
+            let doc = document.read(cx);
+            let Some(path) = doc.path.clone().filter(|_| doc.dirty) else {
+                return;
+            };
+            if !path.exists()
+                || doc
+                    .disk_state
+                    .as_ref()
+                    .is_some_and(|state| state.changed_since(&path))
+            {
+                return;
             }
+            (path, doc.text())
+        };
+        if write_atomic(&path, &contents).is_ok() {
+            document.update(cx, |d, cx| {
+                d.disk_state = DiskState::new(&path, contents.as_bytes());
+                // Edits made since the text was taken stay unsaved.
+                if d.text() == contents {
+                    d.save_snapshot();
+                }
+                cx.notify();
+            });
+            if !document.read(cx).dirty {
+                self.forget_journaled(document, cx);
+            }
+        }
+    }
+
+    /// Saves the active document once it has gone `after_idle` without edits.
+    fn schedule_autosave(&mut self, doc_revision: u64, doc_dirty: bool, cx: &mut Context<Self>) {
+        let document = self.document().clone();
+        let key = (document.entity_id(), doc_revision);
+        if self.autosave.after_idle.is_none() || !doc_dirty || self.scheduled_autosave == Some(key)
+        {
+            return;
+        }
+        self.scheduled_autosave = Some(key);
+        self.autosave_debounce.schedule(cx, move |this, cx| {
+            this.autosave_document(&document, cx);
+        });
+    }
+
+    /// Asks what to do if the active document's file at `path` was changed by
+    /// someone else since it was loaded or saved: overwrite it, save
+    /// elsewhere, or compare the two. Returns true to go ahead and overwrite.
+    fn confirm_overwrite_changed_file(
+        &mut self,
+        path: &Utf8PathBuf,
+        cx: &mut Context<Self>,
+    ) -> bool {
+        let changed = {
+            let doc = self.document().read(cx);
+            doc.path.as_ref() == Some(path)
+                && doc
+                    .disk_state
+                    .as_ref()
+                    .is_some_and(|state| state.changed_since(path))
+        };
+        if !changed {
+            return true;
+        }
+
+        let choice = MessageDialog::new()
+            .set_level(MessageLevel::Warning)
+            .set_title(format!("{} changed on disk", file_label(path)))
+            .set_description(
+                "The file was modified by another program since you opened or saved it. \
+                 Overwriting it discards those changes.",
+            )
+            .set_buttons(MessageButtons::YesNoCancelCustom(
+                "Overwrite".to_string(),
+                "Save As…".to_string(),
+                "Compare".to_string(),
+            ))
+            .show();
+        let save_elsewhere = |this: &mut Self, cx: &mut Context<Self>| {
+            this.save_document(cx, true);
+            false
+        };
+        match choice {
+            MessageDialogResult::Ok | MessageDialogResult::Yes => true,
+            MessageDialogResult::No => save_elsewhere(self, cx),
+            MessageDialogResult::Custom(label) => match label.as_str() {
+                "Overwrite" => true,
+                "Save As…" => save_elsewhere(self, cx),
+                "Compare" => {
+                    let document = self.document().clone();
+                    self.compare_with_disk(&document, cx);
+                    false
+                }
+                _ => false,
+            },
+            _ => false,
         }
     }
 
@@ -161,14 +780,15 @@ impl RootView {
         cx: &mut Context<Self>,
         prompt: &str,
     ) -> bool {
-        let is_dirty = self.document.read(cx).dirty;
+        let is_dirty = self.document().read(cx).dirty;
         if !is_dirty {
             return true;
         }
 
+        let title = format!("Unsaved changes in {}", self.tabs().active().title(cx));
         let choice = MessageDialog::new()
             .set_level(MessageLevel::Warning)
-            .set_title("Unsaved changes")
+            .set_title(&title)
             .set_description(prompt)
             .set_buttons(MessageButtons::YesNoCancelCustom(
                 "Save".to_string(),
@@ -179,10 +799,9 @@ impl RootView {
 
         let save_sync = |this: &mut Self, cx: &mut Context<Self>| -> bool {
             // Only save synchronously if we have an existing path
-            let current_path = this.document.read(cx).path.clone();
+            let current_path = this.document().read(cx).path.clone();
             if let Some(path) = current_path {
-                this.do_save_to_path_sync(path, cx);
-                true
+                this.do_save_to_path_sync(path, cx)
             } else {
                 // No path - need async dialog, cancel for now
                 // Start async save in background
@@ -203,110 +822,1287 @@ impl RootView {
         }
     }
 
-    pub fn open_path(
+    pub fn open_path(
+        &mut self,
+        path: &camino::Utf8PathBuf,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.open_path_internal(path, cx);
+    }
+
+    /// Internal open path that doesn't require window - for async context
+    fn open_path_internal(&mut self, path: &camino::Utf8PathBuf, cx: &mut Context<Self>) {
+        let from = self.document().read(cx).jump_position();
+        // Leaving a saved file is a jump; untitled buffers can't be returned to.
+        if self.show_path(path, cx) && from.path.as_ref().is_some_and(|p| p != path) {
+            self.document().update(cx, |d, _| d.jumps.record(from));
+        }
+    }
+
+    /// Switch to the tab showing `path` in the active pane, open another view
+    /// of it if a different pane shows it, or else load it into a new tab
+    /// (reusing an untouched untitled one). Returns false if it can't be read.
+    fn show_path(&mut self, path: &camino::Utf8PathBuf, cx: &mut Context<Self>) -> bool {
+        if let Some(ix) = self.tab_for_path(path, cx) {
+            self.activate_tab(ix, cx);
+            return true;
+        }
+        if let Some(tab) = self.any_tab_for_path(path, cx) {
+            let tab = tab.share(cx);
+            self.open_tab_with(tab, cx);
+            return true;
+        }
+        match read_to_string(path) {
+            Ok(text) => {
+                if !self.active_tab_is_blank(cx) {
+                    self.open_tab(cx);
+                }
+                let _ = self.document().update(cx, |d, cx| {
+                    d.path = Some(path.clone());
+                    d.set_text(&text);
+                    d.clear_undo_history();
+                    d.save_snapshot();
+                    d.disk_state = DiskState::new(path, text.as_bytes());
+                    cx.notify();
+                });
+                remember_recent(path, RecentKind::File, cx);
+                true
+            }
+            Err(_err) => {
+                // Silently fail for async context - no window for notification
+                false
+            }
+        }
+    }
+
+    /// Keeps the file watcher on the files the tabs show.
+    fn watch_open_files(&mut self, cx: &App) {
+        let paths = self
+            .panes
+            .iter()
+            .flat_map(|pane| pane.tabs.iter())
+            .filter_map(|tab| tab.document.read(cx).path.clone())
+            .collect();
+        self.file_watcher.set_files(paths);
+    }
+
+    fn files_changed(
+        &mut self,
+        events: &[notify::Event],
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        for change in self.file_watcher.changes(events) {
+            match change {
+                FileChange::Modified(path) => self.file_modified(&path, window, cx),
+                FileChange::Renamed { from, to } => self.file_renamed(&from, to, window, cx),
+                FileChange::Removed(path) => self.file_removed(&path, window, cx),
+            }
+        }
+    }
+
+    /// Reloads a document without unsaved changes from disk, keeping its
+    /// cursor. A document with unsaved changes gets a notification asking
+    /// whether to reload, keep the buffer, or compare the two.
+    fn file_modified(&mut self, path: &Utf8PathBuf, window: &mut Window, cx: &mut Context<Self>) {
+        if self.any_tab_for_path(path, cx).is_none() {
+            return;
+        }
+        let path = path.clone();
+        cx.spawn_in(window, async move |this, cx| {
+            let read = cx
+                .background_executor()
+                .spawn({
+                    let path = path.clone();
+                    async move { read_from_disk(&path) }
+                })
+                .await;
+            if let Some(read) = read {
+                let _ = this.update_in(cx, |this, window, cx| {
+                    this.apply_file_modified(&path, read, window, cx);
+                });
+            }
+        })
+        .detach();
+    }
+
+    /// Reloads a document whose file changed on disk, or asks what to do if
+    /// it has unsaved changes.
+    fn apply_file_modified(
+        &mut self,
+        path: &Utf8PathBuf,
+        read: DiskRead,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(document) = self
+            .any_tab_for_path(path, cx)
+            .map(|tab| tab.document.clone())
+        else {
+            return;
+        };
+        // Our own saves come back as changes too.
+        if document.read(cx).last_saved_hash == read.hash {
+            return;
+        }
+        if !document.read(cx).dirty {
+            document.update(cx, |d, cx| {
+                d.reload(&read.text);
+                d.disk_state = read.disk_state;
+                cx.notify();
+            });
+            return;
+        }
+
+        let root = cx.entity().downgrade();
+        let notification = Notification::warning("It has unsaved changes here.")
+            .title(format!("{} changed on disk", file_label(path)))
+            .id1::<FileChange>(SharedString::from(path.to_string()))
+            .autohide(false)
+            .content(move |_, _, cx| {
+                let reload = {
+                    let (root, document) = (root.clone(), document.clone());
+                    cx.listener(move |note: &mut Notification, _, window, cx| {
+                        note.dismiss(window, cx);
+                        let _ = root.update(cx, |this, cx| this.reload_from_disk(&document, cx));
+                    })
+                };
+                let keep = cx.listener(|note: &mut Notification, _, window, cx| {
+                    note.dismiss(window, cx);
+                });
+                let compare = {
+                    let (root, document) = (root.clone(), document.clone());
+                    cx.listener(move |note: &mut Notification, _, window, cx| {
+                        note.dismiss(window, cx);
+                        let _ = root.update(cx, |this, cx| this.compare_with_disk(&document, cx));
+                    })
+                };
+                div()
+                    .flex()
+                    .gap_2()
+                    .pt(px(6.))
+                    .child(notification_button("reload", "Reload").on_click(reload))
+                    .child(notification_button("keep-mine", "Keep Mine").on_click(keep))
+                    .child(notification_button("compare", "Compare").on_click(compare))
+                    .into_any_element()
+            });
+        self.notifications
+            .update(cx, |list, cx| list.push(notification, window, cx));
+    }
+
+    /// Replaces `document`, unsaved changes and all, with its file on disk.
+    fn reload_from_disk(&mut self, document: &Entity<DocumentState>, cx: &mut Context<Self>) {
+        let Some(path) = document.read(cx).path.clone() else {
+            return;
+        };
+        let document = document.clone();
+        cx.spawn(async move |_, cx| {
+            let read = cx
+                .background_executor()
+                .spawn(async move { read_from_disk(&path) })
+                .await;
+            if let Some(read) = read {
+                let _ = document.update(cx, |d, cx| {
+                    d.reload(&read.text);
+                    d.disk_state = read.disk_state;
+                    cx.notify();
+                });
+            }
+        })
+        .detach();
+    }
+
+    /// Opens a diff from the file on disk to `document` in a pane beside the
+    /// active one.
+    fn compare_with_disk(&mut self, document: &Entity<DocumentState>, cx: &mut Context<Self>) {
+        let Some(path) = document.read(cx).path.clone() else {
+            return;
+        };
+        let document = document.clone();
+        cx.spawn(async move |this, cx| {
+            let disk_text = cx
+                .background_executor()
+                .spawn({
+                    let path = path.clone();
+                    async move { read_to_string(&path) }
+                })
+                .await;
+            if let Ok(disk_text) = disk_text {
+                let _ = this.update(cx, |this, cx| {
+                    this.show_disk_diff(&path, &disk_text, &document, cx);
+                });
+            }
+        })
+        .detach();
+    }
+
+    fn show_disk_diff(
+        &mut self,
+        path: &Utf8PathBuf,
+        disk_text: &str,
+        document: &Entity<DocumentState>,
+        cx: &mut Context<Self>,
+    ) {
+        let text = document.read(cx).text();
+        let diff = unified_diff(
+            disk_text,
+            &text,
+            &format!("{path} (on disk)"),
+            &format!("{path} (unsaved)"),
+        );
+        let tab = DocumentTab::new_empty(cx);
+        tab.document.update(cx, |d, _| {
+            d.set_text(&format!("```diff\n{diff}```\n"));
+            d.set_cursor(0);
+            d.save_snapshot();
+        });
+        self.split_pane_with(tab, SplitAxis::Horizontal, cx);
+    }
+
+    /// Follows a document's file to its new name.
+    fn file_renamed(
+        &mut self,
+        from: &Utf8PathBuf,
+        to: Utf8PathBuf,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(document) = self
+            .any_tab_for_path(from, cx)
+            .map(|tab| tab.document.clone())
+        else {
+            return;
+        };
+        let notification = Notification::info(format!("{} was moved to {to}", file_label(from)));
+        document.update(cx, |d, cx| {
+            d.path = Some(to);
+            cx.notify();
+        });
+        self.notifications
+            .update(cx, |list, cx| list.push(notification, window, cx));
+    }
+
+    /// Keeps the text of a document whose file was deleted as unsaved
+    /// changes, so closing it asks first.
+    fn file_removed(&mut self, path: &Utf8PathBuf, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(document) = self
+            .any_tab_for_path(path, cx)
+            .map(|tab| tab.document.clone())
+        else {
+            return;
+        };
+        document.update(cx, |d, cx| {
+            d.dirty = true;
+            cx.notify();
+        });
+        let notification = Notification::warning("Save it to keep its text.")
+            .title(format!("{} was deleted from disk", file_label(path)))
+            .id1::<FileChange>(SharedString::from(path.to_string()))
+            .autohide(false);
+        self.notifications
+            .update(cx, |list, cx| list.push(notification, window, cx));
+    }
+
+    /// Step back (or forward) through the jump list, switching files if needed.
+    fn action_navigate(&mut self, back: bool, cx: &mut Context<Self>) {
+        let step = |d: &mut DocumentState, current, back: bool| {
+            if back {
+                d.jumps.go_back(current)
+            } else {
+                d.jumps.go_forward(current)
+            }
+        };
+        let Some(target) = self
+            .document()
+            .update(cx, |d, _| step(d, d.jump_position(), back))
+        else {
+            return;
+        };
+
+        if target.path != self.document().read(cx).path {
+            let restored = match &target.path {
+                Some(path) => self.show_path(path, cx),
+                None => false,
+            };
+            if !restored {
+                self.document().update(cx, |d, _| {
+                    let _ = step(d, target.clone(), !back);
+                    d.jumps.discard(&target);
+                });
+                return;
+            }
+        }
+
+        self.editor_view()
+            .update(cx, |editor, cx| editor.move_cursor_to(target.char_idx, cx));
+    }
+
+    fn action_new_file(&mut self, cx: &mut Context<Self>) {
+        if self.shows_welcome(cx) {
+            self.welcome_dismissed = true;
+            self.focus_editor = true;
+            cx.notify();
+            return;
+        }
+        self.open_tab(cx);
+        // No notification for new file - only save gets a notification
+    }
+
+    fn action_open_file(&mut self, cx: &mut Context<Self>) {
+        let picker = pick_open_markdown_path_async();
+        cx.spawn(async move |this, cx| {
+            if let Some(utf8_path) = picker.await {
+                let _ = this.update(&mut *cx, |this, cx| {
+                    this.open_path_internal(&utf8_path, cx);
+                });
+            }
+        })
+        .detach();
+    }
+
+    fn action_open_folder(&mut self, cx: &mut Context<Self>) {
+        let picker = pick_folder_async();
+        cx.spawn(async move |this, cx| {
+            if let Some(root) = picker.await {
+                let _ = this.update(&mut *cx, |this, cx| this.open_folder(root, cx));
+            }
+        })
+        .detach();
+    }
+
+    /// Shows the quick open picker over the workspace folder's files, or
+    /// closes it if it is shown.
+    fn toggle_quick_open(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.quick_open.take().is_some() {
+            self.focus_editor = true;
+            cx.notify();
+            return;
+        }
+        let Some(root) = self
+            .file_explorer_view
+            .read(cx)
+            .folder()
+            .map(Utf8Path::to_path_buf)
+        else {
+            let notification = Notification::info("Open a folder to quick open its files.");
+            self.notifications
+                .update(cx, |list, cx| list.push(notification, window, cx));
+            return;
+        };
+        let cached = self
+            .workspace_index
+            .as_ref()
+            .filter(|(indexed, _)| *indexed == root)
+            .map(|(_, files)| files.clone());
+        let view = cx.new(|cx| QuickOpenView::new(cached, cx));
+        let subscription = cx.subscribe_in(
+            &view,
+            window,
+            |this, _, event: &QuickOpenEvent, window, cx| {
+                this.quick_open = None;
+                this.focus_editor = true;
+                if let QuickOpenEvent::Open(path) = event {
+                    this.action_open_path(path.clone(), window, cx);
+                }
+                cx.notify();
+            },
+        );
+        view.read(cx).focus_handle(cx).focus(window);
+        self.quick_open = Some((view, subscription));
+        cx.notify();
+
+        // Index again each time, so files added since the last time show up.
+        cx.spawn(async move |this, cx| {
+            let indexed = root.clone();
+            let files = cx
+                .background_executor()
+                .spawn(async move { index_workspace(&indexed) })
+                .await;
+            let files = Arc::new(files);
+            let _ = this.update(cx, |this, cx| {
+                if this.file_explorer_view.read(cx).folder() != Some(root.as_path()) {
+                    return;
+                }
+                if let Some((view, _)) = &this.quick_open {
+                    view.update(cx, |view, cx| view.set_files(files.clone(), cx));
+                }
+                this.workspace_index = Some((root, files));
+            });
+        })
+        .detach();
+    }
+
+    /// Shows `root` as the workspace in the sidebar.
+    pub fn open_folder(&mut self, root: Utf8PathBuf, cx: &mut Context<Self>) {
+        remember_recent(&root, RecentKind::Folder, cx);
+        self.file_explorer_view
+            .update(cx, |explorer, cx| explorer.open_folder(root, cx));
+    }
+
+    pub fn action_open_path(
+        &mut self,
+        path: camino::Utf8PathBuf,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if path.is_dir() {
+            self.open_folder(path, cx);
+            return;
+        }
+        self.open_path(&path, window, cx);
+    }
+
+    pub fn confirm_before_quit(&mut self, window: &mut Window, cx: &mut Context<Self>) -> bool {
+        self.confirm_close_tabs(window, cx, "Save changes before quitting?")
+    }
+
+    pub fn confirm_before_close(&mut self, window: &mut Window, cx: &mut Context<Self>) -> bool {
+        self.confirm_close_tabs(window, cx, "Save changes before closing?")
+    }
+
+    /// Writes documents with new unsaved changes to the recovery journal, and
+    /// drops the entries of documents since saved or closed.
+    fn journal_unsaved_documents(&mut self, cx: &mut Context<Self>) {
+        let documents = self.documents();
+        let mut removals = Vec::new();
+        self.journaled.retain(|id, _| {
+            let open = documents.iter().any(|document| document.entity_id() == *id);
+            if !open {
+                removals.push(RecoveryJournal::key(id.as_u64()));
+            }
+            open
+        });
+        let mut writes = Vec::new();
+        for document in &documents {
+            let id = document.entity_id();
+            let doc = document.read(cx);
+            if !doc.dirty {
+                if self.journaled.remove(&id).is_some() {
+                    removals.push(RecoveryJournal::key(id.as_u64()));
+                }
+            } else if self.journaled.insert(id, doc.revision) != Some(doc.revision) {
+                let entry = RecoveredDocument {
+                    path: doc.path.clone().map(Utf8PathBuf::into_std_path_buf),
+                    text: doc.text(),
+                };
+                writes.push((RecoveryJournal::key(id.as_u64()), entry));
+            }
+        }
+        self.update_recovery_journal(writes, removals, cx);
+    }
+
+    /// Applies journal writes and removals on the background executor. Their
+    /// ticket keeps a late write from undoing a later removal.
+    fn update_recovery_journal(
+        &self,
+        writes: Vec<(String, RecoveredDocument)>,
+        removals: Vec<String>,
+        cx: &mut Context<Self>,
+    ) {
+        if writes.is_empty() && removals.is_empty() {
+            return;
+        }
+        let journal = self.recovery_journal.clone();
+        let ticket = journal.ticket();
+        cx.background_executor()
+            .spawn(async move {
+                for (key, entry) in writes {
+                    if let Err(err) = journal.write(&key, ticket, &entry) {
+                        tracing::warn!("can't write recovery journal: {err}");
+                    }
+                }
+                for key in removals {
+                    journal.remove(&key, ticket);
+                }
+            })
+            .detach();
+    }
+
+    /// Drops the journal entry of a document that was just saved.
+    fn forget_journaled(&mut self, document: &Entity<DocumentState>, cx: &mut Context<Self>) {
+        let id = document.entity_id();
+        if self.journaled.remove(&id).is_some() {
+            self.update_recovery_journal(Vec::new(), vec![RecoveryJournal::key(id.as_u64())], cx);
+        }
+    }
+
+    /// Drops this window's recovery journal entries once closing it has dealt
+    /// with every unsaved document.
+    fn discard_recovery_journal(&mut self) {
+        let ticket = self.recovery_journal.ticket();
+        for id in std::mem::take(&mut self.journaled).into_keys() {
+            self.recovery_journal
+                .remove(&RecoveryJournal::key(id.as_u64()), ticket);
+        }
+    }
+
+    /// Opens documents recovered from the journal as tabs with unsaved changes.
+    pub fn restore_documents(&mut self, documents: Vec<RecoveredDocument>, cx: &mut Context<Self>) {
+        for recovered in documents {
+            let path = recovered
+                .path
+                .and_then(|path| Utf8PathBuf::try_from(path).ok());
+            let disk_text = path.as_ref().and_then(|path| read_to_string(path).ok());
+            // Nothing to restore if the file already has the text.
+            if disk_text.as_deref() == Some(recovered.text.as_str()) {
+                continue;
+            }
+            // A file the last session reopened gets its unsaved text back.
+            let document = match path.as_ref().and_then(|p| self.any_tab_for_path(p, cx)) {
+                Some(tab) => tab.document.clone(),
+                None => {
+                    if !self.active_tab_is_blank(cx) {
+                        self.open_tab(cx);
+                    }
+                    self.document().clone()
+                }
+            };
+            document.update(cx, |d, cx| {
+                d.disk_state = path
+                    .as_ref()
+                    .zip(disk_text.as_ref())
+                    .and_then(|(path, text)| DiskState::new(path, text.as_bytes()));
+                d.path = path;
+                d.set_text(&recovered.text);
+                d.clear_undo_history();
+                d.dirty = true;
+                cx.notify();
+            });
+        }
+        self.journal_unsaved_documents(cx);
+    }
+
+    /// This window's layout, files and view state, for reopening it on the
+    /// next launch. Untitled documents are left out.
+    pub fn session(&self, window: &Window, cx: &App) -> WindowSession {
+        let (state, bounds) = match window.window_bounds() {
+            WindowBounds::Windowed(bounds) => (SavedWindowState::Windowed, bounds),
+            WindowBounds::Maximized(bounds) => (SavedWindowState::Maximized, bounds),
+            WindowBounds::Fullscreen(bounds) => (SavedWindowState::Fullscreen, bounds),
+        };
+        let panes = self
+            .panes
+            .iter()
+            .map(|pane| {
+                let mut active_tab = 0;
+                let mut tabs = Vec::new();
+                for (ix, tab) in pane.tabs.iter().enumerate() {
+                    let Some(path) = tab.document.read(cx).path.clone() else {
+                        continue;
+                    };
+                    if ix == pane.tabs.active_index() {
+                        active_tab = tabs.len();
+                    }
+                    let editor = tab.editor_view.read(cx);
+                    let cursor = editor.cursor_state(cx);
+                    tabs.push(TabSession {
+                        path: path.into_std_path_buf(),
+                        cursor: cursor.cursor,
+                        selection: cursor.selection,
+                        scroll_y: editor.scroll_offset().into(),
+                        find: editor.find_query().map(str::to_string),
+                    });
+                }
+                PaneSession {
+                    id: pane.id,
+                    active_tab,
+                    tabs,
+                }
+            })
+            .collect();
+        WindowSession {
+            bounds: SavedBounds {
+                x: bounds.origin.x.into(),
+                y: bounds.origin.y.into(),
+                width: bounds.size.width.into(),
+                height: bounds.size.height.into(),
+                state,
+            },
+            layout: self.pane_layout.clone(),
+            panes,
+            active_pane: self.active_pane,
+            sidebar_width: self.sidebar_width,
+            sidebar_visible: self.sidebar_visible,
+            folder: self
+                .file_explorer_view
+                .read(cx)
+                .folder()
+                .map(|folder| folder.as_std_path().to_path_buf()),
+        }
+    }
+
+    /// Reopens the files of a saved window in its layout, in place of this
+    /// window's tabs. Files that can no longer be read are skipped.
+    pub fn restore_session(&mut self, mut session: WindowSession, cx: &mut Context<Self>) {
+        self.sidebar_width = session.sidebar_width.clamp(100.0, 400.0);
+        self.sidebar_visible = session.sidebar_visible;
+        if let Some(folder) = session
+            .folder
+            .take()
+            .and_then(|folder| Utf8PathBuf::try_from(folder).ok())
+            .filter(|folder| folder.is_dir())
+        {
+            self.open_folder(folder, cx);
+        }
+
+        let mut texts: HashMap<PathBuf, Option<String>> = HashMap::new();
+        let any_left = session.retain_tabs(|tab| {
+            texts
+                .entry(tab.path.clone())
+                .or_insert_with(|| {
+                    Utf8PathBuf::try_from(tab.path.clone())
+                        .ok()
+                        .and_then(|path| read_to_string(&path).ok())
+                })
+                .is_some()
+        });
+        if !any_left {
+            cx.notify();
+            return;
+        }
+
+        let mut panes: Vec<Pane> = Vec::new();
+        for saved_pane in session.panes {
+            let mut tabs: Vec<DocumentTab> = Vec::new();
+            for saved in saved_pane.tabs {
+                let Ok(path) = Utf8PathBuf::try_from(saved.path) else {
+                    continue;
+                };
+                // Tabs of one file share its document, as after a split.
+                let shown = panes
+                    .iter()
+                    .flat_map(|pane| pane.tabs.iter())
+                    .chain(tabs.iter())
+                    .find(|tab| tab.document.read(cx).path.as_ref() == Some(&path));
+                let tab = match shown {
+                    Some(tab) => tab.share(cx),
+                    None => {
+                        let tab = DocumentTab::new_empty(cx);
+                        let text = texts
+                            .remove(path.as_std_path())
+                            .flatten()
+                            .unwrap_or_default();
+                        tab.document.update(cx, |d, _| {
+                            d.disk_state = DiskState::new(&path, text.as_bytes());
+                            d.path = Some(path.clone());
+                            d.set_text(&text);
+                            d.clear_undo_history();
+                            d.save_snapshot();
+                        });
+                        tab
+                    }
+                };
+                let (revision, len) = {
+                    let doc = tab.document.read(cx);
+                    (doc.revision, doc.len_chars())
+                };
+                let cursor = saved.cursor.min(len);
+                let selection = saved
+                    .selection
+                    .map(|range| range.start.min(len)..range.end.min(len))
+                    .filter(|range| !range.is_empty());
+                let selection_anchor = selection.as_ref().map(|range| {
+                    if range.start == cursor {
+                        range.end
+                    } else {
+                        range.start
+                    }
+                });
+                let state = CursorState {
+                    revision,
+                    cursor,
+                    selection,
+                    selection_anchor,
+                };
+                tab.editor_view.update(cx, |editor, cx| {
+                    editor.restore_view(state, px(saved.scroll_y), saved.find, cx);
+                });
+                tabs.push(tab);
+            }
+            let mut tabs = tabs.into_iter();
+            let Some(first) = tabs.next() else {
+                continue;
+            };
+            let mut list = TabList::new(first);
+            for tab in tabs {
+                list.open(tab);
+            }
+            list.activate(saved_pane.active_tab);
+            panes.push(Pane {
+                id: saved_pane.id,
+                tabs: list,
+            });
+        }
+
+        let jumps = self.document().read(cx).jumps.clone();
+        self.next_pane_id = panes.iter().map(|pane| pane.id + 1).max().unwrap_or(1);
+        self.panes = panes;
+        self.pane_layout = session.layout;
+        self.active_pane = session.active_pane;
+        self.tab_switched(jumps, cx);
+    }
+
+    /// Offers to save each dirty document in turn, once even if several panes
+    /// show it. Returns false if one was cancelled.
+    fn confirm_close_tabs(
         &mut self,
-        path: &camino::Utf8PathBuf,
-        _window: &mut Window,
+        window: &mut Window,
         cx: &mut Context<Self>,
-    ) {
-        self.open_path_internal(path, cx);
+        prompt: &str,
+    ) -> bool {
+        let mut asked = HashSet::new();
+        for pane in self.pane_layout.pane_ids() {
+            let len = self.panes[self.pane_index(pane)].tabs.len();
+            for ix in 0..len {
+                let Some(document) = self.panes[self.pane_index(pane)]
+                    .tabs
+                    .get(ix)
+                    .map(|tab| tab.document.clone())
+                else {
+                    continue;
+                };
+                if !document.read(cx).dirty || !asked.insert(document.entity_id()) {
+                    continue;
+                }
+                self.focus_pane(pane, cx);
+                self.activate_tab(ix, cx);
+                if !self.confirm_can_discard_changes(window, cx, prompt) {
+                    return false;
+                }
+            }
+        }
+        self.discard_recovery_journal();
+        true
     }
 
-    /// Internal open path that doesn't require window - for async context
-    fn open_path_internal(&mut self, path: &camino::Utf8PathBuf, cx: &mut Context<Self>) {
-        match read_to_string(path) {
-            Ok(text) => {
-                let _ = self.document.update(cx, |d, cx| {
-                    d.path = Some(path.clone());
-                    d.set_text(&text);
-                    d.clear_undo_history();
-                    d.save_snapshot();
-                    cx.notify();
-                });
-                cx.add_recent_document(path.as_std_path());
+    fn action_save(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        self.save_document(cx, false);
+    }
+
+    fn action_save_as(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        self.save_document(cx, true);
+    }
+
+    /// Ask for a destination and export the current document in the background.
+    fn action_export(&mut self, format: ExportFormat, cx: &mut Context<Self>) {
+        let (markdown, source_path) = {
+            let doc = self.document().read(cx);
+            (doc.text(), doc.path.clone())
+        };
+        let receiver = pick_export_path_async(cx, source_path.as_ref(), format.extension());
+        let options = ExportOptions {
+            source_path,
+            css_path: settings::get_export_css().and_then(|path| Utf8PathBuf::try_from(path).ok()),
+            pdf: settings::get_pdf_options(),
+        };
+
+        cx.spawn(async move |_, cx| {
+            let Ok(Ok(Some(path))) = receiver.await else {
+                return;
+            };
+            let Ok(mut path) = Utf8PathBuf::try_from(path) else {
+                return;
+            };
+            if path.extension().is_none() {
+                path.set_extension(format.extension());
             }
-            Err(_err) => {
-                // Silently fail for async context - no window for notification
+            let result = cx
+                .background_executor()
+                .spawn(async move {
+                    export_to_path(&markdown, format, &path, &options).map_err(|err| (path, err))
+                })
+                .await;
+            match result {
+                Ok(warnings) if !warnings.is_empty() => {
+                    MessageDialog::new()
+                        .set_level(MessageLevel::Warning)
+                        .set_title("Exported with warnings")
+                        .set_description(warnings.join("\n"))
+                        .set_buttons(MessageButtons::Ok)
+                        .show();
+                }
+                Ok(_) => {}
+                Err((path, err)) => {
+                    MessageDialog::new()
+                        .set_level(MessageLevel::Error)
+                        .set_title("Export failed")
+                        .set_description(format!("Could not export to {path}: {err}"))
+                        .set_buttons(MessageButtons::Ok)
+                        .show();
+                }
             }
-        }
+        })
+        .detach();
     }
 
-    fn action_new_file(&mut self, window: &mut Window, cx: &mut Context<Self>) {
-        if !self.confirm_can_discard_changes(window, cx, "Save changes before creating a new file?")
-        {
+    fn action_close_window(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if !self.confirm_before_close(window, cx) {
             return;
         }
+        window.remove_window();
+    }
 
-        let _ = self.document.update(cx, |d, cx| {
-            d.path = None;
-            d.set_text("");
-            d.clear_undo_history();
-            d.save_snapshot();
-            cx.notify();
-        });
-        // No notification for new file - only save gets a notification
+    fn set_marker_visibility(&mut self, visibility: MarkerVisibility, cx: &mut Context<Self>) {
+        settings::set_marker_visibility(visibility);
+        for editor in self.editors() {
+            editor.update(cx, |_, cx| cx.notify());
+        }
     }
 
-    fn action_open_file(&mut self, window: &mut Window, cx: &mut Context<Self>) {
-        if !self.confirm_can_discard_changes(
-            window,
-            cx,
-            "Save changes before opening another file?",
-        ) {
+    /// Highlight fenced code blocks of the current parse that are not cached yet.
+    /// Each distinct block content is highlighted once, in the background.
+    fn schedule_code_highlights(
+        &mut self,
+        doc_text: &str,
+        doc_revision: u64,
+        cx: &mut Context<Self>,
+    ) {
+        let inline = self.inline_markdown().read(cx);
+        if inline.source_revision != doc_revision {
             return;
         }
+        let jobs: Vec<(u64, String, String)> = inline
+            .missing_highlights()
+            .filter(|block| !self.pending_code_highlights.contains(&block.hash))
+            .filter_map(|block| {
+                let body = doc_text.get(block.body.clone())?;
+                Some((block.hash, block.language.clone(), body.to_string()))
+            })
+            .collect();
 
-        let picker = pick_open_markdown_path_async();
-        cx.spawn(async move |this, cx| {
-            if let Some(utf8_path) = picker.await {
-                let _ = this.update(&mut *cx, |this, cx| {
-                    this.open_path_internal(&utf8_path, cx);
+        for (hash, language, body) in jobs {
+            self.pending_code_highlights.insert(hash);
+            let inline_markdown = self.inline_markdown().clone();
+            cx.spawn(async move |this, cx| {
+                let tokens = cx
+                    .background_executor()
+                    .spawn(async move { highlight_code(&language, &body) })
+                    .await;
+                let _ = inline_markdown.update(cx, |state, cx| {
+                    if state.store_highlights(hash, tokens) {
+                        cx.notify();
+                    }
                 });
-            }
-        })
-        .detach();
+                let _ = this.update(cx, |this, _| {
+                    this.pending_code_highlights.remove(&hash);
+                });
+            })
+            .detach();
+        }
     }
 
-    pub fn action_open_path(
+    /// Rebuild the preview once the inline parse has caught up with `doc_revision`,
+    /// so both views of the document advance through the same revisions.
+    fn schedule_preview(
         &mut self,
-        path: camino::Utf8PathBuf,
-        window: &mut Window,
+        doc_text: &str,
+        doc_revision: u64,
+        doc_path: Option<Utf8PathBuf>,
         cx: &mut Context<Self>,
     ) {
-        if !self.confirm_can_discard_changes(
-            window,
-            cx,
-            "Save changes before opening another file?",
-        ) {
+        if self.inline_markdown().read(cx).source_revision != doc_revision
+            || self.scheduled_preview_revision == Some(doc_revision)
+        {
             return;
         }
-        self.open_path(&path, window, cx);
+        self.scheduled_preview_revision = Some(doc_revision);
+
+        let text = doc_text.to_string();
+        let document_id = self.document().entity_id();
+        let preview_view = self.preview_view.clone();
+        let previous_code = preview_view.read(cx).content().code.clone();
+        self.preview_debounce.schedule(cx, move |_, cx| {
+            let text = text.clone();
+            let previous_code = previous_code.clone();
+            let preview_view = preview_view.clone();
+            let doc_path = doc_path.clone();
+            cx.spawn(async move |_, cx| {
+                let content = cx
+                    .background_executor()
+                    .spawn(async move { build_preview(&text, &previous_code) })
+                    .await;
+                let _ = preview_view.update(cx, |preview, cx| {
+                    preview.apply(document_id, doc_revision, content, doc_path, cx);
+                });
+            })
+            .detach();
+        });
     }
 
-    pub fn confirm_before_quit(&mut self, window: &mut Window, cx: &mut Context<Self>) -> bool {
-        self.confirm_can_discard_changes(window, cx, "Save changes before quitting?")
+    fn toggle_preview(&mut self, cx: &mut Context<Self>) {
+        self.preview_visible = !self.preview_visible;
+        cx.notify();
     }
 
-    fn action_save(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
-        self.save_document(cx, false);
+    /// Whether the window is empty: one untouched untitled tab, which the
+    /// welcome screen stands in for until New is chosen.
+    fn shows_welcome(&self, cx: &App) -> bool {
+        !self.welcome_dismissed
+            && self.panes.len() == 1
+            && self.tabs().len() == 1
+            && self.active_tab_is_blank(cx)
     }
 
-    fn action_save_as(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
-        self.save_document(cx, true);
+    fn update_recent(&mut self, f: impl FnOnce(&mut RecentList), cx: &mut Context<Self>) {
+        settings::update_recent(f);
+        crate::app::set_app_menus(cx);
+        cx.notify();
     }
 
-    fn action_close_window(&mut self, window: &mut Window, cx: &mut Context<Self>) {
-        if !self.confirm_can_discard_changes(window, cx, "Save changes before closing?") {
+    /// Ways to start, and the recent files and folders with pinned ones first.
+    /// Reloads the welcome screen's recent list if it changed, checking once
+    /// which entries are missing.
+    fn refresh_welcome_recent(&mut self) {
+        let recent = settings::get_recent();
+        if self
+            .welcome_recent
+            .as_ref()
+            .is_some_and(|(shown, _)| *shown == recent)
+        {
             return;
         }
-        window.remove_window();
+        let exists = recent.entries().map(RecentEntry::exists).collect();
+        self.welcome_recent = Some((recent, exists));
+    }
+
+    fn render_welcome(&self, cx: &mut Context<Self>) -> AnyElement {
+        let Some((recent, exists)) = &self.welcome_recent else {
+            return div().flex_1().into_any_element();
+        };
+        let any_missing = exists.contains(&false);
+        let start = div()
+            .flex()
+            .gap_2()
+            .child(
+                notification_button("welcome-new", "New Document").on_click(cx.listener(
+                    |this, _, _, cx| {
+                        this.action_new_file(cx);
+                    },
+                )),
+            )
+            .child(
+                notification_button("welcome-open", "Open File…").on_click(cx.listener(
+                    |this, _, _, cx| {
+                        this.action_open_file(cx);
+                    },
+                )),
+            )
+            .child(
+                notification_button("welcome-open-folder", "Open Folder…").on_click(cx.listener(
+                    |this, _, _, cx| {
+                        this.action_open_folder(cx);
+                    },
+                )),
+            );
+        let rows: Vec<_> = recent
+            .entries()
+            .zip(exists)
+            .enumerate()
+            .map(|(ix, (entry, exists))| self.render_recent_row(ix, entry, *exists, cx))
+            .collect();
+
+        div()
+            .id("welcome")
+            .flex_1()
+            .min_h(px(0.))
+            .min_w(px(0.))
+            .overflow_y_scroll()
+            .flex()
+            .justify_center()
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap_4()
+                    .w(px(520.))
+                    .pt(px(96.))
+                    .pb(px(48.))
+                    .child(div().text_2xl().child("Aster"))
+                    .child(start)
+                    .when(!recent.is_empty(), |this| {
+                        this.child(
+                            div()
+                                .flex()
+                                .items_center()
+                                .justify_between()
+                                .pt(px(16.))
+                                .text_sm()
+                                .text_color(Theme::muted())
+                                .child("Recent")
+                                .when(any_missing, |this| {
+                                    this.child(
+                                        notification_button(
+                                            "welcome-remove-missing",
+                                            "Remove Missing",
+                                        )
+                                        .on_click(
+                                            cx.listener(|this, _, _, cx| {
+                                                this.update_recent(RecentList::remove_missing, cx);
+                                            }),
+                                        ),
+                                    )
+                                }),
+                        )
+                        .child(div().flex().flex_col().children(rows))
+                    }),
+            )
+            .into_any_element()
+    }
+
+    fn render_recent_row(
+        &self,
+        ix: usize,
+        entry: &RecentEntry,
+        exists: bool,
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        let name = entry
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| entry.path.display().to_string());
+        let detail = match entry.kind {
+            RecentKind::File => entry.path.parent().map(|dir| dir.display().to_string()),
+            RecentKind::Folder => Some("Folder".to_string()),
+        }
+        .unwrap_or_default();
+        let pinned = entry.pinned;
+        let (open_path, pin_path, remove_path) =
+            (entry.path.clone(), entry.path.clone(), entry.path.clone());
+        div()
+            .id(("recent", ix))
+            .group("recent")
+            .flex()
+            .items_center()
+            .gap_2()
+            .px(px(8.))
+            .py(px(4.))
+            .rounded(px(4.))
+            .cursor_pointer()
+            .hover(|s| s.bg(Theme::panel_alt()))
+            .on_click(cx.listener(move |_, _, window, cx| {
+                window.dispatch_action(Box::new(OpenRecent(open_path.clone())), cx);
+            }))
+            .child(
+                div()
+                    .flex_1()
+                    .min_w(px(0.))
+                    .flex()
+                    .flex_col()
+                    .child(
+                        div()
+                            .text_sm()
+                            .when(!exists, |this| {
+                                this.text_color(Theme::muted()).line_through()
+                            })
+                            .child(name),
+                    )
+                    .child(div().text_xs().text_color(Theme::muted()).child(if exists {
+                        detail
+                    } else {
+                        "Missing".to_string()
+                    })),
+            )
+            .child(
+                div()
+                    .id(("recent-pin", ix))
+                    .px(px(6.))
+                    .rounded(px(3.))
+                    .text_xs()
+                    .text_color(if pinned {
+                        Theme::accent()
+                    } else {
+                        Theme::muted()
+                    })
+                    .when(!pinned, |this| {
+                        this.invisible().group_hover("recent", |s| s.visible())
+                    })
+                    .hover(|s| s.bg(Theme::border()))
+                    .on_click(cx.listener(move |this, _, _, cx| {
+                        cx.stop_propagation();
+                        this.update_recent(|recent| recent.set_pinned(&pin_path, !pinned), cx);
+                    }))
+                    .child(if pinned { "Pinned" } else { "Pin" }),
+            )
+            .child(
+                div()
+                    .id(("recent-remove", ix))
+                    .w(px(16.))
+                    .text_center()
+                    .rounded(px(3.))
+                    .text_color(Theme::muted())
+                    .hover(|s| s.bg(Theme::border()).text_color(Theme::text()))
+                    .on_click(cx.listener(move |this, _, _, cx| {
+                        cx.stop_propagation();
+                        this.update_recent(|recent| recent.remove(&remove_path), cx);
+                    }))
+                    .child("×"),
+            )
+            .into_any_element()
+    }
+
+    /// The panes of `layout`, split side by side or stacked.
+    fn render_pane_layout(&self, layout: &PaneLayout, cx: &mut Context<Self>) -> AnyElement {
+        let (axis, children) = match layout {
+            PaneLayout::Pane(id) => return self.render_pane(*id, cx),
+            PaneLayout::Split { axis, children } => (*axis, children),
+        };
+        let children: Vec<_> = children
+            .iter()
+            .enumerate()
+            .map(|(ix, child)| {
+                div()
+                    .flex_1()
+                    .min_h(px(0.))
+                    .min_w(px(0.))
+                    .flex()
+                    .when(ix > 0, |this| match axis {
+                        SplitAxis::Horizontal => this.border_l_1(),
+                        SplitAxis::Vertical => this.border_t_1(),
+                    })
+                    .border_color(Theme::border())
+                    .child(self.render_pane_layout(child, cx))
+            })
+            .collect();
+        div()
+            .flex_1()
+            .min_h(px(0.))
+            .min_w(px(0.))
+            .flex()
+            .when(axis == SplitAxis::Vertical, |this| this.flex_col())
+            .children(children)
+            .into_any_element()
+    }
+
+    /// A pane's tab strip above the editor of its active tab. Clicking
+    /// anywhere in a pane focuses it.
+    fn render_pane(&self, id: usize, cx: &mut Context<Self>) -> AnyElement {
+        let pane = &self.panes[self.pane_index(id)];
+        div()
+            .flex_1()
+            .min_h(px(0.))
+            .min_w(px(0.))
+            .flex()
+            .flex_col()
+            .capture_any_mouse_down(cx.listener(move |this, _, _, cx| {
+                this.focus_pane(id, cx);
+            }))
+            .child(self.render_tab_bar(pane, cx))
+            .child(pane.tabs.active().editor_view.clone())
+            .into_any_element()
+    }
+
+    /// Tab strip above the editor: click to switch, middle-click or × to close,
+    /// drag to reorder. With several panes, the focused pane's active tab is
+    /// marked with an accent line.
+    fn render_tab_bar(&self, pane: &Pane, cx: &mut Context<Self>) -> AnyElement {
+        let pane_id = pane.id;
+        let active = pane.tabs.active_index();
+        let focused = pane_id == self.active_pane && self.panes.len() > 1;
+        let tabs: Vec<_> = pane
+            .tabs
+            .iter()
+            .enumerate()
+            .map(|(ix, tab)| {
+                let title = tab.title(cx);
+                let dirty = tab.document.read(cx).dirty;
+                let is_active = ix == active;
+                div()
+                    .id(("tab", ix))
+                    .group("tab")
+                    .flex()
+                    .items_center()
+                    .gap(px(6.))
+                    .h_full()
+                    .pl(px(12.))
+                    .pr(px(6.))
+                    .border_r_1()
+                    .border_color(Theme::border())
+                    .cursor_pointer()
+                    .text_sm()
+                    .when(is_active, |this| {
+                        this.bg(Theme::bg()).text_color(Theme::text())
+                    })
+                    .when(is_active && focused, |this| {
+                        this.border_t_2().border_color(Theme::accent())
+                    })
+                    .when(!is_active, |this| {
+                        this.text_color(Theme::muted())
+                            .hover(|s| s.bg(Theme::panel_alt()))
+                    })
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(move |this, _: &MouseDownEvent, _, cx| {
+                            this.activate_tab(ix, cx);
+                        }),
+                    )
+                    .on_mouse_up(
+                        MouseButton::Middle,
+                        cx.listener(move |this, _, window, cx| {
+                            this.close_tab(ix, window, cx);
+                        }),
+                    )
+                    .on_drag(
+                        DraggedTab {
+                            pane: pane_id,
+                            ix,
+                            title: title.clone(),
+                        },
+                        |tab, _, _, cx| cx.new(|_| tab.clone()),
+                    )
+                    .drag_over::<DraggedTab>(|style, _, _, _| style.bg(Theme::panel_alt()))
+                    .on_drop(cx.listener(move |this, dragged: &DraggedTab, _, cx| {
+                        if dragged.pane == pane_id {
+                            let pane = this.pane_index(pane_id);
+                            this.panes[pane].tabs.move_tab(dragged.ix, ix);
+                            cx.notify();
+                        }
+                    }))
+                    .child(title)
+                    .when(dirty, |this| {
+                        this.child(div().text_color(Theme::accent()).child("•"))
+                    })
+                    .child(
+                        div()
+                            .id(("close-tab", ix))
+                            .w(px(16.))
+                            .text_center()
+                            .rounded(px(3.))
+                            .text_color(Theme::muted())
+                            .hover(|s| s.bg(Theme::border()).text_color(Theme::text()))
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(move |this, _: &MouseDownEvent, window, cx| {
+                                    cx.stop_propagation();
+                                    this.close_tab(ix, window, cx);
+                                }),
+                            )
+                            .child("×"),
+                    )
+            })
+            .collect();
+
+        div()
+            .id(("tab-bar", pane_id))
+            .flex()
+            .flex_shrink_0()
+            .h(px(30.))
+            .w_full()
+            .overflow_x_scroll()
+            .bg(Theme::panel())
+            .border_b_1()
+            .border_color(Theme::border())
+            .children(tabs)
+            .into_any_element()
     }
 }
 
 impl Render for RootView {
     fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        self.watch_open_files(cx);
         let (doc_path, doc_dirty, doc_revision, word_count) = {
-            self.document.update(cx, |doc, _| {
+            self.document().update(cx, |doc, _| {
                 (
                     doc.path.clone(),
                     doc.dirty,
@@ -321,55 +2117,60 @@ impl Render for RootView {
             if cached_rev == doc_revision {
                 text.clone()
             } else {
-                let text = self.document.read(cx).text();
+                let text = self.document().read(cx).text();
                 self.cached_doc_text = Some((doc_revision, text.clone()));
                 text
             }
         } else {
-            let text = self.document.read(cx).text();
+            let text = self.document().read(cx).text();
             self.cached_doc_text = Some((doc_revision, text.clone()));
             text
         };
-        let inline_rev = self.inline_markdown.read(cx).source_revision;
+        let (inline_rev, previous_blocks) = {
+            let inline = self.inline_markdown().read(cx);
+            (inline.source_revision, inline.blocks.clone())
+        };
 
         if doc_revision != inline_rev && self.scheduled_inline_revision < doc_revision {
             self.scheduled_inline_revision = doc_revision;
-            let last_edit = self.document.read(cx).last_edit.clone();
+            let edit = self.document().read(cx).edit_since(inline_rev);
             let target_rev = doc_revision;
-            if doc_text.len() <= INLINE_SYNC_PARSE_MAX_BYTES {
-                // Small/medium notes: parse inline to avoid style flicker between keystrokes.
-                let parsed = compute_inline_spans(&doc_text, last_edit.as_ref());
-                let _ = self.inline_markdown.update(cx, |state, cx| {
-                    if target_rev >= state.source_revision {
-                        state.spans = std::sync::Arc::new(parsed.spans);
-                        state.source_revision = target_rev;
-                        state.parse_millis = parsed.parse_millis;
+            // Parse on the UI thread when it is cheap to avoid style flicker between
+            // keystrokes: small notes always, large notes when the edit is incremental.
+            let parsed = if doc_text.len() <= INLINE_SYNC_PARSE_MAX_BYTES {
+                Some(compute_inline_spans(
+                    &doc_text,
+                    Some(&previous_blocks),
+                    edit.as_ref(),
+                ))
+            } else {
+                edit.as_ref().and_then(|edit| {
+                    compute_inline_spans_incremental(&doc_text, &previous_blocks, edit)
+                })
+            };
+
+            if let Some(parsed) = parsed {
+                self.inline_markdown().update(cx, |state, cx| {
+                    if state.apply_parse(target_rev, parsed) {
                         cx.notify();
-                    } else {
-                        state.dropped_updates = state.dropped_updates.saturating_add(1);
                     }
                 });
             } else {
-                // Large notes: debounce and parse in background to protect typing latency.
+                // Large notes needing a full parse: debounce and parse in background to
+                // protect typing latency.
                 let text = doc_text.clone();
-                let inline_markdown = self.inline_markdown.clone();
+                let inline_markdown = self.inline_markdown().clone();
                 self.inline_debounce.schedule(cx, move |_, cx| {
                     let text = text.clone();
-                    let last_edit = last_edit.clone();
                     let inline_markdown = inline_markdown.clone();
                     cx.spawn(async move |_, cx| {
                         let parsed = cx
                             .background_executor()
-                            .spawn(async move { compute_inline_spans(&text, last_edit.as_ref()) })
+                            .spawn(async move { compute_inline_spans(&text, None, None) })
                             .await;
                         let _ = inline_markdown.update(cx, |state, cx| {
-                            if target_rev >= state.source_revision {
-                                state.spans = std::sync::Arc::new(parsed.spans);
-                                state.source_revision = target_rev;
-                                state.parse_millis = parsed.parse_millis;
+                            if state.apply_parse(target_rev, parsed) {
                                 cx.notify();
-                            } else {
-                                state.dropped_updates = state.dropped_updates.saturating_add(1);
                             }
                         });
                     })
@@ -378,8 +2179,23 @@ impl Render for RootView {
             }
         }
 
+        self.schedule_code_highlights(&doc_text, doc_revision, cx);
+        self.schedule_autosave(doc_revision, doc_dirty, cx);
+        if self.preview_visible {
+            self.schedule_preview(&doc_text, doc_revision, doc_path.clone(), cx);
+            if let Some(line) = self.editor_view().read(cx).top_visible_source_line(cx) {
+                self.preview_view
+                    .update(cx, |preview, _| preview.sync_to_line(line));
+            }
+        }
+
+        if std::mem::take(&mut self.focus_editor) {
+            self.editor_view().read(cx).focus(window);
+        }
+
+        let vim_mode = self.editor_view().read(cx).vim_mode();
         let (inline_parse_millis, inline_dropped_updates) = {
-            let inline = self.inline_markdown.read(cx);
+            let inline = self.inline_markdown().read(cx);
             (inline.parse_millis, inline.dropped_updates)
         };
         // Expose inline parser timing in status for quick perf monitoring.
@@ -414,13 +2230,48 @@ impl Render for RootView {
             .border_b_1()
             .border_color(Theme::border())
             .flex_shrink_0()
+            .flex()
+            .items_center()
+            .justify_end()
+            .px(px(12.))
             .on_mouse_down(
                 MouseButton::Left,
                 cx.listener(|_, _: &MouseDownEvent, window, _| {
                     window.start_window_move();
                 }),
+            )
+            .child(
+                div()
+                    .id("toggle-preview")
+                    .p(px(4.))
+                    .rounded(px(4.))
+                    .cursor_pointer()
+                    .when(self.preview_visible, |this| this.bg(Theme::panel_alt()))
+                    .hover(|s| s.bg(Theme::panel_alt()))
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|this, _: &MouseDownEvent, _, cx| {
+                            cx.stop_propagation();
+                            this.toggle_preview(cx);
+                        }),
+                    )
+                    .child(
+                        svg()
+                            .path("icons/panel-right.svg")
+                            .size(px(16.))
+                            .text_color(Theme::muted()),
+                    ),
             );
 
+        let panes = if self.shows_welcome(cx) {
+            self.refresh_welcome_recent();
+            self.render_welcome(cx)
+        } else {
+            // Checked afresh the next time the welcome screen shows.
+            self.welcome_recent = None;
+            self.render_pane_layout(&self.pane_layout, cx)
+        };
+
         let resize_line_color = if self.resizing_sidebar {
             gpui::rgba(0x2d7fd299)
         } else {
@@ -437,6 +2288,16 @@ impl Render for RootView {
             .border_t_1()
             .border_color(Theme::border())
             .flex_shrink_0()
+            .when_some(vim_mode, |this, mode| {
+                this.child(
+                    div()
+                        .flex_shrink_0()
+                        .text_sm()
+                        .font_weight(gpui::FontWeight::BOLD)
+                        .text_color(Theme::accent())
+                        .child(format!("-- {} --", mode.label())),
+                )
+            })
             .child(
                 div().w_full().flex().justify_end().child(
                     div()
@@ -455,11 +2316,21 @@ impl Render for RootView {
             .bg(Theme::bg())
             .text_color(Theme::text())
             .size_full()
-            .on_action(cx.listener(|this, _: &NewFile, window, cx| {
-                this.action_new_file(window, cx);
+            .on_action(cx.listener(|this, _: &NewFile, _window, cx| {
+                this.action_new_file(cx);
+            }))
+            .on_action(cx.listener(|this, _: &OpenFile, _window, cx| {
+                this.action_open_file(cx);
             }))
-            .on_action(cx.listener(|this, _: &OpenFile, window, cx| {
-                this.action_open_file(window, cx);
+            .on_action(cx.listener(|this, _: &OpenFolder, _window, cx| {
+                this.action_open_folder(cx);
+            }))
+            .on_action(cx.listener(|this, _: &QuickOpen, window, cx| {
+                this.toggle_quick_open(window, cx);
+            }))
+            .on_action(cx.listener(|this, _: &RevealActiveFile, _window, cx| {
+                this.file_explorer_view
+                    .update(cx, |explorer, cx| explorer.reveal_active_file(cx));
             }))
             .on_action(cx.listener(|this, _: &SaveFile, window, cx| {
                 this.action_save(window, cx);
@@ -467,9 +2338,63 @@ impl Render for RootView {
             .on_action(cx.listener(|this, _: &SaveFileAs, window, cx| {
                 this.action_save_as(window, cx);
             }))
+            .on_action(cx.listener(|this, _: &ExportHtml, _window, cx| {
+                this.action_export(ExportFormat::Html, cx);
+            }))
+            .on_action(cx.listener(|this, _: &ExportPdf, _window, cx| {
+                this.action_export(ExportFormat::Pdf, cx);
+            }))
+            .on_action(cx.listener(|this, _: &ExportEpub, _window, cx| {
+                this.action_export(ExportFormat::Epub, cx);
+            }))
+            .on_action(cx.listener(|this, _: &ExportDocx, _window, cx| {
+                this.action_export(ExportFormat::Docx, cx);
+            }))
+            .on_action(cx.listener(|this, _: &CloseTab, window, cx| {
+                this.close_tab(this.tabs().active_index(), window, cx);
+            }))
+            .on_action(cx.listener(|this, _: &SplitRight, _window, cx| {
+                this.split_pane(SplitAxis::Horizontal, cx);
+            }))
+            .on_action(cx.listener(|this, _: &SplitDown, _window, cx| {
+                this.split_pane(SplitAxis::Vertical, cx);
+            }))
+            .on_action(cx.listener(|this, _: &NextPane, _window, cx| {
+                this.focus_relative_pane(1, cx);
+            }))
+            .on_action(cx.listener(|this, _: &PreviousPane, _window, cx| {
+                this.focus_relative_pane(-1, cx);
+            }))
+            .on_action(cx.listener(|this, action: &ActivateTab, _window, cx| {
+                this.activate_tab(action.0, cx);
+            }))
+            .on_action(cx.listener(|this, _: &NextTab, _window, cx| {
+                this.activate_relative_tab(1, cx);
+            }))
+            .on_action(cx.listener(|this, _: &PreviousTab, _window, cx| {
+                this.activate_relative_tab(-1, cx);
+            }))
             .on_action(cx.listener(|this, _: &CloseWindow, window, cx| {
                 this.action_close_window(window, cx);
             }))
+            .on_action(cx.listener(|this, _: &NavigateBack, _window, cx| {
+                this.action_navigate(true, cx);
+            }))
+            .on_action(cx.listener(|this, _: &NavigateForward, _window, cx| {
+                this.action_navigate(false, cx);
+            }))
+            .on_mouse_down(
+                MouseButton::Navigate(NavigationDirection::Back),
+                cx.listener(|this, _: &MouseDownEvent, _window, cx| {
+                    this.action_navigate(true, cx);
+                }),
+            )
+            .on_mouse_down(
+                MouseButton::Navigate(NavigationDirection::Forward),
+                cx.listener(|this, _: &MouseDownEvent, _window, cx| {
+                    this.action_navigate(false, cx);
+                }),
+            )
             .on_action(cx.listener(|this, _: &FontSizeIncrease, _window, cx| {
                 this.font_size =
                     Settings::clamp_font_size(this.font_size + Settings::FONT_SIZE_STEP);
@@ -487,6 +2412,30 @@ impl Render for RootView {
                 settings::set_font_size(this.font_size);
                 cx.notify();
             }))
+            .on_action(cx.listener(|this, _: &TogglePreview, _window, cx| {
+                this.toggle_preview(cx);
+            }))
+            .on_action(cx.listener(|this, _: &ToggleSidebar, _window, cx| {
+                this.sidebar_visible = !this.sidebar_visible;
+                cx.notify();
+            }))
+            .on_action(cx.listener(|this, _: &ToggleVimMode, _window, cx| {
+                let enabled = !settings::get_vim_mode();
+                settings::set_vim_mode(enabled);
+                for editor in this.editors() {
+                    editor.update(cx, |editor, cx| editor.set_vim_enabled(enabled, cx));
+                }
+                cx.notify();
+            }))
+            .on_action(cx.listener(|this, _: &RevealMarkersAtCursor, _window, cx| {
+                this.set_marker_visibility(MarkerVisibility::Contextual, cx);
+            }))
+            .on_action(cx.listener(|this, _: &ShowAllMarkers, _window, cx| {
+                this.set_marker_visibility(MarkerVisibility::Always, cx);
+            }))
+            .on_action(cx.listener(|this, _: &HideAllMarkers, _window, cx| {
+                this.set_marker_visibility(MarkerVisibility::Never, cx);
+            }))
             // Handle sidebar resize drag at root level so we don't lose events
             .on_mouse_move(cx.listener(|this, event: &MouseMoveEvent, _, cx| {
                 if !this.resizing_sidebar {
@@ -514,43 +2463,52 @@ impl Render for RootView {
                     .min_w(px(0.))
                     .flex()
                     .flex_row()
-                    .child({
-                        // Keep the sidebar width in sync with the resize state
-                        let fe = self.file_explorer_view.clone();
-                        let width = self.sidebar_width;
-                        let _ = fe.update(cx, |view, cx| {
-                            view.set_width(width, cx);
-                        });
-                        fe
+                    .when(self.sidebar_visible, |this| {
+                        this.child({
+                            // Keep the sidebar width in sync with the resize state
+                            let fe = self.file_explorer_view.clone();
+                            let width = self.sidebar_width;
+                            let _ = fe.update(cx, |view, cx| {
+                                view.set_width(width, cx);
+                            });
+                            fe
+                        })
+                        // Resize handle
+                        .child(
+                            div()
+                                .id("sidebar-resize-handle")
+                                .w(px(1.))
+                                .h_full()
+                                .cursor_col_resize()
+                                .bg(resize_line_color)
+                                .hover(|s| s.bg(gpui::rgba(0x2d7fd24d)))
+                                .on_mouse_down(
+                                    MouseButton::Left,
+                                    cx.listener(|this, _: &MouseDownEvent, _, cx| {
+                                        this.resizing_sidebar = true;
+                                        cx.notify();
+                                    }),
+                                ),
+                        )
                     })
-                    // Resize handle
-                    .child(
-                        div()
-                            .id("sidebar-resize-handle")
-                            .w(px(1.))
-                            .h_full()
-                            .cursor_col_resize()
-                            .bg(resize_line_color)
-                            .hover(|s| s.bg(gpui::rgba(0x2d7fd24d)))
-                            .on_mouse_down(
-                                MouseButton::Left,
-                                cx.listener(|this, _: &MouseDownEvent, _, cx| {
-                                    this.resizing_sidebar = true;
-                                    cx.notify();
-                                }),
-                            ),
-                    )
-                    .child(
-                        div()
-                            .flex_1()
-                            .min_h(px(0.))
-                            .min_w(px(0.))
-                            .flex()
-                            .flex_col()
-                            .child(self.editor_view.clone()),
-                    ),
+                    .child(panes)
+                    .when(self.preview_visible, |this| {
+                        this.child(
+                            div()
+                                .flex_1()
+                                .min_h(px(0.))
+                                .min_w(px(0.))
+                                .flex()
+                                .border_l_1()
+                                .border_color(Theme::border())
+                                .child(self.preview_view.clone()),
+                        )
+                    }),
             )
             .child(bottom_bar)
+            .when_some(self.quick_open.as_ref(), |this, (quick_open, _)| {
+                this.child(quick_open.clone())
+            })
             .child(self.notifications.clone())
     }
 }
@@ -33,4 +33,30 @@ impl Theme {
     pub fn selection_bg() -> Rgba {
         rgba(0x2d7fd233) // accent with low alpha for text selection
     }
+
+    // Syntax colors for fenced code blocks
+    pub fn code_keyword() -> Rgba {
+        rgb(0xa626a4)
+    }
+    pub fn code_string() -> Rgba {
+        rgb(0x50a14f)
+    }
+    pub fn code_comment() -> Rgba {
+        rgb(0xa0a1a7)
+    }
+    pub fn code_constant() -> Rgba {
+        rgb(0x986801)
+    }
+    pub fn code_function() -> Rgba {
+        rgb(0x4078f2)
+    }
+    pub fn code_type() -> Rgba {
+        rgb(0xc18401)
+    }
+    pub fn code_tag() -> Rgba {
+        rgb(0xe45649)
+    }
+    pub fn code_operator() -> Rgba {
+        rgb(0x0184bc)
+    }
 }
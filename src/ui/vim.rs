@@ -0,0 +1,1610 @@
+use crate::model::document::DocumentState;
+use ropey::Rope;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Spaces inserted or removed per level by `>` and `<`.
+const SHIFT_WIDTH: usize = 4;
+
+/// Current editing mode of the Vim layer.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum VimMode {
+    Normal,
+    Insert,
+    Visual,
+    VisualLine,
+}
+
+impl VimMode {
+    /// Label shown in the status bar.
+    pub fn label(self) -> &'static str {
+        match self {
+            VimMode::Normal => "NORMAL",
+            VimMode::Insert => "INSERT",
+            VimMode::Visual => "VISUAL",
+            VimMode::VisualLine => "VISUAL LINE",
+        }
+    }
+}
+
+/// Key press translated from a GPUI keystroke.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum VimKey {
+    Char(char),
+    Ctrl(char),
+    Escape,
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// Side effects the editor has to perform on behalf of the Vim layer.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum VimEffect {
+    SetClipboard(String),
+    StartSearch,
+    SearchNext { forward: bool },
+}
+
+#[derive(Debug, Default)]
+pub struct VimOutcome {
+    /// False when the key should fall through to the regular editor handling.
+    pub handled: bool,
+    pub effects: Vec<VimEffect>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+    Indent,
+    Outdent,
+}
+
+impl Operator {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            'd' => Some(Operator::Delete),
+            'c' => Some(Operator::Change),
+            'y' => Some(Operator::Yank),
+            '>' => Some(Operator::Indent),
+            '<' => Some(Operator::Outdent),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    WordForward,
+    WordBackward,
+    WordEnd,
+    LineStart,
+    FirstNonBlank,
+    LineEnd,
+    FirstLine,
+    LastLine,
+    ParagraphBackward,
+    ParagraphForward,
+}
+
+impl Motion {
+    fn from_key(key: VimKey) -> Option<Self> {
+        match key {
+            VimKey::Char('h') | VimKey::Left | VimKey::Backspace => Some(Motion::Left),
+            VimKey::Char('l') | VimKey::Right => Some(Motion::Right),
+            VimKey::Char('k') | VimKey::Up => Some(Motion::Up),
+            VimKey::Char('j') | VimKey::Down | VimKey::Enter => Some(Motion::Down),
+            VimKey::Char('w') => Some(Motion::WordForward),
+            VimKey::Char('b') => Some(Motion::WordBackward),
+            VimKey::Char('e') => Some(Motion::WordEnd),
+            VimKey::Char('0') => Some(Motion::LineStart),
+            VimKey::Char('^') => Some(Motion::FirstNonBlank),
+            VimKey::Char('$') => Some(Motion::LineEnd),
+            VimKey::Char('G') => Some(Motion::LastLine),
+            VimKey::Char('{') => Some(Motion::ParagraphBackward),
+            VimKey::Char('}') => Some(Motion::ParagraphForward),
+            _ => None,
+        }
+    }
+
+    /// Motions that can cross the document and so are remembered for Back.
+    fn is_jump(self) -> bool {
+        matches!(
+            self,
+            Motion::FirstLine
+                | Motion::LastLine
+                | Motion::ParagraphBackward
+                | Motion::ParagraphForward
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum MotionKind {
+    Exclusive,
+    Inclusive,
+    Linewise,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct MotionTarget {
+    pos: usize,
+    kind: MotionKind,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum TextObject {
+    Word,
+    Paragraph,
+    Quote(char),
+}
+
+impl TextObject {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            'w' => Some(TextObject::Word),
+            'p' => Some(TextObject::Paragraph),
+            '"' | '\'' | '`' => Some(TextObject::Quote(c)),
+            _ => None,
+        }
+    }
+}
+
+/// Region an operator acts on: a char range or an inclusive line range.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum OpRange {
+    Chars(Range<usize>),
+    Lines(usize, usize),
+}
+
+#[derive(Clone, Debug, Default)]
+struct Register {
+    text: String,
+    linewise: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+enum Awaiting {
+    #[default]
+    Command,
+    Register,
+    GPrefix,
+    TextObject {
+        around: bool,
+    },
+}
+
+/// Partially typed command such as `"a3d2` waiting for a motion.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+struct Pending {
+    register: Option<char>,
+    count: Option<usize>,
+    operator: Option<Operator>,
+    operator_count: Option<usize>,
+    awaiting: Awaiting,
+}
+
+impl Pending {
+    fn push_digit(&mut self, digit: usize) {
+        let slot = if self.operator.is_some() {
+            &mut self.operator_count
+        } else {
+            &mut self.count
+        };
+        *slot = Some((slot.unwrap_or(0) * 10 + digit).min(9999));
+    }
+
+    fn has_count(&self) -> bool {
+        if self.operator.is_some() {
+            self.operator_count.is_some()
+        } else {
+            self.count.is_some()
+        }
+    }
+
+    fn total_count(&self) -> Option<usize> {
+        match (self.count, self.operator_count) {
+            (None, None) => None,
+            (count, operator_count) => Some(count.unwrap_or(1) * operator_count.unwrap_or(1)),
+        }
+    }
+}
+
+enum Step {
+    Pending,
+    Done { changed: bool },
+    Cancel,
+}
+
+/// Modal editing state layered over a `DocumentState`.
+///
+/// Keys are fed through [`VimState::handle_key`]; anything that needs the
+/// window (clipboard writes, the find panel) is reported back as a
+/// [`VimEffect`] so this type stays free of GPUI.
+pub struct VimState {
+    mode: VimMode,
+    pending: Pending,
+    registers: HashMap<char, Register>,
+    visual_anchor: usize,
+    /// Keys of the command currently being typed, used to build `last_change`.
+    change_keys: Vec<VimKey>,
+    /// Keys of the last completed change, replayed by `.`.
+    last_change: Vec<VimKey>,
+    recording_insert: bool,
+    replaying: bool,
+    /// Where the text typed after a counted `i`, `a`, `I` or `A` starts, and
+    /// how many more copies of it Escape inserts.
+    insert_repeat: Option<(usize, usize)>,
+}
+
+impl VimState {
+    pub fn new() -> Self {
+        Self {
+            mode: VimMode::Normal,
+            pending: Pending::default(),
+            registers: HashMap::new(),
+            visual_anchor: 0,
+            change_keys: Vec::new(),
+            last_change: Vec::new(),
+            recording_insert: false,
+            replaying: false,
+            insert_repeat: None,
+        }
+    }
+
+    pub fn mode(&self) -> VimMode {
+        self.mode
+    }
+
+    /// Whether `key` may read the clipboard, so callers only fetch it when needed.
+    pub fn wants_clipboard(key: VimKey) -> bool {
+        matches!(key, VimKey::Char('p' | 'P' | '.'))
+    }
+
+    /// Processes one key press against `doc`.
+    ///
+    /// `clipboard` is the current system clipboard text and backs the unnamed,
+    /// `+` and `*` registers.
+    pub fn handle_key(
+        &mut self,
+        key: VimKey,
+        doc: &mut DocumentState,
+        clipboard: Option<&str>,
+    ) -> VimOutcome {
+        let mut out = VimOutcome {
+            handled: true,
+            effects: Vec::new(),
+        };
+        match self.mode {
+            VimMode::Insert => self.insert_key(key, doc, &mut out),
+            VimMode::Normal => self.normal_key(key, doc, clipboard, &mut out),
+            VimMode::Visual | VimMode::VisualLine => self.visual_key(key, doc, &mut out),
+        }
+        out
+    }
+
+    /// Leaves visual mode after the editor moved the selection itself.
+    pub fn collapse_to_normal(&mut self, doc: &mut DocumentState) {
+        if self.mode != VimMode::Insert {
+            self.mode = VimMode::Normal;
+            self.pending = Pending::default();
+            let cursor = doc
+                .selection_range()
+                .map_or(doc.cursor, |selection| selection.start);
+            doc.set_cursor(clamp_normal(&doc.rope, cursor));
+        }
+    }
+
+    // ============ Insert mode ============
+
+    fn insert_key(&mut self, key: VimKey, doc: &mut DocumentState, out: &mut VimOutcome) {
+        match key {
+            VimKey::Escape => {
+                self.record(key);
+                if let Some((start, copies)) = self.insert_repeat.take()
+                    && start < doc.cursor
+                {
+                    let text = doc.slice_chars(start..doc.cursor).repeat(copies);
+                    doc.insert(doc.cursor, &text);
+                    doc.cursor += text.chars().count();
+                }
+                doc.commit_edit();
+                self.mode = VimMode::Normal;
+                if self.recording_insert {
+                    self.recording_insert = false;
+                    if !self.replaying {
+                        self.last_change = std::mem::take(&mut self.change_keys);
+                    }
+                }
+                let line_start = doc.rope.line_to_char(doc.rope.char_to_line(doc.cursor));
+                if doc.cursor > line_start {
+                    doc.cursor -= 1;
+                }
+                doc.cursor = clamp_normal(&doc.rope, doc.cursor);
+            }
+            VimKey::Char(c) => {
+                self.record(key);
+                doc.delete_selection();
+                doc.insert(doc.cursor, c.encode_utf8(&mut [0; 4]));
+                doc.cursor += 1;
+            }
+            VimKey::Enter => {
+                self.record(key);
+                doc.delete_selection();
+                doc.insert(doc.cursor, "\n");
+                doc.cursor += 1;
+            }
+            VimKey::Backspace => {
+                self.record(key);
+                if doc.delete_selection().is_none() && doc.cursor > 0 {
+                    let start = doc.cursor - 1;
+                    doc.delete_range(start..doc.cursor);
+                    doc.cursor = start;
+                }
+            }
+            VimKey::Delete => {
+                self.record(key);
+                if doc.delete_selection().is_none() && doc.cursor < doc.len_chars() {
+                    doc.delete_range(doc.cursor..doc.cursor + 1);
+                }
+            }
+            _ => out.handled = false,
+        }
+    }
+
+    fn record(&mut self, key: VimKey) {
+        if self.recording_insert && !self.replaying {
+            self.change_keys.push(key);
+        }
+    }
+
+    fn enter_insert(&mut self, doc: &mut DocumentState, at: usize) {
+        doc.begin_edit();
+        doc.set_cursor(at);
+        self.mode = VimMode::Insert;
+        self.insert_repeat = None;
+    }
+
+    /// Enters insert mode for `i`, `a`, `I` or `A`; with a count, the typed
+    /// text is inserted `count` times in all.
+    fn enter_counted_insert(&mut self, doc: &mut DocumentState, at: usize, count: usize) {
+        self.enter_insert(doc, at);
+        self.insert_repeat = (count > 1).then_some((doc.cursor, count - 1));
+    }
+
+    // ============ Normal mode ============
+
+    fn normal_key(
+        &mut self,
+        key: VimKey,
+        doc: &mut DocumentState,
+        clipboard: Option<&str>,
+        out: &mut VimOutcome,
+    ) {
+        if self.pending == Pending::default() {
+            self.change_keys.clear();
+        }
+        if !self.replaying {
+            self.change_keys.push(key);
+        }
+
+        match self.normal_command(key, doc, clipboard, out) {
+            Step::Pending => return,
+            Step::Done { changed } => {
+                if changed && !self.replaying {
+                    if self.mode == VimMode::Insert {
+                        self.recording_insert = true;
+                    } else {
+                        self.last_change = std::mem::take(&mut self.change_keys);
+                    }
+                }
+            }
+            Step::Cancel => {}
+        }
+
+        self.pending = Pending::default();
+        if self.mode == VimMode::Normal {
+            doc.cursor = clamp_normal(&doc.rope, doc.cursor);
+        }
+    }
+
+    fn normal_command(
+        &mut self,
+        key: VimKey,
+        doc: &mut DocumentState,
+        clipboard: Option<&str>,
+        out: &mut VimOutcome,
+    ) -> Step {
+        match self.pending.awaiting {
+            Awaiting::Command => {}
+            Awaiting::Register => {
+                self.pending.awaiting = Awaiting::Command;
+                return match key {
+                    VimKey::Char(c) if is_register_name(c) => {
+                        self.pending.register = Some(c);
+                        Step::Pending
+                    }
+                    _ => Step::Cancel,
+                };
+            }
+            Awaiting::GPrefix => {
+                self.pending.awaiting = Awaiting::Command;
+                return match key {
+                    VimKey::Char('g') => self.apply_motion(Motion::FirstLine, doc, out),
+                    _ => Step::Cancel,
+                };
+            }
+            Awaiting::TextObject { around } => {
+                self.pending.awaiting = Awaiting::Command;
+                let (VimKey::Char(c), Some(op)) = (key, self.pending.operator) else {
+                    return Step::Cancel;
+                };
+                let Some(object) = TextObject::from_char(c) else {
+                    return Step::Cancel;
+                };
+                let Some(range) = text_object_range(&doc.rope, doc.cursor, object, around) else {
+                    return Step::Cancel;
+                };
+                let changed = self.operate(op, range, doc, out);
+                return Step::Done { changed };
+            }
+        }
+
+        let count = self.pending.total_count();
+        let n = count.unwrap_or(1);
+
+        if let VimKey::Char(c) = key {
+            if let Some(digit) = c.to_digit(10)
+                && (digit != 0 || self.pending.has_count())
+            {
+                self.pending.push_digit(digit as usize);
+                return Step::Pending;
+            }
+
+            if let Some(op) = self.pending.operator {
+                if Operator::from_char(c) == Some(op) {
+                    let line = doc.rope.char_to_line(doc.cursor);
+                    let last = (line + n - 1).min(last_line(&doc.rope));
+                    let changed = self.operate(op, OpRange::Lines(line, last), doc, out);
+                    return Step::Done { changed };
+                }
+                if c == 'i' || c == 'a' {
+                    self.pending.awaiting = Awaiting::TextObject { around: c == 'a' };
+                    return Step::Pending;
+                }
+            }
+        }
+
+        if let Some(motion) = Motion::from_key(key) {
+            return self.apply_motion(motion, doc, out);
+        }
+
+        if self.pending.operator.is_some() {
+            return Step::Cancel;
+        }
+
+        let cursor = doc.cursor.min(doc.len_chars());
+        let line = doc.rope.char_to_line(cursor);
+        match key {
+            VimKey::Ctrl('r') => {
+                for _ in 0..n {
+                    if !doc.redo() {
+                        break;
+                    }
+                }
+                Step::Done { changed: false }
+            }
+            VimKey::Char(c) => match c {
+                '"' => {
+                    self.pending.awaiting = Awaiting::Register;
+                    Step::Pending
+                }
+                'g' => {
+                    self.pending.awaiting = Awaiting::GPrefix;
+                    Step::Pending
+                }
+                'd' | 'c' | 'y' | '>' | '<' => {
+                    self.pending.operator = Operator::from_char(c);
+                    Step::Pending
+                }
+                'x' => self.operator_motion(Operator::Delete, Motion::Right, doc, out),
+                'X' => self.operator_motion(Operator::Delete, Motion::Left, doc, out),
+                'D' => self.operator_motion(Operator::Delete, Motion::LineEnd, doc, out),
+                'C' => self.operator_motion(Operator::Change, Motion::LineEnd, doc, out),
+                's' => self.operator_motion(Operator::Change, Motion::Right, doc, out),
+                'S' | 'Y' => {
+                    let op = if c == 'S' {
+                        Operator::Change
+                    } else {
+                        Operator::Yank
+                    };
+                    let last = (line + n - 1).min(last_line(&doc.rope));
+                    let changed = self.operate(op, OpRange::Lines(line, last), doc, out);
+                    Step::Done { changed }
+                }
+                'i' => {
+                    self.enter_counted_insert(doc, cursor, n);
+                    Step::Done { changed: true }
+                }
+                'a' => {
+                    let at = if cursor < line_end(&doc.rope, line) {
+                        cursor + 1
+                    } else {
+                        cursor
+                    };
+                    self.enter_counted_insert(doc, at, n);
+                    Step::Done { changed: true }
+                }
+                'I' => {
+                    self.enter_counted_insert(doc, first_non_blank(&doc.rope, line), n);
+                    Step::Done { changed: true }
+                }
+                'A' => {
+                    self.enter_counted_insert(doc, line_end(&doc.rope, line), n);
+                    Step::Done { changed: true }
+                }
+                'o' | 'O' => {
+                    let at = if c == 'o' {
+                        line_end(&doc.rope, line)
+                    } else {
+                        doc.rope.line_to_char(line)
+                    };
+                    doc.begin_edit();
+                    doc.insert(at, "\n");
+                    doc.cursor = if c == 'o' { at + 1 } else { at };
+                    self.mode = VimMode::Insert;
+                    Step::Done { changed: true }
+                }
+                'p' | 'P' => {
+                    let changed = self.paste(doc, clipboard, c == 'P', n);
+                    Step::Done { changed }
+                }
+                'u' => {
+                    for _ in 0..n {
+                        if !doc.undo() {
+                            break;
+                        }
+                    }
+                    doc.clear_selection();
+                    Step::Done { changed: false }
+                }
+                'v' | 'V' => {
+                    self.mode = if c == 'v' {
+                        VimMode::Visual
+                    } else {
+                        VimMode::VisualLine
+                    };
+                    self.visual_anchor = cursor;
+                    self.sync_visual_selection(doc);
+                    Step::Done { changed: false }
+                }
+                '/' => {
+                    out.effects.push(VimEffect::StartSearch);
+                    Step::Done { changed: false }
+                }
+                'n' | 'N' => {
+                    for _ in 0..n {
+                        out.effects
+                            .push(VimEffect::SearchNext { forward: c == 'n' });
+                    }
+                    Step::Done { changed: false }
+                }
+                '.' => {
+                    self.repeat_last_change(doc, clipboard, n, out);
+                    Step::Done { changed: false }
+                }
+                _ => Step::Cancel,
+            },
+            _ => Step::Cancel,
+        }
+    }
+
+    fn operator_motion(
+        &mut self,
+        op: Operator,
+        motion: Motion,
+        doc: &mut DocumentState,
+        out: &mut VimOutcome,
+    ) -> Step {
+        self.pending.operator = Some(op);
+        self.apply_motion(motion, doc, out)
+    }
+
+    fn apply_motion(
+        &mut self,
+        motion: Motion,
+        doc: &mut DocumentState,
+        out: &mut VimOutcome,
+    ) -> Step {
+        let cursor = doc.cursor.min(doc.len_chars());
+        let op = self.pending.operator;
+        // `cw` behaves like `ce` when started on a word, as in Vim.
+        let motion = if op == Some(Operator::Change)
+            && motion == Motion::WordForward
+            && cursor < doc.len_chars()
+            && !doc.rope.char(cursor).is_whitespace()
+        {
+            Motion::WordEnd
+        } else {
+            motion
+        };
+        let target = motion_target(
+            &doc.rope,
+            cursor,
+            motion,
+            self.pending.total_count(),
+            op.is_some(),
+        );
+
+        match op {
+            None => {
+                if motion.is_jump() {
+                    doc.jump_to(target.pos);
+                } else {
+                    doc.set_cursor(target.pos);
+                }
+                Step::Done { changed: false }
+            }
+            Some(op) => {
+                let range = motion_range(&doc.rope, cursor, target);
+                let changed = self.operate(op, range, doc, out);
+                Step::Done { changed }
+            }
+        }
+    }
+
+    fn repeat_last_change(
+        &mut self,
+        doc: &mut DocumentState,
+        clipboard: Option<&str>,
+        times: usize,
+        out: &mut VimOutcome,
+    ) {
+        let keys = self.last_change.clone();
+        if keys.is_empty() {
+            return;
+        }
+        self.pending = Pending::default();
+        self.replaying = true;
+        for _ in 0..times {
+            for key in &keys {
+                let replayed = self.handle_key(*key, doc, clipboard);
+                out.effects.extend(replayed.effects);
+            }
+        }
+        self.replaying = false;
+    }
+
+    // ============ Visual mode ============
+
+    fn visual_key(&mut self, key: VimKey, doc: &mut DocumentState, out: &mut VimOutcome) {
+        if let Step::Pending = self.visual_command(key, doc, out) {
+            return;
+        }
+        self.pending = Pending::default();
+        match self.mode {
+            VimMode::Visual | VimMode::VisualLine => {
+                doc.cursor = clamp_normal(&doc.rope, doc.cursor);
+                self.sync_visual_selection(doc);
+            }
+            VimMode::Normal => doc.cursor = clamp_normal(&doc.rope, doc.cursor),
+            VimMode::Insert => {}
+        }
+    }
+
+    fn visual_command(
+        &mut self,
+        key: VimKey,
+        doc: &mut DocumentState,
+        out: &mut VimOutcome,
+    ) -> Step {
+        match self.pending.awaiting {
+            Awaiting::Command => {}
+            Awaiting::Register => {
+                self.pending.awaiting = Awaiting::Command;
+                return match key {
+                    VimKey::Char(c) if is_register_name(c) => {
+                        self.pending.register = Some(c);
+                        Step::Pending
+                    }
+                    _ => Step::Cancel,
+                };
+            }
+            Awaiting::GPrefix => {
+                self.pending.awaiting = Awaiting::Command;
+                if key == VimKey::Char('g') {
+                    let target = motion_target(
+                        &doc.rope,
+                        doc.cursor,
+                        Motion::FirstLine,
+                        self.pending.count,
+                        false,
+                    );
+                    doc.cursor = target.pos;
+                }
+                return Step::Done { changed: false };
+            }
+            Awaiting::TextObject { around } => {
+                self.pending.awaiting = Awaiting::Command;
+                let range = match key {
+                    VimKey::Char(c) => TextObject::from_char(c).and_then(|object| {
+                        text_object_range(&doc.rope, doc.cursor, object, around)
+                    }),
+                    _ => None,
+                };
+                match range {
+                    Some(OpRange::Chars(range)) if !range.is_empty() => {
+                        self.visual_anchor = range.start;
+                        doc.cursor = range.end - 1;
+                    }
+                    Some(OpRange::Lines(first, last)) => {
+                        self.mode = VimMode::VisualLine;
+                        self.visual_anchor = doc.rope.line_to_char(first);
+                        doc.cursor = doc.rope.line_to_char(last);
+                    }
+                    _ => {}
+                }
+                return Step::Done { changed: false };
+            }
+        }
+
+        if let VimKey::Char(c) = key
+            && let Some(digit) = c.to_digit(10)
+            && (digit != 0 || self.pending.has_count())
+        {
+            self.pending.push_digit(digit as usize);
+            return Step::Pending;
+        }
+
+        if let Some(motion) = Motion::from_key(key) {
+            let target = motion_target(&doc.rope, doc.cursor, motion, self.pending.count, false);
+            doc.cursor = target.pos;
+            return Step::Done { changed: false };
+        }
+
+        let op = match key {
+            VimKey::Escape => {
+                self.mode = VimMode::Normal;
+                doc.clear_selection();
+                return Step::Done { changed: false };
+            }
+            VimKey::Char(c) => match c {
+                '"' => {
+                    self.pending.awaiting = Awaiting::Register;
+                    return Step::Pending;
+                }
+                'g' => {
+                    self.pending.awaiting = Awaiting::GPrefix;
+                    return Step::Pending;
+                }
+                'i' | 'a' => {
+                    self.pending.awaiting = Awaiting::TextObject { around: c == 'a' };
+                    return Step::Pending;
+                }
+                'o' => {
+                    std::mem::swap(&mut self.visual_anchor, &mut doc.cursor);
+                    return Step::Done { changed: false };
+                }
+                'v' | 'V' => {
+                    let requested = if c == 'v' {
+                        VimMode::Visual
+                    } else {
+                        VimMode::VisualLine
+                    };
+                    if self.mode == requested {
+                        self.mode = VimMode::Normal;
+                        doc.clear_selection();
+                    } else {
+                        self.mode = requested;
+                    }
+                    return Step::Done { changed: false };
+                }
+                '/' => {
+                    out.effects.push(VimEffect::StartSearch);
+                    return Step::Done { changed: false };
+                }
+                'd' | 'x' => Operator::Delete,
+                'c' | 's' => Operator::Change,
+                'y' => Operator::Yank,
+                '>' => Operator::Indent,
+                '<' => Operator::Outdent,
+                _ => return Step::Cancel,
+            },
+            _ => return Step::Cancel,
+        };
+
+        let range = self.visual_range(doc);
+        self.mode = VimMode::Normal;
+        doc.clear_selection();
+        let changed = self.operate(op, range, doc, out);
+        Step::Done { changed }
+    }
+
+    fn visual_range(&self, doc: &DocumentState) -> OpRange {
+        let start = self.visual_anchor.min(doc.cursor);
+        let end = self.visual_anchor.max(doc.cursor);
+        if self.mode == VimMode::VisualLine {
+            OpRange::Lines(doc.rope.char_to_line(start), doc.rope.char_to_line(end))
+        } else {
+            OpRange::Chars(start..(end + 1).min(doc.len_chars()))
+        }
+    }
+
+    fn sync_visual_selection(&self, doc: &mut DocumentState) {
+        let range = match self.visual_range(doc) {
+            OpRange::Chars(range) => range,
+            OpRange::Lines(first, last) => doc.rope.line_to_char(first)..line_end(&doc.rope, last),
+        };
+        doc.selection = if range.is_empty() { None } else { Some(range) };
+        doc.selection_anchor = Some(self.visual_anchor);
+    }
+
+    // ============ Operators and registers ============
+
+    fn operate(
+        &mut self,
+        op: Operator,
+        range: OpRange,
+        doc: &mut DocumentState,
+        out: &mut VimOutcome,
+    ) -> bool {
+        match op {
+            Operator::Delete => self.delete(range, doc, out),
+            Operator::Change => {
+                self.change(range, doc, out);
+                true
+            }
+            Operator::Yank => {
+                self.yank(range, doc, out);
+                false
+            }
+            Operator::Indent | Operator::Outdent => {
+                let (first, last) = match range {
+                    OpRange::Chars(range) => (
+                        doc.rope.char_to_line(range.start),
+                        doc.rope
+                            .char_to_line(range.end.saturating_sub(1).max(range.start)),
+                    ),
+                    OpRange::Lines(first, last) => (first, last),
+                };
+                shift_lines(doc, first, last, op == Operator::Indent)
+            }
+        }
+    }
+
+    fn delete(&mut self, range: OpRange, doc: &mut DocumentState, out: &mut VimOutcome) -> bool {
+        match range {
+            OpRange::Chars(range) => {
+                if range.is_empty() {
+                    return false;
+                }
+                self.store_register(doc.slice_chars(range.clone()), false, out);
+                doc.begin_edit();
+                doc.delete_range(range.clone());
+                doc.cursor = range.start;
+                doc.commit_edit();
+            }
+            OpRange::Lines(first, last) => {
+                self.store_register(lines_text(&doc.rope, first, last), true, out);
+                let len = doc.len_chars();
+                let mut start = doc.rope.line_to_char(first);
+                let end = if last + 1 < doc.rope.len_lines() {
+                    doc.rope.line_to_char(last + 1)
+                } else {
+                    len
+                };
+                // The final line has no newline of its own, so take the one before it.
+                let ends_with_newline = len > 0 && doc.rope.char(len - 1) == '\n';
+                if end == len && !ends_with_newline && first > 0 {
+                    start -= 1;
+                }
+                doc.begin_edit();
+                doc.delete_range(start..end);
+                let line = first.min(last_line(&doc.rope));
+                doc.cursor = first_non_blank(&doc.rope, line);
+                doc.commit_edit();
+            }
+        }
+        true
+    }
+
+    fn change(&mut self, range: OpRange, doc: &mut DocumentState, out: &mut VimOutcome) {
+        let (delete, text, linewise) = match range {
+            OpRange::Chars(range) => {
+                let text = doc.slice_chars(range.clone());
+                (range, text, false)
+            }
+            OpRange::Lines(first, last) => {
+                let text = lines_text(&doc.rope, first, last);
+                (
+                    doc.rope.line_to_char(first)..line_end(&doc.rope, last),
+                    text,
+                    true,
+                )
+            }
+        };
+        if !text.is_empty() {
+            self.store_register(text, linewise, out);
+        }
+        doc.begin_edit();
+        doc.delete_range(delete.clone());
+        doc.set_cursor(delete.start);
+        self.mode = VimMode::Insert;
+    }
+
+    fn yank(&mut self, range: OpRange, doc: &mut DocumentState, out: &mut VimOutcome) {
+        match range {
+            OpRange::Chars(range) => {
+                if range.is_empty() {
+                    return;
+                }
+                self.store_register(doc.slice_chars(range.clone()), false, out);
+                doc.set_cursor(range.start);
+            }
+            OpRange::Lines(first, last) => {
+                self.store_register(lines_text(&doc.rope, first, last), true, out);
+                if doc.rope.char_to_line(doc.cursor) > first {
+                    doc.set_cursor(first_non_blank(&doc.rope, first));
+                }
+            }
+        }
+    }
+
+    fn store_register(&mut self, text: String, linewise: bool, out: &mut VimOutcome) {
+        let register = Register { text, linewise };
+        match self.pending.register.unwrap_or('"') {
+            '_' => return,
+            name @ 'a'..='z' => {
+                self.registers.insert(name, register.clone());
+            }
+            name @ 'A'..='Z' => {
+                let entry = self.registers.entry(name.to_ascii_lowercase()).or_default();
+                entry.text.push_str(&register.text);
+                entry.linewise |= register.linewise;
+            }
+            _ => out
+                .effects
+                .push(VimEffect::SetClipboard(register.text.clone())),
+        }
+        self.registers.insert('"', register);
+    }
+
+    fn read_register(&self, clipboard: Option<&str>) -> Option<Register> {
+        match self.pending.register.unwrap_or('"') {
+            name @ ('a'..='z' | 'A'..='Z') => {
+                self.registers.get(&name.to_ascii_lowercase()).cloned()
+            }
+            _ => {
+                let unnamed = self.registers.get(&'"');
+                match clipboard {
+                    Some(text) if !text.is_empty() => {
+                        if let Some(register) = unnamed.filter(|r| r.text == text) {
+                            Some(register.clone())
+                        } else {
+                            Some(Register {
+                                text: text.to_string(),
+                                linewise: text.ends_with('\n'),
+                            })
+                        }
+                    }
+                    _ => unnamed.cloned(),
+                }
+            }
+        }
+    }
+
+    fn paste(
+        &mut self,
+        doc: &mut DocumentState,
+        clipboard: Option<&str>,
+        before: bool,
+        count: usize,
+    ) -> bool {
+        let Some(register) = self.read_register(clipboard) else {
+            return false;
+        };
+        if register.text.is_empty() {
+            return false;
+        }
+
+        let cursor = doc.cursor.min(doc.len_chars());
+        let line = doc.rope.char_to_line(cursor);
+        doc.begin_edit();
+        if register.linewise {
+            let mut text = register.text.repeat(count);
+            if !text.ends_with('\n') {
+                text.push('\n');
+            }
+            let end = line_end(&doc.rope, line);
+            let target_line = if before {
+                doc.insert(doc.rope.line_to_char(line), &text);
+                line
+            } else if end < doc.len_chars() {
+                doc.insert(end + 1, &text);
+                line + 1
+            } else {
+                text.pop();
+                doc.insert(end, &format!("\n{text}"));
+                line + 1
+            };
+            doc.cursor = first_non_blank(&doc.rope, target_line);
+        } else {
+            let text = register.text.repeat(count);
+            let at = if !before && cursor < line_end(&doc.rope, line) {
+                cursor + 1
+            } else {
+                cursor
+            };
+            doc.insert(at, &text);
+            doc.cursor = (at + text.chars().count()).saturating_sub(1);
+        }
+        doc.commit_edit();
+        true
+    }
+}
+
+fn is_register_name(c: char) -> bool {
+    c.is_ascii_alphabetic() || matches!(c, '"' | '+' | '*' | '_')
+}
+
+fn shift_lines(doc: &mut DocumentState, first: usize, last: usize, indent: bool) -> bool {
+    let mut changed = false;
+    doc.begin_edit();
+    for line in (first..=last).rev() {
+        let start = doc.rope.line_to_char(line);
+        if indent {
+            if !is_blank_line(&doc.rope, line) {
+                doc.insert(start, &" ".repeat(SHIFT_WIDTH));
+                changed = true;
+            }
+        } else {
+            let end = line_end(&doc.rope, line);
+            let mut remove = 0;
+            while start + remove < end && remove < SHIFT_WIDTH {
+                match doc.rope.char(start + remove) {
+                    ' ' => remove += 1,
+                    '\t' if remove == 0 => {
+                        remove = 1;
+                        break;
+                    }
+                    _ => break,
+                }
+            }
+            if remove > 0 {
+                doc.delete_range(start..start + remove);
+                changed = true;
+            }
+        }
+    }
+    doc.cursor = first_non_blank(&doc.rope, first);
+    doc.commit_edit();
+    changed
+}
+
+// ============ Rope helpers ============
+
+/// Char index just past the last character of `line`, excluding its newline.
+fn line_end(rope: &Rope, line: usize) -> usize {
+    let slice = rope.line(line);
+    let mut len = slice.len_chars();
+    if len > 0 && slice.char(len - 1) == '\n' {
+        len -= 1;
+    }
+    rope.line_to_char(line) + len
+}
+
+/// Index of the last real line, ignoring the empty line after a trailing newline.
+fn last_line(rope: &Rope) -> usize {
+    let lines = rope.len_lines();
+    let len = rope.len_chars();
+    if lines > 1 && len > 0 && rope.char(len - 1) == '\n' {
+        lines - 2
+    } else {
+        lines - 1
+    }
+}
+
+fn first_non_blank(rope: &Rope, line: usize) -> usize {
+    let end = line_end(rope, line);
+    let mut ix = rope.line_to_char(line);
+    while ix < end && matches!(rope.char(ix), ' ' | '\t') {
+        ix += 1;
+    }
+    ix
+}
+
+fn is_blank_line(rope: &Rope, line: usize) -> bool {
+    rope.line(line).chars().all(char::is_whitespace)
+}
+
+/// Keeps the normal-mode cursor on a character rather than past the line end.
+fn clamp_normal(rope: &Rope, pos: usize) -> usize {
+    let pos = pos.min(rope.len_chars());
+    let line = rope.char_to_line(pos).min(last_line(rope));
+    let start = rope.line_to_char(line);
+    let end = line_end(rope, line);
+    if pos >= end {
+        end.saturating_sub(1).max(start)
+    } else {
+        pos.max(start)
+    }
+}
+
+fn lines_text(rope: &Rope, first: usize, last: usize) -> String {
+    let start = rope.line_to_char(first);
+    let end = line_end(rope, last);
+    let mut text = rope.slice(start..end).to_string();
+    text.push('\n');
+    text
+}
+
+/// Character class used by word motions: blank, punctuation or word.
+fn char_class(c: char) -> u8 {
+    if c.is_whitespace() {
+        0
+    } else if c.is_alphanumeric() || c == '_' {
+        2
+    } else {
+        1
+    }
+}
+
+fn next_word_start(rope: &Rope, pos: usize) -> usize {
+    let len = rope.len_chars();
+    if pos >= len {
+        return len;
+    }
+    let mut ix = pos;
+    let class = char_class(rope.char(ix));
+    if class != 0 {
+        while ix < len && char_class(rope.char(ix)) == class {
+            ix += 1;
+        }
+    }
+    while ix < len && char_class(rope.char(ix)) == 0 {
+        ix += 1;
+        // An empty line counts as a word.
+        if ix < len && rope.char(ix) == '\n' && rope.char(ix - 1) == '\n' {
+            return ix;
+        }
+    }
+    ix
+}
+
+fn next_word_end(rope: &Rope, pos: usize) -> usize {
+    let len = rope.len_chars();
+    let mut ix = pos + 1;
+    while ix < len && char_class(rope.char(ix)) == 0 {
+        ix += 1;
+    }
+    if ix >= len {
+        return len.saturating_sub(1);
+    }
+    let class = char_class(rope.char(ix));
+    while ix + 1 < len && char_class(rope.char(ix + 1)) == class {
+        ix += 1;
+    }
+    ix
+}
+
+fn prev_word_start(rope: &Rope, pos: usize) -> usize {
+    if pos == 0 {
+        return 0;
+    }
+    let mut ix = pos - 1;
+    while ix > 0 && char_class(rope.char(ix)) == 0 {
+        ix -= 1;
+    }
+    let class = char_class(rope.char(ix));
+    while ix > 0 && char_class(rope.char(ix - 1)) == class {
+        ix -= 1;
+    }
+    ix
+}
+
+fn motion_target(
+    rope: &Rope,
+    cursor: usize,
+    motion: Motion,
+    count: Option<usize>,
+    for_operator: bool,
+) -> MotionTarget {
+    let n = count.unwrap_or(1).max(1);
+    let line = rope.char_to_line(cursor);
+    let start = rope.line_to_char(line);
+    let end = line_end(rope, line);
+    let last = last_line(rope);
+    let exclusive = |pos| MotionTarget {
+        pos,
+        kind: MotionKind::Exclusive,
+    };
+    let linewise = |pos| MotionTarget {
+        pos,
+        kind: MotionKind::Linewise,
+    };
+
+    match motion {
+        Motion::Left => exclusive(cursor.saturating_sub(n).max(start)),
+        Motion::Right => {
+            let limit = if for_operator {
+                end
+            } else {
+                end.saturating_sub(1).max(start)
+            };
+            exclusive((cursor + n).min(limit))
+        }
+        Motion::Up | Motion::Down => {
+            let target_line = if motion == Motion::Up {
+                line.saturating_sub(n)
+            } else {
+                (line + n).min(last)
+            };
+            let column = cursor - start;
+            let target_start = rope.line_to_char(target_line);
+            let target_end = line_end(rope, target_line);
+            linewise((target_start + column).min(target_end))
+        }
+        Motion::WordForward => {
+            let mut pos = cursor;
+            for _ in 0..n {
+                pos = next_word_start(rope, pos);
+            }
+            // `dw` on the last word of a line stops at the line end.
+            let target_line = rope.char_to_line(pos.min(rope.len_chars()));
+            if for_operator && target_line > line && pos == rope.line_to_char(target_line) {
+                pos = line_end(rope, target_line - 1);
+            }
+            exclusive(pos)
+        }
+        Motion::WordEnd => {
+            let mut pos = cursor;
+            for _ in 0..n {
+                pos = next_word_end(rope, pos);
+            }
+            MotionTarget {
+                pos,
+                kind: MotionKind::Inclusive,
+            }
+        }
+        Motion::WordBackward => {
+            let mut pos = cursor;
+            for _ in 0..n {
+                pos = prev_word_start(rope, pos);
+            }
+            exclusive(pos)
+        }
+        Motion::LineStart => exclusive(start),
+        Motion::FirstNonBlank => exclusive(first_non_blank(rope, line)),
+        Motion::LineEnd => {
+            let target_line = (line + n - 1).min(last);
+            let target_start = rope.line_to_char(target_line);
+            let target_end = line_end(rope, target_line);
+            if target_end > target_start {
+                MotionTarget {
+                    pos: target_end - 1,
+                    kind: MotionKind::Inclusive,
+                }
+            } else {
+                exclusive(target_end)
+            }
+        }
+        Motion::FirstLine | Motion::LastLine => {
+            let default = if motion == Motion::FirstLine { 0 } else { last };
+            let target_line = count.map_or(default, |c| c.saturating_sub(1)).min(last);
+            linewise(first_non_blank(rope, target_line))
+        }
+        Motion::ParagraphForward => {
+            let mut target = line;
+            for _ in 0..n {
+                target += 1;
+                while target <= last && is_blank_line(rope, target) {
+                    target += 1;
+                }
+                while target <= last && !is_blank_line(rope, target) {
+                    target += 1;
+                }
+            }
+            if target > last {
+                exclusive(rope.len_chars())
+            } else {
+                exclusive(rope.line_to_char(target))
+            }
+        }
+        Motion::ParagraphBackward => {
+            let mut target = line;
+            for _ in 0..n {
+                target = target.saturating_sub(1);
+                while target > 0 && is_blank_line(rope, target) {
+                    target -= 1;
+                }
+                while target > 0 && !is_blank_line(rope, target) {
+                    target -= 1;
+                }
+            }
+            exclusive(rope.line_to_char(target))
+        }
+    }
+}
+
+fn motion_range(rope: &Rope, cursor: usize, target: MotionTarget) -> OpRange {
+    let start = cursor.min(target.pos);
+    let end = cursor.max(target.pos);
+    match target.kind {
+        MotionKind::Exclusive => OpRange::Chars(start..end),
+        MotionKind::Inclusive => OpRange::Chars(start..(end + 1).min(rope.len_chars())),
+        MotionKind::Linewise => OpRange::Lines(rope.char_to_line(start), rope.char_to_line(end)),
+    }
+}
+
+fn text_object_range(
+    rope: &Rope,
+    cursor: usize,
+    object: TextObject,
+    around: bool,
+) -> Option<OpRange> {
+    let len = rope.len_chars();
+    let cursor = cursor.min(len);
+    let line = rope.char_to_line(cursor);
+    let line_start = rope.line_to_char(line);
+    let end_of_line = line_end(rope, line);
+
+    match object {
+        TextObject::Word => {
+            if end_of_line == line_start {
+                return None;
+            }
+            let pos = cursor.min(end_of_line - 1);
+            let class = char_class(rope.char(pos));
+            let mut start = pos;
+            while start > line_start && char_class(rope.char(start - 1)) == class {
+                start -= 1;
+            }
+            let mut end = pos + 1;
+            while end < end_of_line && char_class(rope.char(end)) == class {
+                end += 1;
+            }
+            if around {
+                if class == 0 {
+                    if end < end_of_line {
+                        let next = char_class(rope.char(end));
+                        while end < end_of_line && char_class(rope.char(end)) == next {
+                            end += 1;
+                        }
+                    }
+                } else {
+                    let word_end = end;
+                    while end < end_of_line && rope.char(end).is_whitespace() {
+                        end += 1;
+                    }
+                    if end == word_end {
+                        while start > line_start && rope.char(start - 1).is_whitespace() {
+                            start -= 1;
+                        }
+                    }
+                }
+            }
+            Some(OpRange::Chars(start..end))
+        }
+        TextObject::Paragraph => {
+            let last = last_line(rope);
+            let line = line.min(last);
+            let blank = is_blank_line(rope, line);
+            let mut first = line;
+            while first > 0 && is_blank_line(rope, first - 1) == blank {
+                first -= 1;
+            }
+            let mut end = line;
+            while end < last && is_blank_line(rope, end + 1) == blank {
+                end += 1;
+            }
+            if around {
+                if end < last {
+                    let next = is_blank_line(rope, end + 1);
+                    while end < last && is_blank_line(rope, end + 1) == next {
+                        end += 1;
+                    }
+                } else if !blank {
+                    while first > 0 && is_blank_line(rope, first - 1) {
+                        first -= 1;
+                    }
+                }
+            }
+            Some(OpRange::Lines(first, end))
+        }
+        TextObject::Quote(quote) => {
+            let mut quotes = Vec::new();
+            let mut escaped = false;
+            for ix in line_start..end_of_line {
+                let c = rope.char(ix);
+                if c == quote && !escaped {
+                    quotes.push(ix);
+                }
+                escaped = c == '\\' && !escaped;
+            }
+            let (open, close) = quotes
+                .chunks_exact(2)
+                .map(|pair| (pair[0], pair[1]))
+                .find(|(open, close)| cursor >= *open && cursor <= *close)
+                .or_else(|| {
+                    quotes
+                        .chunks_exact(2)
+                        .map(|pair| (pair[0], pair[1]))
+                        .find(|(open, _)| *open > cursor)
+                })?;
+            if around {
+                Some(OpRange::Chars(open..close + 1))
+            } else {
+                Some(OpRange::Chars(open + 1..close))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc_with(text: &str, cursor: usize) -> DocumentState {
+        let mut doc = DocumentState::new_empty();
+        doc.set_text(text);
+        doc.save_snapshot();
+        doc.set_cursor(cursor);
+        doc
+    }
+
+    /// Feeds `keys` to a fresh Vim state; `\x1b` stands for Escape.
+    fn run(vim: &mut VimState, doc: &mut DocumentState, keys: &str) -> Vec<VimEffect> {
+        let mut effects = Vec::new();
+        for c in keys.chars() {
+            let key = match c {
+                '\x1b' => VimKey::Escape,
+                '\x08' => VimKey::Backspace,
+                '\n' => VimKey::Enter,
+                c => VimKey::Char(c),
+            };
+            effects.extend(vim.handle_key(key, doc, None).effects);
+        }
+        effects
+    }
+
+    fn apply(text: &str, cursor: usize, keys: &str) -> (String, usize) {
+        let mut doc = doc_with(text, cursor);
+        let mut vim = VimState::new();
+        run(&mut vim, &mut doc, keys);
+        (doc.text(), doc.cursor)
+    }
+
+    #[test]
+    fn word_and_line_motions() {
+        assert_eq!(apply("one two three", 0, "w").1, 4);
+        assert_eq!(apply("one two three", 0, "2w").1, 8);
+        assert_eq!(apply("one two three", 0, "e").1, 2);
+        assert_eq!(apply("one two three", 8, "b").1, 4);
+        assert_eq!(apply("one two three", 5, "$").1, 12);
+        assert_eq!(apply("  one", 4, "0").1, 0);
+        assert_eq!(apply("a\nb\nc\n", 0, "G").1, 4);
+        assert_eq!(apply("a\nb\nc\n", 4, "gg").1, 0);
+        assert_eq!(apply("a\nb\n\nc\n", 0, "}").1, 4);
+        assert_eq!(apply("a\n\nb\nc", 6, "{").1, 2);
+    }
+
+    #[test]
+    fn far_motions_are_recorded_for_back() {
+        let mut doc = doc_with(&"line\n".repeat(40), 0);
+        let mut vim = VimState::new();
+        run(&mut vim, &mut doc, "j");
+        assert!(!doc.jumps.can_go_back());
+        run(&mut vim, &mut doc, "G");
+        assert!(doc.jumps.can_go_back());
+        let back = doc.jumps.go_back(doc.jump_position()).unwrap();
+        assert_eq!(back.char_idx, 5);
+    }
+
+    #[test]
+    fn operators_take_counts_and_motions() {
+        assert_eq!(apply("one two three", 0, "dw").0, "two three");
+        assert_eq!(apply("one two three", 0, "2dw").0, "three");
+        assert_eq!(apply("one two three", 0, "d2w").0, "three");
+        assert_eq!(apply("one two", 4, "dw").0, "one ");
+        assert_eq!(apply("a\nb\nc\n", 0, "2dd").0, "c\n");
+        assert_eq!(apply("a\nb\nc", 4, "dd").0, "a\nb");
+        assert_eq!(apply("one two", 4, "d$").0, "one ");
+        assert_eq!(apply("a\nb\n", 0, ">j").0, "    a\n    b\n");
+        assert_eq!(apply("      a\n", 0, "<<").0, "  a\n");
+    }
+
+    #[test]
+    fn change_enters_insert_mode_and_groups_undo() {
+        let mut doc = doc_with("one two", 0);
+        let mut vim = VimState::new();
+        run(&mut vim, &mut doc, "cwxy\x1b");
+        assert_eq!(doc.text(), "xy two");
+        assert_eq!(vim.mode(), VimMode::Normal);
+        run(&mut vim, &mut doc, "u");
+        assert_eq!(doc.text(), "one two");
+    }
+
+    #[test]
+    fn counted_inserts_repeat_the_typed_text() {
+        assert_eq!(apply("ab", 1, "3ix\x1b"), ("axxxb".to_string(), 3));
+        assert_eq!(apply("ab", 0, "2a-=\x1b").0, "a-=-=b");
+        assert_eq!(apply("  one", 4, "2Ix\x1b").0, "  xxone");
+        assert_eq!(apply("one", 0, "3A!\x1b").0, "one!!!");
+        assert_eq!(apply("ab", 1, "3ixy\x08\x1b").0, "axxxb");
+        assert_eq!(apply("ab", 0, "2ix\x1bu").0, "ab");
+        assert_eq!(apply("a\nb\n", 0, "2A.\x1bj.").0, "a..\nb..\n");
+    }
+
+    #[test]
+    fn text_objects_select_words_quotes_and_paragraphs() {
+        assert_eq!(apply("say hello there", 5, "diw").0, "say  there");
+        assert_eq!(apply("say hello there", 5, "daw").0, "say there");
+        assert_eq!(apply(r#"x = "quoted" + y"#, 7, "di\"").0, r#"x = "" + y"#);
+        assert_eq!(
+            apply("run `cargo test` now", 8, "ci`ls\x1b").0,
+            "run `ls` now"
+        );
+        assert_eq!(apply("a\nb\n\nc\n", 0, "dap").0, "c\n");
+    }
+
+    #[test]
+    fn dot_repeats_last_change() {
+        assert_eq!(apply("a b c d", 0, "dw.").0, "c d");
+        assert_eq!(apply("one\ntwo\n", 0, "A!\x1bj.").0, "one!\ntwo!\n");
+        assert_eq!(apply("x\ny\nz\n", 0, "dd2.").0, "");
+    }
+
+    #[test]
+    fn yank_and_put_use_clipboard_registers() {
+        let mut doc = doc_with("alpha\nbeta\n", 0);
+        let mut vim = VimState::new();
+        let effects = run(&mut vim, &mut doc, "yy");
+        assert_eq!(effects, vec![VimEffect::SetClipboard("alpha\n".into())]);
+        run(&mut vim, &mut doc, "jp");
+        assert_eq!(doc.text(), "alpha\nbeta\nalpha\n");
+
+        let mut doc = doc_with("ab", 0);
+        vim.handle_key(VimKey::Char('p'), &mut doc, Some("XY"));
+        assert_eq!(doc.text(), "aXYb");
+
+        let mut doc = doc_with("one two", 0);
+        let effects = run(&mut vim, &mut doc, "\"ayw\"ap");
+        assert!(effects.is_empty());
+        assert_eq!(doc.text(), "oone ne two");
+    }
+
+    #[test]
+    fn visual_mode_operates_on_selection() {
+        let mut doc = doc_with("hello world", 0);
+        let mut vim = VimState::new();
+        run(&mut vim, &mut doc, "vl");
+        assert_eq!(vim.mode(), VimMode::Visual);
+        assert_eq!(doc.selection_range(), Some(0..2));
+        run(&mut vim, &mut doc, "d");
+        assert_eq!(doc.text(), "llo world");
+        assert_eq!(vim.mode(), VimMode::Normal);
+
+        assert_eq!(apply("a\nb\nc\n", 0, "Vjd").0, "c\n");
+        assert_eq!(apply("one two", 5, "viwy$p").0, "one twotwo");
+    }
+
+    #[test]
+    fn search_keys_are_forwarded_to_the_editor() {
+        let mut doc = doc_with("text", 0);
+        let mut vim = VimState::new();
+        let effects = run(&mut vim, &mut doc, "/");
+        assert_eq!(effects, vec![VimEffect::StartSearch]);
+        let effects = run(&mut vim, &mut doc, "N");
+        assert_eq!(effects, vec![VimEffect::SearchNext { forward: false }]);
+    }
+}